@@ -1,6 +1,8 @@
-use crate::{Console, Cppc, EnvInfo, Fence, Hsm, Ipi, Nacl, Pmu, Reset, Sta, Susp, Timer};
+use crate::{
+    Console, Cppc, Dbtr, EnvInfo, Fence, Fwft, Hsm, Ipi, Mpxy, Nacl, Pmu, Reset, Sta, Susp, Timer,
+};
 use sbi_spec::{
-    binary::{CounterMask, HartMask, Physical, SbiRet, SharedPtr},
+    binary::{CounterMask, HartMask, Physical, SbiRet, SharedPtr, TriggerMask},
     nacl, pmu,
 };
 
@@ -137,6 +139,112 @@ impl Cppc for Forward {
     }
 }
 
+impl Dbtr for Forward {
+    #[inline]
+    fn num_triggers(&self, trig_tdata1: usize) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => SbiRet::success(sbi_rt::debug_num_triggers(trig_tdata1)),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = trig_tdata1;
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn set_shmem(&self, shmem: SharedPtr<u8>, flags: usize) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::debug_set_shmem(shmem, flags),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = (shmem, flags);
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn read_triggers(&self, trig_idx_base: usize, trig_count: usize) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::debug_read_triggers(trig_idx_base, trig_count),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = (trig_idx_base, trig_count);
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn install_triggers(&self, trig_count: usize) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::debug_install_triggers(trig_count),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = trig_count;
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn update_triggers(&self, trig_count: usize) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::debug_update_triggers(trig_count),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = trig_count;
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn uninstall_triggers(&self, trig_mask: TriggerMask) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::debug_uninstall_triggers(trig_mask),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = trig_mask;
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn enable_triggers(&self, trig_mask: TriggerMask) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::debug_enable_triggers(trig_mask),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = trig_mask;
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn disable_triggers(&self, trig_mask: TriggerMask) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::debug_disable_triggers(trig_mask),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = trig_mask;
+                unimplemented!()
+            }
+        }
+    }
+}
+
 impl Fence for Forward {
     #[inline]
     fn remote_fence_i(&self, hart_mask: HartMask) -> SbiRet {
@@ -248,6 +356,34 @@ impl Fence for Forward {
     }
 }
 
+impl Fwft for Forward {
+    #[inline]
+    fn set(&self, feature: u32, value: usize, flags: usize) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::fwft_set(feature, value, flags),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = (feature, value, flags);
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn get(&self, feature: u32) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::fwft_get(feature),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = feature;
+                unimplemented!()
+            }
+        }
+    }
+}
+
 impl Hsm for Forward {
     #[inline]
     fn hart_start(&self, hartid: usize, start_addr: usize, opaque: usize) -> SbiRet {
@@ -316,6 +452,131 @@ impl Ipi for Forward {
     }
 }
 
+impl Mpxy for Forward {
+    #[inline]
+    fn get_shmem_size(&self) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => SbiRet::success(sbi_rt::mpxy_get_shmem_size()),
+            #[cfg(not(feature = "forward"))]
+            () => unimplemented!(),
+        }
+    }
+
+    #[inline]
+    fn set_shmem(&self, shmem: SharedPtr<u8>, flags: usize) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::mpxy_set_shmem(shmem, flags),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = (shmem, flags);
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn get_channel_ids(&self, start_index: u32) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::mpxy_get_channel_ids(start_index),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = start_index;
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn read_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::mpxy_read_attributes(channel_id, base_attribute_id, attribute_count),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = (channel_id, base_attribute_id, attribute_count);
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn write_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::mpxy_write_attributes(channel_id, base_attribute_id, attribute_count),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = (channel_id, base_attribute_id, attribute_count);
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn send_message_with_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::mpxy_send_message_with_response(channel_id, message_id, message_data_len),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = (channel_id, message_id, message_data_len);
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn send_message_without_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => {
+                sbi_rt::mpxy_send_message_without_response(channel_id, message_id, message_data_len)
+            }
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = (channel_id, message_id, message_data_len);
+                unimplemented!()
+            }
+        }
+    }
+
+    #[inline]
+    fn get_notification_events(&self, channel_id: u32) -> SbiRet {
+        match () {
+            #[cfg(feature = "forward")]
+            () => sbi_rt::mpxy_get_notification_events(channel_id),
+            #[cfg(not(feature = "forward"))]
+            () => {
+                let _ = channel_id;
+                unimplemented!()
+            }
+        }
+    }
+}
+
 impl Nacl for Forward {
     #[inline]
     fn probe_feature(&self, feature_id: u32) -> SbiRet {
@@ -0,0 +1,89 @@
+use sbi_spec::binary::SbiRet;
+
+/// Firmware Features extension.
+///
+/// Some platform features might be managed by the SBI implementation or
+/// require configuration through machine mode registers. The Firmware
+/// Features (FWFT) extension allows the supervisor-mode software to query
+/// and configure such features, for example misaligned access exception
+/// delegation or control-flow integrity features.
+///
+/// Each feature is identified by a 32-bit feature ID. Features may be
+/// either local to the calling hart or global to the whole supervisor
+/// domain, as defined by the RISC-V SBI Specification.
+pub trait Fwft {
+    /// Set the configuration value of a specific firmware feature.
+    ///
+    /// A successful return results in the requested firmware feature to be set
+    /// according to the `value` and `flags` parameters. If the `LOCK` bit is set
+    /// in `flags`, the feature value can no longer be modified until the
+    /// feature is reset.
+    ///
+    /// # Return value
+    ///
+    /// In case of failure, `feature` value is not modified and the possible error codes
+    /// returned in `SbiRet.error` are shown in the table below:
+    ///
+    /// | Return code                 | Description
+    /// |:----------------------------|:----------------------------------------------
+    /// | `SbiRet::success()`         | `feature` was set successfully.
+    /// | `SbiRet::not_supported()`   | `feature` is not reserved and valid, but the platform does not support it due to one or more missing dependencies (Hardware or SBI implementation).
+    /// | `SbiRet::invalid_param()`   | Provided `value` or `flags` parameter is invalid.
+    /// | `SbiRet::denied()`          | `feature` set operation failed because either it was denied by the SBI implementation, or `feature` is reserved or is platform-specific and unimplemented.
+    /// | `SbiRet::denied_locked()`   | `feature` set operation failed because the `feature` is locked.
+    /// | `SbiRet::failed()`          | The set operation failed for unspecified or unknown other reasons.
+    fn set(&self, feature: u32, value: usize, flags: usize) -> SbiRet;
+    /// Get the configuration value of a specific firmware feature.
+    ///
+    /// # Return value
+    ///
+    /// A successful return results in the firmware feature configuration value to be
+    /// returned in `SbiRet.value`. In case of failure, the content of `SbiRet.value` is zero
+    /// and the possible error codes returned in `SbiRet.error` are shown in the table below:
+    ///
+    /// | Return code                 | Description
+    /// |:----------------------------|:----------------------------------------------
+    /// | `SbiRet::success()`         | Feature status was retrieved successfully.
+    /// | `SbiRet::not_supported()`   | `feature` is not reserved and valid, but the platform does not support it due to one or more missing dependencies (Hardware or SBI implementation).
+    /// | `SbiRet::denied()`          | `feature` is reserved or is platform-specific and unimplemented.
+    /// | `SbiRet::failed()`          | The get operation failed for unspecified or unknown other reasons.
+    fn get(&self, feature: u32) -> SbiRet;
+    /// Function internal to macros. Do not use.
+    #[doc(hidden)]
+    #[inline]
+    fn _rustsbi_probe(&self) -> usize {
+        sbi_spec::base::UNAVAILABLE_EXTENSION.wrapping_add(1)
+    }
+}
+
+impl<T: Fwft> Fwft for &T {
+    #[inline]
+    fn set(&self, feature: u32, value: usize, flags: usize) -> SbiRet {
+        T::set(self, feature, value, flags)
+    }
+    #[inline]
+    fn get(&self, feature: u32) -> SbiRet {
+        T::get(self, feature)
+    }
+}
+
+impl<T: Fwft> Fwft for Option<T> {
+    #[inline]
+    fn set(&self, feature: u32, value: usize, flags: usize) -> SbiRet {
+        self.as_ref().map_or(SbiRet::not_supported(), |inner| {
+            T::set(inner, feature, value, flags)
+        })
+    }
+    #[inline]
+    fn get(&self, feature: u32) -> SbiRet {
+        self.as_ref()
+            .map_or(SbiRet::not_supported(), |inner| T::get(inner, feature))
+    }
+    #[inline]
+    fn _rustsbi_probe(&self) -> usize {
+        match self {
+            Some(_) => sbi_spec::base::UNAVAILABLE_EXTENSION.wrapping_add(1),
+            None => sbi_spec::base::UNAVAILABLE_EXTENSION,
+        }
+    }
+}
@@ -541,8 +541,11 @@
 
 mod console;
 mod cppc;
+mod dbtr;
+mod fwft;
 mod hsm;
 mod ipi;
+mod mpxy;
 mod nacl;
 mod pmu;
 mod reset;
@@ -582,7 +585,7 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub extern crate sbi_spec as spec;
 
-pub use sbi_spec::binary::{CounterMask, HartMask, Physical, SbiRet, SharedPtr};
+pub use sbi_spec::binary::{CounterMask, HartMask, Physical, SbiRet, SharedPtr, TriggerMask};
 
 /// Generate `RustSBI` implementation for structure of each extension.
 ///
@@ -841,6 +844,9 @@ pub use sbi_spec::binary::{CounterMask, HartMask, Physical, SbiRet, SharedPtr};
 /// | `cppc` | [`Cppc`](trait.Cppc.html) | SBI CPPC extension |
 /// | `nacl` | [`Nacl`](trait.Nacl.html) | Nested Acceleration extension |
 /// | `sta` | [`Sta`](trait.Sta.html) | Steal Time Accounting extension |
+/// | `fwft` | [`Fwft`](trait.Fwft.html) | Firmware Features extension |
+/// | `dbtr` | [`Dbtr`](trait.Dbtr.html) | Debug Triggers extension |
+/// | `mpxy` | [`Mpxy`](trait.Mpxy.html) | Message Proxy extension |
 ///
 /// The `EnvInfo` parameter is used by RISC-V SBI Base extension which is always supported on all
 /// RISC-V SBI implementations. RustSBI provides the Base extension with additional `EnvInfo` by default.
@@ -1127,8 +1133,11 @@ pub use rustsbi_macros::RustSBI;
 
 pub use console::Console;
 pub use cppc::Cppc;
+pub use dbtr::Dbtr;
+pub use fwft::Fwft;
 pub use hsm::Hsm;
 pub use ipi::Ipi;
+pub use mpxy::Mpxy;
 pub use nacl::Nacl;
 pub use pmu::Pmu;
 pub use reset::Reset;
@@ -1148,14 +1157,16 @@ pub use traits::_rustsbi_base_bare;
 #[doc(hidden)]
 pub use traits::{
     _ExtensionProbe, _StandardExtensionProbe, _rustsbi_base_env_info, _rustsbi_console,
-    _rustsbi_cppc, _rustsbi_fence, _rustsbi_hsm, _rustsbi_ipi, _rustsbi_nacl, _rustsbi_pmu,
-    _rustsbi_reset, _rustsbi_sta, _rustsbi_susp, _rustsbi_timer,
+    _rustsbi_cppc, _rustsbi_dbtr, _rustsbi_fence, _rustsbi_fwft, _rustsbi_hsm, _rustsbi_ipi,
+    _rustsbi_mpxy, _rustsbi_nacl, _rustsbi_pmu, _rustsbi_reset, _rustsbi_sta, _rustsbi_susp,
+    _rustsbi_timer,
 };
 #[doc(hidden)]
 pub use traits::{
-    _rustsbi_console_probe, _rustsbi_cppc_probe, _rustsbi_fence_probe, _rustsbi_hsm_probe,
-    _rustsbi_ipi_probe, _rustsbi_nacl_probe, _rustsbi_pmu_probe, _rustsbi_reset_probe,
-    _rustsbi_sta_probe, _rustsbi_susp_probe, _rustsbi_timer_probe,
+    _rustsbi_console_probe, _rustsbi_cppc_probe, _rustsbi_dbtr_probe, _rustsbi_fence_probe,
+    _rustsbi_fwft_probe, _rustsbi_hsm_probe, _rustsbi_ipi_probe, _rustsbi_mpxy_probe,
+    _rustsbi_nacl_probe, _rustsbi_pmu_probe, _rustsbi_reset_probe, _rustsbi_sta_probe,
+    _rustsbi_susp_probe, _rustsbi_timer_probe,
 };
 
 // 导出Virtio相关类型
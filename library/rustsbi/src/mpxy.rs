@@ -0,0 +1,303 @@
+use sbi_spec::binary::{SbiRet, SharedPtr};
+
+/// Message Proxy extension.
+///
+/// The Message Proxy (MPXY) extension allows supervisor software to send and receive
+/// messages through the SBI implementation. It provides an abstraction over message
+/// channels managed by the SBI implementation, for example mailboxes implementing the
+/// RISC-V Platform Management Interface (RPMI).
+///
+/// Messages and attributes are exchanged through a per-hart shared memory set up by
+/// supervisor software. Each message channel is identified by a 32-bit channel ID, and
+/// the layout of message data is defined by the message protocol of that channel.
+pub trait Mpxy {
+    /// Get the shared memory size in number of bytes for sending and receiving messages.
+    ///
+    /// The shared memory size MUST be the same for all harts, at least 4096 bytes and a
+    /// multiple of 4096 bytes, and not less than the biggest `MSG_DATA_MAX_LEN` attribute
+    /// value across all message channels.
+    ///
+    /// This function always returns `SbiRet::success()` in `SbiRet.error` with the shared
+    /// memory size in `SbiRet.value`.
+    fn get_shmem_size(&self) -> SbiRet;
+    /// Set the shared memory for sending and receiving messages on the calling hart.
+    ///
+    /// If physical address of `shmem` is not all-ones bitwise, then `shmem` specifies the
+    /// shared memory physical base address. The `shmem` MUST be 4096 bytes aligned and the
+    /// size of shared memory is the one returned by [`get_shmem_size`](Mpxy::get_shmem_size).
+    ///
+    /// If physical address of `shmem` is all-ones bitwise, then shared memory is disabled.
+    ///
+    /// The `flags[1:0]` field selects the shared memory setup mode: `0b00` for OVERWRITE and
+    /// `0b01` for OVERWRITE-RETURN, which writes the previous shared memory address to the new
+    /// shared memory at offset `0x0`. Other bits of `flags` are reserved and MUST be zero.
+    ///
+    /// # Return value
+    ///
+    /// | Return code                 | Description
+    /// |:----------------------------|:----------------------------------------------
+    /// | `SbiRet::success()`         | Shared memory was set or cleared successfully.
+    /// | `SbiRet::invalid_param()`   | The `flags` parameter has invalid value, or the `shmem` parameter is not 4096 bytes aligned.
+    /// | `SbiRet::invalid_address()` | The shared memory pointed to by the `shmem` parameter does not satisfy the requirements.
+    /// | `SbiRet::failed()`          | The request failed for unspecified or unknown other reasons.
+    fn set_shmem(&self, shmem: SharedPtr<u8>, flags: usize) -> SbiRet;
+    /// Get channel IDs of the message channels accessible to the supervisor software in the
+    /// shared memory of the calling hart.
+    ///
+    /// The shared memory holds the `REMAINING` and `RETURNED` counts as 32-bit words at offset
+    /// `0x0` and `0x4`, followed by an array of channel IDs starting from `start_index`.
+    ///
+    /// # Return value
+    ///
+    /// | Return code                 | Description
+    /// |:----------------------------|:----------------------------------------------
+    /// | `SbiRet::success()`         | The channel ID array has been written successfully.
+    /// | `SbiRet::invalid_param()`   | `start_index` is invalid.
+    /// | `SbiRet::no_shmem()`        | The shared memory setup is not done or disabled for the calling hart.
+    /// | `SbiRet::denied()`          | Getting channel ID array is not allowed on the calling hart.
+    /// | `SbiRet::failed()`          | The request failed for unspecified or unknown other reasons.
+    fn get_channel_ids(&self, start_index: u32) -> SbiRet;
+    /// Read message channel attributes into the shared memory of the calling hart.
+    ///
+    /// The value of the attribute with `attribute_id = base_attribute_id + i` is written at
+    /// the shared memory offset `4 * i`.
+    ///
+    /// # Return value
+    ///
+    /// | Return code                 | Description
+    /// |:----------------------------|:----------------------------------------------
+    /// | `SbiRet::success()`         | Message channel attributes has been read successfully.
+    /// | `SbiRet::invalid_param()`   | `attribute_count` is 0, or `attribute_count > (shared memory size) / 4`, or `base_attribute_id` is not valid.
+    /// | `SbiRet::not_supported()`   | `channel_id` is not supported or invalid.
+    /// | `SbiRet::bad_range()`       | One of the attributes in the range does not exist.
+    /// | `SbiRet::no_shmem()`        | The shared memory setup is not done or disabled for the calling hart.
+    /// | `SbiRet::failed()`          | The request failed for unspecified or unknown other reasons.
+    fn read_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet;
+    /// Write message channel attributes from the shared memory of the calling hart.
+    ///
+    /// The value of the attribute with `attribute_id = base_attribute_id + i` is read from
+    /// the shared memory offset `4 * i`.
+    ///
+    /// # Return value
+    ///
+    /// | Return code                 | Description
+    /// |:----------------------------|:----------------------------------------------
+    /// | `SbiRet::success()`         | Message channel attributes has been written successfully.
+    /// | `SbiRet::invalid_param()`   | `attribute_count` is 0, or `attribute_count > (shared memory size) / 4`, or `base_attribute_id` is not valid.
+    /// | `SbiRet::not_supported()`   | `channel_id` is not supported or invalid.
+    /// | `SbiRet::bad_range()`       | One of the attributes in the range does not exist or is read-only.
+    /// | `SbiRet::no_shmem()`        | The shared memory setup is not done or disabled for the calling hart.
+    /// | `SbiRet::denied()`          | An attribute write dependency is not satisfied.
+    /// | `SbiRet::failed()`          | The request failed for unspecified or unknown other reasons.
+    fn write_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet;
+    /// Send a message to the channel and wait until a message response is received.
+    ///
+    /// The message data of `message_data_len` bytes is located at offset `0x0` of the shared
+    /// memory of the calling hart. Upon success, response data is written at offset `0x0` of
+    /// the shared memory and its length is returned in `SbiRet.value`.
+    ///
+    /// # Return value
+    ///
+    /// | Return code                 | Description
+    /// |:----------------------------|:----------------------------------------------
+    /// | `SbiRet::success()`         | Message sent and response received successfully.
+    /// | `SbiRet::invalid_param()`   | `message_data_len` is greater than `MSG_DATA_MAX_LEN` of the channel or the size of shared memory.
+    /// | `SbiRet::not_supported()`   | `channel_id` or `message_id` is not supported or invalid, or this function is not supported.
+    /// | `SbiRet::no_shmem()`        | The shared memory setup is not done or disabled for the calling hart.
+    /// | `SbiRet::timeout()`         | Waiting for response timeout.
+    /// | `SbiRet::io()`              | Failed due to I/O error.
+    /// | `SbiRet::failed()`          | The request failed for unspecified or unknown other reasons.
+    fn send_message_with_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet;
+    /// Send a message to the channel without waiting for a message response.
+    ///
+    /// The message data of `message_data_len` bytes is located at offset `0x0` of the shared
+    /// memory of the calling hart.
+    ///
+    /// # Return value
+    ///
+    /// | Return code                 | Description
+    /// |:----------------------------|:----------------------------------------------
+    /// | `SbiRet::success()`         | Message sent successfully.
+    /// | `SbiRet::invalid_param()`   | `message_data_len` is greater than `MSG_DATA_MAX_LEN` of the channel or the size of shared memory.
+    /// | `SbiRet::not_supported()`   | `channel_id` or `message_id` is not supported or invalid, or this function is not supported.
+    /// | `SbiRet::no_shmem()`        | The shared memory setup is not done or disabled for the calling hart.
+    /// | `SbiRet::timeout()`         | Message send timeout.
+    /// | `SbiRet::io()`              | Failed due to I/O error.
+    /// | `SbiRet::failed()`          | The request failed for unspecified or unknown other reasons.
+    fn send_message_without_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet;
+    /// Get the message protocol specific notification events of the channel.
+    ///
+    /// Events state data (`REMAINING`, `RETURNED`, `LOST` and a reserved word) occupies
+    /// 16 bytes at offset `0x0` of the shared memory, and notification events data is
+    /// written starting from offset `0x10`. The number of bytes of events data is
+    /// returned in `SbiRet.value`.
+    ///
+    /// # Return value
+    ///
+    /// | Return code                 | Description
+    /// |:----------------------------|:----------------------------------------------
+    /// | `SbiRet::success()`         | Notifications received successfully.
+    /// | `SbiRet::not_supported()`   | `channel_id` is not supported or invalid, or this function is not supported.
+    /// | `SbiRet::no_shmem()`        | The shared memory setup is not done or disabled for the calling hart.
+    /// | `SbiRet::io()`              | Failed due to I/O error.
+    /// | `SbiRet::failed()`          | The request failed for unspecified or unknown other reasons.
+    fn get_notification_events(&self, channel_id: u32) -> SbiRet;
+    /// Function internal to macros. Do not use.
+    #[doc(hidden)]
+    #[inline]
+    fn _rustsbi_probe(&self) -> usize {
+        sbi_spec::base::UNAVAILABLE_EXTENSION.wrapping_add(1)
+    }
+}
+
+impl<T: Mpxy> Mpxy for &T {
+    #[inline]
+    fn get_shmem_size(&self) -> SbiRet {
+        T::get_shmem_size(self)
+    }
+    #[inline]
+    fn set_shmem(&self, shmem: SharedPtr<u8>, flags: usize) -> SbiRet {
+        T::set_shmem(self, shmem, flags)
+    }
+    #[inline]
+    fn get_channel_ids(&self, start_index: u32) -> SbiRet {
+        T::get_channel_ids(self, start_index)
+    }
+    #[inline]
+    fn read_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet {
+        T::read_attributes(self, channel_id, base_attribute_id, attribute_count)
+    }
+    #[inline]
+    fn write_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet {
+        T::write_attributes(self, channel_id, base_attribute_id, attribute_count)
+    }
+    #[inline]
+    fn send_message_with_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet {
+        T::send_message_with_response(self, channel_id, message_id, message_data_len)
+    }
+    #[inline]
+    fn send_message_without_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet {
+        T::send_message_without_response(self, channel_id, message_id, message_data_len)
+    }
+    #[inline]
+    fn get_notification_events(&self, channel_id: u32) -> SbiRet {
+        T::get_notification_events(self, channel_id)
+    }
+}
+
+impl<T: Mpxy> Mpxy for Option<T> {
+    #[inline]
+    fn get_shmem_size(&self) -> SbiRet {
+        self.as_ref()
+            .map_or(SbiRet::not_supported(), |inner| T::get_shmem_size(inner))
+    }
+    #[inline]
+    fn set_shmem(&self, shmem: SharedPtr<u8>, flags: usize) -> SbiRet {
+        self.as_ref().map_or(SbiRet::not_supported(), |inner| {
+            T::set_shmem(inner, shmem, flags)
+        })
+    }
+    #[inline]
+    fn get_channel_ids(&self, start_index: u32) -> SbiRet {
+        self.as_ref().map_or(SbiRet::not_supported(), |inner| {
+            T::get_channel_ids(inner, start_index)
+        })
+    }
+    #[inline]
+    fn read_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet {
+        self.as_ref().map_or(SbiRet::not_supported(), |inner| {
+            T::read_attributes(inner, channel_id, base_attribute_id, attribute_count)
+        })
+    }
+    #[inline]
+    fn write_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet {
+        self.as_ref().map_or(SbiRet::not_supported(), |inner| {
+            T::write_attributes(inner, channel_id, base_attribute_id, attribute_count)
+        })
+    }
+    #[inline]
+    fn send_message_with_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet {
+        self.as_ref().map_or(SbiRet::not_supported(), |inner| {
+            T::send_message_with_response(inner, channel_id, message_id, message_data_len)
+        })
+    }
+    #[inline]
+    fn send_message_without_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet {
+        self.as_ref().map_or(SbiRet::not_supported(), |inner| {
+            T::send_message_without_response(inner, channel_id, message_id, message_data_len)
+        })
+    }
+    #[inline]
+    fn get_notification_events(&self, channel_id: u32) -> SbiRet {
+        self.as_ref().map_or(SbiRet::not_supported(), |inner| {
+            T::get_notification_events(inner, channel_id)
+        })
+    }
+    #[inline]
+    fn _rustsbi_probe(&self) -> usize {
+        match self {
+            Some(_) => sbi_spec::base::UNAVAILABLE_EXTENSION.wrapping_add(1),
+            None => sbi_spec::base::UNAVAILABLE_EXTENSION,
+        }
+    }
+}
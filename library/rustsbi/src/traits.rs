@@ -1,6 +1,6 @@
 #[cfg(feature = "machine")]
 use riscv::register::{marchid, mimpid, mvendorid};
-use spec::binary::{HartMask, Physical, SbiRet, SharedPtr};
+use spec::binary::{HartMask, Physical, SbiRet, SharedPtr, TriggerMask};
 
 /// RustSBI environment call handler.
 pub trait RustSBI {
@@ -118,6 +118,9 @@ pub struct _StandardExtensionProbe {
     pub cppc: usize,
     pub nacl: usize,
     pub sta: usize,
+    pub fwft: usize,
+    pub dbtr: usize,
+    pub mpxy: usize,
     // NOTE: remember to add to `fn probe_extension` in `impl _ExtensionProbe` as well
 }
 
@@ -137,6 +140,9 @@ impl _ExtensionProbe for _StandardExtensionProbe {
             spec::cppc::EID_CPPC => self.cppc,
             spec::nacl::EID_NACL => self.nacl,
             spec::sta::EID_STA => self.sta,
+            spec::fwft::EID_FWFT => self.fwft,
+            spec::dbtr::EID_DBTR => self.dbtr,
+            spec::mpxy::EID_MPXY => self.mpxy,
             _ => spec::base::UNAVAILABLE_EXTENSION,
         }
     }
@@ -393,6 +399,98 @@ pub fn _rustsbi_sta<T: crate::Sta>(sta: &T, param: [usize; 6], function: usize)
     }
 }
 
+#[doc(hidden)]
+#[inline(always)]
+pub fn _rustsbi_fwft<T: crate::Fwft>(fwft: &T, param: [usize; 6], function: usize) -> SbiRet {
+    let [param0, param1, param2] = [param[0], param[1], param[2]];
+    match function {
+        spec::fwft::SET => match u32::try_from(param0) {
+            Ok(feature) => fwft.set(feature, param1, param2),
+            _ => SbiRet::invalid_param(),
+        },
+        spec::fwft::GET => match u32::try_from(param0) {
+            Ok(feature) => fwft.get(feature),
+            _ => SbiRet::invalid_param(),
+        },
+        _ => SbiRet::not_supported(),
+    }
+}
+
+#[doc(hidden)]
+#[inline(always)]
+pub fn _rustsbi_dbtr<T: crate::Dbtr>(dbtr: &T, param: [usize; 6], function: usize) -> SbiRet {
+    let [param0, param1, param2] = [param[0], param[1], param[2]];
+    match function {
+        spec::dbtr::NUM_TRIGGERS => dbtr.num_triggers(param0),
+        spec::dbtr::SET_SHMEM => dbtr.set_shmem(SharedPtr::new(param0, param1), param2),
+        spec::dbtr::READ_TRIGGERS => dbtr.read_triggers(param0, param1),
+        spec::dbtr::INSTALL_TRIGGERS => dbtr.install_triggers(param0),
+        spec::dbtr::UPDATE_TRIGGERS => dbtr.update_triggers(param0),
+        spec::dbtr::UNINSTALL_TRIGGERS => {
+            dbtr.uninstall_triggers(TriggerMask::from_mask_base(param1, param0))
+        }
+        spec::dbtr::ENABLE_TRIGGERS => {
+            dbtr.enable_triggers(TriggerMask::from_mask_base(param1, param0))
+        }
+        spec::dbtr::DISABLE_TRIGGERS => {
+            dbtr.disable_triggers(TriggerMask::from_mask_base(param1, param0))
+        }
+        _ => SbiRet::not_supported(),
+    }
+}
+
+#[doc(hidden)]
+#[inline(always)]
+pub fn _rustsbi_mpxy<T: crate::Mpxy>(mpxy: &T, param: [usize; 6], function: usize) -> SbiRet {
+    let [param0, param1, param2] = [param[0], param[1], param[2]];
+    match function {
+        spec::mpxy::GET_SHMEM_SIZE => mpxy.get_shmem_size(),
+        spec::mpxy::SET_SHMEM => mpxy.set_shmem(SharedPtr::new(param0, param1), param2),
+        spec::mpxy::GET_CHANNEL_IDS => match u32::try_from(param0) {
+            Ok(start_index) => mpxy.get_channel_ids(start_index),
+            Err(_) => SbiRet::invalid_param(),
+        },
+        spec::mpxy::READ_ATTRIBUTE => match mpxy_attribute_params(param0, param1, param2) {
+            Some((channel_id, base, count)) => mpxy.read_attributes(channel_id, base, count),
+            None => SbiRet::invalid_param(),
+        },
+        spec::mpxy::WRITE_ATTRIBUTE => match mpxy_attribute_params(param0, param1, param2) {
+            Some((channel_id, base, count)) => mpxy.write_attributes(channel_id, base, count),
+            None => SbiRet::invalid_param(),
+        },
+        spec::mpxy::SEND_MESSAGE_WITH_RESPONSE => {
+            match (u32::try_from(param0), u32::try_from(param1)) {
+                (Ok(channel_id), Ok(message_id)) => {
+                    mpxy.send_message_with_response(channel_id, message_id, param2)
+                }
+                _ => SbiRet::invalid_param(),
+            }
+        }
+        spec::mpxy::SEND_MESSAGE_WITHOUT_RESPONSE => {
+            match (u32::try_from(param0), u32::try_from(param1)) {
+                (Ok(channel_id), Ok(message_id)) => {
+                    mpxy.send_message_without_response(channel_id, message_id, param2)
+                }
+                _ => SbiRet::invalid_param(),
+            }
+        }
+        spec::mpxy::GET_NOTIFICATION_EVENTS => match u32::try_from(param0) {
+            Ok(channel_id) => mpxy.get_notification_events(channel_id),
+            Err(_) => SbiRet::invalid_param(),
+        },
+        _ => SbiRet::not_supported(),
+    }
+}
+
+#[inline]
+fn mpxy_attribute_params(channel_id: usize, base: usize, count: usize) -> Option<(u32, u32, u32)> {
+    Some((
+        u32::try_from(channel_id).ok()?,
+        u32::try_from(base).ok()?,
+        u32::try_from(count).ok()?,
+    ))
+}
+
 #[cfg(target_pointer_width = "32")]
 #[inline]
 const fn concat_u32(h: usize, l: usize) -> u64 {
@@ -464,3 +562,21 @@ pub fn _rustsbi_nacl_probe<T: crate::Nacl>(nacl: &T) -> usize {
 pub fn _rustsbi_sta_probe<T: crate::Sta>(sta: &T) -> usize {
     sta._rustsbi_probe()
 }
+
+#[doc(hidden)]
+#[inline(always)]
+pub fn _rustsbi_fwft_probe<T: crate::Fwft>(fwft: &T) -> usize {
+    fwft._rustsbi_probe()
+}
+
+#[doc(hidden)]
+#[inline(always)]
+pub fn _rustsbi_dbtr_probe<T: crate::Dbtr>(dbtr: &T) -> usize {
+    dbtr._rustsbi_probe()
+}
+
+#[doc(hidden)]
+#[inline(always)]
+pub fn _rustsbi_mpxy_probe<T: crate::Mpxy>(mpxy: &T) -> usize {
+    mpxy._rustsbi_probe()
+}
@@ -1,7 +1,7 @@
 use core::cell::RefCell;
 use rustsbi::RustSBI;
 use sbi_spec::{
-    binary::{HartMask, Physical, SbiRet, SharedPtr},
+    binary::{HartMask, Physical, SbiRet, SharedPtr, TriggerMask},
     nacl::shmem_size::NATIVE,
     pmu::shmem_size::SIZE,
 };
@@ -19,6 +19,9 @@ struct FullyImplemented {
     sta: DummySta,
     susp: DummySusp,
     timer: DummyTimer,
+    fwft: DummyFwft,
+    dbtr: DummyDbtr,
+    mpxy: DummyMpxy,
     info: DummyEnvInfo,
 }
 
@@ -35,6 +38,9 @@ struct AlternateName {
     sta: DummySta,
     susp: DummySusp,
     time: DummyTimer,
+    fwft: DummyFwft,
+    dbtr: DummyDbtr,
+    mpxy: DummyMpxy,
     info: DummyEnvInfo,
 }
 
@@ -51,6 +57,9 @@ struct TupleStruct(
     #[rustsbi(sta)] DummySta,
     #[rustsbi(susp)] DummySusp,
     #[rustsbi(time)] DummyTimer,
+    #[rustsbi(fwft)] DummyFwft,
+    #[rustsbi(dbtr)] DummyDbtr,
+    #[rustsbi(mpxy)] DummyMpxy,
     #[rustsbi(info)] DummyEnvInfo,
 );
 
@@ -72,6 +81,9 @@ fn rustsbi_impl_id() {
         sta: DummySta,
         susp: DummySusp,
         timer: DummyTimer(RefCell::new(0)),
+        fwft: DummyFwft,
+        dbtr: DummyDbtr,
+        mpxy: DummyMpxy,
         info: DummyEnvInfo,
     };
     assert_eq!(sbi.handle_ecall(0x10, 0x1, [0; 6]).value, 4);
@@ -87,6 +99,9 @@ fn rustsbi_impl_id() {
         sta: DummySta,
         susp: DummySusp,
         time: DummyTimer(RefCell::new(0)),
+        fwft: DummyFwft,
+        dbtr: DummyDbtr,
+        mpxy: DummyMpxy,
         info: DummyEnvInfo,
     };
     assert_eq!(sbi.handle_ecall(0x10, 0x1, [0; 6]).value, 4);
@@ -102,6 +117,9 @@ fn rustsbi_impl_id() {
         DummySta,
         DummySusp,
         DummyTimer(RefCell::new(0)),
+        DummyFwft,
+        DummyDbtr,
+        DummyMpxy,
         DummyEnvInfo,
     );
     assert_eq!(sbi.handle_ecall(0x10, 0x1, [0; 6]).value, 4);
@@ -128,6 +146,9 @@ fn generated_extensions() {
         sta: DummySta,
         susp: DummySusp,
         timer: DummyTimer(RefCell::new(0)),
+        fwft: DummyFwft,
+        dbtr: DummyDbtr,
+        mpxy: DummyMpxy,
         info: DummyEnvInfo,
     };
     assert_eq!(
@@ -139,7 +160,7 @@ fn generated_extensions() {
     // All SBI 2.0 extensions, including Base, are supported
     for eid in [
         0x10, 0x54494d45, 0x735049, 0x52464e43, 0x48534d, 0x53525354, 0x504d55, 0x4442434e,
-        0x53555350, 0x4e41434c, 0x535441, 0x43505043,
+        0x53555350, 0x4e41434c, 0x535441, 0x43505043, 0x46574654, 0x44425452, 0x4d505859,
     ] {
         assert_eq!(
             sbi.handle_ecall(0x10, 3, [eid, 0, 0, 0, 0, 0]),
@@ -194,6 +215,24 @@ fn generated_extensions() {
     assert_eq!(sbi.handle_ecall(0x10, 4, [0; 6]), SbiRet::success(37));
     assert_eq!(sbi.handle_ecall(0x10, 5, [0; 6]), SbiRet::success(38));
     assert_eq!(sbi.handle_ecall(0x10, 6, [0; 6]), SbiRet::success(39));
+    assert_eq!(sbi.handle_ecall(0x46574654, 0, [0; 6]), SbiRet::success(40));
+    assert_eq!(sbi.handle_ecall(0x46574654, 1, [0; 6]), SbiRet::success(41));
+    assert_eq!(sbi.handle_ecall(0x44425452, 0, [0; 6]), SbiRet::success(42));
+    assert_eq!(sbi.handle_ecall(0x44425452, 1, [0; 6]), SbiRet::success(43));
+    assert_eq!(sbi.handle_ecall(0x44425452, 2, [0; 6]), SbiRet::success(44));
+    assert_eq!(sbi.handle_ecall(0x44425452, 3, [0; 6]), SbiRet::success(45));
+    assert_eq!(sbi.handle_ecall(0x44425452, 4, [0; 6]), SbiRet::success(46));
+    assert_eq!(sbi.handle_ecall(0x44425452, 5, [0; 6]), SbiRet::success(47));
+    assert_eq!(sbi.handle_ecall(0x44425452, 6, [0; 6]), SbiRet::success(48));
+    assert_eq!(sbi.handle_ecall(0x44425452, 7, [0; 6]), SbiRet::success(49));
+    assert_eq!(sbi.handle_ecall(0x4D505859, 0, [0; 6]), SbiRet::success(50));
+    assert_eq!(sbi.handle_ecall(0x4D505859, 1, [0; 6]), SbiRet::success(51));
+    assert_eq!(sbi.handle_ecall(0x4D505859, 2, [0; 6]), SbiRet::success(52));
+    assert_eq!(sbi.handle_ecall(0x4D505859, 3, [0; 6]), SbiRet::success(53));
+    assert_eq!(sbi.handle_ecall(0x4D505859, 4, [0; 6]), SbiRet::success(54));
+    assert_eq!(sbi.handle_ecall(0x4D505859, 5, [0; 6]), SbiRet::success(55));
+    assert_eq!(sbi.handle_ecall(0x4D505859, 6, [0; 6]), SbiRet::success(56));
+    assert_eq!(sbi.handle_ecall(0x4D505859, 7, [0; 6]), SbiRet::success(57));
 }
 
 struct DummyConsole;
@@ -384,6 +423,90 @@ impl rustsbi::Timer for DummyTimer {
     }
 }
 
+struct DummyFwft;
+
+impl rustsbi::Fwft for DummyFwft {
+    fn set(&self, _: u32, _: usize, _: usize) -> SbiRet {
+        SbiRet::success(40)
+    }
+
+    fn get(&self, _: u32) -> SbiRet {
+        SbiRet::success(41)
+    }
+}
+
+struct DummyDbtr;
+
+impl rustsbi::Dbtr for DummyDbtr {
+    fn num_triggers(&self, _: usize) -> SbiRet {
+        SbiRet::success(42)
+    }
+
+    fn set_shmem(&self, _: SharedPtr<u8>, _: usize) -> SbiRet {
+        SbiRet::success(43)
+    }
+
+    fn read_triggers(&self, _: usize, _: usize) -> SbiRet {
+        SbiRet::success(44)
+    }
+
+    fn install_triggers(&self, _: usize) -> SbiRet {
+        SbiRet::success(45)
+    }
+
+    fn update_triggers(&self, _: usize) -> SbiRet {
+        SbiRet::success(46)
+    }
+
+    fn uninstall_triggers(&self, _: TriggerMask) -> SbiRet {
+        SbiRet::success(47)
+    }
+
+    fn enable_triggers(&self, _: TriggerMask) -> SbiRet {
+        SbiRet::success(48)
+    }
+
+    fn disable_triggers(&self, _: TriggerMask) -> SbiRet {
+        SbiRet::success(49)
+    }
+}
+
+struct DummyMpxy;
+
+impl rustsbi::Mpxy for DummyMpxy {
+    fn get_shmem_size(&self) -> SbiRet {
+        SbiRet::success(50)
+    }
+
+    fn set_shmem(&self, _: SharedPtr<u8>, _: usize) -> SbiRet {
+        SbiRet::success(51)
+    }
+
+    fn get_channel_ids(&self, _: u32) -> SbiRet {
+        SbiRet::success(52)
+    }
+
+    fn read_attributes(&self, _: u32, _: u32, _: u32) -> SbiRet {
+        SbiRet::success(53)
+    }
+
+    fn write_attributes(&self, _: u32, _: u32, _: u32) -> SbiRet {
+        SbiRet::success(54)
+    }
+
+    fn send_message_with_response(&self, _: u32, _: u32, _: usize) -> SbiRet {
+        SbiRet::success(55)
+    }
+
+    fn send_message_without_response(&self, _: u32, _: u32, _: usize) -> SbiRet {
+        SbiRet::success(56)
+    }
+
+    fn get_notification_events(&self, _: u32) -> SbiRet {
+        SbiRet::success(57)
+    }
+}
+
 struct DummyEnvInfo;
 
 impl rustsbi::EnvInfo for DummyEnvInfo {
@@ -0,0 +1,100 @@
+//! Conformance checks shared by extension test suites.
+
+use core::marker::PhantomData;
+use sbi::SbiRet;
+use sbi_spec::binary::Error;
+
+/// Test cases for an extension verified by a list of conformance checks.
+///
+/// Each extension defines its own `Check` type naming every check it performs.
+#[derive(Clone, Debug)]
+pub enum Case<C> {
+    /// Can't proceed test for the extension does not exist.
+    NotExist,
+    /// Test begin.
+    Begin,
+    /// Test process for a check has passed.
+    Passed(C),
+    /// Test process for a check has been skipped, as the function is optional and not supported.
+    Skipped(C),
+    /// Test failed for a check returned an unexpected value.
+    Failed(C, SbiRet),
+    /// All checks on the extension has passed or been skipped.
+    Pass,
+}
+
+/// Reports results of conformance checks, remembering whether any of them has failed.
+pub(crate) struct Checks<C, F: FnMut(Case<C>)> {
+    f: F,
+    failed: bool,
+    _check: PhantomData<fn(C)>,
+}
+
+impl<C, F: FnMut(Case<C>)> Checks<C, F> {
+    /// Begins the checks on an extension.
+    pub fn begin(mut f: F) -> Self {
+        f(Case::Begin);
+        Self {
+            f,
+            failed: false,
+            _check: PhantomData,
+        }
+    }
+
+    /// Reports `check` as passed.
+    pub fn pass(&mut self, check: C) {
+        (self.f)(Case::Passed(check));
+    }
+
+    /// Reports `check` as skipped.
+    pub fn skip(&mut self, check: C) {
+        (self.f)(Case::Skipped(check));
+    }
+
+    /// Reports `check` as failed with the unexpected `ret`.
+    pub fn fail(&mut self, check: C, ret: SbiRet) {
+        self.failed = true;
+        (self.f)(Case::Failed(check, ret));
+    }
+
+    /// Expects `ret` to be successful, returns its value if so.
+    pub fn ok(&mut self, check: C, ret: SbiRet) -> Option<usize> {
+        match ret.into_result() {
+            Ok(value) => {
+                self.pass(check);
+                Some(value)
+            }
+            Err(_) => {
+                self.fail(check, ret);
+                None
+            }
+        }
+    }
+
+    /// Expects `ret` to be successful, or skips `check` if the function is not supported.
+    pub fn optional(&mut self, check: C, ret: SbiRet) -> Option<usize> {
+        match ret.into_result() {
+            Err(Error::NotSupported) => {
+                self.skip(check);
+                None
+            }
+            _ => self.ok(check, ret),
+        }
+    }
+
+    /// Expects `ret` to fail with `error`.
+    pub fn error(&mut self, check: C, ret: SbiRet, error: Error) {
+        if ret.into_result() == Err(error) {
+            self.pass(check);
+        } else {
+            self.fail(check, ret);
+        }
+    }
+
+    /// Ends the checks, reports the extension as passed if none of the checks failed.
+    pub fn end(mut self) {
+        if !self.failed {
+            (self.f)(Case::Pass);
+        }
+    }
+}
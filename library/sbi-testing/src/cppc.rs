@@ -0,0 +1,75 @@
+//! Collaborative processor performance control extension test suite.
+
+use crate::checks::Checks;
+use sbi_spec::binary::Error;
+
+/// Collaborative processor performance control extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on collaborative processor performance control extension.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for probing the given register.
+    Probe(u32),
+    /// Test process for reading the given implemented register.
+    ///
+    /// Write-only registers are allowed to deny the read.
+    Read(u32),
+    /// Test process for rejecting to read the given unimplemented register.
+    ReadUnimplemented(u32),
+    /// Test process for rejecting to write the read-only `HighestPerformance` register.
+    WriteReadOnly,
+    /// Test process for rejecting to probe the given reserved register.
+    ProbeReserved(u32),
+    /// Test process for rejecting to read the given reserved register.
+    ReadReserved(u32),
+    /// Test process for rejecting to write the given reserved register.
+    WriteReserved(u32),
+}
+
+const HIGHEST_PERFORMANCE: u32 = 0x00;
+const NOMINAL_FREQUENCY: u32 = 0x14;
+const TRANSITION_LATENCY: u32 = 0x8000_0000;
+const RESERVED: [u32; 2] = [0x15, 0x8000_0001];
+
+/// Test collaborative processor performance control extension.
+///
+/// Only read-only and reserved registers are written, so the performance state is never changed.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Cppc).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    let mut checks = Checks::begin(f);
+    for reg in (HIGHEST_PERFORMANCE..=NOMINAL_FREQUENCY).chain([TRANSITION_LATENCY]) {
+        let ret = sbi::cppc_probe(reg);
+        match checks.ok(Check::Probe(reg), ret) {
+            Some(0) => {
+                let ret = sbi::cppc_read(reg);
+                checks.error(Check::ReadUnimplemented(reg), ret, Error::NotSupported);
+            }
+            Some(_) => {
+                let ret = sbi::cppc_read(reg);
+                match ret.into_result() {
+                    Ok(_) | Err(Error::Denied) => checks.pass(Check::Read(reg)),
+                    Err(_) => checks.fail(Check::Read(reg), ret),
+                }
+                if reg == HIGHEST_PERFORMANCE {
+                    let ret = sbi::cppc_write(reg, 0);
+                    checks.error(Check::WriteReadOnly, ret, Error::Denied);
+                }
+            }
+            None => {}
+        }
+    }
+    for reg in RESERVED {
+        let ret = sbi::cppc_probe(reg);
+        checks.error(Check::ProbeReserved(reg), ret, Error::InvalidParam);
+        let ret = sbi::cppc_read(reg);
+        checks.error(Check::ReadReserved(reg), ret, Error::InvalidParam);
+        let ret = sbi::cppc_write(reg, 0);
+        checks.error(Check::WriteReserved(reg), ret, Error::InvalidParam);
+    }
+    checks.end();
+}
@@ -0,0 +1,97 @@
+//! Debug triggers extension test suite.
+
+use crate::checks::Checks;
+use sbi::SharedPtr;
+use sbi_spec::binary::{Error, TriggerMask};
+
+/// Debug triggers extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on debug triggers extension.
+///
+/// Triggers are never installed, so no breakpoint is hit during the checks.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for getting the number of debug triggers on the current hart.
+    NumTriggers(usize),
+    /// Test process for disabling shared memory.
+    DisableShmem,
+    /// Test process for rejecting to read triggers without shared memory.
+    ReadNoShmem,
+    /// Test process for rejecting to install triggers without shared memory.
+    InstallNoShmem,
+    /// Test process for rejecting shared memory with reserved flags.
+    SetShmemFlags,
+    /// Test process for rejecting misaligned shared memory.
+    SetShmemMisaligned,
+    /// Test process for setting shared memory.
+    ///
+    /// Skipped if the debug triggers do not fit into the shared memory of this test suite.
+    SetShmem,
+    /// Test process for reading the first trigger.
+    Read,
+    /// Test process for rejecting to read triggers out of range.
+    ReadBadRange,
+    /// Test process for rejecting to uninstall a trigger that does not exist.
+    UninstallInvalid,
+    /// Test process for rejecting to enable a trigger that does not exist.
+    EnableInvalid,
+    /// Test process for rejecting to disable a trigger that does not exist.
+    DisableInvalid,
+}
+
+/// Maximum number of triggers the shared memory holds, each takes `tstate` and `tdata1` to `tdata3`.
+const SHMEM_TRIGGERS: usize = 32;
+
+/// Test debug triggers extension.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Dbtr).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    static mut SHMEM: [[usize; 4]; SHMEM_TRIGGERS] = [[0; 4]; SHMEM_TRIGGERS];
+
+    let mut checks = Checks::begin(f);
+    let count = sbi::debug_num_triggers(0);
+    checks.pass(Check::NumTriggers(count));
+
+    let disabled = SharedPtr::new(usize::MAX, usize::MAX);
+    let ret = sbi::debug_set_shmem(disabled, 0);
+    checks.ok(Check::DisableShmem, ret);
+    let ret = sbi::debug_read_triggers(0, 1);
+    checks.error(Check::ReadNoShmem, ret, Error::NoShmem);
+    let ret = sbi::debug_install_triggers(1);
+    checks.error(Check::InstallNoShmem, ret, Error::NoShmem);
+
+    let shmem = &raw mut SHMEM as usize;
+    let ret = sbi::debug_set_shmem(SharedPtr::new(shmem, 0), 1);
+    checks.error(Check::SetShmemFlags, ret, Error::InvalidParam);
+    let ret = sbi::debug_set_shmem(SharedPtr::new(shmem + 1, 0), 0);
+    checks.error(Check::SetShmemMisaligned, ret, Error::InvalidParam);
+    if count > SHMEM_TRIGGERS {
+        checks.skip(Check::SetShmem);
+    } else {
+        let ret = sbi::debug_set_shmem(SharedPtr::new(shmem, 0), 0);
+        if checks.ok(Check::SetShmem, ret).is_some() {
+            // The range is invalid if `trig_idx_base + trig_count` reaches the number of triggers.
+            if count > 1 {
+                let ret = sbi::debug_read_triggers(0, 1);
+                checks.ok(Check::Read, ret);
+            }
+            let ret = sbi::debug_read_triggers(count, 1);
+            checks.error(Check::ReadBadRange, ret, Error::BadRange);
+            let ret = sbi::debug_set_shmem(disabled, 0);
+            checks.ok(Check::DisableShmem, ret);
+        }
+    }
+
+    let invalid = TriggerMask::from_mask_base(1, count);
+    let ret = sbi::debug_uninstall_triggers(invalid);
+    checks.error(Check::UninstallInvalid, ret, Error::InvalidParam);
+    let ret = sbi::debug_enable_triggers(invalid);
+    checks.error(Check::EnableInvalid, ret, Error::InvalidParam);
+    let ret = sbi::debug_disable_triggers(invalid);
+    checks.error(Check::DisableInvalid, ret, Error::InvalidParam);
+    checks.end();
+}
@@ -0,0 +1,60 @@
+//! Firmware features extension test suite.
+
+use crate::checks::Checks;
+use sbi_spec::{
+    binary::Error,
+    fwft::{
+        feature_type::{MISALIGNED_EXC_DELEG, POINTER_MASKING_PMLEN},
+        flags::SetFlags,
+    },
+};
+
+/// Firmware features extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on firmware features extension.
+///
+/// Features are never locked, and are only set to their current values.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for getting the given feature.
+    Get(u32),
+    /// Test process for setting the given feature to its current value.
+    SetCurrent(u32),
+    /// Test process for rejecting to set a feature with reserved flags.
+    SetReservedFlags,
+    /// Test process for rejecting to get the given reserved feature.
+    GetReserved(u32),
+    /// Test process for rejecting to set the given reserved feature.
+    SetReserved(u32),
+}
+
+const RESERVED: [u32; 2] = [POINTER_MASKING_PMLEN as u32 + 1, 0x3fff_ffff];
+
+/// Test firmware features extension.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Fwft).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    let mut checks = Checks::begin(f);
+    for feature in MISALIGNED_EXC_DELEG as u32..=POINTER_MASKING_PMLEN as u32 {
+        let ret = sbi::fwft_get(feature);
+        if let Some(value) = checks.optional(Check::Get(feature), ret) {
+            let ret = sbi::fwft_set(feature, value, 0);
+            checks.ok(Check::SetCurrent(feature), ret);
+            if feature == MISALIGNED_EXC_DELEG as u32 {
+                let ret = sbi::fwft_set(feature, value, !SetFlags::all().bits());
+                checks.error(Check::SetReservedFlags, ret, Error::InvalidParam);
+            }
+        }
+    }
+    for feature in RESERVED {
+        let ret = sbi::fwft_get(feature);
+        checks.error(Check::GetReserved(feature), ret, Error::Denied);
+        let ret = sbi::fwft_set(feature, 0, 0);
+        checks.error(Check::SetReserved(feature), ret, Error::Denied);
+    }
+    checks.end();
+}
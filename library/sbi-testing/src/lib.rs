@@ -13,6 +13,9 @@ mod log_test;
 #[cfg(feature = "log")]
 pub use log_test::Testing;
 
+mod checks;
+pub use checks::Case as CheckCase;
+
 // §4
 mod base;
 pub use base::{Case as BaseCase, Extensions, test as test_base};
@@ -23,14 +26,41 @@ pub use time::{Case as TimerCase, test as test_timer};
 mod spi;
 pub use spi::{Case as IpiCase, test as test_ipi};
 // §8
-// pub mod rfnc;
+mod rfnc;
+pub use rfnc::{Case as RfenceCase, Check as RfenceCheck, Fence, test as test_rfence};
 // §9
 mod hsm;
 pub use hsm::{Case as HsmCase, test as test_hsm};
 // §10
-// pub mod srst;
+mod srst;
+pub use srst::{Case as ResetCase, Check as ResetCheck, test as test_reset};
 // §11
-// pub mod pmu;
+mod pmu;
+pub use pmu::{Case as PmuCase, Check as PmuCheck, test as test_pmu};
 // §12
 mod dbcn;
 pub use dbcn::{Case as DbcnCase, test as test_dbcn};
+// §13
+mod susp;
+pub use susp::{Case as SuspendCase, Check as SuspendCheck, test as test_suspend};
+// §14
+mod cppc;
+pub use cppc::{Case as CppcCase, Check as CppcCheck, test as test_cppc};
+// §15
+mod nacl;
+pub use nacl::{Case as NaclCase, Check as NaclCheck, test as test_nacl};
+// §16
+mod sta;
+pub use sta::{Case as StaCase, Check as StaCheck, test as test_sta};
+// §17
+mod sse;
+pub use sse::{Case as SseCase, Check as SseCheck, test as test_sse};
+// §18
+mod fwft;
+pub use fwft::{Case as FwftCase, Check as FwftCheck, test as test_fwft};
+// §19
+mod dbtr;
+pub use dbtr::{Case as DbtrCase, Check as DbtrCheck, test as test_dbtr};
+// §20
+mod mpxy;
+pub use mpxy::{Case as MpxyCase, Check as MpxyCheck, test as test_mpxy};
@@ -1,13 +1,19 @@
-﻿use crate::{base, dbcn, hsm, spi, time};
+﻿use crate::{
+    CheckCase, base, cppc, dbcn, dbtr, fwft, hsm, mpxy, nacl, pmu, rfnc, spi, srst, sse, sta, susp,
+    time,
+};
+use core::fmt::Debug;
 use log::*;
 
 /// Automatic SBI testing with logging enabled.
 pub struct Testing {
     /// The hart ID to test most of single core extensions.
     pub hartid: usize,
-    /// A list of harts to test Hart State Monitor extension.
+    /// A list of harts to test Hart State Monitor and Remote Fence extensions.
+    ///
+    /// It should cover all harts on the platform to test invalid hart masks on Remote Fence extension.
     pub hart_mask: usize,
-    /// Base of hart list to test Hart State Monitor extension.
+    /// Base of hart list to test Hart State Monitor and Remote Fence extensions.
     pub hart_mask_base: usize,
     /// Delay value to test Timer programmer extension.
     pub delay: u64,
@@ -162,6 +168,59 @@ impl Testing {
                 }
             }
         });
-        result
+        let mut summary = Summary::default();
+        rfnc::test(self.hart_mask, self.hart_mask_base, |case| {
+            summary.log("RFNC", case)
+        });
+        pmu::test(self.hartid, |case| summary.log("PMU", case));
+        srst::test(|case| summary.log("SRST", case));
+        susp::test(|case| summary.log("SUSP", case));
+        cppc::test(|case| summary.log("CPPC", case));
+        nacl::test(|case| summary.log("NACL", case));
+        sta::test(|case| summary.log("STA", case));
+        sse::test(|case| summary.log("SSE", case));
+        fwft::test(|case| summary.log("FWFT", case));
+        dbtr::test(|case| summary.log("DBTR", case));
+        mpxy::test(|case| summary.log("MPXY", case));
+        info!(
+            target: TARGET,
+            "conformance checks: {} passed, {} failed, {} skipped",
+            summary.passed, summary.failed, summary.skipped
+        );
+        result && summary.failed == 0
+    }
+}
+
+/// Numbers of conformance checks by result.
+#[derive(Default)]
+struct Summary {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+impl Summary {
+    /// Logs a conformance check case on extension `name`.
+    ///
+    /// Extensions tested by conformance checks are optional, so missing ones are only warned.
+    fn log<C: Debug>(&mut self, name: &str, case: CheckCase<C>) {
+        use CheckCase::*;
+        match case {
+            NotExist => warn!(target: TARGET, "Sbi `{name}` not exist"),
+            Begin => info!(target: TARGET, "Testing `{name}`"),
+            Pass => info!(target: TARGET, "Sbi `{name}` test pass"),
+            Passed(check) => {
+                info!(target: TARGET, "{name} {check:?} passed");
+                self.passed += 1;
+            }
+            Skipped(check) => {
+                warn!(target: TARGET, "{name} {check:?} skipped: not supported");
+                self.skipped += 1;
+            }
+            Failed(check, ret) => {
+                error!(target: TARGET, "{name} {check:?} failed: {ret:?}");
+                self.failed += 1;
+            }
+        }
     }
 }
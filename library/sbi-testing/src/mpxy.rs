@@ -0,0 +1,185 @@
+//! Message proxy extension test suite.
+
+use crate::checks::Checks;
+use sbi::{SbiRet, SharedPtr};
+use sbi_spec::binary::Error;
+
+/// Message proxy extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on message proxy extension.
+///
+/// Messages are only sent on loopback channels, as messages of real protocols have side effects.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for shared memory size being a non-zero multiple of 4096 bytes.
+    ShmemSize,
+    /// Test process for disabling shared memory.
+    DisableShmem,
+    /// Test process for rejecting to get channel IDs without shared memory.
+    ChannelIdsNoShmem,
+    /// Test process for rejecting shared memory with reserved flags.
+    SetShmemFlags,
+    /// Test process for rejecting shared memory which is not 4096-byte aligned.
+    SetShmemMisaligned,
+    /// Test process for setting shared memory.
+    SetShmem,
+    /// Test process for getting channel IDs.
+    ChannelIds,
+    /// Test process for reading standard attributes of the channel with given ID.
+    ReadAttributes(u32),
+    /// Test process for rejecting to read no attributes of the channel with given ID.
+    ReadAttributesEmpty(u32),
+    /// Test process for rejecting to read attributes of a channel that does not exist.
+    ReadAttributesInvalid,
+    /// Test process for a message echoed by the loopback channel with given ID.
+    SendWithResponse(u32),
+    /// Test process for sending a message without response to the loopback channel with given ID.
+    SendWithoutResponse(u32),
+    /// Test process for the message coming back as a notification event of the loopback channel.
+    NotificationEvents(u32),
+}
+
+/// Message protocol ID of the loopback channel of RustSBI Prototyper.
+const LOOPBACK_PROTOCOL_ID: u32 = 0x8000_0000;
+/// Number of standard message channel attributes.
+const STD_ATTRIBUTES: u32 = 0xc;
+/// Index of `MSG_DATA_MAX_LEN` in standard attributes.
+const MSG_DATA_MAX_LEN: usize = 0x2;
+/// Index of `CHANNEL_CAPABILITY` in standard attributes.
+const CHANNEL_CAPABILITY: usize = 0x5;
+/// Capability bits of sending messages with and without response, and getting notifications.
+const LOOPBACK_CAPABILITY: u32 = (1 << 3) | (1 << 4) | (1 << 5);
+const SHMEM_SIZE: usize = 4096;
+const MESSAGE: [u8; 8] = *b"rustsbi\0";
+
+#[repr(C, align(4096))]
+struct Shmem([u8; SHMEM_SIZE]);
+
+/// Test message proxy extension.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Mpxy).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    static mut SHMEM: Shmem = Shmem([0; SHMEM_SIZE]);
+
+    let mut checks = Checks::begin(f);
+    let size = sbi::mpxy_get_shmem_size();
+    if size != 0 && size.is_multiple_of(SHMEM_SIZE) {
+        checks.pass(Check::ShmemSize);
+    } else {
+        checks.fail(Check::ShmemSize, SbiRet::success(size));
+    }
+
+    let disabled = SharedPtr::new(usize::MAX, usize::MAX);
+    let ret = sbi::mpxy_set_shmem(disabled, 0);
+    checks.ok(Check::DisableShmem, ret);
+    let ret = sbi::mpxy_get_channel_ids(0);
+    checks.error(Check::ChannelIdsNoShmem, ret, Error::NoShmem);
+
+    let shmem = &raw mut SHMEM as usize;
+    let ret = sbi::mpxy_set_shmem(SharedPtr::new(shmem, 0), 0b10);
+    checks.error(Check::SetShmemFlags, ret, Error::InvalidParam);
+    let ret = sbi::mpxy_set_shmem(SharedPtr::new(shmem + 8, 0), 0);
+    checks.error(Check::SetShmemMisaligned, ret, Error::InvalidParam);
+    // The shared memory here is too small for implementations with larger ones.
+    if size != SHMEM_SIZE {
+        checks.skip(Check::SetShmem);
+        checks.end();
+        return;
+    }
+    let ret = sbi::mpxy_set_shmem(SharedPtr::new(shmem, 0), 0);
+    if checks.ok(Check::SetShmem, ret).is_none() {
+        checks.end();
+        return;
+    }
+
+    let ret = sbi::mpxy_get_channel_ids(0);
+    let mut channels = [0u32; 16];
+    let mut count = 0;
+    if checks.ok(Check::ChannelIds, ret).is_some() {
+        // Channel IDs follow the `REMAINING` and `RETURNED` fields.
+        count = (read_u32(shmem, 1) as usize).min(channels.len());
+        for (i, id) in channels[..count].iter_mut().enumerate() {
+            *id = read_u32(shmem, 2 + i);
+        }
+    }
+    for &id in &channels[..count] {
+        test_channel(&mut checks, shmem, id);
+    }
+    let invalid = channels[..count].iter().max().map_or(0, |id| id + 1);
+    let ret = sbi::mpxy_read_attributes(invalid, 0, STD_ATTRIBUTES);
+    checks.error(Check::ReadAttributesInvalid, ret, Error::NotSupported);
+
+    let ret = sbi::mpxy_set_shmem(disabled, 0);
+    checks.ok(Check::DisableShmem, ret);
+    checks.end();
+}
+
+fn test_channel<F: FnMut(Case)>(checks: &mut Checks<Check, F>, shmem: usize, id: u32) {
+    let ret = sbi::mpxy_read_attributes(id, 0, 0);
+    checks.error(Check::ReadAttributesEmpty(id), ret, Error::InvalidParam);
+    let ret = sbi::mpxy_read_attributes(id, 0, STD_ATTRIBUTES);
+    if checks.ok(Check::ReadAttributes(id), ret).is_none() {
+        return;
+    }
+    let protocol_id = read_u32(shmem, 0);
+    let max_len = read_u32(shmem, MSG_DATA_MAX_LEN) as usize;
+    let capability = read_u32(shmem, CHANNEL_CAPABILITY);
+    if protocol_id != LOOPBACK_PROTOCOL_ID
+        || capability & LOOPBACK_CAPABILITY != LOOPBACK_CAPABILITY
+        || max_len < MESSAGE.len()
+    {
+        return;
+    }
+
+    write_message(shmem, 0);
+    let ret = sbi::mpxy_send_message_with_response(id, 1, MESSAGE.len());
+    match ret.ok() {
+        Some(len) if len == MESSAGE.len() && read_message(shmem, 0) => {
+            checks.pass(Check::SendWithResponse(id))
+        }
+        _ => checks.fail(Check::SendWithResponse(id), ret),
+    }
+
+    write_message(shmem, 0);
+    let ret = sbi::mpxy_send_message_without_response(id, 2, MESSAGE.len());
+    checks.ok(Check::SendWithoutResponse(id), ret);
+    let ret = sbi::mpxy_get_notification_events(id);
+    // Events start at offset 0x10, each is message ID, data length and data.
+    let event = 0x10 / 4;
+    let echoed = read_u32(shmem, event) == 2
+        && read_u32(shmem, event + 1) as usize == MESSAGE.len()
+        && read_message(shmem, 0x18);
+    match ret.ok() {
+        Some(len) if len == 8 + MESSAGE.len() && echoed => {
+            checks.pass(Check::NotificationEvents(id))
+        }
+        _ => checks.fail(Check::NotificationEvents(id), ret),
+    }
+}
+
+/// Reads the `index`-th 32-bit word of the shared memory.
+#[inline]
+fn read_u32(shmem: usize, index: usize) -> u32 {
+    u32::from_le(unsafe { (shmem as *const u32).add(index).read_volatile() })
+}
+
+#[inline]
+fn write_message(shmem: usize, offset: usize) {
+    let bytes = (shmem + offset) as *mut u8;
+    for (i, &byte) in MESSAGE.iter().enumerate() {
+        unsafe { bytes.add(i).write_volatile(byte) };
+    }
+}
+
+#[inline]
+fn read_message(shmem: usize, offset: usize) -> bool {
+    let bytes = (shmem + offset) as *const u8;
+    MESSAGE
+        .iter()
+        .enumerate()
+        .all(|(i, &byte)| unsafe { bytes.add(i).read_volatile() } == byte)
+}
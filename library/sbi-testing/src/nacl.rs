@@ -0,0 +1,100 @@
+//! Nested acceleration extension test suite.
+
+use crate::checks::Checks;
+use sbi::SharedPtr;
+use sbi_spec::{
+    binary::Error,
+    nacl::{
+        feature_id::{AUTOSWAP_CSR, SYNC_CSR, SYNC_HFENCE},
+        shmem_size::NATIVE,
+    },
+};
+
+/// Nested acceleration extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on nested acceleration extension.
+///
+/// `sbi_nacl_sync_sret` is never called, as it does not return on success.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for probing the given feature.
+    ProbeFeature(u32),
+    /// Test process for a reserved feature being reported as unavailable.
+    ProbeReserved,
+    /// Test process for disabling shared memory.
+    DisableShmem,
+    /// Test process for rejecting to synchronize CSRs without shared memory.
+    SyncCsrNoShmem,
+    /// Test process for rejecting shared memory with reserved flags.
+    SetShmemFlags,
+    /// Test process for rejecting shared memory which is not 4096-byte aligned.
+    SetShmemMisaligned,
+    /// Test process for setting shared memory.
+    SetShmem,
+    /// Test process for synchronizing all CSRs.
+    SyncCsr,
+    /// Test process for rejecting to synchronize a CSR which is not a hypervisor CSR.
+    SyncCsrInvalid,
+    /// Test process for synchronizing all HFENCE entries.
+    SyncHfence,
+    /// Test process for rejecting to synchronize an HFENCE entry out of range.
+    SyncHfenceInvalid,
+}
+
+#[repr(C, align(4096))]
+struct Shmem([u8; NATIVE]);
+
+/// Test nested acceleration extension.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Nacl).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    static mut SHMEM: Shmem = Shmem([0; NATIVE]);
+
+    let mut checks = Checks::begin(f);
+    let mut features = [false; AUTOSWAP_CSR + 1];
+    for (id, available) in features.iter_mut().enumerate() {
+        let ret = sbi::nacl_probe_feature(id as u32);
+        *available = checks.ok(Check::ProbeFeature(id as u32), ret) == Some(1);
+    }
+    let ret = sbi::nacl_probe_feature(AUTOSWAP_CSR as u32 + 1);
+    match ret.ok() {
+        Some(0) => checks.pass(Check::ProbeReserved),
+        _ => checks.fail(Check::ProbeReserved, ret),
+    }
+
+    let disabled = SharedPtr::new(usize::MAX, usize::MAX);
+    let ret = sbi::nacl_set_shmem(disabled, 0);
+    checks.ok(Check::DisableShmem, ret);
+    if features[SYNC_CSR] {
+        let ret = sbi::nacl_sync_csr(usize::MAX);
+        checks.error(Check::SyncCsrNoShmem, ret, Error::NoShmem);
+    }
+
+    let shmem = &raw mut SHMEM as usize;
+    let ret = sbi::nacl_set_shmem(SharedPtr::new(shmem, 0), 1);
+    checks.error(Check::SetShmemFlags, ret, Error::InvalidParam);
+    let ret = sbi::nacl_set_shmem(SharedPtr::new(shmem + 8, 0), 0);
+    checks.error(Check::SetShmemMisaligned, ret, Error::InvalidParam);
+    let ret = sbi::nacl_set_shmem(SharedPtr::new(shmem, 0), 0);
+    if checks.ok(Check::SetShmem, ret).is_some() {
+        if features[SYNC_CSR] {
+            let ret = sbi::nacl_sync_csr(usize::MAX);
+            checks.ok(Check::SyncCsr, ret);
+            let ret = sbi::nacl_sync_csr(0x1000);
+            checks.error(Check::SyncCsrInvalid, ret, Error::InvalidParam);
+        }
+        if features[SYNC_HFENCE] {
+            let ret = sbi::nacl_sync_hfence(usize::MAX);
+            checks.ok(Check::SyncHfence, ret);
+            let ret = sbi::nacl_sync_hfence(3840 / usize::BITS as usize);
+            checks.error(Check::SyncHfenceInvalid, ret, Error::InvalidParam);
+        }
+        let ret = sbi::nacl_set_shmem(disabled, 0);
+        checks.ok(Check::DisableShmem, ret);
+    }
+    checks.end();
+}
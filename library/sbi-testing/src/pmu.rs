@@ -0,0 +1,151 @@
+//! Performance monitoring unit extension test suite.
+
+use crate::checks::Checks;
+use sbi::{ConfigFlags, CounterMask, HartMask, SharedPtr, StartFlags, StopFlags};
+use sbi_spec::{
+    binary::Error,
+    pmu::{
+        event_type, firmware_event,
+        flags::{CounterCfgFlags, CounterStartFlags, CounterStopFlags},
+    },
+};
+
+/// Performance monitoring unit extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on performance monitoring unit extension.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for getting information of the counter with given index.
+    CounterInfo(usize),
+    /// Test process for rejecting information request of a counter that does not exist.
+    CounterInfoInvalid,
+    /// Test process for rejecting configuration with counter index base out of range.
+    ConfigInvalidBase,
+    /// Test process for rejecting configuration with reserved flags.
+    ConfigReservedFlags,
+    /// Test process for configuring a firmware counter to count `FENCE_I_SENT` events.
+    ConfigFirmware,
+    /// Test process for starting the firmware counter.
+    Start,
+    /// Test process for rejecting to start the firmware counter again.
+    StartStarted,
+    /// Test process for the firmware counter counting a `FENCE_I_SENT` event.
+    FirmwareRead,
+    /// Test process for reading upper bits of the firmware counter.
+    FirmwareReadHi,
+    /// Test process for stopping the firmware counter and resetting its mapping.
+    Stop,
+    /// Test process for rejecting to stop the firmware counter again.
+    StopStopped,
+    /// Test process for rejecting to read a hardware counter as a firmware counter.
+    FirmwareReadHardware,
+    /// Test process for rejecting to read a counter that does not exist.
+    FirmwareReadInvalid,
+    /// Test process for rejecting to start a counter that does not exist.
+    StartInvalid,
+    /// Test process for rejecting to stop a counter that does not exist.
+    StopInvalid,
+    /// Test process for disabling snapshot shared memory.
+    SnapshotDisable,
+}
+
+/// Test performance monitoring unit extension.
+pub fn test(hart_id: usize, mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Pmu).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    let mut checks = Checks::begin(f);
+    let num = sbi::pmu_num_counters();
+    let width = num.min(usize::BITS as usize) as u32;
+    let mask = usize::MAX.checked_shr(usize::BITS - width).unwrap_or(0);
+    let all = CounterMask::from_mask_base(mask, 0);
+
+    let mut hardware = None;
+    for idx in 0..num {
+        let ret = sbi::pmu_counter_get_info(idx);
+        let info = checks.ok(Check::CounterInfo(idx), ret);
+        // The most significant bit of counter information is set for firmware counters.
+        if info.is_some_and(|info| info >> (usize::BITS - 1) == 0) {
+            hardware.get_or_insert(idx);
+        }
+    }
+    let ret = sbi::pmu_counter_get_info(num);
+    checks.error(Check::CounterInfoInvalid, ret, Error::InvalidParam);
+
+    let fence_i_sent = event_type::FIRMWARE << 16 | firmware_event::FENCE_I_SENT;
+    let clear = Flags(CounterCfgFlags::CLEAR_VALUE.bits());
+    let ret = sbi::pmu_counter_config_matching(
+        CounterMask::from_mask_base(1, num),
+        clear,
+        fence_i_sent,
+        0,
+    );
+    checks.error(Check::ConfigInvalidBase, ret, Error::InvalidParam);
+    let reserved = Flags(!CounterCfgFlags::all().bits());
+    let ret = sbi::pmu_counter_config_matching(all, reserved, fence_i_sent, 0);
+    checks.error(Check::ConfigReservedFlags, ret, Error::InvalidParam);
+
+    let ret = sbi::pmu_counter_config_matching(all, clear, fence_i_sent, 0);
+    if let Some(idx) = checks.optional(Check::ConfigFirmware, ret) {
+        let counter = CounterMask::from_mask_base(1, idx);
+        let ret = sbi::pmu_counter_start(counter, Flags(0), 0);
+        checks.ok(Check::Start, ret);
+        let ret = sbi::pmu_counter_start(counter, Flags(0), 0);
+        checks.error(Check::StartStarted, ret, Error::AlreadyStarted);
+        sbi::remote_fence_i(HartMask::from_mask_base(1, hart_id));
+        let ret = sbi::pmu_counter_fw_read(idx);
+        match ret.ok() {
+            Some(count) if count > 0 => checks.pass(Check::FirmwareRead),
+            _ => checks.fail(Check::FirmwareRead, ret),
+        }
+        let ret = sbi::pmu_counter_fw_read_hi(idx);
+        checks.ok(Check::FirmwareReadHi, ret);
+        let reset = Flags(CounterStopFlags::RESET.bits());
+        let ret = sbi::pmu_counter_stop(counter, reset);
+        checks.ok(Check::Stop, ret);
+        let ret = sbi::pmu_counter_stop(counter, reset);
+        checks.error(Check::StopStopped, ret, Error::AlreadyStopped);
+    }
+
+    if let Some(idx) = hardware {
+        let ret = sbi::pmu_counter_fw_read(idx);
+        checks.error(Check::FirmwareReadHardware, ret, Error::InvalidParam);
+    }
+    let ret = sbi::pmu_counter_fw_read(num);
+    checks.error(Check::FirmwareReadInvalid, ret, Error::InvalidParam);
+    let invalid = CounterMask::from_mask_base(1, num);
+    let init_value = Flags(CounterStartFlags::INIT_VALUE.bits());
+    let ret = sbi::pmu_counter_start(invalid, init_value, 0);
+    checks.error(Check::StartInvalid, ret, Error::InvalidParam);
+    let ret = sbi::pmu_counter_stop(invalid, Flags(0));
+    checks.error(Check::StopInvalid, ret, Error::InvalidParam);
+
+    let ret = sbi::pmu_snapshot_set_shmem(SharedPtr::new(usize::MAX, usize::MAX), 0);
+    checks.optional(Check::SnapshotDisable, ret);
+    checks.end();
+}
+
+/// Raw flags for configuring, starting and stopping counters.
+#[derive(Clone, Copy)]
+struct Flags(usize);
+
+impl ConfigFlags for Flags {
+    fn raw(&self) -> usize {
+        self.0
+    }
+}
+
+impl StartFlags for Flags {
+    fn raw(&self) -> usize {
+        self.0
+    }
+}
+
+impl StopFlags for Flags {
+    fn raw(&self) -> usize {
+        self.0
+    }
+}
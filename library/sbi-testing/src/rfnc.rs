@@ -0,0 +1,104 @@
+//! Remote fence extension test suite.
+
+use crate::checks::Checks;
+use sbi::{HartMask, SbiRet};
+use sbi_spec::binary::Error;
+
+/// Remote fence extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on remote fence extension.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for fencing the whole address space on all tested harts.
+    Flush(Fence),
+    /// Test process for fencing a page aligned address range on all tested harts.
+    Range(Fence),
+    /// Test process for rejecting an address range overflowing the address space.
+    Overflow(Fence),
+    /// Test process for rejecting a hart mask including a hart that does not exist.
+    InvalidHart(Fence),
+}
+
+/// Remote fence functions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fence {
+    /// `sbi_remote_fence_i`.
+    FenceI,
+    /// `sbi_remote_sfence_vma`.
+    SfenceVma,
+    /// `sbi_remote_sfence_vma_asid`.
+    SfenceVmaAsid,
+    /// `sbi_remote_hfence_gvma_vmid`.
+    HfenceGvmaVmid,
+    /// `sbi_remote_hfence_gvma`.
+    HfenceGvma,
+    /// `sbi_remote_hfence_vvma_asid`.
+    HfenceVvmaAsid,
+    /// `sbi_remote_hfence_vvma`.
+    HfenceVvma,
+}
+
+impl Fence {
+    const ALL: [Self; 7] = [
+        Self::FenceI,
+        Self::SfenceVma,
+        Self::SfenceVmaAsid,
+        Self::HfenceGvmaVmid,
+        Self::HfenceGvma,
+        Self::HfenceVvmaAsid,
+        Self::HfenceVvma,
+    ];
+
+    /// Hypervisor fences are only supported on harts with the H extension.
+    fn is_hypervisor(self) -> bool {
+        !matches!(self, Self::FenceI | Self::SfenceVma | Self::SfenceVmaAsid)
+    }
+
+    fn remote(self, hart_mask: HartMask, start_addr: usize, size: usize) -> SbiRet {
+        match self {
+            Self::FenceI => sbi::remote_fence_i(hart_mask),
+            Self::SfenceVma => sbi::remote_sfence_vma(hart_mask, start_addr, size),
+            Self::SfenceVmaAsid => sbi::remote_sfence_vma_asid(hart_mask, start_addr, size, 0),
+            Self::HfenceGvmaVmid => sbi::remote_hfence_gvma_vmid(hart_mask, start_addr, size, 0),
+            Self::HfenceGvma => sbi::remote_hfence_gvma(hart_mask, start_addr, size),
+            Self::HfenceVvmaAsid => sbi::remote_hfence_vvma_asid(hart_mask, start_addr, size, 0),
+            Self::HfenceVvma => sbi::remote_hfence_vvma(hart_mask, start_addr, size),
+        }
+    }
+}
+
+const PAGE_SIZE: usize = 4096;
+
+/// Test remote fence extension.
+///
+/// `hart_mask` and `hart_mask_base` should cover all harts on the platform,
+/// the hart right after the highest one in the mask is used to test invalid hart masks.
+pub fn test(hart_mask: usize, hart_mask_base: usize, mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Fence).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    let mut checks = Checks::begin(f);
+    let harts = HartMask::from_mask_base(hart_mask, hart_mask_base);
+    let invalid_hart = hart_mask_base + (usize::BITS - hart_mask.leading_zeros()) as usize;
+    let invalid = HartMask::from_mask_base(1, invalid_hart);
+    for fence in Fence::ALL {
+        let ret = fence.remote(harts, 0, 0);
+        if fence.is_hypervisor() && ret.into_result() == Err(Error::NotSupported) {
+            checks.skip(Check::Flush(fence));
+            continue;
+        }
+        checks.ok(Check::Flush(fence), ret);
+        if fence != Fence::FenceI {
+            let ret = fence.remote(harts, PAGE_SIZE, PAGE_SIZE);
+            checks.ok(Check::Range(fence), ret);
+            let ret = fence.remote(harts, usize::MAX & !(PAGE_SIZE - 1), 2 * PAGE_SIZE);
+            checks.error(Check::Overflow(fence), ret, Error::InvalidAddress);
+        }
+        let ret = fence.remote(invalid, 0, 0);
+        checks.error(Check::InvalidHart(fence), ret, Error::InvalidParam);
+    }
+    checks.end();
+}
@@ -0,0 +1,50 @@
+//! System reset extension test suite.
+
+use crate::checks::Checks;
+use sbi::{ResetReason, ResetType};
+use sbi_spec::{
+    binary::Error,
+    srst::{RESET_REASON_NO_REASON, RESET_TYPE_WARM_REBOOT},
+};
+
+/// System reset extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on system reset extension.
+///
+/// Checks never reset the system; supported reset types are not called.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for rejecting the given reserved reset type.
+    ReservedType(u32),
+}
+
+/// Test system reset extension.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Reset).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    let mut checks = Checks::begin(f);
+    for reset_type in [RESET_TYPE_WARM_REBOOT + 1, 0xefff_ffff] {
+        let ret = sbi::system_reset(Raw(reset_type), Raw(RESET_REASON_NO_REASON));
+        checks.error(Check::ReservedType(reset_type), ret, Error::InvalidParam);
+    }
+    checks.end();
+}
+
+/// Raw reset type or reason.
+struct Raw(u32);
+
+impl ResetType for Raw {
+    fn raw(&self) -> u32 {
+        self.0
+    }
+}
+
+impl ResetReason for Raw {
+    fn raw(&self) -> u32 {
+        self.0
+    }
+}
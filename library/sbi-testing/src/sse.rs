@@ -0,0 +1,75 @@
+//! Supervisor software events extension test suite.
+
+use crate::checks::Checks;
+use sbi::SharedPtr;
+use sbi_spec::binary::Error;
+
+/// Supervisor software events extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on supervisor software events extension.
+///
+/// Handlers are never registered, so no event is delivered during the checks.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for reading status of the local software event.
+    ReadStatus,
+    /// Test process for rejecting to read zero attributes.
+    ReadZeroCount,
+    /// Test process for rejecting to read attributes of a reserved event.
+    ReadReserved,
+    /// Test process for rejecting to enable the unregistered local software event.
+    EnableUnregistered,
+    /// Test process for rejecting to unregister the unregistered local software event.
+    UnregisterUnregistered,
+    /// Test process for unmasking software events on the current hart.
+    HartUnmask,
+    /// Test process for masking software events on the current hart.
+    HartMask,
+    /// Test process for rejecting to mask software events which are already masked.
+    HartMaskMasked,
+    /// Test process for rejecting to unmask software events which are already unmasked.
+    HartUnmaskUnmasked,
+}
+
+const LOCAL_SOFTWARE: u32 = 0xffff_0000;
+const LOCAL_RESERVED: u32 = 0x0000_0002;
+const ATTR_STATUS: u32 = 0;
+
+/// Test supervisor software events extension.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Sse).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    let mut checks = Checks::begin(f);
+    let mut status = 0usize;
+    let output = SharedPtr::new(&raw mut status as usize, 0);
+    let ret = sbi::sse_read_attrs(LOCAL_SOFTWARE, ATTR_STATUS, 1, output);
+    if checks.optional(Check::ReadStatus, ret).is_some() {
+        let ret = sbi::sse_read_attrs(LOCAL_SOFTWARE, ATTR_STATUS, 0, output);
+        checks.error(Check::ReadZeroCount, ret, Error::InvalidParam);
+        let ret = sbi::sse_enable(LOCAL_SOFTWARE);
+        checks.error(Check::EnableUnregistered, ret, Error::InvalidState);
+        let ret = sbi::sse_unregister(LOCAL_SOFTWARE);
+        checks.error(Check::UnregisterUnregistered, ret, Error::InvalidState);
+    }
+    let ret = sbi::sse_read_attrs(LOCAL_RESERVED, ATTR_STATUS, 1, output);
+    checks.error(Check::ReadReserved, ret, Error::InvalidParam);
+
+    let ret = sbi::sse_hart_unmask();
+    match ret.into_result() {
+        Ok(_) | Err(Error::AlreadyStarted) => checks.pass(Check::HartUnmask),
+        Err(_) => checks.fail(Check::HartUnmask, ret),
+    }
+    let ret = sbi::sse_hart_mask();
+    checks.ok(Check::HartMask, ret);
+    let ret = sbi::sse_hart_mask();
+    checks.error(Check::HartMaskMasked, ret, Error::AlreadyStopped);
+    let ret = sbi::sse_hart_unmask();
+    checks.ok(Check::HartUnmask, ret);
+    let ret = sbi::sse_hart_unmask();
+    checks.error(Check::HartUnmaskUnmasked, ret, Error::AlreadyStarted);
+    checks.end();
+}
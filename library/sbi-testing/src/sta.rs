@@ -0,0 +1,59 @@
+//! Steal-time accounting extension test suite.
+
+use crate::checks::Checks;
+use sbi::SharedPtr;
+use sbi_spec::binary::Error;
+
+/// Steal-time accounting extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on steal-time accounting extension.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for rejecting shared memory with reserved flags.
+    SetShmemFlags,
+    /// Test process for rejecting shared memory which is not 64-byte aligned.
+    SetShmemMisaligned,
+    /// Test process for setting shared memory.
+    SetShmem,
+    /// Test process for shared memory being zeroed, except for `sequence`, `steal` and `preempted` fields.
+    Zeroed,
+    /// Test process for disabling shared memory.
+    DisableShmem,
+}
+
+#[repr(C, align(64))]
+struct Shmem([u8; 64]);
+
+/// Test steal-time accounting extension.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Sta).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    static mut SHMEM: Shmem = Shmem([0xff; 64]);
+
+    let mut checks = Checks::begin(f);
+    let shmem = &raw mut SHMEM as usize;
+    let ret = sbi::sta_set_shmem(SharedPtr::new(shmem, 0), 1);
+    checks.error(Check::SetShmemFlags, ret, Error::InvalidParam);
+    let ret = sbi::sta_set_shmem(SharedPtr::new(shmem + 8, 0), 0);
+    checks.error(Check::SetShmemMisaligned, ret, Error::InvalidParam);
+    let ret = sbi::sta_set_shmem(SharedPtr::new(shmem, 0), 0);
+    if checks.ok(Check::SetShmem, ret).is_some() {
+        let bytes = shmem as *const u8;
+        let zeroed = (4..8)
+            .chain(17..64)
+            .all(|i| unsafe { bytes.add(i).read_volatile() } == 0);
+        if zeroed {
+            checks.pass(Check::Zeroed);
+        } else {
+            // The call itself succeeded, but left the shared memory dirty.
+            checks.fail(Check::Zeroed, ret);
+        }
+    }
+    let ret = sbi::sta_set_shmem(SharedPtr::new(usize::MAX, usize::MAX), 0);
+    checks.ok(Check::DisableShmem, ret);
+    checks.end();
+}
@@ -0,0 +1,57 @@
+//! System suspend extension test suite.
+
+use crate::checks::Checks;
+use sbi::SleepType;
+use sbi_spec::binary::Error;
+
+/// System suspend extension test cases.
+pub type Case = crate::checks::Case<Check>;
+
+/// Conformance checks on system suspend extension.
+///
+/// Checks never suspend the system; every call is expected to be rejected.
+#[derive(Clone, Copy, Debug)]
+pub enum Check {
+    /// Test process for rejecting the given reserved sleep type.
+    ReservedSleepType(u32),
+    /// Test process for rejecting a resume address which is not a valid physical address.
+    ///
+    /// Skipped if other harts are not stopped, as entry criteria of system suspend are not satisfied.
+    InvalidResumeAddress,
+}
+
+/// Test system suspend extension.
+///
+/// Other harts should have been stopped to check the resume address.
+pub fn test(mut f: impl FnMut(Case)) {
+    if sbi::probe_extension(sbi::Suspend).is_unavailable() {
+        f(Case::NotExist);
+        return;
+    }
+
+    let mut checks = Checks::begin(f);
+    for sleep_type in [1, 0x7fff_ffff] {
+        let ret = sbi::system_suspend(Raw(sleep_type), 0, 0);
+        checks.error(
+            Check::ReservedSleepType(sleep_type),
+            ret,
+            Error::InvalidParam,
+        );
+    }
+    let ret = sbi::system_suspend(sbi::SuspendToRam, usize::MAX & !0b11, 0);
+    if ret.into_result() == Err(Error::Denied) {
+        checks.skip(Check::InvalidResumeAddress);
+    } else {
+        checks.error(Check::InvalidResumeAddress, ret, Error::InvalidAddress);
+    }
+    checks.end();
+}
+
+/// Raw sleep type.
+struct Raw(u32);
+
+impl SleepType for Raw {
+    fn raw(&self) -> u32 {
+        self.0
+    }
+}
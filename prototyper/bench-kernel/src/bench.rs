@@ -0,0 +1,476 @@
+use core::fmt;
+use core::hint::spin_loop;
+use core::sync::atomic::Ordering;
+use log::{info, warn};
+use sbi::{ConfigFlags, CounterMask, HartMask, Physical, SbiRet, StartFlags, StopFlags};
+use sbi_spec::binary::{Error, MaskError};
+use sbi_spec::hsm::hart_state;
+use sbi_spec::pmu::hardware_event;
+use sbi_testing::sbi;
+
+use crate::{IPI_ACK, PARKED, STOP_HART, get_time, start_hart};
+
+/// Line printed before the JSON report, looked for by `cargo xtask bench`.
+pub const REPORT_BEGIN: &str = "==== rustsbi-bench-kernel report begin ====";
+/// Line printed after the JSON report.
+pub const REPORT_END: &str = "==== rustsbi-bench-kernel report end ====";
+/// Version of the report format, bumped on incompatible changes.
+const REPORT_SCHEMA: u32 = 1;
+
+/// Samples taken per benchmark.
+const SAMPLES: usize = 32;
+/// Calls per sample for calls too short for the timer resolution.
+const BATCH: usize = 64;
+const MAX_RESULTS: usize = 32;
+/// Bytes per debug console write.
+const DBCN_CHUNK: usize = 256;
+/// Debug console writes per sample.
+const DBCN_WRITES: usize = 8;
+/// STOP_FLAG_RESET of `sbi_pmu_counter_stop`, releasing the counter.
+const PMU_STOP_RESET: usize = 1;
+
+/// Benchmark runner, collecting the results of every case.
+pub struct Bench {
+    frequency: u64,
+    boot_hart: usize,
+    smp: usize,
+    results: [Option<BenchResult>; MAX_RESULTS],
+    len: usize,
+}
+
+#[derive(Clone, Copy)]
+struct BenchResult {
+    name: &'static str,
+    /// Number of target harts, for cases scaling with it.
+    harts: Option<usize>,
+    outcome: Outcome,
+}
+
+#[derive(Clone, Copy)]
+enum Outcome {
+    /// Nanoseconds per call.
+    Latency(Stats),
+    /// Bytes per second.
+    Throughput(Stats),
+    Skipped(&'static str),
+}
+
+#[derive(Clone, Copy)]
+struct Stats {
+    min: u64,
+    median: u64,
+    mean: u64,
+    max: u64,
+}
+
+impl Stats {
+    fn new(samples: &mut [u64]) -> Self {
+        samples.sort_unstable();
+        let sum: u64 = samples.iter().sum();
+        Stats {
+            min: samples[0],
+            median: samples[samples.len() / 2],
+            mean: sum / samples.len() as u64,
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+impl Bench {
+    pub const fn new(frequency: u64, boot_hart: usize, smp: usize) -> Self {
+        Bench {
+            frequency,
+            boot_hart,
+            smp,
+            results: [None; MAX_RESULTS],
+            len: 0,
+        }
+    }
+
+    /// Runs every benchmark.
+    pub fn run(&mut self) {
+        self.base();
+        self.timer();
+        self.ipi();
+        self.rfence();
+        self.hsm();
+        self.pmu();
+        self.dbcn();
+    }
+
+    /// Prints the results as JSON between [`REPORT_BEGIN`] and [`REPORT_END`].
+    pub fn report(&self) {
+        println!("{REPORT_BEGIN}");
+        println!(
+            "{{\"schema\":{},\"timebase_frequency\":{},\"harts\":{},\"samples\":{},\"results\":[",
+            REPORT_SCHEMA, self.frequency, self.smp, SAMPLES
+        );
+        let results = self.results[..self.len].iter().flatten();
+        for (i, result) in results.enumerate() {
+            let separator = if i + 1 == self.len { "" } else { "," };
+            println!("{result}{separator}");
+        }
+        println!("]}}");
+        println!("{REPORT_END}");
+    }
+
+    fn push(&mut self, name: &'static str, harts: Option<usize>, outcome: Outcome) {
+        match outcome {
+            Outcome::Latency(stats) => info!("{name} {harts:?}: {} ns", stats.median),
+            Outcome::Throughput(stats) => info!("{name} {harts:?}: {} B/s", stats.median),
+            Outcome::Skipped(reason) => warn!("{name} {harts:?}: skipped, {reason}"),
+        }
+        assert!(self.len < MAX_RESULTS, "too many benchmark results");
+        self.results[self.len] = Some(BenchResult {
+            name,
+            harts,
+            outcome,
+        });
+        self.len += 1;
+    }
+
+    fn skip(&mut self, name: &'static str, reason: &'static str) {
+        self.push(name, None, Outcome::Skipped(reason));
+    }
+
+    /// Records nanoseconds per call, `sample` making `calls` calls and returning elapsed ticks.
+    fn latency(
+        &mut self,
+        name: &'static str,
+        harts: Option<usize>,
+        calls: usize,
+        mut sample: impl FnMut() -> u64,
+    ) {
+        let mut samples = [0; SAMPLES];
+        for value in samples.iter_mut() {
+            *value = ticks_to_ns(sample(), self.frequency) / calls as u64;
+        }
+        self.push(name, harts, Outcome::Latency(Stats::new(&mut samples)));
+    }
+
+    /// Records bytes per second, `sample` moving `bytes` bytes and returning elapsed ticks.
+    fn throughput(&mut self, name: &'static str, bytes: usize, mut sample: impl FnMut() -> u64) {
+        let mut samples = [0; SAMPLES];
+        for value in samples.iter_mut() {
+            let ticks = sample().max(1);
+            *value = (bytes as u128 * self.frequency as u128 / ticks as u128) as u64;
+        }
+        self.push(name, None, Outcome::Throughput(Stats::new(&mut samples)));
+    }
+
+    fn base(&mut self) {
+        self.latency("base.get_spec_version", None, BATCH, || {
+            batch(|| {
+                sbi::get_spec_version();
+            })
+        });
+        self.latency("base.probe_extension", None, BATCH, || {
+            batch(|| {
+                sbi::probe_extension(sbi::Timer);
+            })
+        });
+    }
+
+    fn timer(&mut self) {
+        const NAME: &str = "time.set_timer";
+        if sbi::probe_extension(sbi::Timer).is_unavailable() {
+            return self.skip(NAME, "extension not available");
+        }
+        // Setting the timer far in the future never raises an interrupt.
+        self.latency(NAME, None, BATCH, || {
+            batch(|| {
+                sbi::set_timer(u64::MAX);
+            })
+        });
+    }
+
+    /// Sends an IPI to 1, 2, 4, ... remote harts and waits for all of them to take it.
+    fn ipi(&mut self) {
+        const NAME: &str = "ipi.fanout";
+        if sbi::probe_extension(sbi::Ipi).is_unavailable() {
+            return self.skip(NAME, "extension not available");
+        }
+        let remote = self.smp - 1;
+        if remote == 0 {
+            return self.skip(NAME, "no remote harts");
+        }
+        let (boot_hart, smp) = (self.boot_hart, self.smp);
+        let mut harts = 1;
+        loop {
+            self.latency(NAME, Some(harts), 1, || {
+                IPI_ACK.store(0, Ordering::Release);
+                let start = get_time();
+                for_each_mask(remote_harts(boot_hart, smp).take(harts), |mask| {
+                    sbi::send_ipi(mask);
+                });
+                while IPI_ACK.load(Ordering::Acquire) != harts {
+                    spin_loop();
+                }
+                get_time() - start
+            });
+            if harts == remote {
+                break;
+            }
+            harts = (harts * 2).min(remote);
+        }
+    }
+
+    /// Issues every remote fence to all remote harts, or to the boot hart alone.
+    fn rfence(&mut self) {
+        type Fence = fn(HartMask) -> SbiRet;
+        const FENCES: [(&str, Fence); 7] = [
+            ("rfence.remote_fence_i", |mask| sbi::remote_fence_i(mask)),
+            ("rfence.remote_sfence_vma", |mask| {
+                sbi::remote_sfence_vma(mask, 0, 4096)
+            }),
+            ("rfence.remote_sfence_vma_asid", |mask| {
+                sbi::remote_sfence_vma_asid(mask, 0, 4096, 0)
+            }),
+            ("rfence.remote_hfence_gvma_vmid", |mask| {
+                sbi::remote_hfence_gvma_vmid(mask, 0, 4096, 0)
+            }),
+            ("rfence.remote_hfence_gvma", |mask| {
+                sbi::remote_hfence_gvma(mask, 0, 4096)
+            }),
+            ("rfence.remote_hfence_vvma_asid", |mask| {
+                sbi::remote_hfence_vvma_asid(mask, 0, 4096, 0)
+            }),
+            ("rfence.remote_hfence_vvma", |mask| {
+                sbi::remote_hfence_vvma(mask, 0, 4096)
+            }),
+        ];
+        let available = sbi::probe_extension(sbi::Fence).is_available();
+        let (boot_hart, smp) = (self.boot_hart, self.smp);
+        let harts = (smp - 1).max(1);
+        let fence_all = move |fence: Fence| {
+            let mut ret = SbiRet::success(0);
+            let on_mask = |mask| {
+                let this = fence(mask);
+                if ret.is_ok() {
+                    ret = this;
+                }
+            };
+            if smp > 1 {
+                for_each_mask(remote_harts(boot_hart, smp), on_mask);
+            } else {
+                for_each_mask(core::iter::once(boot_hart), on_mask);
+            }
+            ret
+        };
+        for (name, fence) in FENCES {
+            if !available {
+                self.skip(name, "extension not available");
+                continue;
+            }
+            if let Err(error) = fence_all(fence).into_result() {
+                self.skip(name, error_reason(error));
+                continue;
+            }
+            self.latency(name, Some(harts), BATCH, || {
+                batch(|| {
+                    fence_all(fence);
+                })
+            });
+        }
+    }
+
+    /// Stops and restarts the last remote hart, waiting for each to complete.
+    fn hsm(&mut self) {
+        const STOP: &str = "hsm.hart_stop";
+        const START: &str = "hsm.hart_start";
+        if sbi::probe_extension(sbi::Hsm).is_unavailable() {
+            self.skip(STOP, "extension not available");
+            return self.skip(START, "extension not available");
+        }
+        let Some(hart) = remote_harts(self.boot_hart, self.smp).last() else {
+            self.skip(STOP, "no remote harts");
+            return self.skip(START, "no remote harts");
+        };
+        self.latency(STOP, None, 1, || {
+            let elapsed = stop_parked(hart);
+            start_parked(hart);
+            elapsed
+        });
+        self.latency(START, None, 1, || {
+            stop_parked(hart);
+            start_parked(hart)
+        });
+    }
+
+    /// Starts and stops a counter monitoring CPU cycles.
+    fn pmu(&mut self) {
+        const NAME: &str = "pmu.counter_start_stop";
+        if sbi::probe_extension(sbi::Pmu).is_unavailable() {
+            return self.skip(NAME, "extension not available");
+        }
+        let counters = sbi::pmu_num_counters();
+        if counters == 0 {
+            return self.skip(NAME, "no counters");
+        }
+        let all = match counters {
+            n if n >= usize::BITS as usize => usize::MAX,
+            n => (1 << n) - 1,
+        };
+        let ret = sbi::pmu_counter_config_matching(
+            CounterMask::from_mask_base(all, 0),
+            Flag(0),
+            hardware_event::CPU_CYCLES,
+            0,
+        );
+        let Ok(counter) = ret.into_result() else {
+            return self.skip(NAME, "no counter for CPU cycles");
+        };
+        let counter = CounterMask::from_mask_base(1, counter);
+        self.latency(NAME, None, BATCH, || {
+            batch(|| {
+                sbi::pmu_counter_start(counter, Flag(0), 0);
+                sbi::pmu_counter_stop(counter, Flag(0));
+            })
+        });
+        sbi::pmu_counter_start(counter, Flag(0), 0);
+        sbi::pmu_counter_stop(counter, Flag(PMU_STOP_RESET));
+    }
+
+    /// Writes blank lines to the debug console, each returning to the line start.
+    fn dbcn(&mut self) {
+        const NAME: &str = "dbcn.console_write";
+        if sbi::probe_extension(sbi::Console).is_unavailable() {
+            return self.skip(NAME, "extension not available");
+        }
+        let mut chunk = [b' '; DBCN_CHUNK];
+        chunk[DBCN_CHUNK - 1] = b'\r';
+        if let Err(error) = console_write_all(&chunk).into_result() {
+            return self.skip(NAME, error_reason(error));
+        }
+        self.throughput(NAME, DBCN_CHUNK * DBCN_WRITES, || {
+            let start = get_time();
+            for _ in 0..DBCN_WRITES {
+                console_write_all(&chunk);
+            }
+            get_time() - start
+        });
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{\"name\":\"{}\"", self.name)?;
+        if let Some(harts) = self.harts {
+            write!(f, ",\"harts\":{harts}")?;
+        }
+        let (unit, stats) = match self.outcome {
+            Outcome::Latency(stats) => ("ns", stats),
+            Outcome::Throughput(stats) => ("B/s", stats),
+            Outcome::Skipped(reason) => return write!(f, ",\"skipped\":\"{reason}\"}}"),
+        };
+        write!(
+            f,
+            ",\"unit\":\"{unit}\",\"min\":{},\"median\":{},\"mean\":{},\"max\":{}}}",
+            stats.min, stats.median, stats.mean, stats.max
+        )
+    }
+}
+
+/// Times `BATCH` calls of `f`, in ticks.
+#[inline]
+fn batch(mut f: impl FnMut()) -> u64 {
+    let start = get_time();
+    for _ in 0..BATCH {
+        f();
+    }
+    get_time() - start
+}
+
+#[inline]
+fn ticks_to_ns(ticks: u64, frequency: u64) -> u64 {
+    (ticks as u128 * 1_000_000_000 / frequency as u128) as u64
+}
+
+fn remote_harts(boot_hart: usize, smp: usize) -> impl Iterator<Item = usize> {
+    (0..smp).filter(move |&hart| hart != boot_hart)
+}
+
+/// Calls `f` with the fewest hart masks covering `harts`, given in ascending order.
+fn for_each_mask(harts: impl Iterator<Item = usize>, mut f: impl FnMut(HartMask)) {
+    let mut mask: Option<HartMask> = None;
+    for hart in harts {
+        if let Some(ref mut mask) = mask {
+            match mask.insert(hart) {
+                Ok(_) => continue,
+                Err(MaskError::InvalidBit) => f(*mask),
+                Err(_) => unreachable!("Failed to construct mask"),
+            }
+        }
+        mask = Some(HartMask::from_mask_base(0b1, hart));
+    }
+    if let Some(mask) = mask {
+        f(mask);
+    }
+}
+
+/// Asks a parked hart to stop, returning ticks until it is stopped.
+fn stop_parked(hart: usize) -> u64 {
+    const STOPPED: SbiRet = SbiRet::success(hart_state::STOPPED);
+    STOP_HART.store(hart, Ordering::Release);
+    let start = get_time();
+    sbi::send_ipi(HartMask::from_mask_base(0b1, hart));
+    while sbi::hart_get_status(hart) != STOPPED {
+        spin_loop();
+    }
+    let elapsed = get_time() - start;
+    STOP_HART.store(usize::MAX, Ordering::Release);
+    elapsed
+}
+
+/// Starts a stopped hart, returning ticks until it is parked.
+fn start_parked(hart: usize) -> u64 {
+    let start = get_time();
+    start_hart(hart);
+    while !PARKED[hart].load(Ordering::Acquire) {
+        spin_loop();
+    }
+    get_time() - start
+}
+
+/// Writes all of `bytes`, which may take several calls.
+fn console_write_all(bytes: &[u8]) -> SbiRet {
+    let mut written = 0;
+    while written < bytes.len() {
+        let addr = bytes[written..].as_ptr() as usize;
+        let ret = sbi::console_write(Physical::new(bytes.len() - written, addr, 0));
+        match ret.into_result() {
+            Ok(len) => written += len,
+            Err(_) => return ret,
+        }
+    }
+    SbiRet::success(written)
+}
+
+/// Raw flags of PMU calls.
+struct Flag(usize);
+
+impl ConfigFlags for Flag {
+    fn raw(&self) -> usize {
+        self.0
+    }
+}
+
+impl StartFlags for Flag {
+    fn raw(&self) -> usize {
+        self.0
+    }
+}
+
+impl StopFlags for Flag {
+    fn raw(&self) -> usize {
+        self.0
+    }
+}
+
+fn error_reason(error: Error) -> &'static str {
+    match error {
+        Error::NotSupported => "not supported",
+        Error::Denied => "denied",
+        _ => "failed",
+    }
+}
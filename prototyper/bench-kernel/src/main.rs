@@ -5,16 +5,13 @@
 #[macro_use]
 extern crate rcore_console;
 
-use core::mem::MaybeUninit;
-use core::sync::{atomic::AtomicBool, atomic::AtomicU64, atomic::Ordering};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use core::{
     arch::{asm, naked_asm},
     ptr::null,
 };
 use log::*;
 use sbi::SbiRet;
-use sbi_spec::binary::{HartMask, MaskError};
-use sbi_spec::hsm::hart_state;
 use sbi_testing::sbi;
 use serde::Deserialize;
 use serde_device_tree::{
@@ -23,6 +20,10 @@ use serde_device_tree::{
 };
 use uart16550::Uart16550;
 
+mod bench;
+
+use crate::bench::Bench;
+
 const RISCV_HEAD_FLAGS: u64 = 0;
 const RISCV_HEADER_VERSION: u32 = 0x2;
 const RISCV_IMAGE_MAGIC: u64 = 0x5643534952; /* Magic number, little endian, "RISCV" */
@@ -72,13 +73,6 @@ impl HartStack {
 static mut STACK: HartStack = HartStack::new();
 #[unsafe(link_section = ".bss.uninit")]
 static mut HART_STACK: [HartStack; MAX_HART_NUM] = [HartStack::new(); MAX_HART_NUM];
-#[unsafe(link_section = ".bss.uninit")]
-static mut IPI_SENT: [MaybeUninit<AtomicBool>; MAX_HART_NUM] =
-    [const { MaybeUninit::uninit() }; MAX_HART_NUM];
-#[unsafe(link_section = ".bss.uninit")]
-static mut SMP_COUNT: usize = 0;
-#[unsafe(link_section = ".bss.uninit")]
-static mut BOOT_HART_ID: usize = 0;
 
 /// 内核入口。
 ///
@@ -117,67 +111,44 @@ extern "C" fn init_hart(hartid: usize, opaque: usize) {
     )
 }
 
-#[unsafe(naked)]
-#[unsafe(no_mangle)]
-extern "C" fn core_send_ipi(hartid: usize, opaque: usize) {
-    naked_asm!(
-        "add sp, a1, zero",
-        "csrw sscratch, sp",
-        "call {send_ipi}",
-        send_ipi = sym send_ipi,
-    )
-}
+/// Secondary harts waiting for inter-processor interrupts in [`init_main`].
+static PARKED: [AtomicBool; MAX_HART_NUM] = [const { AtomicBool::new(false) }; MAX_HART_NUM];
+/// Inter-processor interrupts taken by parked harts.
+static IPI_ACK: AtomicUsize = AtomicUsize::new(0);
+/// Hart to stop on its next inter-processor interrupt, `usize::MAX` for none.
+static STOP_HART: AtomicUsize = AtomicUsize::new(usize::MAX);
 
-extern "C" fn send_ipi(hartid: usize) -> ! {
-    if unsafe { !(IPI_SENT[hartid].assume_init_mut().load(Ordering::Relaxed)) } {
-        unsafe {
-            IPI_SENT[hartid]
-                .assume_init_mut()
-                .swap(true, Ordering::AcqRel);
-        };
-        let mut mask = Some(HartMask::from_mask_base(0, 0));
-        for i in 0..unsafe { SMP_COUNT } {
-            if i == unsafe { BOOT_HART_ID } {
-                continue;
-            }
-            if let Some(ref mut mask) = mask {
-                match mask.insert(i) {
-                    Ok(_) => continue,
-                    Err(MaskError::InvalidBit) => {
-                        sbi::remote_sfence_vma(*mask, 0, 0);
-                    }
-                    Err(_) => unreachable!("Failed to construct mask"),
-                }
-            }
-            mask = Some(HartMask::from_mask_base(0b1, i));
-        }
-        if let Some(mask) = mask {
-            sbi::remote_sfence_vma(mask, 0, 0);
+const SIE_SSIE: usize = 1 << 1;
+const SIP_SSIP: usize = 1 << 1;
+
+extern "C" fn init_main(hartid: usize) -> ! {
+    // Wake up from `wfi` on supervisor software interrupts, without trapping.
+    unsafe { asm!("csrs sie, {}", in(reg) SIE_SSIE) };
+    PARKED[hartid].store(true, Ordering::Release);
+    loop {
+        unsafe { asm!("wfi") };
+        let sip: usize;
+        unsafe { asm!("csrr {}, sip", out(reg) sip) };
+        if sip & SIP_SSIP == 0 {
+            continue;
         }
-        unsafe {
-            WAIT_COUNT.fetch_sub(1, Ordering::AcqRel);
-            while WAIT_COUNT.load(Ordering::Relaxed) != 0 {}
+        unsafe { asm!("csrc sip, {}", in(reg) SIP_SSIP) };
+        if STOP_HART.load(Ordering::Acquire) == hartid {
+            PARKED[hartid].store(false, Ordering::Release);
+            sbi::hart_stop();
+            unreachable!()
         }
-    } else {
-        unreachable!("resend {}", hartid);
+        IPI_ACK.fetch_add(1, Ordering::AcqRel);
     }
-    sbi::hart_suspend(sbi::NonRetentive, core_send_ipi as _, unsafe {
-        core::ptr::addr_of!(HART_STACK[hartid + 1]) as _
-    });
-    unreachable!()
 }
 
-extern "C" fn init_main(hartid: usize) -> ! {
-    sbi::hart_suspend(sbi::NonRetentive, core_send_ipi as _, unsafe {
+/// Starts a secondary hart into [`init_main`].
+fn start_hart(hartid: usize) -> SbiRet {
+    sbi::hart_start(hartid, init_hart as _, unsafe {
         core::ptr::addr_of!(HART_STACK[hartid + 1]) as _
-    });
-    unreachable!()
+    })
 }
 
-static mut WAIT_COUNT: AtomicU64 = AtomicU64::new(0);
-
-const SUSPENDED: SbiRet = SbiRet::success(hart_state::SUSPENDED);
-
 fn get_time() -> u64 {
     const CSR_TIME: u32 = 0xc01;
     let mut low_time: u64;
@@ -233,60 +204,18 @@ extern "C" fn rust_main(hartid: usize, dtb_pa: usize) -> ! {
 | dtb physical address  | {dtb_pa:#20x} |
 ----------------------------------------------------------"
     );
-    unsafe {
-        SMP_COUNT = smp;
-        BOOT_HART_ID = hartid;
-    }
     for i in 0..smp {
-        unsafe {
-            IPI_SENT[i].write(AtomicBool::new(false));
-        }
         if i != hartid {
-            sbi::hart_start(i, init_hart as _, unsafe {
-                core::ptr::addr_of!(HART_STACK[i + 1]) as _
-            });
-            while sbi::hart_get_status(i) != SUSPENDED {
+            start_hart(i);
+            while !PARKED[i].load(Ordering::Acquire) {
                 core::hint::spin_loop();
             }
         }
     }
-    info!("Starting test");
-    for i in 0..4 {
-        info!("Test #{i} started");
-        unsafe {
-            for (i, ipi_sent) in IPI_SENT.iter_mut().enumerate().take(smp) {
-                ipi_sent.assume_init_mut().swap(false, Ordering::AcqRel);
-                if i != hartid {
-                    while sbi::hart_get_status(i) != SUSPENDED {}
-                }
-            }
-            WAIT_COUNT.swap((smp - 1) as u64, Ordering::AcqRel);
-        }
-        debug!("send ipi!");
-        let start_time = get_time();
-        let mut mask = Some(HartMask::from_mask_base(0, 0));
-        for i in 0..smp {
-            if i == hartid {
-                continue;
-            }
-            if let Some(ref mut mask) = mask {
-                match mask.insert(i) {
-                    Ok(_) => continue,
-                    Err(MaskError::InvalidBit) => {
-                        sbi::send_ipi(*mask);
-                    }
-                    Err(_) => unreachable!("Failed to construct mask"),
-                }
-            }
-            mask = Some(HartMask::from_mask_base(0b1, i));
-        }
-        if let Some(mask) = mask {
-            sbi::send_ipi(mask);
-        }
-        while unsafe { WAIT_COUNT.load(Ordering::Acquire) } != 0 {}
-        let end_time = get_time();
-        println!("Test #{}: {}", i, end_time - start_time);
-    }
+    info!("Starting benchmarks");
+    let mut bench = Bench::new(frequency as u64, hartid, smp);
+    bench.run();
+    bench.report();
     sbi::system_reset(sbi::Shutdown, sbi::NoReason);
     unreachable!()
 }
@@ -68,6 +68,8 @@ SECTIONS {
         sbi_heap_start = .;
         *(.bss.heap)
         sbi_heap_end = .;
+        . = ALIGN(0x1000);
+        *(.bss.pstore)
         . = ALIGN(0x1000); 
         sbi_bss_start = .;
         *(.bss .bss.*)
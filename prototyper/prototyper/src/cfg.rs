@@ -26,5 +26,16 @@ pub const JUMP_ADDRESS: usize = CONFIG.jump_address as usize;
 /// If the TLB refresh range is greater than TLB_FLUSH_LIMIT, the entire TLB is refreshed.
 pub const TLB_FLUSH_LIMIT: usize = CONFIG.tlb_flush_limit as usize;
 
+/// Emulate `time` reads with the platform timer.
+pub const EMULATE_TIME: bool = CONFIG.emulation.time;
+/// Emulate `cycle` reads with `mcycle`.
+pub const EMULATE_CYCLE: bool = CONFIG.emulation.cycle;
+/// Emulate `instret` reads with `minstret`.
+pub const EMULATE_INSTRET: bool = CONFIG.emulation.instret;
+/// Emulate `stimecmp` reads with the platform timer on harts without Sstc.
+pub const EMULATE_STIMECMP: bool = CONFIG.emulation.stimecmp;
+/// Emulate AMO and LR/SC instructions on harts without the A extension.
+pub const EMULATE_ATOMIC: bool = CONFIG.emulation.atomic;
+
 /// The dynamic valid next addr ranges.
 pub const DYNAMIC_NEXT_ADDR_RANGE: &NextAddr = &CONFIG.next_addr;
@@ -5,6 +5,9 @@ use serde_device_tree::{
     value::riscv_pmu::{EventToMhpmcounters, EventToMhpmevent, RawEventToMhpcounters},
 };
 
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::ops::Range;
 
 /// Root device tree structure containing system information.
@@ -22,6 +25,8 @@ pub struct Tree<'a> {
 #[derive(Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Cpus<'a> {
+    /// Machine timer frequency in Hz.
+    pub timebase_frequency: Option<u32>,
     /// Sequence of CPU nodes.
     pub cpu: NodeSeq<'a>,
 }
@@ -36,6 +41,24 @@ pub struct Cpu<'a> {
     pub isa: Option<StrSeq<'a>>,
     /// CPU register information.
     pub reg: Reg<'a>,
+    /// CPU clock frequency in Hz.
+    #[serde(rename = "clock-frequency")]
+    pub clock_frequency: Option<u32>,
+    /// CPPC highest performance level.
+    #[serde(rename = "rustsbi,cppc-highest-perf")]
+    pub cppc_highest_perf: Option<u32>,
+    /// CPPC nominal performance level.
+    #[serde(rename = "rustsbi,cppc-nominal-perf")]
+    pub cppc_nominal_perf: Option<u32>,
+    /// CPPC lowest nonlinear performance level.
+    #[serde(rename = "rustsbi,cppc-lowest-nonlinear-perf")]
+    pub cppc_lowest_nonlinear_perf: Option<u32>,
+    /// CPPC lowest performance level.
+    #[serde(rename = "rustsbi,cppc-lowest-perf")]
+    pub cppc_lowest_perf: Option<u32>,
+    /// CPPC performance state transition latency in nanoseconds.
+    #[serde(rename = "rustsbi,cppc-transition-latency")]
+    pub cppc_transition_latency: Option<u32>,
 }
 
 /// Generic device node information.
@@ -112,3 +135,34 @@ pub fn get_compatible<'de>(node: &Node) -> Option<StrSeq<'de>> {
         None
     }
 }
+
+/// Gets a property as a sequence of 32-bit cells.
+pub fn get_prop_cells(node: &Node, name: &str) -> Option<Vec<u32>> {
+    node.get_prop(name).map(|prop_item| {
+        prop_item
+            .deserialize::<&[u8]>()
+            .chunks_exact(4)
+            .map(|cell| u32::from_be_bytes([cell[0], cell[1], cell[2], cell[3]]))
+            .collect()
+    })
+}
+
+/// Gets a single-cell property.
+pub fn get_prop_u32(node: &Node, name: &str) -> Option<u32> {
+    get_prop_cells(node, name).and_then(|cells| cells.first().copied())
+}
+
+/// Finds the path of the node at `base` whose name is one of `names`.
+///
+/// Only direct children of the root node and of `/soc` are looked up.
+pub fn find_node_path(root: &Node, names: &[&str], base: usize) -> Option<String> {
+    for parent in ["", "/soc"] {
+        for name in names {
+            let path = format!("{}/{}@{:x}", parent, name, base);
+            if root.find(&path).is_some() {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
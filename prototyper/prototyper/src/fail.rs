@@ -1,32 +1,29 @@
-use crate::riscv::current_hartid;
 use serde_device_tree::Dtb;
 
 use crate::devicetree;
+use crate::sbi::crash;
 
 use riscv::interrupt::machine::{Exception, Interrupt};
-use riscv::register::{mcause::Trap, mepc, mtval};
+use riscv::register::mcause::Trap;
 
 #[cfg(all(feature = "payload", feature = "jump"))]
 compile_error!("feature \"payload\" and feature \"jump\" cannot be enabled at the same time");
 
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
-    use ::riscv::register::*;
-    error!("Hart {} {info}", current_hartid());
     error!("-----------------------------");
-    error!("mcause:  {:?}", mcause::read().cause());
-    error!("mepc:    {:#018x}", mepc::read());
-    error!("mtval:   {:#018x}", mtval::read());
+    crash::report_panic(info);
     error!("-----------------------------");
     error!("System shutdown scheduled due to RustSBI panic");
     loop {}
 }
 
-pub fn unsupported_trap(trap: Option<Trap<Interrupt, Exception>>) -> ! {
+pub fn unsupported_trap(
+    trap: Option<Trap<Interrupt, Exception>>,
+    regs: &crash::TrapRegisters,
+) -> ! {
     error!("-----------------------------");
-    error!("trap:    {trap:?}");
-    error!("mepc:    {:#018x}", mepc::read());
-    error!("mtval:   {:#018x}", mtval::read());
+    crash::report_trap(trap, regs);
     error!("-----------------------------");
     panic!("Stopped with unsupported trap")
 }
@@ -48,6 +45,58 @@ pub fn device_tree_deserialize_root<'a>(
     }
 }
 
+/// Handles invalid FIT images by logging details and resetting.
+#[cold]
+#[cfg(feature = "fit")]
+pub fn invalid_fit<T>(err: crate::firmware::fit::FitError) -> T {
+    use crate::firmware::fit::FitError;
+    match err {
+        FitError::Malformed => error!(
+            "Invalid FIT image: not a flattened image tree with /images in supervisor memory"
+        ),
+        FitError::NoConfiguration => error!("Invalid FIT image: no configuration to boot"),
+        FitError::MissingImage(name) => error!("Invalid FIT image: image {} not found", name),
+        FitError::Unsupported(name) => error!(
+            "Invalid FIT image: image {} is compressed, misaligned or lacks data or load address",
+            name
+        ),
+        FitError::HashMismatch(name) => {
+            error!(
+                "Invalid FIT image: SHA-256 hash of image {} does not match",
+                name
+            )
+        }
+        FitError::MissingHash(name) => {
+            error!("Invalid FIT image: image {} has no SHA-256 hash", name)
+        }
+        FitError::BadSignature(name) => {
+            error!(
+                "Invalid FIT image: image {} has no signature verified by a board key",
+                name
+            )
+        }
+        FitError::BadConfigSignature(name) => {
+            error!(
+                "Invalid FIT image: configuration {} has no signature verified by a board key",
+                name
+            )
+        }
+        FitError::OutOfMemory(name) => error!(
+            "Invalid FIT image: image {} would be loaded outside supervisor memory",
+            name
+        ),
+        FitError::Overlap(name) => error!(
+            "Invalid FIT image: image {} would be loaded over firmware, the FIT image, the board device tree or another image",
+            name
+        ),
+        FitError::BadEntry(name) => error!(
+            "Invalid FIT image: entry of image {} is outside the image or the allowed next stage addresses",
+            name
+        ),
+    }
+    crate::sbi::reset::fail()
+}
+
 #[cold]
 pub fn stop() -> ! {
     loop {
@@ -59,10 +108,9 @@ cfg_if::cfg_if! {
     if #[cfg(not(any(feature = "payload", feature = "jump")))] {
         use crate::firmware::dynamic;
         use crate::sbi::reset;
-        use riscv::register::mstatus;
         /// Handles invalid dynamic information data by logging details and resetting.
         #[cold]
-        pub fn invalid_dynamic_data(err: dynamic::DynamicError) -> (mstatus::MPP, usize) {
+        pub fn invalid_dynamic_data<T>(err: dynamic::DynamicError) -> T {
             error!("Invalid data in dynamic information:");
             if err.invalid_mpp {
                 error!("* dynamic information contains invalid privilege mode");
@@ -70,6 +118,15 @@ cfg_if::cfg_if! {
             if err.invalid_next_addr {
                 error!("* dynamic information contains invalid next jump address");
             }
+            if err.invalid_fdt_addr {
+                error!("* dynamic information contains misaligned device tree address");
+            }
+            if err.invalid_payload_size {
+                error!("* dynamic information contains payload size overlapping firmware");
+            }
+            if err.invalid_cmdline {
+                error!("* dynamic information contains invalid, too long or inaccessible command line");
+            }
             let explain_next_mode = match err.bad_info.next_mode {
                 3 => "Machine",
                 1 => "Supervisor",
@@ -80,6 +137,12 @@ cfg_if::cfg_if! {
                 "@ help: dynamic information contains magic value 0x{:x}, version {}, next jump address 0x{:x}, next privilege mode {} ({}), options {:x}, boot hart ID {}",
                 err.bad_info.magic, err.bad_info.version, err.bad_info.next_addr, err.bad_info.next_mode, explain_next_mode, err.bad_info.options, err.bad_info.boot_hart
             );
+            if err.bad_info.version >= 3 {
+                error!(
+                    "@ help: device tree address 0x{:x}, payload size 0x{:x}, command line address 0x{:x}",
+                    err.bad_info.fdt_addr, err.bad_info.payload_size, err.bad_info.cmdline
+                );
+            }
             reset::fail()
         }
 
@@ -119,12 +182,8 @@ cfg_if::cfg_if! {
         #[allow(unused)]
         pub fn use_lottery(_err: dynamic::DynamicReadError) -> dynamic::DynamicInfo {
             dynamic::DynamicInfo {
-                magic: 0,
-                version: 0,
-                next_addr: 0,
-                next_mode: 0,
-                options: 0,
                 boot_hart: usize::MAX,
+                ..dynamic::DynamicInfo::EMPTY
             }
         }
     }
@@ -1,9 +1,14 @@
 //! Frequently used first boot stage dynamic information on RISC-V.
 
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::ops::Range;
+use log::LevelFilter;
 
-use super::BootInfo;
+use super::{BootInfo, BootOptions};
 use crate::fail;
+use crate::platform::PLATFORM;
+use crate::riscv::current_hartid;
 
 use riscv::register::mstatus;
 
@@ -13,9 +18,11 @@ use riscv::register::mstatus;
 pub fn get_boot_info(nonstandard_a2: usize) -> BootInfo {
     let dynamic_info = read_paddr(nonstandard_a2).unwrap_or_else(fail::no_dynamic_info_available);
     let (mpp, next_addr) = mpp_next_addr(&dynamic_info).unwrap_or_else(fail::invalid_dynamic_data);
+    let extension = extension(&dynamic_info).unwrap_or_else(fail::invalid_dynamic_data);
     BootInfo {
         next_address: next_addr,
         mpp,
+        cmdline: extension.cmdline,
     }
 }
 
@@ -35,6 +42,27 @@ pub struct DynamicInfo {
     pub options: usize,
     /// Boot hart ID of current environment.
     pub boot_hart: usize,
+    /// Device tree address overriding the one in `a1`, 0 for none (version 3).
+    pub fdt_addr: usize,
+    /// Size of the next stage image in bytes, 0 if unknown (version 3).
+    pub payload_size: usize,
+    /// Address of a NUL-terminated command line for the next stage, 0 for none (version 3).
+    pub cmdline: usize,
+}
+
+/// Bits of [`DynamicInfo::options`].
+///
+/// Bits 0 and 1 match OpenSBI, the others are specific to RustSBI Prototyper.
+pub mod options {
+    /// Skips the banner and boot information.
+    pub const NO_BOOT_PRINTS: usize = 1 << 0;
+    /// Logs at debug level, unless a log level is given.
+    pub const DEBUG_PRINTS: usize = 1 << 1;
+    /// Hands the device tree to the next stage without patching it.
+    pub const NO_FDT_PATCH: usize = 1 << 8;
+    /// Log level, 1 to 5 for error to trace, 0 keeps the configured one.
+    pub const LOG_LEVEL_SHIFT: usize = 9;
+    pub const LOG_LEVEL_MASK: usize = 0x7 << LOG_LEVEL_SHIFT;
 }
 
 // Definition of `boot_hart` can be found at:
@@ -42,7 +70,11 @@ pub struct DynamicInfo {
 
 const DYNAMIC_INFO_INVALID_ADDRESSES: usize = 0x00000000;
 pub(crate) const MAGIC: usize = 0x4942534f;
-const SUPPORTED_VERSION: Range<usize> = 0..3;
+const SUPPORTED_VERSION: Range<usize> = 0..4;
+/// First version with `fdt_addr`, `payload_size` and `cmdline`.
+const VERSION_EXTENDED: usize = 3;
+/// Longest command line accepted, without its terminating NUL.
+const CMDLINE_MAX: usize = 4096;
 
 /// Error type for dynamic info read failures.
 pub struct DynamicReadError {
@@ -66,7 +98,23 @@ pub fn read_paddr(paddr: usize) -> Result<DynamicInfo, DynamicReadError> {
         error.bad_paddr = Some(paddr);
         return Err(error);
     }
-    let ans = unsafe { *(paddr as *const DynamicInfo) };
+    let mut ans = DynamicInfo::EMPTY;
+    {
+        let _mapping = super::map_supervisor_memory(paddr, size_of::<DynamicInfo>());
+        // Older versions end before the extended fields.
+        let version = unsafe { (paddr as *const usize).add(1).read() };
+        let len = match version >= VERSION_EXTENDED {
+            true => size_of::<DynamicInfo>(),
+            false => core::mem::offset_of!(DynamicInfo, fdt_addr),
+        };
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                paddr as *const u8,
+                &mut ans as *mut DynamicInfo as *mut u8,
+                len,
+            )
+        };
+    }
 
     // Validate magic number and version.
     if ans.magic != MAGIC {
@@ -81,27 +129,80 @@ pub fn read_paddr(paddr: usize) -> Result<DynamicInfo, DynamicReadError> {
     Ok(ans)
 }
 
+impl DynamicInfo {
+    pub const EMPTY: Self = DynamicInfo {
+        magic: 0,
+        version: 0,
+        next_addr: 0,
+        next_mode: 0,
+        options: 0,
+        boot_hart: 0,
+        fdt_addr: 0,
+        payload_size: 0,
+        cmdline: 0,
+    };
+}
+
+/// Decodes firmware options of dynamic info.
+pub fn boot_options(info: &DynamicInfo) -> BootOptions {
+    let log_level = match (info.options & options::LOG_LEVEL_MASK) >> options::LOG_LEVEL_SHIFT {
+        0 if info.options & options::DEBUG_PRINTS != 0 => Some(LevelFilter::Debug),
+        0 => None,
+        1 => Some(LevelFilter::Error),
+        2 => Some(LevelFilter::Warn),
+        3 => Some(LevelFilter::Info),
+        4 => Some(LevelFilter::Debug),
+        _ => Some(LevelFilter::Trace),
+    };
+    BootOptions {
+        no_boot_prints: info.options & options::NO_BOOT_PRINTS != 0,
+        log_level,
+        no_fdt_patch: info.options & options::NO_FDT_PATCH != 0,
+        boot_hart: (info.boot_hart != usize::MAX).then_some(info.boot_hart),
+    }
+}
+
+/// Gets the device tree address overriding `a1`, if any.
+///
+/// Misaligned addresses are ignored here and reported by [`extension`] once the
+/// console is up.
+pub fn fdt_override(info: &DynamicInfo) -> Option<usize> {
+    let valid =
+        info.version >= VERSION_EXTENDED && info.fdt_addr != 0 && info.fdt_addr.is_multiple_of(8);
+    valid.then_some(info.fdt_addr)
+}
+
 /// Error type for dynamic info validation failures.
 pub struct DynamicError<'a> {
     pub invalid_mpp: bool,
     pub invalid_next_addr: bool,
+    pub invalid_fdt_addr: bool,
+    pub invalid_payload_size: bool,
+    pub invalid_cmdline: bool,
     pub bad_info: &'a DynamicInfo,
 }
 
+impl<'a> DynamicError<'a> {
+    fn new(info: &'a DynamicInfo) -> Self {
+        DynamicError {
+            invalid_mpp: false,
+            invalid_next_addr: false,
+            invalid_fdt_addr: false,
+            invalid_payload_size: false,
+            invalid_cmdline: false,
+            bad_info: info,
+        }
+    }
+}
+
 /// Validates and extracts privilege mode and next address from dynamic info.
 ///
 /// Returns Result containing tuple of (MPP, next_addr) or error details.
 pub fn mpp_next_addr(info: &DynamicInfo) -> Result<(mstatus::MPP, usize), DynamicError<'_>> {
-    let mut error = DynamicError {
-        invalid_mpp: false,
-        invalid_next_addr: false,
-        bad_info: info,
-    };
+    let mut error = DynamicError::new(info);
 
     // fail safe, errors will be aggregated after whole checking process.
-    let next_addr_valid = crate::cfg::DYNAMIC_NEXT_ADDR_RANGE
-        .iter()
-        .any(|r| info.next_addr >= r.start as usize && info.next_addr < r.end as usize);
+    let next_addr_valid = unsafe { PLATFORM.info.config.next_addr_allowed(info.next_addr) };
     let mpp_valid = matches!(info.next_mode, 0 | 1 | 3);
 
     if !next_addr_valid {
@@ -125,3 +226,66 @@ pub fn mpp_next_addr(info: &DynamicInfo) -> Result<(mstatus::MPP, usize), Dynami
 
     Ok((mpp, info.next_addr))
 }
+
+/// Validated extended fields of dynamic info.
+pub struct DynamicExtension {
+    pub cmdline: Option<String>,
+}
+
+/// Validates and extracts extended fields of dynamic info version 3.
+///
+/// The next stage image must not overlap the firmware, and the command line must be
+/// UTF-8 of at most [`CMDLINE_MAX`] bytes in supervisor memory.
+pub fn extension(info: &DynamicInfo) -> Result<DynamicExtension, DynamicError<'_>> {
+    if info.version < VERSION_EXTENDED {
+        return Ok(DynamicExtension { cmdline: None });
+    }
+    let mut error = DynamicError::new(info);
+
+    if !info.fdt_addr.is_multiple_of(8) {
+        error.invalid_fdt_addr = true;
+    }
+    let firmware = super::firmware_range();
+    let payload_valid = info.payload_size == 0
+        || info
+            .next_addr
+            .checked_add(info.payload_size)
+            .is_some_and(|end| end <= firmware.start || info.next_addr >= firmware.end);
+    if !payload_valid {
+        error.invalid_payload_size = true;
+    }
+    // Only the root domain boots with the command line.
+    let root = unsafe { PLATFORM.hart_domain(current_hartid()).is_none() };
+    let cmdline = match info.cmdline {
+        0 => None,
+        _ if !root => None,
+        addr => {
+            let cmdline = read_cmdline(addr);
+            error.invalid_cmdline = cmdline.is_none();
+            cmdline
+        }
+    };
+
+    if error.invalid_fdt_addr || error.invalid_payload_size || error.invalid_cmdline {
+        return Err(error);
+    }
+    Ok(DynamicExtension { cmdline })
+}
+
+/// Reads the NUL-terminated command line at `addr`.
+fn read_cmdline(addr: usize) -> Option<String> {
+    // A short command line may end close to the end of its memory region.
+    let len = (1..=CMDLINE_MAX + 1)
+        .take_while(|&len| unsafe { PLATFORM.is_supervisor_memory(addr, len) })
+        .last()?;
+    let _mapping = super::map_supervisor_memory(addr, len);
+    let mut bytes = Vec::new();
+    for offset in 0..len {
+        // Stops at the terminating NUL, as memory after it may not exist.
+        match unsafe { ((addr + offset) as *const u8).read_volatile() } {
+            0 => return String::from_utf8(bytes).ok(),
+            byte => bytes.push(byte),
+        }
+    }
+    None
+}
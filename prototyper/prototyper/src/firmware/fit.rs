@@ -0,0 +1,679 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+use sha2::{Digest, Sha256};
+
+use super::{NextImage, dtb_total_size, firmware_range, map_supervisor_memory};
+use crate::fail;
+use crate::platform::PLATFORM;
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+
+/// Size of the buffer images are relocated through.
+const BOUNCE_SIZE: usize = 4096;
+
+/// Error type for FIT image validation failures.
+pub enum FitError<'a> {
+    /// The image tree is not a valid flattened device tree with `/images`, or it runs
+    /// past supervisor memory.
+    Malformed,
+    /// No configuration to boot from `/configurations`.
+    NoConfiguration,
+    /// The selected configuration refers to a missing image.
+    MissingImage(&'a str),
+    /// The image is compressed, or lacks its data or load address.
+    Unsupported(&'a str),
+    /// A SHA-256 hash of the image does not match its data.
+    HashMismatch(&'a str),
+    /// The image has no SHA-256 hash, required with feature `fit-signature`.
+    MissingHash(&'a str),
+    /// No signature of the image is verified by a trusted key.
+    BadSignature(&'a str),
+    /// The configuration is signed, but no signature is verified by a trusted key.
+    BadConfigSignature(&'a str),
+    /// The image would be loaded outside supervisor memory.
+    OutOfMemory(&'a str),
+    /// The image would be loaded over firmware, the FIT image, the board device tree or
+    /// another image.
+    Overlap(&'a str),
+    /// The kernel entry lies outside the loaded kernel or the allowed next stage ranges.
+    BadEntry(&'a str),
+}
+
+/// Checks whether the next stage at `addr` is a FIT image rather than a raw binary.
+pub fn is_fit(addr: usize) -> bool {
+    let _mapping = map_supervisor_memory(addr, 4);
+    u32::from_be(unsafe { (addr as *const u32).read_volatile() }) == FDT_MAGIC
+}
+
+/// Loads the next stage from the FIT image at `addr`.
+///
+/// The configuration whose `description` or `compatible` matches the board model is
+/// selected, otherwise the default one. Hashes, and signatures with feature
+/// `fit-signature`, of its images are checked before they are copied to their `load`
+/// addresses in supervisor memory. `board_fdt` is used if the configuration has no
+/// device tree.
+pub fn load(addr: usize, board_fdt: usize) -> NextImage {
+    #[cfg(feature = "fit-signature")]
+    let keys = signature::board_keys(board_fdt);
+
+    let extent = fit_extent(addr);
+    let _mapping = map_supervisor_memory(addr, extent.len());
+    let blob = unsafe { core::slice::from_raw_parts(addr as *const u8, extent.len()) };
+    let root = Fdt::new(blob)
+        .and_then(Fdt::root)
+        .unwrap_or_else(|| fail::invalid_fit(FitError::Malformed));
+    let images = root
+        .child("images")
+        .unwrap_or_else(|| fail::invalid_fit(FitError::Malformed));
+    let config =
+        select_config(root).unwrap_or_else(|| fail::invalid_fit(FitError::NoConfiguration));
+    info!("{:<30}: {}", "FIT Configuration", config.name);
+    #[cfg(feature = "fit-signature")]
+    let signed_nodes = signature::verify_config(&keys, config);
+
+    let board_fdt_range = board_fdt..board_fdt + dtb_total_size(board_fdt);
+    let mut loaded: Vec<Range<usize>> = Vec::new();
+    let mut load_image = |kind: &str| {
+        let name = config.str_prop(kind)?;
+        let image = images
+            .child(name)
+            .unwrap_or_else(|| fail::invalid_fit(FitError::MissingImage(name)));
+        let data = image_data(blob, image)
+            .unwrap_or_else(|| fail::invalid_fit(FitError::Unsupported(name)));
+        verify_hashes(image, data);
+        #[cfg(feature = "fit-signature")]
+        if !signature::is_covered(signed_nodes.as_deref(), image)
+            && !signature::verify(&keys, image, data)
+        {
+            return fail::invalid_fit(FitError::BadSignature(name));
+        }
+
+        let Some(load) = image.addr_prop("load") else {
+            // Kernels must be placed, other images may be used where they are.
+            if kind == "kernel" {
+                return fail::invalid_fit(FitError::Unsupported(name));
+            }
+            let start = data.as_ptr() as usize;
+            return Some((start..start + data.len(), image.addr_prop("entry")));
+        };
+        let target = load..load
+            .checked_add(data.len())
+            .unwrap_or_else(|| fail::invalid_fit(FitError::Overlap(name)));
+        if !unsafe { PLATFORM.is_supervisor_memory(load, data.len()) } {
+            return fail::invalid_fit(FitError::OutOfMemory(name));
+        }
+        let firmware = firmware_range();
+        let overlaps = |other: &Range<usize>| target.start < other.end && other.start < target.end;
+        if overlaps(&firmware)
+            || overlaps(&extent)
+            || overlaps(&board_fdt_range)
+            || loaded.iter().any(overlaps)
+        {
+            return fail::invalid_fit(FitError::Overlap(name));
+        }
+        relocate(data, load);
+        info!(
+            "{:<30}: {} at {:#x}, {} bytes",
+            "FIT Image",
+            name,
+            load,
+            data.len()
+        );
+        loaded.push(target.clone());
+        Some((target, image.addr_prop("entry")))
+    };
+
+    let (kernel, entry) =
+        load_image("kernel").unwrap_or_else(|| fail::invalid_fit(FitError::MissingImage("kernel")));
+    let next_addr = entry.unwrap_or(kernel.start);
+    if !kernel.contains(&next_addr) || !unsafe { PLATFORM.info.config.next_addr_allowed(next_addr) }
+    {
+        return fail::invalid_fit(FitError::BadEntry(config.str_prop("kernel").unwrap()));
+    }
+    let initrd = load_image("ramdisk").map(|(range, _)| range);
+    let fdt_address = match load_image("fdt") {
+        Some((range, _)) if range.start.is_multiple_of(8) => range.start,
+        Some(_) => fail::invalid_fit(FitError::Unsupported(config.str_prop("fdt").unwrap())),
+        None => board_fdt,
+    };
+
+    NextImage {
+        next_addr,
+        fdt_address,
+        initrd,
+    }
+}
+
+/// Gets the memory range of the FIT image at `addr`, including external data.
+///
+/// The whole range must lie in supervisor memory before the image is read.
+fn fit_extent(addr: usize) -> Range<usize> {
+    let size = dtb_total_size(addr);
+    if !unsafe { PLATFORM.is_supervisor_memory(addr, size) } {
+        return fail::invalid_fit(FitError::Malformed);
+    }
+    let _mapping = map_supervisor_memory(addr, size);
+    let blob = unsafe { core::slice::from_raw_parts(addr as *const u8, size) };
+    let images = Fdt::new(blob)
+        .and_then(Fdt::root)
+        .and_then(|root| root.child("images"))
+        .unwrap_or_else(|| fail::invalid_fit(FitError::Malformed));
+    let end = images
+        .children()
+        .filter_map(|image| {
+            let (start, size) = external_data(size, image)?;
+            start.checked_add(size)
+        })
+        .fold(size, usize::max);
+    match addr.checked_add(end) {
+        Some(extent_end) if unsafe { PLATFORM.is_supervisor_memory(addr, end) } => addr..extent_end,
+        _ => fail::invalid_fit(FitError::Malformed),
+    }
+}
+
+/// Selects the configuration matching the board model, or the default one.
+fn select_config<'a>(root: FdtNode<'a>) -> Option<FdtNode<'a>> {
+    let configs = root.child("configurations")?;
+    let model = unsafe { PLATFORM.info.model.as_str() };
+    configs
+        .children()
+        .find(|config| {
+            config.str_prop("description") == Some(model)
+                || config
+                    .strings("compatible")
+                    .any(|compatible| compatible == model)
+        })
+        .or_else(|| {
+            configs
+                .str_prop("default")
+                .and_then(|name| configs.child(name))
+        })
+        .or_else(|| configs.children().next())
+}
+
+/// Gets data of `image`, embedded in the tree or external to it.
+fn image_data<'a>(blob: &'a [u8], image: FdtNode<'a>) -> Option<&'a [u8]> {
+    if image
+        .str_prop("compression")
+        .is_some_and(|compression| compression != "none")
+    {
+        return None;
+    }
+    if let Some(data) = image.prop("data") {
+        return Some(data);
+    }
+    let (start, size) = external_data(image.fdt.total_size, image)?;
+    blob.get(start..start.checked_add(size)?)
+}
+
+/// Gets offset and size of external data, as placed by `mkimage -E`.
+///
+/// `data-position` is relative to the image start, `data-offset` to the end of the tree.
+fn external_data(total_size: usize, image: FdtNode) -> Option<(usize, usize)> {
+    let size = image.addr_prop("data-size")?;
+    let start = match image.addr_prop("data-position") {
+        Some(position) => position,
+        None => total_size
+            .next_multiple_of(4)
+            .checked_add(image.addr_prop("data-offset")?)?,
+    };
+    Some((start, size))
+}
+
+/// Checks SHA-256 hash nodes of `image`, other algorithms are skipped.
+fn verify_hashes(image: FdtNode, data: &[u8]) {
+    let mut verified = false;
+    for hash in image
+        .children()
+        .filter(|node| node.name.starts_with("hash"))
+    {
+        match hash.str_prop("algo") {
+            Some("sha256") => {
+                if hash.prop("value") != Some(&Sha256::digest(data)[..]) {
+                    return fail::invalid_fit(FitError::HashMismatch(image.name));
+                }
+                verified = true;
+            }
+            algo => warn!(
+                "FIT image {}: hash algorithm {:?} is not supported, skipped",
+                image.name, algo
+            ),
+        }
+    }
+    if !verified {
+        // Signed configurations cover image data only through hashes.
+        #[cfg(feature = "fit-signature")]
+        fail::invalid_fit::<()>(FitError::MissingHash(image.name));
+        #[cfg(not(feature = "fit-signature"))]
+        warn!("FIT image {} has no SHA-256 hash", image.name);
+    }
+}
+
+/// Copies `data` to `dest` in supervisor memory.
+///
+/// Only one range of supervisor memory is mapped at a time, so data goes through a
+/// buffer in firmware memory while the FIT image is unmapped.
+fn relocate(data: &[u8], dest: usize) {
+    let mut buffer = vec![0u8; BOUNCE_SIZE];
+    for (index, chunk) in data.chunks(BOUNCE_SIZE).enumerate() {
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let chunk_dest = dest + index * BOUNCE_SIZE;
+        let _mapping = map_supervisor_memory(chunk_dest, chunk.len());
+        unsafe {
+            core::ptr::copy_nonoverlapping(buffer.as_ptr(), chunk_dest as *mut u8, chunk.len())
+        };
+    }
+}
+
+/// Minimal reader of flattened device trees.
+///
+/// FIT images carry binary properties and node names chosen by their authors, which
+/// the device tree deserializer does not model.
+#[derive(Clone, Copy)]
+struct Fdt<'a> {
+    structs: &'a [u8],
+    strings: &'a [u8],
+    total_size: usize,
+}
+
+/// Node of a [`Fdt`], `offset` being where its properties start.
+#[derive(Clone, Copy)]
+struct FdtNode<'a> {
+    fdt: Fdt<'a>,
+    name: &'a str,
+    offset: usize,
+}
+
+enum FdtEntry<'a> {
+    Prop(&'a str, &'a [u8]),
+    Node(FdtNode<'a>),
+    Nop,
+}
+
+fn be32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn cstr(bytes: &[u8]) -> Option<&str> {
+    let len = bytes.iter().position(|&byte| byte == 0)?;
+    core::str::from_utf8(&bytes[..len]).ok()
+}
+
+impl<'a> Fdt<'a> {
+    fn new(blob: &'a [u8]) -> Option<Self> {
+        let header = |index: usize| be32(blob, index * 4).map(|word| word as usize);
+        if header(0)? != FDT_MAGIC as usize {
+            return None;
+        }
+        let total_size = header(1)?;
+        let (structs_offset, strings_offset) = (header(2)?, header(3)?);
+        let (strings_size, structs_size) = (header(8)?, header(9)?);
+        let blob = blob.get(..total_size)?;
+        Some(Fdt {
+            structs: blob.get(structs_offset..structs_offset.checked_add(structs_size)?)?,
+            strings: blob.get(strings_offset..strings_offset.checked_add(strings_size)?)?,
+            total_size,
+        })
+    }
+
+    fn root(self) -> Option<FdtNode<'a>> {
+        let mut offset = 0;
+        while be32(self.structs, offset)? == FDT_NOP {
+            offset += 4;
+        }
+        self.node_at(offset)
+    }
+
+    fn node_at(self, offset: usize) -> Option<FdtNode<'a>> {
+        if be32(self.structs, offset)? != FDT_BEGIN_NODE {
+            return None;
+        }
+        let name = cstr(self.structs.get(offset + 4..)?)?;
+        Some(FdtNode {
+            fdt: self,
+            name,
+            offset: (offset + 4 + name.len() + 1).next_multiple_of(4),
+        })
+    }
+
+    /// Reads the entry at `offset` and the offset after it, `None` at the end of a node.
+    fn entry(self, offset: usize) -> Option<(FdtEntry<'a>, usize)> {
+        match be32(self.structs, offset)? {
+            FDT_PROP => {
+                let len = be32(self.structs, offset + 4)? as usize;
+                let name_offset = be32(self.structs, offset + 8)? as usize;
+                let value = self
+                    .structs
+                    .get(offset + 12..(offset + 12).checked_add(len)?)?;
+                let name = cstr(self.strings.get(name_offset..)?)?;
+                Some((
+                    FdtEntry::Prop(name, value),
+                    (offset + 12 + len).next_multiple_of(4),
+                ))
+            }
+            FDT_BEGIN_NODE => {
+                let node = self.node_at(offset)?;
+                Some((FdtEntry::Node(node), node.end()?))
+            }
+            FDT_NOP => Some((FdtEntry::Nop, offset + 4)),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FdtNode<'a> {
+    /// Gets the offset after the end of this node.
+    fn end(self) -> Option<usize> {
+        let mut offset = self.offset;
+        while be32(self.fdt.structs, offset)? != FDT_END_NODE {
+            offset = self.fdt.entry(offset)?.1;
+        }
+        Some(offset + 4)
+    }
+
+    fn entries(self) -> impl Iterator<Item = FdtEntry<'a>> {
+        let mut offset = self.offset;
+        core::iter::from_fn(move || {
+            let (entry, next) = self.fdt.entry(offset)?;
+            offset = next;
+            Some(entry)
+        })
+    }
+
+    fn children(self) -> impl Iterator<Item = FdtNode<'a>> {
+        self.entries().filter_map(|entry| match entry {
+            FdtEntry::Node(node) => Some(node),
+            _ => None,
+        })
+    }
+
+    fn child(self, name: &str) -> Option<FdtNode<'a>> {
+        self.children().find(|node| node.name == name)
+    }
+
+    fn prop(self, name: &str) -> Option<&'a [u8]> {
+        self.entries().find_map(|entry| match entry {
+            FdtEntry::Prop(prop, value) if prop == name => Some(value),
+            _ => None,
+        })
+    }
+
+    fn str_prop(self, name: &str) -> Option<&'a str> {
+        cstr(self.prop(name)?)
+    }
+
+    /// Iterates over items of string list property `name`.
+    fn strings(self, name: &str) -> impl Iterator<Item = &'a str> {
+        self.prop(name)
+            .unwrap_or_default()
+            .split(|&byte| byte == 0)
+            .filter(|item| !item.is_empty())
+            .filter_map(|item| core::str::from_utf8(item).ok())
+    }
+
+    /// Reads address property `name` of one or two cells.
+    fn addr_prop(self, name: &str) -> Option<usize> {
+        match self.prop(name)? {
+            [a, b, c, d] => Some(u32::from_be_bytes([*a, *b, *c, *d]) as usize),
+            value @ [_, _, _, _, _, _, _, _] => {
+                Some(u64::from_be_bytes(value.try_into().unwrap()) as usize)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Signature checks of FIT images against keys of the board device tree.
+///
+/// Keys are looked up in `/signature` with the properties `mkimage -K` writes, and
+/// configuration and image signatures of `sha256,rsa*` and `sha256,ecdsa256` are
+/// supported.
+#[cfg(feature = "fit-signature")]
+mod signature {
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use p256::ecdsa::signature::hazmat::PrehashVerifier;
+    use p256::ecdsa::{Signature, VerifyingKey};
+    use rsa::{BigUint, Pkcs1v15Sign, RsaPublicKey};
+    use sha2::digest::Output;
+    use sha2::{Digest, Sha256};
+
+    use super::{
+        FDT_BEGIN_NODE, FDT_END_NODE, FDT_NOP, FDT_PROP, Fdt, FdtNode, FitError, be32, cstr,
+        dtb_total_size, map_supervisor_memory,
+    };
+    use crate::fail;
+
+    /// RSA exponent used by `mkimage` when `rsa,exponent` is absent.
+    const DEFAULT_RSA_EXPONENT: u64 = 65537;
+
+    /// Properties left out of configuration signatures, image hashes cover the data.
+    const EXCLUDED_PROPS: [&str; 4] = ["data", "data-size", "data-position", "data-offset"];
+
+    const FDT_END: u32 = 9;
+
+    enum KeyData {
+        Rsa { modulus: Vec<u8>, exponent: u64 },
+        Ecdsa { x: Vec<u8>, y: Vec<u8> },
+    }
+
+    /// Public key copied out of the board device tree.
+    pub struct PublicKey {
+        name: String,
+        data: KeyData,
+    }
+
+    /// Copies keys of `/signature/key-*` out of the board device tree at `fdt_address`.
+    pub fn board_keys(fdt_address: usize) -> Vec<PublicKey> {
+        let size = dtb_total_size(fdt_address);
+        let _mapping = map_supervisor_memory(fdt_address, size);
+        let blob = unsafe { core::slice::from_raw_parts(fdt_address as *const u8, size) };
+        let Some(signature) = Fdt::new(blob)
+            .and_then(Fdt::root)
+            .and_then(|root| root.child("signature"))
+        else {
+            warn!("Board device tree has no /signature node to verify FIT images");
+            return Vec::new();
+        };
+        signature
+            .children()
+            .filter_map(|key| {
+                let name = key.name.strip_prefix("key-")?;
+                let data = if let Some(modulus) = key.prop("rsa,modulus") {
+                    let exponent = key
+                        .prop("rsa,exponent")
+                        .and_then(|value| value.try_into().ok())
+                        .map_or(DEFAULT_RSA_EXPONENT, u64::from_be_bytes);
+                    KeyData::Rsa {
+                        modulus: modulus.to_vec(),
+                        exponent,
+                    }
+                } else {
+                    KeyData::Ecdsa {
+                        x: key.prop("ecdsa,x-point")?.to_vec(),
+                        y: key.prop("ecdsa,y-point")?.to_vec(),
+                    }
+                };
+                Some(PublicKey {
+                    name: String::from(name),
+                    data,
+                })
+            })
+            .collect()
+    }
+
+    /// Checks whether a signature node of `image` is verified by one of `keys`.
+    pub fn verify(keys: &[PublicKey], image: FdtNode, data: &[u8]) -> bool {
+        let digest = Sha256::digest(data);
+        image
+            .children()
+            .filter(|node| node.name.starts_with("signature"))
+            .any(|signature| {
+                let (Some(algo), Some(value)) =
+                    (signature.str_prop("algo"), signature.prop("value"))
+                else {
+                    return false;
+                };
+                let hint = signature.str_prop("key-name-hint");
+                keys.iter()
+                    .filter(|key| hint.is_none_or(|hint| hint == key.name))
+                    .any(|key| key.verify(algo, &digest, value))
+            })
+    }
+
+    /// Checks signature nodes of configuration `config` over the nodes listed in their
+    /// `hashed-nodes`.
+    ///
+    /// Returns the signed node paths, or `None` if the configuration is not signed. A
+    /// signed configuration without a signature verified by one of `keys` is rejected.
+    pub fn verify_config<'a>(keys: &[PublicKey], config: FdtNode<'a>) -> Option<Vec<&'a str>> {
+        let config_path = format!("/configurations/{}", config.name);
+        let mut signatures = config
+            .children()
+            .filter(|node| node.name.starts_with("signature"))
+            .peekable();
+        signatures.peek()?;
+        let verified = signatures.find_map(|signature| {
+            let (Some(algo), Some(value)) = (signature.str_prop("algo"), signature.prop("value"))
+            else {
+                return None;
+            };
+            let nodes: Vec<&str> = signature.strings("hashed-nodes").collect();
+            // The configuration itself selects the images, so it must be signed too.
+            if !nodes.contains(&config_path.as_str()) {
+                return None;
+            }
+            let strings_size = match signature.prop("hashed-strings") {
+                Some(value) => be32(value, 4)? as usize,
+                None => 0,
+            };
+            let digest = region_digest(config.fdt, &nodes, strings_size)?;
+            let hint = signature.str_prop("key-name-hint");
+            keys.iter()
+                .filter(|key| hint.is_none_or(|hint| hint == key.name))
+                .any(|key| key.verify(algo, &digest, value))
+                .then_some(nodes)
+        });
+        match verified {
+            Some(nodes) => {
+                info!("{:<30}: {}", "FIT Signed Configuration", config.name);
+                Some(nodes)
+            }
+            None => fail::invalid_fit(FitError::BadConfigSignature(config.name)),
+        }
+    }
+
+    /// Checks whether `image` and its hash nodes are covered by configuration signature
+    /// `signed_nodes`.
+    pub fn is_covered(signed_nodes: Option<&[&str]>, image: FdtNode) -> bool {
+        let Some(nodes) = signed_nodes else {
+            return false;
+        };
+        let image_path = format!("/images/{}", image.name);
+        nodes.contains(&image_path.as_str())
+            && image
+                .children()
+                .filter(|node| node.name.starts_with("hash"))
+                .all(|hash| nodes.contains(&format!("{}/{}", image_path, hash.name).as_str()))
+    }
+
+    /// Hashes the regions of `fdt` a configuration signature covers, as `mkimage` selects
+    /// them.
+    ///
+    /// Listed nodes are hashed with their properties but external data, their direct
+    /// subnodes and the parents of listed nodes by their tags only. The first
+    /// `strings_size` bytes of the strings block follow the structure block.
+    fn region_digest(fdt: Fdt, nodes: &[&str], strings_size: usize) -> Option<Output<Sha256>> {
+        let mut hasher = Sha256::new();
+        let mut path = String::new();
+        // Path length and inclusion level of parent nodes: 2 for listed nodes, 1 for
+        // their subnodes, 0 for nodes left out.
+        let mut parents: Vec<(usize, u8)> = Vec::new();
+        let mut want = 0;
+        let mut offset = 0;
+        loop {
+            let (include, next) = match be32(fdt.structs, offset)? {
+                FDT_BEGIN_NODE => {
+                    let name = cstr(fdt.structs.get(offset + 4..)?)?;
+                    parents.push((path.len(), want));
+                    if !path.ends_with('/') {
+                        path.push('/');
+                    }
+                    path.push_str(name);
+                    want = if nodes.contains(&path.as_str()) {
+                        2
+                    } else {
+                        want.saturating_sub(1)
+                    };
+                    (want > 0, (offset + 4 + name.len() + 1).next_multiple_of(4))
+                }
+                FDT_END_NODE => {
+                    let include = want > 0;
+                    let (len, parent_want) = parents.pop()?;
+                    path.truncate(len);
+                    want = parent_want;
+                    (include, offset + 4)
+                }
+                FDT_PROP => {
+                    let len = be32(fdt.structs, offset + 4)? as usize;
+                    let name = cstr(fdt.strings.get(be32(fdt.structs, offset + 8)? as usize..)?)?;
+                    (
+                        want >= 2 && !EXCLUDED_PROPS.contains(&name),
+                        (offset + 12 + len).next_multiple_of(4),
+                    )
+                }
+                FDT_NOP => (want >= 2, offset + 4),
+                FDT_END => {
+                    hasher.update(fdt.structs.get(offset..offset + 4)?);
+                    break;
+                }
+                _ => return None,
+            };
+            if include {
+                hasher.update(fdt.structs.get(offset..next)?);
+            }
+            offset = next;
+        }
+        hasher.update(fdt.strings.get(..strings_size)?);
+        Some(hasher.finalize())
+    }
+
+    impl PublicKey {
+        fn verify(&self, algo: &str, digest: &[u8], signature: &[u8]) -> bool {
+            match (&self.data, algo) {
+                (
+                    KeyData::Rsa { modulus, exponent },
+                    "sha256,rsa2048" | "sha256,rsa3072" | "sha256,rsa4096",
+                ) => {
+                    let key = RsaPublicKey::new(
+                        BigUint::from_bytes_be(modulus),
+                        BigUint::from(*exponent),
+                    );
+                    key.is_ok_and(|key| {
+                        key.verify(Pkcs1v15Sign::new::<Sha256>(), digest, signature)
+                            .is_ok()
+                    })
+                }
+                (KeyData::Ecdsa { x, y }, "sha256,ecdsa256") => {
+                    // Uncompressed SEC1 point, which checks coordinate lengths.
+                    let point = [&[0x04], x.as_slice(), y.as_slice()].concat();
+                    let (Ok(key), Ok(signature)) = (
+                        VerifyingKey::from_sec1_bytes(&point),
+                        Signature::from_slice(signature),
+                    ) else {
+                        return false;
+                    };
+                    key.verify_prehash(digest, &signature).is_ok()
+                }
+                _ => false,
+            }
+        }
+    }
+}
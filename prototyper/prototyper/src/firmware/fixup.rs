@@ -0,0 +1,313 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::ops::Range;
+use serde::{Serialize, Serializer, ser::SerializeTuple};
+use serde_device_tree::buildin::Node;
+use serde_device_tree::ser::patch::Patch;
+use serde_device_tree::ser::serializer::ValueType;
+
+use crate::devicetree::{Cpu, Tree};
+use crate::platform::PLATFORM;
+use crate::sbi::features::{Extension, hart_extension_probe};
+
+/// Device tree fixup, collecting changes to the tree handed to the next stage.
+///
+/// `root` is the original device tree, which is not modified.
+pub type Fixup = fn(root: &Node, fixups: &mut Fixups);
+
+/// Fixups applied to every patched device tree, before those registered by platforms.
+pub(crate) const BUILTIN_FIXUPS: &[Fixup] = &[
+    reserve_firmware,
+    hide_machine_nodes,
+    trim_isa_extensions,
+    disable_harts,
+    isolate_domains,
+    add_firmware_node,
+];
+
+/// Value of a property set by a fixup.
+pub enum PropValue {
+    /// String, or a string list with items separated by `\0`.
+    String(String),
+    /// 32-bit cells.
+    Cells(Vec<u32>),
+}
+
+impl Serialize for PropValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PropValue::String(value) => serializer.serialize_str(value),
+            PropValue::Cells(cells) => {
+                let mut tuple = serializer.serialize_tuple(cells.len())?;
+                for cell in cells {
+                    tuple.serialize_element(cell)?;
+                }
+                tuple.end()
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ReservedMemory {
+    #[serde(rename = "#address-cells")]
+    address_cell: u32,
+    #[serde(rename = "#size-cells")]
+    size_cell: u32,
+    ranges: (),
+}
+
+#[derive(Serialize)]
+struct ReservedMemoryItem {
+    reg: [u32; 4],
+    #[serde(rename = "no-map")]
+    no_map: (),
+}
+
+#[derive(Serialize)]
+struct EmptyNode {}
+
+#[derive(Serialize)]
+struct FirmwareNode {
+    compatible: &'static str,
+    version: &'static str,
+    #[serde(rename = "sbi-extensions")]
+    sbi_extensions: String,
+}
+
+/// Node added by a fixup.
+enum NodeValue {
+    ReservedMemory(ReservedMemory),
+    ReservedMemoryItem(ReservedMemoryItem),
+    Empty(EmptyNode),
+    Firmware(FirmwareNode),
+}
+
+impl Serialize for NodeValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            NodeValue::ReservedMemory(node) => node.serialize(serializer),
+            NodeValue::ReservedMemoryItem(node) => node.serialize(serializer),
+            NodeValue::Empty(node) => node.serialize(serializer),
+            NodeValue::Firmware(node) => node.serialize(serializer),
+        }
+    }
+}
+
+/// Changes collected from fixups, turned into `serde_device_tree` patches.
+pub struct Fixups {
+    nodes: Vec<(String, NodeValue)>,
+    props: Vec<(String, PropValue)>,
+    has_reserved_memory: bool,
+    has_firmware: bool,
+}
+
+impl Fixups {
+    pub fn new(root: &Node) -> Self {
+        Self {
+            nodes: Vec::new(),
+            props: Vec::new(),
+            has_reserved_memory: root.find("/reserved-memory").is_some(),
+            has_firmware: root.find("/firmware").is_some(),
+        }
+    }
+
+    /// Sets property `path`, replacing its value if it exists.
+    pub fn set_prop(&mut self, path: String, value: PropValue) {
+        self.props.push((path, value));
+    }
+
+    /// Disables the node at `node_path` by setting its `status`.
+    pub fn disable(&mut self, node_path: &str) {
+        self.set_prop(
+            format!("{}/status", node_path),
+            PropValue::String(String::from("disabled")),
+        );
+    }
+
+    /// Reserves `range` from supervisor software as a `no-map` region.
+    pub fn reserve_memory(&mut self, name: &str, range: Range<usize>) {
+        if !self.has_reserved_memory {
+            self.has_reserved_memory = true;
+            self.nodes.push((
+                String::from("/reserved-memory"),
+                NodeValue::ReservedMemory(ReservedMemory {
+                    address_cell: 2,
+                    size_cell: 2,
+                    ranges: (),
+                }),
+            ));
+        }
+        let size = range.end - range.start;
+        self.nodes.push((
+            format!("/reserved-memory/{}@{:x}", name, range.start),
+            NodeValue::ReservedMemoryItem(ReservedMemoryItem {
+                reg: [
+                    (range.start >> 32) as u32,
+                    range.start as u32,
+                    (size >> 32) as u32,
+                    size as u32,
+                ],
+                no_map: (),
+            }),
+        ));
+    }
+
+    fn add_firmware_node(&mut self, node: FirmwareNode) {
+        if !self.has_firmware {
+            self.has_firmware = true;
+            self.nodes
+                .push((String::from("/firmware"), NodeValue::Empty(EmptyNode {})));
+        }
+        self.nodes
+            .push((String::from("/firmware/rustsbi"), NodeValue::Firmware(node)));
+    }
+
+    /// Builds patches of all collected changes, nodes before properties.
+    pub fn patches(&self) -> Vec<Patch<'_>> {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(path, node)| Patch::new(path.as_str(), node as _, ValueType::Node));
+        let props = self
+            .props
+            .iter()
+            .map(|(path, value)| Patch::new(path.as_str(), value as _, ValueType::Prop));
+        nodes.chain(props).collect()
+    }
+}
+
+/// Reserves firmware text and rodata, and firmware data, as separate regions.
+fn reserve_firmware(_root: &Node, fixups: &mut Fixups) {
+    let (sbi_start, sbi_end, _, rodata_end) = super::firmware_layout();
+    fixups.reserve_memory("mmode_resv0", sbi_start..rodata_end);
+    fixups.reserve_memory("mmode_resv1", rodata_end..sbi_end);
+}
+
+/// Hides M-mode only devices, which supervisor software must not drive.
+fn hide_machine_nodes(_root: &Node, fixups: &mut Fixups) {
+    for path in unsafe { &PLATFORM.info.machine_only_nodes } {
+        fixups.disable(path);
+    }
+}
+
+/// Finds the node of `hart_id`, unit addresses may be hexadecimal or decimal.
+fn cpu_node_path(root: &Node, hart_id: usize) -> Option<String> {
+    [
+        format!("/cpus/cpu@{:x}", hart_id),
+        format!("/cpus/cpu@{}", hart_id),
+    ]
+    .into_iter()
+    .find(|path| root.find(path).is_some())
+}
+
+/// Removes extensions the firmware detected as unsupported from `riscv,isa-extensions`.
+fn trim_isa_extensions(root: &Node, fixups: &mut Fixups) {
+    let tree: Tree = root.deserialize();
+    for cpu_iter in tree.cpus.cpu.iter() {
+        let cpu = cpu_iter.deserialize::<Cpu>();
+        let hart_id = cpu.reg.iter().next().unwrap().0.start;
+        let Some(isa_extensions) = cpu.isa_extensions else {
+            continue;
+        };
+        let unsupported = |ext: &str| {
+            Extension::iter()
+                .any(|known| known.as_str() == ext && !hart_extension_probe(hart_id, known))
+        };
+        if !isa_extensions.iter().any(unsupported) {
+            continue;
+        }
+        let kept: Vec<&str> = isa_extensions
+            .iter()
+            .filter(|ext| !unsupported(*ext))
+            .collect();
+        if let Some(path) = cpu_node_path(root, hart_id) {
+            fixups.set_prop(
+                format!("{}/riscv,isa-extensions", path),
+                PropValue::String(kept.join("\0")),
+            );
+        }
+    }
+}
+
+/// Disables harts which are not enabled, or which belong to an isolation domain.
+fn disable_harts(root: &Node, fixups: &mut Fixups) {
+    let platform = unsafe { &PLATFORM };
+    let Some(cpu_enabled) = &platform.info.cpu_enabled else {
+        return;
+    };
+    let tree: Tree = root.deserialize();
+    for cpu_iter in tree.cpus.cpu.iter() {
+        let cpu = cpu_iter.deserialize::<Cpu>();
+        let hart_id = cpu.reg.iter().next().unwrap().0.start;
+        let enabled = cpu_enabled.get(hart_id).copied().unwrap_or(false)
+            && platform.hart_domain(hart_id).is_none();
+        if enabled {
+            continue;
+        }
+        if let Some(path) = cpu_node_path(root, hart_id) {
+            fixups.disable(&path);
+        }
+    }
+}
+
+/// Reserves memory regions of isolation domains, and disables their devices.
+fn isolate_domains(root: &Node, fixups: &mut Fixups) {
+    let info = unsafe { &PLATFORM.info };
+    for (index, domain) in info.domains.iter().enumerate() {
+        // Other regions are MMIO, which supervisor software only knows as devices.
+        let in_memory = |range: &Range<usize>| {
+            info.memory_regions
+                .iter()
+                .any(|memory| memory.start < range.end && range.start < memory.end)
+        };
+        for region in domain
+            .regions
+            .iter()
+            .filter(|region| in_memory(&region.range))
+        {
+            fixups.reserve_memory(&format!("domain{}_resv", index), region.range.clone());
+        }
+        for path in &domain.devices {
+            match root.find(path) {
+                Some(_) => fixups.disable(path),
+                None => warn!("Isolation domain device {} not found", path),
+            }
+        }
+    }
+}
+
+/// Adds `/firmware/rustsbi` with the firmware version and enabled SBI extensions.
+fn add_firmware_node(_root: &Node, fixups: &mut Fixups) {
+    let platform = unsafe { &PLATFORM };
+    let mut extensions = vec!["base"];
+    for (enabled, name) in [
+        (platform.have_ipi(), "time"),
+        (platform.have_ipi(), "ipi"),
+        (platform.have_rfence(), "rfence"),
+        (platform.have_hsm(), "hsm"),
+        (platform.have_reset(), "srst"),
+        (platform.have_pmu(), "pmu"),
+        (platform.have_console(), "dbcn"),
+        (platform.have_susp(), "susp"),
+        (platform.have_cppc(), "cppc"),
+        (platform.have_sta(), "sta"),
+        (platform.have_fwft(), "fwft"),
+        (platform.have_dbtr(), "dbtr"),
+        (platform.have_mpxy(), "mpxy"),
+    ] {
+        if enabled {
+            extensions.push(name);
+        }
+    }
+    #[cfg(feature = "hypervisor")]
+    if platform.have_nacl() {
+        extensions.push("nacl");
+    }
+    fixups.add_firmware_node(FirmwareNode {
+        compatible: "rustsbi,prototyper",
+        version: env!("CARGO_PKG_VERSION"),
+        sbi_extensions: extensions.join("\0"),
+    });
+}
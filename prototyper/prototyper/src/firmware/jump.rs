@@ -7,5 +7,6 @@ pub fn get_boot_info(_nonstandard_a2: usize) -> BootInfo {
     BootInfo {
         next_address: JUMP_ADDRESS,
         mpp: mstatus::MPP::Supervisor,
+        cmdline: None,
     }
 }
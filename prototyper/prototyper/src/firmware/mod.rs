@@ -1,3 +1,7 @@
+#[cfg(feature = "fit")]
+pub mod fit;
+pub mod fixup;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "payload")] {
         pub mod payload;
@@ -53,24 +57,89 @@ pub fn is_work_hart(nonstandard_a2: usize, boot: bool) -> bool {
     }
 }
 
+use alloc::string::String;
+use alloc::vec::Vec;
 use alloc::{format, vec};
 #[allow(unused)]
 use core::arch::{asm, naked_asm};
 use core::ops::Range;
 
 use crate::fail;
+use crate::platform::PLATFORM;
+use crate::platform::domain::{Domain, perm};
+use crate::riscv::csr::mseccfg;
+use crate::sbi::features::{hart_has_mseccfg, hart_pmp_count};
 
+use log::LevelFilter;
 use riscv::register::mstatus;
-use serde::Serialize;
 
 pub struct BootInfo {
     pub next_address: usize,
     pub mpp: mstatus::MPP,
+    /// Command line for the next stage, set as `/chosen/bootargs`.
+    pub cmdline: Option<String>,
 }
 
 pub struct BootHart {
     pub fdt_address: usize,
     pub is_boot_hart: bool,
+    pub options: BootOptions,
+}
+
+/// Next stage to boot, with its device tree and initial ramdisk.
+pub struct NextImage {
+    pub next_addr: usize,
+    pub fdt_address: usize,
+    pub initrd: Option<Range<usize>>,
+}
+
+/// Prepares the next stage at `next_addr`, loading it first if it is a FIT image.
+#[allow(unused_mut)]
+pub fn prepare_next_image(next_addr: usize, fdt_address: usize) -> NextImage {
+    let mut image = NextImage {
+        next_addr,
+        fdt_address,
+        initrd: None,
+    };
+    #[cfg(feature = "fit")]
+    if fit::is_fit(next_addr) {
+        image = fit::load(next_addr, fdt_address);
+    }
+    image
+}
+
+/// Firmware options given by the previous boot stage.
+#[derive(Clone, Copy)]
+pub struct BootOptions {
+    /// Skips the banner and boot information.
+    pub no_boot_prints: bool,
+    /// Log level overriding the configured one.
+    pub log_level: Option<LevelFilter>,
+    /// Hands the device tree to the next stage without patching it.
+    pub no_fdt_patch: bool,
+    /// Hart designated to boot the next stage, the first hart to arrive if `None`.
+    pub boot_hart: Option<usize>,
+}
+
+impl BootOptions {
+    pub const DEFAULT: Self = BootOptions {
+        no_boot_prints: false,
+        log_level: None,
+        no_fdt_patch: false,
+        boot_hart: None,
+    };
+}
+
+static mut BOOT_OPTIONS: BootOptions = BootOptions::DEFAULT;
+
+/// Gets firmware options of this boot, set by the init hart.
+pub fn boot_options() -> BootOptions {
+    unsafe { BOOT_OPTIONS }
+}
+
+/// Sets firmware options of this boot, before the console is initialized.
+pub fn set_boot_options(options: BootOptions) {
+    unsafe { BOOT_OPTIONS = options };
 }
 
 #[unsafe(naked)]
@@ -98,6 +167,13 @@ pub fn get_work_hart(opaque: usize, nonstandard_a2: usize, boot: bool) -> BootHa
     let is_boot_hart = is_work_hart(nonstandard_a2, boot);
 
     let mut fdt_address = opaque;
+    let mut options = BootOptions::DEFAULT;
+
+    #[cfg(not(any(feature = "payload", feature = "jump")))]
+    if let Ok(info) = read_paddr(nonstandard_a2) {
+        options = dynamic::boot_options(&info);
+        fdt_address = dynamic::fdt_override(&info).unwrap_or(fdt_address);
+    }
 
     #[cfg(feature = "fdt")]
     {
@@ -107,71 +183,50 @@ pub fn get_work_hart(opaque: usize, nonstandard_a2: usize, boot: bool) -> BootHa
     BootHart {
         fdt_address,
         is_boot_hart,
+        options,
     }
 }
 
-pub fn patch_device_tree(device_tree_ptr: usize) -> usize {
+pub fn patch_device_tree(
+    device_tree_ptr: usize,
+    cmdline: Option<&str>,
+    initrd: Option<Range<usize>>,
+) -> usize {
     use serde_device_tree::buildin::Node;
-    use serde_device_tree::ser::serializer::ValueType;
     use serde_device_tree::{Dtb, DtbPtr};
+    // The device tree of the previous stage lies in supervisor memory.
+    let _mapping = map_supervisor_memory(device_tree_ptr, dtb_total_size(device_tree_ptr));
     let Ok(ptr) = DtbPtr::from_raw(device_tree_ptr as *mut _) else {
         panic!("Can not parse device tree!");
     };
     let dtb = Dtb::from(ptr);
 
-    // Update const
-    unsafe {
-        asm!("la {}, sbi_start", out(reg) SBI_START_ADDRESS, options(nomem));
-        asm!("la {}, sbi_end", out(reg) SBI_END_ADDRESS, options(nomem));
-    }
-    let sbi_start = unsafe { SBI_START_ADDRESS };
-    let sbi_end = unsafe { SBI_END_ADDRESS };
-
     let dtb = dtb.share();
     let root: Node =
         serde_device_tree::from_raw_mut(&dtb).unwrap_or_else(fail::device_tree_deserialize_root);
     let tree: Node = root.deserialize();
 
-    #[derive(Serialize)]
-    struct ReservedMemory {
-        #[serde(rename = "#address-cells")]
-        pub address_cell: u32,
-        #[serde(rename = "#size-cells")]
-        pub size_cell: u32,
-        pub ranges: (),
+    let mut fixups = fixup::Fixups::new(&tree);
+    let platform_fixups = unsafe { &PLATFORM.info.fdt_fixups };
+    for fixup in fixup::BUILTIN_FIXUPS.iter().chain(platform_fixups) {
+        fixup(&tree, &mut fixups);
     }
-    #[derive(Serialize)]
-    struct ReservedMemoryItem {
-        pub reg: [u32; 4],
-        #[serde(rename = "no-map")]
-        pub no_map: (),
+    if let Some(cmdline) = cmdline {
+        fixups.set_prop(
+            String::from("/chosen/bootargs"),
+            fixup::PropValue::String(String::from(cmdline)),
+        );
     }
-    // Make patch list and generate reserved-memory node.
-    let sbi_length: u32 = (sbi_end - sbi_start) as u32;
-    let new_base = ReservedMemory {
-        address_cell: 2,
-        size_cell: 2,
-        ranges: (),
-    };
-    let new_base_2 = ReservedMemoryItem {
-        reg: [(sbi_start >> 32) as u32, sbi_start as u32, 0, sbi_length],
-        no_map: (),
-    };
-    let patch1 = serde_device_tree::ser::patch::Patch::new(
-        "/reserved-memory",
-        &new_base as _,
-        ValueType::Node,
-    );
-    let path_name = format!("/reserved-memory/mmode_resv1@{:x}", sbi_start);
-    let patch2 =
-        serde_device_tree::ser::patch::Patch::new(&path_name, &new_base_2 as _, ValueType::Node);
-    let raw_list = [patch1, patch2];
-    // Only add `reserved-memory` section when it not exists.
-    let list = if tree.find("/reserved-memory").is_some() {
-        &raw_list[1..]
-    } else {
-        &raw_list[..]
-    };
+    if let Some(initrd) = initrd {
+        for (name, addr) in [("start", initrd.start), ("end", initrd.end)] {
+            fixups.set_prop(
+                format!("/chosen/linux,initrd-{}", name),
+                fixup::PropValue::Cells(vec![(addr >> 32) as u32, addr as u32]),
+            );
+        }
+    }
+    let list = fixups.patches();
+    let list = &list[..];
 
     let patched_length = serde_device_tree::ser::probe_dtb_length(&tree, &list).unwrap();
 
@@ -185,20 +240,60 @@ pub fn patch_device_tree(device_tree_ptr: usize) -> usize {
     };
     serde_device_tree::ser::to_dtb(&tree, &list, &mut patched_dtb_buffer_u8).unwrap();
 
+    let patched_dtb = patched_dtb_buffer.as_ptr() as usize;
+    unsafe { PATCHED_DTB = (patched_dtb, patched_dtb + patched_length) };
+
     info!(
         "The patched dtb is located at 0x{:x} with length 0x{:x}.",
-        patched_dtb_buffer.as_ptr() as usize,
-        patched_length
+        patched_dtb, patched_length
     );
-    patched_dtb_buffer.as_ptr() as usize
+    patched_dtb
+}
+
+/// Reads the `totalsize` field of a device tree header.
+fn dtb_total_size(device_tree_ptr: usize) -> usize {
+    let _mapping = map_supervisor_memory(device_tree_ptr, 8);
+    u32::from_be(unsafe { ((device_tree_ptr + 4) as *const u32).read_volatile() }) as usize
 }
 
+/// Patched device tree as `(start, end)`, shared read-only with the supervisor under
+/// Smepmp lockdown.
+static mut PATCHED_DTB: (usize, usize) = (0, 0);
+
 static mut SBI_START_ADDRESS: usize = 0;
 static mut SBI_END_ADDRESS: usize = 0;
 static mut RODATA_START_ADDRESS: usize = 0;
 static mut RODATA_END_ADDRESS: usize = 0;
 
-pub fn set_pmp(memory_range: &Range<usize>) {
+/// Gets the memory range occupied by the SBI firmware image.
+pub fn firmware_range() -> Range<usize> {
+    let (start, end): (usize, usize);
+    unsafe {
+        asm!("la {}, sbi_start", out(reg) start, options(nomem));
+        asm!("la {}, sbi_end", out(reg) end, options(nomem));
+    }
+    start..end
+}
+
+/// Finds the memory region holding the firmware.
+///
+/// Firmware loaded outside all memory regions, e.g. into on-chip SRAM, is treated as
+/// a region of its own.
+fn firmware_memory_region(memory_regions: &[Range<usize>]) -> Range<usize> {
+    let firmware = firmware_range();
+    memory_regions
+        .iter()
+        .find(|region| region.start <= firmware.start && firmware.end <= region.end)
+        .cloned()
+        .unwrap_or(firmware)
+}
+
+/// Protects the firmware from lower privilege modes.
+///
+/// Memory regions other than the one holding the firmware, and MMIO, are covered by
+/// the RWX entries around it.
+pub fn set_pmp(memory_regions: &[Range<usize>]) {
+    let memory_range = firmware_memory_region(memory_regions);
     unsafe {
         // [0..memory_range.start] RWX
         // [memory_range.start..sbi_start] RWX
@@ -240,7 +335,317 @@ pub fn set_pmp(memory_range: &Range<usize>) {
     }
 }
 
-pub fn log_pmp_cfg(memory_range: &Range<usize>) {
+/// PMP configuration bits used for isolation domains and Smepmp lockdown.
+mod pmp {
+    pub const TOR: u8 = 1 << 3;
+    pub const NAPOT: u8 = 3 << 3;
+    pub const MODE: u8 = 3 << 3;
+    pub const LOCK: u8 = 1 << 7;
+}
+
+/// Protects the firmware and isolates domains on the current hart.
+///
+/// Without isolation domains, harts with Smepmp are locked down as described in
+/// [`smepmp_pmp_entries`]. Other harts, and harts with too few PMP entries for the
+/// lockdown, use the layout of [`set_pmp`]. Harts with too few PMP entries to isolate
+/// domains are stopped.
+pub fn set_hart_pmp() {
+    let info = unsafe { &PLATFORM.info };
+    let lockdown = info.domains.is_empty() && prepare_smepmp(&info.memory_regions);
+    let entries = if lockdown {
+        smepmp_pmp_entries(&info.memory_regions)
+    } else if let Some(entries) = hart_domain_pmp_entries() {
+        entries
+    } else {
+        set_pmp(&info.memory_regions);
+        return;
+    };
+    let (mut pmpcfg0, mut pmpcfg2) = (0usize, 0usize);
+    for (index, &(_, cfg)) in entries.iter().enumerate() {
+        match index {
+            0..8 => pmpcfg0 |= (cfg as usize) << (index * 8),
+            _ => pmpcfg2 |= (cfg as usize) << ((index - 8) * 8),
+        }
+    }
+    unsafe {
+        asm!("csrw pmpcfg0, zero", "csrw pmpcfg2, zero");
+        seq_macro::seq!(N in 0..16 {
+            asm!(
+                "csrw {csr}, {addr}",
+                csr = const 0x3b0 + N,
+                addr = in(reg) entries.get(N).map_or(0, |entry| entry.0),
+            );
+        });
+        asm!("csrw pmpcfg0, {}", in(reg) pmpcfg0);
+        asm!("csrw pmpcfg2, {}", in(reg) pmpcfg2);
+        if lockdown {
+            mseccfg::set_bits(mseccfg::MML | mseccfg::MMWP);
+            // Shared region encodings are reserved until MML is set, write them again.
+            asm!("csrw pmpcfg0, {}", in(reg) pmpcfg0);
+            asm!("csrw pmpcfg2, {}", in(reg) pmpcfg2);
+        }
+        asm!("sfence.vma");
+    }
+}
+
+/// Enables rule locking bypass to set up Smepmp lockdown on the current hart.
+///
+/// Returns false if the hart lacks Smepmp, or the layout needs too many entries.
+fn prepare_smepmp(memory_regions: &[Range<usize>]) -> bool {
+    if !hart_has_mseccfg(current_hartid()) {
+        return false;
+    }
+    if smepmp_pmp_entries(memory_regions).len() > hart_pmp_count(current_hartid()) {
+        warn!("Too many memory regions for Smepmp lockdown, PMP stays unlocked");
+        return false;
+    }
+    // RLB does not stick without Smepmp, nor once locked entries exist, e.g. when
+    // PMP survives non-retentive suspend and is already locked down.
+    mseccfg::set_bits(mseccfg::RLB);
+    mseccfg::read() & (mseccfg::RLB | mseccfg::MML) != 0
+}
+
+/// Checks if the current hart runs under Smepmp lockdown.
+#[inline]
+fn smepmp_lockdown() -> bool {
+    hart_has_mseccfg(current_hartid()) && mseccfg::read() & mseccfg::MML != 0
+}
+
+/// Finishes Smepmp lockdown of the current hart before entering lower privilege modes.
+///
+/// Shares the patched device tree, which may not exist yet when PMP is set up, and
+/// clears `mseccfg.RLB` so locked entries can no longer be changed.
+pub fn lock_hart_pmp() {
+    if !smepmp_lockdown() {
+        return;
+    }
+    let (start, end) = unsafe { PATCHED_DTB };
+    unsafe {
+        if start != end {
+            asm!("csrc pmpcfg0, {}", in(reg) 0xffff << 16);
+            asm!("csrw pmpaddr2, {}", in(reg) start >> 2);
+            asm!("csrw pmpaddr3, {}", in(reg) end.next_multiple_of(4) >> 2);
+            asm!("csrs pmpcfg0, {}", in(reg) ((pmp::TOR | perm::WRITE) as usize) << 24);
+        }
+        mseccfg::clear_bits(mseccfg::RLB);
+        asm!("sfence.vma");
+    }
+}
+
+/// Access to supervisor memory granted to M-mode by [`map_supervisor_memory`].
+///
+/// The previous mapping is restored when dropped.
+pub struct SupervisorMemory {
+    saved: Option<(usize, usize, usize)>,
+}
+
+/// Lets M-mode access `size` bytes of supervisor memory at `start`.
+///
+/// Under Smepmp lockdown M-mode may not touch memory of lower privilege modes, so
+/// the range is shared read-write through PMP entries 0 and 1 until the returned
+/// guard is dropped. Does nothing otherwise.
+pub fn map_supervisor_memory(start: usize, size: usize) -> SupervisorMemory {
+    if !smepmp_lockdown() {
+        return SupervisorMemory { saved: None };
+    }
+    let end = start.saturating_add(size).saturating_add(3) & !3;
+    let (addr0, addr1, cfg): (usize, usize, usize);
+    unsafe {
+        asm!("csrr {}, pmpaddr0", out(reg) addr0);
+        asm!("csrr {}, pmpaddr1", out(reg) addr1);
+        asm!("csrr {}, pmpcfg0", out(reg) cfg);
+    }
+    let shared = (pmp::TOR | perm::WRITE | perm::EXECUTE) as usize;
+    write_pmp_window(start >> 2, end >> 2, shared << 8);
+    SupervisorMemory {
+        saved: Some((addr0, addr1, cfg & 0xffff)),
+    }
+}
+
+impl Drop for SupervisorMemory {
+    fn drop(&mut self) {
+        if let Some((addr0, addr1, cfg)) = self.saved {
+            write_pmp_window(addr0, addr1, cfg);
+        }
+    }
+}
+
+/// Rewrites PMP entries 0 and 1, `cfg` holds their configuration bytes.
+#[inline]
+fn write_pmp_window(addr0: usize, addr1: usize, cfg: usize) {
+    unsafe {
+        asm!("csrc pmpcfg0, {}", in(reg) 0xffff);
+        asm!("csrw pmpaddr0, {}", in(reg) addr0);
+        asm!("csrw pmpaddr1, {}", in(reg) addr1);
+        asm!("csrs pmpcfg0, {}", in(reg) cfg);
+    }
+}
+
+/// Gets the start and end of the firmware, and of its rodata section.
+#[inline]
+fn firmware_layout() -> (usize, usize, usize, usize) {
+    let (sbi_start, sbi_end, rodata_start, rodata_end): (usize, usize, usize, usize);
+    unsafe {
+        asm!("la {}, sbi_start", out(reg) sbi_start, options(nomem));
+        asm!("la {}, sbi_end", out(reg) sbi_end, options(nomem));
+        asm!("la {}, sbi_rodata_start", out(reg) rodata_start, options(nomem));
+        asm!("la {}, sbi_rodata_end", out(reg) rodata_end, options(nomem));
+    }
+    (sbi_start, sbi_end, rodata_start, rodata_end)
+}
+
+/// Appends entries covering `range`, a NAPOT one if possible.
+fn push_pmp_region(entries: &mut Vec<(usize, u8)>, range: &Range<usize>, perm: u8) {
+    let size = range.end - range.start;
+    if size.is_power_of_two() && size >= 8 && range.start.is_multiple_of(size) {
+        entries.push(((range.start | (size / 2 - 1)) >> 2, pmp::NAPOT | perm));
+    } else {
+        entries.push((range.start >> 2, 0));
+        entries.push((range.end >> 2, pmp::TOR | perm));
+    }
+}
+
+/// Builds `(pmpaddr, pmpcfg)` entries of the Smepmp lockdown layout.
+///
+/// With `mseccfg.MML` set, locked entries apply to M-mode only and unlocked ones to
+/// lower privilege modes only, apart from the shared region encodings. `mseccfg.MMWP`
+/// denies M-mode accesses matching no entry.
+///
+/// - entries 0-1: M-mode window into supervisor memory, see [`map_supervisor_memory`].
+/// - entries 2-3: patched device tree, RW for M-mode and R for the supervisor.
+/// - entries 4-7: firmware text RX, rodata R and data RW, locked for M-mode.
+/// - memory regions RWX for lower privilege modes.
+/// - everything else, e.g. MMIO, shared RW.
+fn smepmp_pmp_entries(memory_regions: &[Range<usize>]) -> Vec<(usize, u8)> {
+    let (sbi_start, sbi_end, rodata_start, rodata_end) = firmware_layout();
+    let (dtb_start, dtb_end) = unsafe { PATCHED_DTB };
+    let dtb_cfg = match dtb_start == dtb_end {
+        true => 0,
+        false => pmp::TOR | perm::WRITE,
+    };
+    let mut entries = vec![
+        (0, 0),
+        (0, 0),
+        (dtb_start >> 2, 0),
+        (dtb_end.next_multiple_of(4) >> 2, dtb_cfg),
+        (sbi_start >> 2, 0),
+        (
+            rodata_start >> 2,
+            pmp::TOR | pmp::LOCK | perm::READ | perm::EXECUTE,
+        ),
+        (rodata_end >> 2, pmp::TOR | pmp::LOCK | perm::READ),
+        (
+            sbi_end >> 2,
+            pmp::TOR | pmp::LOCK | perm::READ | perm::WRITE,
+        ),
+    ];
+    for region in memory_regions {
+        push_pmp_region(&mut entries, region, perm::ALL);
+    }
+    entries.push((usize::MAX, pmp::NAPOT | perm::WRITE | perm::EXECUTE));
+    entries
+}
+
+/// Builds `(pmpaddr, pmpcfg)` entries for harts of isolation domain `domain`, or of
+/// the root domain if `None`.
+///
+/// The firmware is protected first. Harts in a domain may only access its regions,
+/// while root domain harts may access everything except regions of `domains`.
+fn domain_pmp_entries(domain: Option<&Domain>, domains: &[Domain]) -> Vec<(usize, u8)> {
+    let (sbi_start, sbi_end, rodata_start, rodata_end) = firmware_layout();
+    let mut entries = vec![
+        (sbi_start >> 2, 0),
+        (rodata_start >> 2, pmp::TOR | perm::READ),
+        (rodata_end >> 2, pmp::TOR),
+        (sbi_end >> 2, pmp::TOR | perm::READ),
+    ];
+    match domain {
+        Some(domain) => {
+            for region in &domain.regions {
+                push_pmp_region(&mut entries, &region.range, region.perm);
+            }
+        }
+        None => {
+            for region in domains.iter().flat_map(|domain| &domain.regions) {
+                push_pmp_region(&mut entries, &region.range, 0);
+            }
+            entries.push((usize::MAX, pmp::NAPOT | perm::ALL));
+        }
+    }
+    entries
+}
+
+/// Builds the isolation domain layout of the current hart, `None` without isolation
+/// domains.
+///
+/// A hart with too few PMP entries for the layout is stopped, as it would run without
+/// isolation otherwise.
+fn hart_domain_pmp_entries() -> Option<Vec<(usize, u8)>> {
+    let info = unsafe { &PLATFORM.info };
+    if info.domains.is_empty() {
+        return None;
+    }
+    let hart_id = current_hartid();
+    let domain = unsafe { PLATFORM.hart_domain(hart_id) };
+    let entries = domain_pmp_entries(domain, &info.domains);
+    if entries.len() > hart_pmp_count(hart_id) {
+        error!(
+            "Too few PMP entries on hart {} to isolate domains, hart stopped",
+            hart_id
+        );
+        fail::stop();
+    }
+    Some(entries)
+}
+
+/// Checks if PMP entries of the current hart suffice to add `domain` to `domains`,
+/// both on its harts and on root domain harts.
+pub fn domain_pmp_fits(domains: &[Domain], domain: &Domain) -> bool {
+    let count = hart_pmp_count(current_hartid());
+    let mut all = domains.to_vec();
+    all.push(domain.clone());
+    domain_pmp_entries(Some(domain), &all).len() <= count
+        && domain_pmp_entries(None, &all).len() <= count
+}
+
+/// Logs PMP configuration of the current hart.
+pub fn log_hart_pmp() {
+    let info = unsafe { &PLATFORM.info };
+    let entries = if smepmp_lockdown() {
+        smepmp_pmp_entries(&info.memory_regions)
+    } else if let Some(entries) = hart_domain_pmp_entries() {
+        entries
+    } else {
+        log_pmp_cfg(&info.memory_regions);
+        return;
+    };
+    info!("PMP Configuration");
+    info!(
+        "{:<10} {:<10} {:<15} {:<30}",
+        "PMP", "Range", "Permission", "Address"
+    );
+    for (index, &(addr, cfg)) in entries.iter().enumerate() {
+        let range = match cfg & pmp::MODE {
+            pmp::TOR => "TOR",
+            pmp::NAPOT => "NAPOT",
+            _ => "OFF",
+        };
+        let permission: String = [(pmp::LOCK, 'L'), (1, 'R'), (2, 'W'), (4, 'X')]
+            .iter()
+            .map(|&(bit, c)| if cfg & bit != 0 { c } else { '-' })
+            .collect();
+        info!(
+            "{:<10} {:<10} {:<15} 0x{:08x}",
+            format!("PMP {}:", index),
+            range,
+            permission,
+            addr << 2
+        );
+    }
+}
+
+pub fn log_pmp_cfg(memory_regions: &[Range<usize>]) {
+    let memory_range = firmware_memory_region(memory_regions);
     unsafe {
         info!("PMP Configuration");
 
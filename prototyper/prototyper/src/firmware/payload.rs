@@ -7,6 +7,7 @@ pub fn get_boot_info(_nonstandard_a2: usize) -> BootInfo {
     BootInfo {
         next_address: get_image_address(),
         mpp: mstatus::MPP::Supervisor,
+        cmdline: None,
     }
 }
 
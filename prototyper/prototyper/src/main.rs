@@ -23,6 +23,7 @@ use core::arch::{asm, naked_asm};
 use crate::platform::PLATFORM;
 use crate::riscv::csr::menvcfg;
 use crate::riscv::current_hartid;
+use crate::sbi::crash;
 use crate::sbi::features::hart_mhpm_mask;
 use crate::sbi::features::{
     Extension, PrivilegedVersion, hart_extension_probe, hart_features_detection,
@@ -46,35 +47,49 @@ extern "C" fn rust_main(_hart_id: usize, opaque: usize, nonstandard_a2: usize) {
 
     // init hart task entry.
     if init_hart_info.is_boot_hart {
+        // Options decide the log level, so set them before the console is up.
+        firmware::set_boot_options(init_hart_info.options);
+        let boot_prints = !init_hart_info.options.no_boot_prints;
+
         // Initialize the sbi heap
         sbi_heap_init();
 
         // parse the device tree
         let fdt_address = init_hart_info.fdt_address;
 
+        // Detection Hart Features, PMP layouts are checked against them on init.
+        hart_features_detection();
         unsafe {
             PLATFORM.init(fdt_address);
-            PLATFORM.print_board_info();
+            if boot_prints {
+                PLATFORM.print_board_info();
+            }
         }
+        crash::report_previous();
+        ipi::hart_init();
 
-        firmware::set_pmp(unsafe { PLATFORM.info.memory_range.as_ref().unwrap() });
-        firmware::log_pmp_cfg(unsafe { PLATFORM.info.memory_range.as_ref().unwrap() });
+        firmware::set_hart_pmp();
+        if boot_prints {
+            firmware::log_hart_pmp();
+        }
 
         // Log boot hart ID and PMP information
         let hart_id = current_hartid();
-        info!("{:<30}: {}", "Boot HART ID", hart_id);
+        if boot_prints {
+            info!("{:<30}: {}", "Boot HART ID", hart_id);
+        }
 
-        // Detection Hart Features
-        hart_features_detection();
         // Other harts task entry.
         trap_stack::prepare_for_trap();
         let priv_version = hart_privileged_version(hart_id);
         let mhpm_mask = hart_mhpm_mask(hart_id);
-        info!(
-            "{:<30}: {:?}",
-            "Boot HART Privileged Version:", priv_version
-        );
-        info!("{:<30}: {:#08x}", "Boot HART MHPM Mask:", mhpm_mask);
+        if boot_prints {
+            info!(
+                "{:<30}: {:?}",
+                "Boot HART Privileged Version:", priv_version
+            );
+            info!("{:<30}: {:#08x}", "Boot HART MHPM Mask:", mhpm_mask);
+        }
     } else {
         // Detection Hart feature
         hart_features_detection();
@@ -85,8 +100,9 @@ extern "C" fn rust_main(_hart_id: usize, opaque: usize, nonstandard_a2: usize) {
         while !unsafe { PLATFORM.ready() } {
             core::hint::spin_loop()
         }
+        ipi::hart_init();
 
-        firmware::set_pmp(unsafe { PLATFORM.info.memory_range.as_ref().unwrap() });
+        firmware::set_hart_pmp();
     }
 
     // Get boot information and prepare for kernel entry.
@@ -96,26 +112,61 @@ extern "C" fn rust_main(_hart_id: usize, opaque: usize, nonstandard_a2: usize) {
     // Check hart privileded.
     hart_privileged_check(mpp);
 
-    let boot_hart_info = firmware::get_work_hart(opaque, nonstandard_a2, true);
-
-    // boot hart task entry.
-    if boot_hart_info.is_boot_hart {
-        let fdt_address = boot_hart_info.fdt_address;
-        let fdt_address = firmware::patch_device_tree(fdt_address);
-
-        // Start kernel.
-        local_remote_hsm().start(NextStage {
-            start_addr: next_addr,
-            next_mode: mpp,
-            opaque: fdt_address,
-        });
-
-        info!(
-            "Redirecting hart {} to {:#016x} in {:?} mode.",
-            current_hartid(),
-            next_addr,
-            mpp
-        );
+    // Harts of isolation domains boot their domain and never race for the boot hart.
+    if let Some(domain) = unsafe { PLATFORM.hart_domain(current_hartid()) } {
+        if domain.boot_hart() == current_hartid() {
+            local_remote_hsm().start(NextStage {
+                start_addr: domain.next_addr,
+                next_mode: domain.next_mode,
+                opaque: domain.next_arg1,
+            });
+
+            info!(
+                "Redirecting hart {} to {:#016x} in {:?} mode for its isolation domain.",
+                current_hartid(),
+                domain.next_addr,
+                domain.next_mode
+            );
+        }
+    } else {
+        let boot_hart_info = firmware::get_work_hart(opaque, nonstandard_a2, true);
+
+        // boot hart task entry.
+        if boot_hart_info.is_boot_hart {
+            let image = firmware::prepare_next_image(next_addr, boot_hart_info.fdt_address);
+            let next_addr = image.next_addr;
+            let fdt_address = match firmware::boot_options().no_fdt_patch {
+                true => {
+                    info!("Device tree patching skipped by firmware options.");
+                    if boot_info.cmdline.is_some() || image.initrd.is_some() {
+                        warn!(
+                            "Kernel command line and initrd are not passed to the next stage \
+                            without device tree patching."
+                        );
+                    }
+                    image.fdt_address
+                }
+                false => firmware::patch_device_tree(
+                    image.fdt_address,
+                    boot_info.cmdline.as_deref(),
+                    image.initrd,
+                ),
+            };
+
+            // Start kernel.
+            local_remote_hsm().start(NextStage {
+                start_addr: next_addr,
+                next_mode: mpp,
+                opaque: fdt_address,
+            });
+
+            info!(
+                "Redirecting hart {} to {:#016x} in {:?} mode.",
+                current_hartid(),
+                next_addr,
+                mpp
+            );
+        }
     }
 
     // Clear all pending IPIs.
@@ -123,7 +174,8 @@ extern "C" fn rust_main(_hart_id: usize, opaque: usize, nonstandard_a2: usize) {
 
     // Configure CSRs
     unsafe {
-        // Delegate all interrupts and exceptions to supervisor mode.
+        // Delegate all interrupts and exceptions to supervisor mode, including counter
+        // overflow interrupts handled by the supervisor PMU driver (Sscofpmf).
         asm!("csrw mideleg,    {}", in(reg) !0);
         asm!("csrw medeleg,    {}", in(reg) !0);
         asm!("csrw mcounteren, {}", in(reg) !0);
@@ -163,6 +215,14 @@ unsafe extern "C" fn start() -> ! {
         // 1. Turn off interrupt.
         "
         csrw    mie, zero",
+        // 1.1 Harts powered down in non-retentive suspend restart here, resume them
+        //     once the firmware has cleared its bss.
+        "
+            lla     t0, 7f
+            lw      t0, 0(t0)
+            beqz    t0, 8f
+            call    {warm_resume}",
+        "8:",
         // 2. Initialize programming language runtime.
         // only clear bss if hartid matches preferred boot hart id.
         // Race
@@ -203,6 +263,7 @@ unsafe extern "C" fn start() -> ! {
         "  .word    0",
         "7:", // bss ready signal.
         "  .word    0",
+        warm_resume  = sym sbi::power::warm_resume,
         relocation_update = sym relocation_update,
         locate_stack = sym trap_stack::locate,
         main         = sym rust_main,
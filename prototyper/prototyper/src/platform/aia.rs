@@ -0,0 +1,272 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use riscv_aia::peripheral::{Aplic, Imsic};
+use serde_device_tree::buildin::Node;
+
+use crate::cfg::NUM_HART_MAX;
+use crate::devicetree::{get_prop_cells, get_prop_u32};
+use crate::riscv::csr::imsic;
+use crate::riscv::current_hartid;
+use crate::sbi::ipi::IpiDevice;
+
+pub(crate) const IMSIC_COMPATIBLE: [&str; 1] = ["riscv,imsics"];
+pub(crate) const APLIC_COMPATIBLE: [&str; 1] = ["riscv,aplic"];
+
+/// Node names of interrupt controllers hidden from supervisor software.
+pub(crate) const IMSIC_NODE_NAMES: [&str; 2] = ["imsics", "interrupt-controller"];
+pub(crate) const APLIC_NODE_NAMES: [&str; 2] = ["aplic", "interrupt-controller"];
+
+/// Machine external interrupt number in `interrupts-extended`.
+const IRQ_M_EXT: u32 = 11;
+
+/// Interrupt identity used for inter-processor interrupts in machine-level interrupt files.
+const IPI_IDENTITY: usize = 1;
+
+/// Incoming MSI controller layout read from a `riscv,imsics` node.
+#[derive(Clone, Copy, Debug)]
+pub struct ImsicInfo {
+    pub base: usize,
+    pub guest_index_bits: u32,
+    pub hart_index_bits: u32,
+    pub group_index_bits: u32,
+    pub group_index_shift: u32,
+    /// Whether the interrupt files are connected to machine external interrupts.
+    pub machine_level: bool,
+    /// Interrupt file index of each hart, its position in `interrupts-extended`.
+    pub hart_files: [Option<u16>; NUM_HART_MAX],
+}
+
+impl ImsicInfo {
+    pub fn from_node(node: &Node, root: &Node, base: usize) -> Self {
+        let parents = get_prop_cells(node, "interrupts-extended").unwrap_or_default();
+        // Each entry is a pair of interrupt controller phandle and interrupt number.
+        let harts = (parents.len() / 2).max(1) as u32;
+        let machine_level = parents.chunks_exact(2).any(|entry| entry[1] == IRQ_M_EXT);
+        // Entries refer to the local interrupt controllers of the harts by phandle.
+        let mut hart_files = [None; NUM_HART_MAX];
+        for (hart_id, file) in hart_files.iter_mut().enumerate() {
+            let path = format!("/cpus/cpu@{:x}/interrupt-controller", hart_id);
+            let Some(phandle) = root
+                .find(&path)
+                .and_then(|intc| get_prop_u32(&intc, "phandle"))
+            else {
+                continue;
+            };
+            *file = parents
+                .chunks_exact(2)
+                .position(|entry| entry[0] == phandle)
+                .map(|index| index as u16);
+        }
+        Self {
+            base,
+            guest_index_bits: get_prop_u32(node, "riscv,guest-index-bits").unwrap_or(0),
+            hart_index_bits: get_prop_u32(node, "riscv,hart-index-bits")
+                .unwrap_or(harts.next_power_of_two().trailing_zeros()),
+            group_index_bits: get_prop_u32(node, "riscv,group-index-bits").unwrap_or(0),
+            group_index_shift: get_prop_u32(node, "riscv,group-index-shift").unwrap_or(24),
+            machine_level,
+            hart_files,
+        }
+    }
+
+    /// Address of the interrupt file of the hart at `index` of `interrupts-extended`.
+    #[inline]
+    pub fn file_address(&self, index: usize) -> usize {
+        let hart = index & ((1 << self.hart_index_bits) - 1);
+        let group = index >> self.hart_index_bits;
+        self.base
+            + (group << self.group_index_shift)
+            + (hart << (self.guest_index_bits as usize + 12))
+    }
+
+    /// Base PPN and `hhxs` field of the MSI address configuration of an APLIC.
+    fn msi_address_config(&self) -> (usize, u8) {
+        let ppn = self.base >> 12;
+        let hhxs = self.group_index_shift.saturating_sub(2 * 12) as u8;
+        (ppn, hhxs)
+    }
+}
+
+/// Advanced platform-level interrupt controller domain read from a `riscv,aplic` node.
+#[derive(Debug)]
+pub struct AplicInfo {
+    pub base: usize,
+    pub num_sources: u32,
+    /// Whether the domain forwards interrupts as MSIs.
+    pub msi_mode: bool,
+    /// Phandles of child domains.
+    pub children: Vec<u32>,
+    /// Delegated source ranges as `(child phandle, first source, last source)`.
+    pub delegations: Vec<(u32, u32, u32)>,
+}
+
+impl AplicInfo {
+    pub fn from_node(node: &Node, base: usize) -> Self {
+        // `riscv,delegate` is the name used by older device trees.
+        let delegations = get_prop_cells(node, "riscv,delegation")
+            .or_else(|| get_prop_cells(node, "riscv,delegate"))
+            .unwrap_or_default()
+            .chunks_exact(3)
+            .map(|entry| (entry[0], entry[1], entry[2]))
+            .collect();
+        Self {
+            base,
+            num_sources: get_prop_u32(node, "riscv,num-sources").unwrap_or(0),
+            msi_mode: node.get_prop("msi-parent").is_some(),
+            children: get_prop_cells(node, "riscv,children").unwrap_or_default(),
+            delegations,
+        }
+    }
+
+    /// Checks if this is a machine-level domain that delegates sources to child domains.
+    #[inline]
+    pub fn is_machine_level(&self) -> bool {
+        !self.delegations.is_empty()
+    }
+}
+
+/// Configures a machine-level APLIC domain and delegates its sources to child domains.
+///
+/// MSI address configurations of both privilege levels are set up from the IMSICs,
+/// as only the root domain holds them.
+pub fn aplic_init(aplic: &AplicInfo, machine: Option<&ImsicInfo>, supervisor: Option<&ImsicInfo>) {
+    let regs = unsafe { &*(aplic.base as *const Aplic) };
+    unsafe { regs.domaincfg.modify(|cfg| cfg.set_interrupt_enable(false)) };
+
+    if aplic.msi_mode {
+        if let Some(imsic) = machine {
+            let (ppn, hhxs) = imsic.msi_address_config();
+            unsafe {
+                regs.mmsiaddrcfg.write(ppn as u32);
+                regs.mmsiaddrcfgh.modify(|cfg| {
+                    cfg.set_lock(false)
+                        .set_high_hart_index_shift(hhxs)
+                        .set_low_hart_index_shift(imsic.guest_index_bits as u8)
+                        .set_high_hart_index_width(imsic.group_index_bits as u8)
+                        .set_low_hart_index_width(imsic.hart_index_bits as u8)
+                        .set_high_base_ppn((ppn >> 32) as u16)
+                });
+            }
+        }
+        if let Some(imsic) = supervisor {
+            let (ppn, _) = imsic.msi_address_config();
+            unsafe {
+                regs.smsiaddrcfg.write(ppn as u32);
+                regs.smsiaddrcfgh.modify(|cfg| {
+                    cfg.set_low_hart_index_shift(imsic.guest_index_bits as u8)
+                        .set_high_base_ppn((ppn >> 32) as u16)
+                });
+            }
+        }
+    }
+
+    for &(child, first, last) in &aplic.delegations {
+        let Some(child_index) = aplic.children.iter().position(|&phandle| phandle == child) else {
+            warn!(
+                "APLIC delegation to unknown child domain {:#x}, ignored",
+                child
+            );
+            continue;
+        };
+        let last = last.min(aplic.num_sources).min(regs.sourcecfg.len() as u32);
+        for source in first.max(1)..=last {
+            unsafe {
+                regs.sourcecfg[source as usize - 1]
+                    .modify(|cfg| cfg.set_delegate(true).set_child_index(child_index as u16))
+            };
+        }
+    }
+
+    unsafe {
+        regs.domaincfg.modify(|cfg| {
+            cfg.set_interrupt_enable(true)
+                .set_delivery_mode(aplic.msi_mode as u8)
+                .set_big_endian(false)
+        })
+    };
+}
+
+/// Machine software interrupts through IMSIC interrupt files.
+///
+/// IPIs are sent by writing `seteipnum_le` of the target hart's machine-level
+/// interrupt file, and arrive as machine external interrupts. Timer operations
+/// are forwarded to `timer`.
+pub struct ImsicIpiWrap {
+    timer: Box<dyn IpiDevice>,
+    imsic: ImsicInfo,
+}
+
+impl ImsicIpiWrap {
+    pub fn new(timer: Box<dyn IpiDevice>, imsic: ImsicInfo) -> Self {
+        Self { timer, imsic }
+    }
+
+    #[inline]
+    fn interrupt_file(&self, hart_idx: usize) -> Option<&Imsic> {
+        let index = (*self.imsic.hart_files.get(hart_idx)?)?;
+        Some(unsafe { &*(self.imsic.file_address(index as usize) as *const Imsic) })
+    }
+}
+
+impl IpiDevice for ImsicIpiWrap {
+    #[inline(always)]
+    fn read_mtime(&self) -> u64 {
+        self.timer.read_mtime()
+    }
+
+    #[inline(always)]
+    fn write_mtime(&self, val: u64) {
+        self.timer.write_mtime(val)
+    }
+
+    #[inline(always)]
+    fn read_mtimecmp(&self, hart_idx: usize) -> u64 {
+        self.timer.read_mtimecmp(hart_idx)
+    }
+
+    #[inline(always)]
+    fn write_mtimecmp(&self, hart_idx: usize, val: u64) {
+        self.timer.write_mtimecmp(hart_idx, val)
+    }
+
+    #[inline(always)]
+    fn read_msip(&self, hart_idx: usize) -> bool {
+        // Interrupt file registers are only accessible from their own hart.
+        hart_idx == current_hartid() && imsic::read(imsic::EIP0) & (1 << IPI_IDENTITY) != 0
+    }
+
+    #[inline(always)]
+    fn set_msip(&self, hart_idx: usize) {
+        match self.interrupt_file(hart_idx) {
+            Some(file) => unsafe { file.seteipnum_le.write(IPI_IDENTITY as u32) },
+            None => warn!("No IMSIC interrupt file for hart {}", hart_idx),
+        }
+    }
+
+    #[inline(always)]
+    fn clear_msip(&self, hart_idx: usize) {
+        if hart_idx == current_hartid() {
+            imsic::clear_bits(imsic::EIP0, 1 << IPI_IDENTITY);
+        }
+    }
+
+    fn hart_init(&self) {
+        imsic::write(imsic::EIDELIVERY, 1);
+        imsic::write(imsic::EITHRESHOLD, 0);
+        imsic::set_bits(imsic::EIE0, 1 << IPI_IDENTITY);
+        unsafe { riscv::register::mie::set_mext() };
+    }
+
+    fn claim_external_ipi(&self) -> bool {
+        let mut is_ipi = false;
+        loop {
+            match imsic::claim() {
+                0 => break,
+                IPI_IDENTITY => is_ipi = true,
+                identity => warn!("Unexpected machine external interrupt {}", identity),
+            }
+        }
+        is_ipi
+    }
+}
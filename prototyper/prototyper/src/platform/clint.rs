@@ -6,6 +6,17 @@ use crate::sbi::ipi::IpiDevice;
 pub(crate) const SIFIVE_CLINT_COMPATIBLE: [&str; 3] =
     ["riscv,clint0", "starfive,jh7110-clint", "sifive,clint0"];
 pub(crate) const THEAD_CLINT_COMPATIBLE: [&str; 1] = ["thead,c900-clint"];
+pub(crate) const ACLINT_MSWI_COMPATIBLE: [&str; 1] = ["riscv,aclint-mswi"];
+pub(crate) const ACLINT_MTIMER_COMPATIBLE: [&str; 1] = ["riscv,aclint-mtimer"];
+pub(crate) const ACLINT_SSWI_COMPATIBLE: [&str; 1] = ["riscv,aclint-sswi"];
+
+/// Possible node names of CLINT and ACLINT M-mode devices.
+pub(crate) const CLINT_NODE_NAMES: [&str; 3] = ["clint", "interrupt-controller", "timer"];
+pub(crate) const MSWI_NODE_NAMES: [&str; 3] = ["mswi", "clint", "interrupt-controller"];
+pub(crate) const MTIMER_NODE_NAMES: [&str; 3] = ["mtimer", "clint", "timer"];
+
+/// Offset of `mtime` in an ACLINT MTIMER device described by a single region.
+pub(crate) const ACLINT_MTIME_OFFSET: usize = 0x7ff8;
 
 #[doc(hidden)]
 #[allow(unused)]
@@ -13,6 +24,7 @@ pub(crate) const THEAD_CLINT_COMPATIBLE: [&str; 1] = ["thead,c900-clint"];
 pub enum MachineClintType {
     SiFiveClint,
     TheadClint,
+    Aclint,
 }
 
 /// For SiFive Clint
@@ -121,3 +133,62 @@ impl IpiDevice for THeadClintWrap {
         unsafe { (*self.inner).clear_msip(hart_idx) }
     }
 }
+
+/// For ACLINT MTIMER with an optional MSWI device
+pub struct AclintWrap {
+    mtime: *mut u64,
+    mtimecmp: *mut u64,
+    msip: Option<*mut u32>,
+}
+
+impl AclintWrap {
+    pub fn new(mtime: usize, mtimecmp: usize, mswi: Option<usize>) -> Self {
+        Self {
+            mtime: mtime as *mut u64,
+            mtimecmp: mtimecmp as *mut u64,
+            msip: mswi.map(|base| base as *mut u32),
+        }
+    }
+}
+
+impl IpiDevice for AclintWrap {
+    #[inline(always)]
+    fn read_mtime(&self) -> u64 {
+        unsafe { self.mtime.read_volatile() }
+    }
+
+    #[inline(always)]
+    fn write_mtime(&self, val: u64) {
+        unsafe { self.mtime.write_volatile(val) }
+    }
+
+    #[inline(always)]
+    fn read_mtimecmp(&self, hart_idx: usize) -> u64 {
+        unsafe { self.mtimecmp.add(hart_idx).read_volatile() }
+    }
+
+    #[inline(always)]
+    fn write_mtimecmp(&self, hart_idx: usize, val: u64) {
+        unsafe { self.mtimecmp.add(hart_idx).write_volatile(val) }
+    }
+
+    #[inline(always)]
+    fn read_msip(&self, hart_idx: usize) -> bool {
+        self.msip
+            .is_some_and(|msip| unsafe { msip.add(hart_idx).read_volatile() } & 1 != 0)
+    }
+
+    #[inline(always)]
+    fn set_msip(&self, hart_idx: usize) {
+        if let Some(msip) = self.msip {
+            unsafe { msip.add(hart_idx).write_volatile(1) }
+        }
+    }
+
+    #[inline(always)]
+    fn clear_msip(&self, hart_idx: usize) {
+        if let Some(msip) = self.msip {
+            unsafe { msip.add(hart_idx).write_volatile(0) }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+use core::str::FromStr;
+use log::LevelFilter;
+use serde_device_tree::buildin::{Node, StrSeq};
+
+use crate::cfg::{DYNAMIC_NEXT_ADDR_RANGE, TLB_FLUSH_LIMIT};
+use crate::devicetree::get_prop_cells;
+
+/// Path of the node overriding the build-time configuration.
+pub const CONFIG_PATH: &str = "/chosen/rustsbi,config";
+
+/// Runtime overrides of the build-time configuration from `cfg.rs`.
+///
+/// The node at [`CONFIG_PATH`] looks like:
+///
+/// ```dts
+/// rustsbi,config {
+///     log-level = "debug";
+///     tlb-flush-limit = <0x4000>;
+///     /* <start end> with 2-cell addresses, replacing `next_addr` of the config file */
+///     next-addr = <0x0 0x80200000 0x0 0x90000000>;
+///     disabled-extensions = "sta", "pmu";
+/// };
+/// ```
+///
+/// A log level in fw_dynamic options takes precedence over `log-level`.
+pub struct RuntimeConfig {
+    pub log_level: Option<LevelFilter>,
+    pub tlb_flush_limit: Option<usize>,
+    pub next_addr: Option<Vec<Range<usize>>>,
+    pub disabled_extensions: Vec<String>,
+}
+
+impl RuntimeConfig {
+    pub const fn new() -> Self {
+        RuntimeConfig {
+            log_level: None,
+            tlb_flush_limit: None,
+            next_addr: None,
+            disabled_extensions: Vec::new(),
+        }
+    }
+
+    /// Parses the configuration node, ignoring invalid properties with a warning.
+    pub fn from_node(node: &Node) -> Self {
+        let strings = |name: &str| node.get_prop(name).map(|prop| prop.deserialize::<StrSeq>());
+        let log_level = strings("log-level").and_then(|level| {
+            let level = level.iter().next().unwrap_or_default();
+            let filter = LevelFilter::from_str(level).ok();
+            if filter.is_none() {
+                warn!("Invalid log level {} in {}", level, CONFIG_PATH);
+            }
+            filter
+        });
+        let tlb_flush_limit =
+            get_prop_cells(node, "tlb-flush-limit").and_then(|cells| match cells[..] {
+                [limit] => Some(limit as usize),
+                [hi, lo] => Some(cell_pair(hi, lo)),
+                _ => {
+                    warn!("Invalid TLB flush limit in {}", CONFIG_PATH);
+                    None
+                }
+            });
+        let next_addr = get_prop_cells(node, "next-addr").and_then(|cells| {
+            let ranges: Vec<Range<usize>> = cells
+                .chunks_exact(4)
+                .map(|range| cell_pair(range[0], range[1])..cell_pair(range[2], range[3]))
+                .collect();
+            let valid = !ranges.is_empty()
+                && cells.len().is_multiple_of(4)
+                && ranges.iter().all(|range| range.start < range.end);
+            if !valid {
+                warn!("Invalid next address ranges in {}", CONFIG_PATH);
+            }
+            valid.then_some(ranges)
+        });
+        let disabled_extensions = strings("disabled-extensions")
+            .map(|names| names.iter().map(String::from).collect())
+            .unwrap_or_default();
+        RuntimeConfig {
+            log_level,
+            tlb_flush_limit,
+            next_addr,
+            disabled_extensions,
+        }
+    }
+
+    /// Gets the size above which remote fences flush the entire TLB.
+    pub fn tlb_flush_limit(&self) -> usize {
+        self.tlb_flush_limit.unwrap_or(TLB_FLUSH_LIMIT)
+    }
+
+    /// Checks whether the next stage may start at `addr`.
+    pub fn next_addr_allowed(&self, addr: usize) -> bool {
+        match &self.next_addr {
+            Some(ranges) => ranges.iter().any(|range| range.contains(&addr)),
+            None => DYNAMIC_NEXT_ADDR_RANGE
+                .iter()
+                .any(|range| addr >= range.start as usize && addr < range.end as usize),
+        }
+    }
+}
+
+/// Parses the runtime configuration, empty if the device tree has none.
+pub fn parse_runtime_config(root: &Node) -> RuntimeConfig {
+    root.find(CONFIG_PATH)
+        .map_or_else(RuntimeConfig::new, |node| RuntimeConfig::from_node(&node))
+}
+
+#[inline]
+fn cell_pair(hi: u32, lo: u32) -> usize {
+    ((hi as usize) << 32) | lo as usize
+}
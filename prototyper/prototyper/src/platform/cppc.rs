@@ -0,0 +1,229 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use rustsbi::SbiRet;
+
+use crate::cfg::NUM_HART_MAX;
+use crate::devicetree::Cpu;
+use crate::platform::PLATFORM;
+use crate::sbi::cppc::{CppcDevice, reg};
+
+/// Static performance capabilities of a hart.
+#[derive(Clone, Copy, Debug)]
+pub struct CppcCaps {
+    pub highest: u32,
+    pub nominal: u32,
+    pub lowest_nonlinear: u32,
+    pub lowest: u32,
+    /// Frequency in MHz at nominal performance.
+    pub nominal_freq: Option<u32>,
+    /// Worst-case performance state transition latency in nanoseconds.
+    pub transition_latency: Option<u32>,
+}
+
+impl CppcCaps {
+    /// Reads capabilities from the `rustsbi,cppc-*` properties of a cpu node.
+    ///
+    /// Only `rustsbi,cppc-nominal-perf` is mandatory; the highest and lowest performance
+    /// default to nominal performance, and the lowest nonlinear performance defaults to
+    /// lowest performance. Nominal frequency is taken from `clock-frequency`.
+    pub fn from_cpu(cpu: &Cpu) -> Option<Self> {
+        let nominal = cpu.cppc_nominal_perf?;
+        let lowest = cpu.cppc_lowest_perf.unwrap_or(nominal);
+        let caps = Self {
+            highest: cpu.cppc_highest_perf.unwrap_or(nominal),
+            nominal,
+            lowest_nonlinear: cpu.cppc_lowest_nonlinear_perf.unwrap_or(lowest),
+            lowest,
+            nominal_freq: cpu.clock_frequency.map(|hz| hz / 1_000_000),
+            transition_latency: cpu.cppc_transition_latency,
+        };
+        caps.is_valid().then_some(caps)
+    }
+
+    #[inline]
+    fn is_valid(&self) -> bool {
+        0 < self.lowest
+            && self.lowest <= self.lowest_nonlinear
+            && self.lowest_nonlinear <= self.nominal
+            && self.nominal <= self.highest
+    }
+}
+
+/// Emulated CPPC registers of one hart.
+struct HartCppc {
+    caps: CppcCaps,
+    desired: u32,
+    min: u32,
+    max: u32,
+    perf_limited: u32,
+    enable: u32,
+    /// Reference counter value at the last update of `delivered`.
+    last_reference: u64,
+    delivered: u64,
+}
+
+impl HartCppc {
+    fn new(caps: CppcCaps) -> Self {
+        Self {
+            caps,
+            desired: caps.nominal,
+            min: caps.lowest,
+            max: caps.highest,
+            perf_limited: 0,
+            enable: 0,
+            last_reference: reference_counter(),
+            delivered: 0,
+        }
+    }
+
+    /// Performance level the hart is considered to run at.
+    fn current_perf(&self) -> u32 {
+        let floor = self.min.max(self.caps.lowest);
+        let ceil = self.max.min(self.caps.highest);
+        self.desired.max(floor).min(ceil)
+    }
+
+    /// Accumulates delivered performance since the last update.
+    fn update(&mut self) {
+        let now = reference_counter();
+        let elapsed = now.wrapping_sub(self.last_reference) as u128;
+        let delivered = elapsed * self.current_perf() as u128 / self.caps.nominal as u128;
+        self.delivered = self.delivered.wrapping_add(delivered as u64);
+        self.last_reference = now;
+    }
+}
+
+/// Reference counter ticks at nominal performance with the machine timer frequency.
+#[inline]
+fn reference_counter() -> u64 {
+    unsafe { PLATFORM.sbi.ipi.as_ref() }.map_or(0, |ipi| ipi.get_time() as u64)
+}
+
+/// Software emulated CPPC registers.
+///
+/// No hardware performance state is changed. The delivered performance counter
+/// advances with the machine timer scaled by the requested performance level, so
+/// supervisor governors observe the performance they ask for.
+pub struct SoftCppc {
+    harts: Vec<Option<HartCppc>>,
+}
+
+impl SoftCppc {
+    pub fn new() -> Self {
+        Self {
+            harts: (0..NUM_HART_MAX).map(|_| None).collect(),
+        }
+    }
+
+    /// Sets the capabilities of `hart_id`.
+    pub fn set_caps(&mut self, hart_id: usize, caps: CppcCaps) {
+        if let Some(hart) = self.harts.get_mut(hart_id) {
+            *hart = Some(HartCppc::new(caps));
+        }
+    }
+
+    /// Checks if any hart has CPPC registers.
+    pub fn is_empty(&self) -> bool {
+        self.harts.iter().all(Option::is_none)
+    }
+
+    #[inline]
+    fn hart(&mut self, hart_id: usize) -> Result<&mut HartCppc, SbiRet> {
+        self.harts
+            .get_mut(hart_id)
+            .and_then(Option::as_mut)
+            .ok_or(SbiRet::not_supported())
+    }
+}
+
+impl CppcDevice for SoftCppc {
+    fn width(&self, hart_id: usize, reg_id: u32) -> u32 {
+        let Some(Some(hart)) = self.harts.get(hart_id) else {
+            return 0;
+        };
+        match reg_id {
+            reg::HIGHEST_PERF..=reg::LOWEST_PERF => 32,
+            reg::DESIRED_PERF..=reg::MAX_PERF => 32,
+            reg::REFERENCE_COUNTER | reg::DELIVERED_COUNTER => 64,
+            reg::PERF_LIMITED | reg::ENABLE | reg::REFERENCE_PERF => 32,
+            reg::LOWEST_FREQ | reg::NOMINAL_FREQ if hart.caps.nominal_freq.is_some() => 32,
+            reg::TRANSITION_LATENCY if hart.caps.transition_latency.is_some() => 32,
+            _ => 0,
+        }
+    }
+
+    fn read(&mut self, hart_id: usize, reg_id: u32) -> Result<u64, SbiRet> {
+        let hart = self.hart(hart_id)?;
+        let caps = hart.caps;
+        let value = match reg_id {
+            reg::HIGHEST_PERF => caps.highest as u64,
+            reg::NOMINAL_PERF | reg::REFERENCE_PERF => caps.nominal as u64,
+            reg::LOWEST_NONLINEAR_PERF => caps.lowest_nonlinear as u64,
+            reg::LOWEST_PERF => caps.lowest as u64,
+            reg::DESIRED_PERF => hart.desired as u64,
+            reg::MIN_PERF => hart.min as u64,
+            reg::MAX_PERF => hart.max as u64,
+            reg::REFERENCE_COUNTER => {
+                hart.update();
+                hart.last_reference
+            }
+            reg::DELIVERED_COUNTER => {
+                hart.update();
+                hart.delivered
+            }
+            reg::PERF_LIMITED => hart.perf_limited as u64,
+            reg::ENABLE => hart.enable as u64,
+            reg::LOWEST_FREQ => caps.nominal_freq.map_or(0, |freq| {
+                (freq as u64 * caps.lowest as u64).div_ceil(caps.nominal as u64)
+            }),
+            reg::NOMINAL_FREQ => caps.nominal_freq.unwrap_or(0) as u64,
+            reg::TRANSITION_LATENCY => caps.transition_latency.unwrap_or(0) as u64,
+            _ => return Err(SbiRet::not_supported()),
+        };
+        Ok(value)
+    }
+
+    fn write(&mut self, hart_id: usize, reg_id: u32, value: u64) -> Result<(), SbiRet> {
+        let hart = self.hart(hart_id)?;
+        let Ok(value) = u32::try_from(value) else {
+            return Err(SbiRet::invalid_param());
+        };
+        let caps = hart.caps;
+        let in_range = caps.lowest <= value && value <= caps.highest;
+        // Delivered performance up to now is accounted at the old performance level.
+        hart.update();
+        match reg_id {
+            reg::DESIRED_PERF if in_range => hart.desired = value,
+            reg::MIN_PERF if in_range => hart.min = value,
+            reg::MAX_PERF if in_range => hart.max = value,
+            reg::DESIRED_PERF | reg::MIN_PERF | reg::MAX_PERF => {
+                return Err(SbiRet::invalid_param());
+            }
+            // Bits are cleared by writing 1; the emulated hart is never limited.
+            reg::PERF_LIMITED => hart.perf_limited &= !value,
+            reg::ENABLE => hart.enable = value & 1,
+            _ => return Err(SbiRet::not_supported()),
+        }
+        Ok(())
+    }
+}
+
+/// Builds the emulated backend from cpu nodes, or `None` if no hart describes CPPC registers.
+pub fn soft_cppc_from_cpus<'a>(cpus: impl Iterator<Item = Cpu<'a>>) -> Option<Box<SoftCppc>> {
+    let mut cppc = SoftCppc::new();
+    for cpu in cpus {
+        let hart_id = cpu.reg.iter().next().unwrap().0.start;
+        let has_properties = cpu.cppc_nominal_perf.is_some();
+        match CppcCaps::from_cpu(&cpu) {
+            Some(caps) => cppc.set_caps(hart_id, caps),
+            None if has_properties => {
+                warn!(
+                    "Invalid CPPC performance levels on hart {}, ignored",
+                    hart_id
+                )
+            }
+            None => {}
+        }
+    }
+    (!cppc.is_empty()).then(|| Box::new(cppc))
+}
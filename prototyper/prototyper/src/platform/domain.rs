@@ -0,0 +1,186 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+use riscv::register::mstatus::MPP;
+use serde_device_tree::buildin::{Node, StrSeq};
+
+use crate::devicetree::{get_compatible, get_prop_cells, get_prop_u32};
+use crate::firmware;
+
+/// Path of the node holding isolation domains.
+pub const DOMAINS_PATH: &str = "/chosen/rustsbi,domains";
+/// Compatible string of each isolation domain node.
+pub const DOMAIN_COMPATIBLE: &str = "rustsbi,domain";
+
+/// Permission bits of the `regions` property.
+pub mod perm {
+    pub const READ: u8 = 1 << 0;
+    pub const WRITE: u8 = 1 << 1;
+    pub const EXECUTE: u8 = 1 << 2;
+    pub const ALL: u8 = READ | WRITE | EXECUTE;
+}
+
+/// Memory region accessible from an isolation domain.
+#[derive(Clone, Debug)]
+pub struct DomainRegion {
+    pub range: Range<usize>,
+    pub perm: u8,
+}
+
+/// Isolation domain, a set of harts confined to memory regions.
+///
+/// A domain node under [`DOMAINS_PATH`] looks like:
+///
+/// ```dts
+/// rtos {
+///     compatible = "rustsbi,domain";
+///     harts = <1>;
+///     /* <address size permission>, 2-cell address and size, permission bits R=1 W=2 X=4 */
+///     regions = <0x0 0x88000000 0x0 0x1000000 0x7>, <0x0 0x10000000 0x0 0x1000 0x3>;
+///     next-addr = <0x0 0x88000000>;
+///     next-arg1 = <0x0 0x0>; /* optional, defaults to 0 */
+///     next-mode = <1>; /* optional, 0 for U-mode, 1 (default) for S-mode */
+///     devices = "/soc/serial@10000000"; /* optional, disabled in the root domain */
+/// };
+/// ```
+///
+/// Harts outside every domain form the root domain, which may access everything but
+/// the firmware and the regions of other domains. The device tree of the root domain
+/// reserves domain regions in memory, and disables the domain devices.
+#[derive(Clone, Debug)]
+pub struct Domain {
+    pub harts: Vec<usize>,
+    pub regions: Vec<DomainRegion>,
+    pub next_addr: usize,
+    pub next_arg1: usize,
+    pub next_mode: MPP,
+    pub devices: Vec<String>,
+}
+
+impl Domain {
+    /// Parses a domain node, checking its boot entry lies in an executable region.
+    pub fn from_node(node: &Node) -> Option<Self> {
+        let harts: Vec<usize> = get_prop_cells(node, "harts")?
+            .into_iter()
+            .map(|hart| hart as usize)
+            .collect();
+        let regions = get_prop_cells(node, "regions")?
+            .chunks_exact(5)
+            .map(|cells| {
+                let start = cell_pair(cells[0], cells[1]);
+                let size = cell_pair(cells[2], cells[3]);
+                Some(DomainRegion {
+                    range: start..start.checked_add(size)?,
+                    perm: cells[4] as u8 & perm::ALL,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let next_addr = match get_prop_cells(node, "next-addr")?.as_slice() {
+            [hi, lo] => cell_pair(*hi, *lo),
+            _ => return None,
+        };
+        let next_arg1 = match get_prop_cells(node, "next-arg1").as_deref() {
+            Some([hi, lo]) => cell_pair(*hi, *lo),
+            _ => 0,
+        };
+        let next_mode = match get_prop_u32(node, "next-mode") {
+            Some(0) => MPP::User,
+            Some(1) | None => MPP::Supervisor,
+            Some(_) => return None,
+        };
+        let devices = node
+            .get_prop("devices")
+            .map(|prop| {
+                prop.deserialize::<StrSeq>()
+                    .iter()
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let valid = !harts.is_empty()
+            && regions.iter().all(|region| {
+                !region.range.is_empty()
+                    && region.range.start.is_multiple_of(4)
+                    && region.range.end.is_multiple_of(4)
+            })
+            && regions.iter().any(|region| {
+                region.range.contains(&next_addr) && region.perm & perm::EXECUTE != 0
+            });
+        valid.then_some(Domain {
+            harts,
+            regions,
+            next_addr,
+            next_arg1,
+            next_mode,
+            devices,
+        })
+    }
+
+    /// Gets the hart which boots this domain, the lowest one in it.
+    #[inline]
+    pub fn boot_hart(&self) -> usize {
+        self.harts.iter().copied().min().unwrap()
+    }
+
+    #[inline]
+    pub fn contains_hart(&self, hart_id: usize) -> bool {
+        self.harts.contains(&hart_id)
+    }
+}
+
+/// Reads isolation domains from the children of [`DOMAINS_PATH`].
+///
+/// Domains are skipped if they have invalid properties, share harts with another
+/// domain, need more PMP entries than available, or hold the hart designated to boot
+/// the root domain or its last enabled hart.
+pub fn parse_domains(root: &Node, cpu_enabled: &[bool]) -> Vec<Domain> {
+    let mut domains: Vec<Domain> = Vec::new();
+    let Some(domains_node) = root.find(DOMAINS_PATH) else {
+        return domains;
+    };
+    let boot_hart = firmware::boot_options().boot_hart;
+    let mut find_domain = |node: &Node| {
+        let is_domain = get_compatible(node)
+            .is_some_and(|compatible| compatible.iter().any(|id| id == DOMAIN_COMPATIBLE));
+        if !is_domain {
+            return;
+        }
+        let Some(domain) = Domain::from_node(node) else {
+            warn!("Invalid isolation domain node ignored");
+            return;
+        };
+        if domains
+            .iter()
+            .any(|other| domain.harts.iter().any(|&hart| other.contains_hart(hart)))
+        {
+            warn!("Isolation domain sharing harts with another domain ignored");
+            return;
+        }
+        if boot_hart.is_some_and(|hart| domain.contains_hart(hart)) {
+            warn!("Isolation domain holding the root domain boot hart ignored");
+            return;
+        }
+        let root_hart_left = cpu_enabled.iter().enumerate().any(|(hart, &enabled)| {
+            enabled
+                && !domain.contains_hart(hart)
+                && !domains.iter().any(|other| other.contains_hart(hart))
+        });
+        if !root_hart_left {
+            warn!("Isolation domain leaving no hart for the root domain ignored");
+            return;
+        }
+        if !firmware::domain_pmp_fits(&domains, &domain) {
+            warn!("Isolation domain needing too many PMP entries ignored");
+            return;
+        }
+        domains.push(domain);
+    };
+    domains_node.search(&mut find_domain);
+    domains
+}
+
+#[inline]
+fn cell_pair(hi: u32, lo: u32) -> usize {
+    ((hi as usize) << 32) | lo as usize
+}
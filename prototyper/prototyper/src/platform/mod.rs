@@ -1,18 +1,32 @@
+use aia::{AplicInfo, ImsicInfo, ImsicIpiWrap};
+use alloc::collections::BTreeMap;
 use alloc::string::String;
+use alloc::vec::Vec;
 use alloc::{boxed::Box, string::ToString};
-use clint::{SifiveClintWrap, THeadClintWrap};
+use clint::{AclintWrap, SifiveClintWrap, THeadClintWrap};
 use core::{
     ops::Range,
     sync::atomic::{AtomicBool, Ordering},
 };
-use reset::SifiveTestDeviceWrap;
+use reset::{BoardResetDevice, ResetController, ResetLine, ResetNode, SifiveTestDeviceWrap};
+use serde_device_tree::buildin::StrSeq;
 use spin::Mutex;
 use uart_xilinx::MmioUartAxiLite;
 
 use crate::cfg::NUM_HART_MAX;
 use crate::devicetree::*;
 use crate::fail;
-use crate::platform::clint::{MachineClintType, SIFIVE_CLINT_COMPATIBLE, THEAD_CLINT_COMPATIBLE};
+use crate::firmware;
+use crate::firmware::fixup::Fixup;
+use crate::platform::aia::{
+    APLIC_COMPATIBLE, APLIC_NODE_NAMES, IMSIC_COMPATIBLE, IMSIC_NODE_NAMES,
+};
+use crate::platform::clint::{
+    ACLINT_MSWI_COMPATIBLE, ACLINT_MTIME_OFFSET, ACLINT_MTIMER_COMPATIBLE, ACLINT_SSWI_COMPATIBLE,
+    CLINT_NODE_NAMES, MSWI_NODE_NAMES, MTIMER_NODE_NAMES, MachineClintType,
+    SIFIVE_CLINT_COMPATIBLE, THEAD_CLINT_COMPATIBLE,
+};
+use crate::platform::config::RuntimeConfig;
 use crate::platform::console::Uart16550Wrap;
 use crate::platform::console::UartBflbWrap;
 use crate::platform::console::UartPl011Wrap;
@@ -21,20 +35,42 @@ use crate::platform::console::{
     MachineConsoleType, UART16650U8_COMPATIBLE, UART16650U32_COMPATIBLE, UARTAXILITE_COMPATIBLE,
     UARTBFLB_COMPATIBLE, UARTPL011_COMPATIBLE, UARTSIFIVE_COMPATIBLE,
 };
-use crate::platform::reset::SIFIVETEST_COMPATIBLE;
+use crate::platform::domain::{Domain, perm};
+use crate::platform::reset::{
+    SIFIVE_GPIO_COMPATIBLE, SIFIVETEST_COMPATIBLE, SIFIVETEST_NODE_NAMES, select_reset_lines,
+};
+use crate::riscv::current_hartid;
 use crate::sbi::SBI;
-use crate::sbi::console::SbiConsole;
-use crate::sbi::features::extension_detection;
+use crate::sbi::console::{ConsoleDevice, SbiConsole};
+use crate::sbi::cppc::{CppcDevice, SbiCppc};
+use crate::sbi::crash::CRASH_LOG_COMPATIBLE;
+use crate::sbi::dbtr::SbiDbtr;
+#[cfg(feature = "hypervisor")]
+use crate::sbi::features::{Extension, hart_extension_probe};
+use crate::sbi::features::{extension_detection, hart_triggers};
+use crate::sbi::fwft::SbiFwft;
 use crate::sbi::hsm::SbiHsm;
-use crate::sbi::ipi::SbiIpi;
+use crate::sbi::ipi::{IpiDevice, SbiIpi};
 use crate::sbi::logger;
+use crate::sbi::mpxy::{MpxyChannel, SbiMpxy};
+#[cfg(feature = "hypervisor")]
+use crate::sbi::nacl::SbiNacl;
 use crate::sbi::pmu::{EventToCounterMap, RawEventToCounterMap};
+use crate::sbi::power::PowerDevice;
 use crate::sbi::reset::SbiReset;
 use crate::sbi::rfence::SbiRFence;
+use crate::sbi::sta::SbiSta;
 use crate::sbi::suspend::SbiSuspend;
 
+mod aia;
 mod clint;
+pub mod config;
 mod console;
+mod cppc;
+pub mod domain;
+#[cfg(feature = "mpxy-loopback")]
+mod mpxy;
+mod power;
 mod reset;
 pub static mut CPU_PRIVILEGED_ENABLED: [bool; NUM_HART_MAX] = [false; NUM_HART_MAX];
 
@@ -43,25 +79,69 @@ type BaseAddress = usize;
 type CpuEnableList = [bool; NUM_HART_MAX];
 
 pub struct BoardInfo {
-    pub memory_range: Option<Range<usize>>,
+    /// Main memory regions from all memory nodes, sorted and merged.
+    pub memory_regions: Vec<Range<usize>>,
     pub console: Option<(BaseAddress, MachineConsoleType)>,
     pub reset: Option<BaseAddress>,
+    /// Poweroff line, used when there is no test device.
+    pub poweroff: Option<ResetLine>,
+    /// Reboot line, used when there is no test device.
+    pub reboot: Option<ResetLine>,
     pub ipi: Option<(BaseAddress, MachineClintType)>,
+    /// ACLINT MSWI base address.
+    pub mswi: Option<BaseAddress>,
+    /// ACLINT MTIMER `mtime` and `mtimecmp` base addresses.
+    pub mtimer: Option<(BaseAddress, BaseAddress)>,
+    /// ACLINT SSWI base address, left to supervisor software.
+    pub sswi: Option<BaseAddress>,
+    /// Machine-level IMSIC.
+    pub machine_imsic: Option<ImsicInfo>,
+    /// Supervisor-level IMSIC.
+    pub supervisor_imsic: Option<ImsicInfo>,
+    /// Machine-level APLIC domain.
+    pub aplic: Option<AplicInfo>,
+    /// Paths of M-mode only nodes disabled in the patched device tree: CLINT, ACLINT
+    /// MSWI and MTIMER, machine-level interrupt controllers and the test device.
+    pub machine_only_nodes: Vec<String>,
     pub cpu_num: Option<usize>,
+    /// Machine timer frequency in Hz.
+    pub timebase_frequency: Option<u32>,
     pub cpu_enabled: Option<CpuEnableList>,
     pub model: String,
+    /// Isolation domains, harts outside all of them belong to the root domain.
+    pub domains: Vec<Domain>,
+    /// Device tree fixups registered by the platform, run after the builtin ones.
+    pub fdt_fixups: Vec<Fixup>,
+    /// Runtime overrides of the build-time configuration.
+    pub config: RuntimeConfig,
+    /// Reserved memory keeping the crash log across warm reset.
+    pub crash_log: Option<Range<usize>>,
 }
 
 impl BoardInfo {
     pub const fn new() -> Self {
         BoardInfo {
-            memory_range: None,
+            memory_regions: Vec::new(),
             console: None,
             reset: None,
+            poweroff: None,
+            reboot: None,
             ipi: None,
+            mswi: None,
+            mtimer: None,
+            sswi: None,
+            machine_imsic: None,
+            supervisor_imsic: None,
+            aplic: None,
+            machine_only_nodes: Vec::new(),
             cpu_enabled: None,
             cpu_num: None,
+            timebase_frequency: None,
             model: String::new(),
+            domains: Vec::new(),
+            fdt_fixups: Vec::new(),
+            config: RuntimeConfig::new(),
+            crash_log: None,
         }
     }
 }
@@ -91,56 +171,117 @@ impl Platform {
 
         // Get console device, init sbi console and logger.
         self.sbi_find_and_init_console(&root);
+        // Get runtime configuration, once the logger can report invalid entries.
+        self.runtime_config_init(&root);
+        // Get crash log region
+        self.crash_log_init(&root);
         // Get clint and reset device, init sbi ipi, reset, hsm, rfence and susp extension.
         self.sbi_init_ipi_reset_hsm_rfence(&root);
         // Initialize pmu extension
         self.sbi_init_pmu(&root);
         // Get other info
         self.sbi_misc_init(&tree);
+        // Initialize firmware features extension
+        self.sbi_fwft_init();
+        // Initialize debug triggers extension
+        self.sbi_dbtr_init();
+        // Register built-in message channels
+        self.sbi_mpxy_init();
+        // Initialize CPPC extension with emulated registers described by cpu nodes
+        self.sbi_cppc_init(&tree);
+        // Enable platform-specific suspend types of idle states described by the device tree
+        self.sbi_power_init(&root);
+        // Initialize steal-time accounting extension
+        self.sbi_sta_init();
+        // Initialize nested acceleration extension
+        #[cfg(feature = "hypervisor")]
+        self.sbi_nacl_init();
+        // Get isolation domains
+        let cpu_enabled = self.info.cpu_enabled.unwrap_or([false; NUM_HART_MAX]);
+        self.info.domains = domain::parse_domains(&root, &cpu_enabled);
+        // Hide extensions disabled by runtime configuration
+        self.sbi_disable_extensions();
 
         self.ready.swap(true, Ordering::Release);
     }
 
+    fn runtime_config_init(&mut self, root: &serde_device_tree::buildin::Node) {
+        self.info.config = config::parse_runtime_config(root);
+        // Log level of firmware options takes precedence, the logger already uses it.
+        if firmware::boot_options().log_level.is_none() {
+            if let Some(level) = self.info.config.log_level {
+                log::set_max_level(level);
+            }
+        }
+    }
+
+    fn crash_log_init(&mut self, root: &serde_device_tree::buildin::Node) {
+        let mut find_crash_log = |node: &serde_device_tree::buildin::Node| {
+            let Some((compatible, range)) = get_compatible_and_range(node) else {
+                return;
+            };
+            if !compatible.iter().any(|id| id == CRASH_LOG_COMPATIBLE) {
+                return;
+            }
+            // Leave room for the log header and some text.
+            if range.len() < 0x100 {
+                warn!("Crash log region {:#x?} is too small, ignored", range);
+                return;
+            }
+            self.info.crash_log = Some(range);
+        };
+        root.search(&mut find_crash_log);
+    }
+
+    fn sbi_disable_extensions(&mut self) {
+        for name in self.info.config.disabled_extensions.iter() {
+            match name.as_str() {
+                "pmu" => self.sbi.pmu = None,
+                "susp" => self.sbi.susp = None,
+                "fwft" => self.sbi.fwft = None,
+                "dbtr" => self.sbi.dbtr = None,
+                "mpxy" => self.sbi.mpxy = None,
+                "cppc" => self.sbi.cppc = None,
+                "sta" => self.sbi.sta = None,
+                #[cfg(feature = "hypervisor")]
+                "nacl" => self.sbi.nacl = None,
+                // The firmware relies on the others itself.
+                _ => {
+                    warn!("SBI extension {} can not be disabled", name);
+                    continue;
+                }
+            }
+            info!("{:<30}: {}", "Disabled SBI Extension", name);
+        }
+    }
+
     fn sbi_find_and_init_console(&mut self, root: &serde_device_tree::buildin::Node) {
         //  Get console device info
         if let Some(stdout_path) = root.chosen_stdout_path() {
             if let Some(node) = root.find(stdout_path) {
-                let info = get_compatible_and_range(&node);
-                if let Some((compatible, regs)) = info {
-                    for device_id in compatible.iter() {
-                        if UART16650U8_COMPATIBLE.contains(&device_id) {
-                            self.info.console = Some((regs.start, MachineConsoleType::Uart16550U8));
-                        }
-                        if UART16650U32_COMPATIBLE.contains(&device_id) {
-                            self.info.console =
-                                Some((regs.start, MachineConsoleType::Uart16550U32));
-                        }
-                        if UARTAXILITE_COMPATIBLE.contains(&device_id) {
-                            self.info.console = Some((regs.start, MachineConsoleType::UartAxiLite));
-                        }
-                        if UARTBFLB_COMPATIBLE.contains(&device_id) {
-                            self.info.console = Some((regs.start, MachineConsoleType::UartBflb));
-                        }
-                        if UARTSIFIVE_COMPATIBLE.contains(&device_id) {
-                            self.info.console = Some((regs.start, MachineConsoleType::UartSifive));
-                        }
-                        if UARTPL011_COMPATIBLE.contains(&device_id) {
-                            self.info.console = Some((regs.start, MachineConsoleType::UartPl011));
-                        }
-                    }
-                }
+                self.info.console = console_info(&node);
             }
         }
 
         // init console and logger
-        self.sbi_console_init();
+        self.sbi_console_init(root);
         logger::Logger::init().unwrap();
         info!("Hello RustSBI!");
     }
 
     fn sbi_init_ipi_reset_hsm_rfence(&mut self, root: &serde_device_tree::buildin::Node) {
         // Get ipi and reset device info
+        let mut reset_nodes = Vec::new();
+        let mut reset_controllers = BTreeMap::new();
         let mut find_device = |node: &serde_device_tree::buildin::Node| {
+            // Poweroff and reboot nodes refer to register maps and GPIO controllers.
+            if let Some(compatible) = get_compatible(node) {
+                reset_nodes.extend(
+                    compatible
+                        .iter()
+                        .find_map(|device_id| ResetNode::from_node(node, device_id)),
+                );
+            }
             let info = get_compatible_and_range(node);
             if let Some(info) = info {
                 let (compatible, regs) = info;
@@ -153,18 +294,104 @@ impl Platform {
                         } else {
                             self.info.ipi = Some((base_address, MachineClintType::SiFiveClint));
                         }
+                        self.info.machine_only_nodes.extend(find_node_path(
+                            root,
+                            &CLINT_NODE_NAMES,
+                            base_address,
+                        ));
                     } else if THEAD_CLINT_COMPATIBLE.contains(&device_id) {
                         self.info.ipi = Some((base_address, MachineClintType::TheadClint));
+                        self.info.machine_only_nodes.extend(find_node_path(
+                            root,
+                            &CLINT_NODE_NAMES,
+                            base_address,
+                        ));
+                    } else if ACLINT_MSWI_COMPATIBLE.contains(&device_id) {
+                        self.info.mswi = Some(base_address);
+                        self.info.machine_only_nodes.extend(find_node_path(
+                            root,
+                            &MSWI_NODE_NAMES,
+                            base_address,
+                        ));
+                    } else if ACLINT_MTIMER_COMPATIBLE.contains(&device_id) {
+                        // Two regions are `mtime` and `mtimecmp`; a single one holds both.
+                        let mtimecmp = node.get_prop("reg").and_then(|prop_item| {
+                            let reg = prop_item.deserialize::<serde_device_tree::buildin::Reg>();
+                            reg.iter().nth(1).map(|region| region.0.start)
+                        });
+                        self.info.mtimer = Some(match mtimecmp {
+                            Some(mtimecmp) => (base_address, mtimecmp),
+                            None => (base_address + ACLINT_MTIME_OFFSET, base_address),
+                        });
+                        self.info.machine_only_nodes.extend(find_node_path(
+                            root,
+                            &MTIMER_NODE_NAMES,
+                            base_address,
+                        ));
+                    } else if ACLINT_SSWI_COMPATIBLE.contains(&device_id) {
+                        self.info.sswi = Some(base_address);
+                    }
+                    // Find AIA interrupt controllers.
+                    if IMSIC_COMPATIBLE.contains(&device_id) {
+                        let imsic = ImsicInfo::from_node(node, root, base_address);
+                        if imsic.machine_level {
+                            self.info.machine_imsic = Some(imsic);
+                            self.info.machine_only_nodes.extend(find_node_path(
+                                root,
+                                &IMSIC_NODE_NAMES,
+                                base_address,
+                            ));
+                        } else {
+                            self.info.supervisor_imsic = Some(imsic);
+                        }
+                    } else if APLIC_COMPATIBLE.contains(&device_id) {
+                        let aplic = AplicInfo::from_node(node, base_address);
+                        if aplic.is_machine_level() {
+                            self.info.aplic = Some(aplic);
+                            self.info.machine_only_nodes.extend(find_node_path(
+                                root,
+                                &APLIC_NODE_NAMES,
+                                base_address,
+                            ));
+                        }
                     }
                     // Initialize reset device.
                     if SIFIVETEST_COMPATIBLE.contains(&device_id) {
                         self.info.reset = Some(base_address);
+                        self.info.machine_only_nodes.extend(find_node_path(
+                            root,
+                            &SIFIVETEST_NODE_NAMES,
+                            base_address,
+                        ));
                     }
                 }
+                if let Some(phandle) = get_prop_u32(node, "phandle") {
+                    let sifive_gpio = compatible
+                        .iter()
+                        .any(|device_id| SIFIVE_GPIO_COMPATIBLE.contains(&device_id));
+                    reset_controllers.insert(
+                        phandle,
+                        ResetController {
+                            base: base_address,
+                            sifive_gpio,
+                        },
+                    );
+                }
             }
         };
         root.search(&mut find_device);
+        (self.info.poweroff, self.info.reboot) =
+            select_reset_lines(&reset_nodes, &reset_controllers);
+        // Use ACLINT when there is no CLINT, with MSWI or IMSIC for software interrupts.
+        if self.info.ipi.is_none()
+            && (self.info.mswi.is_some() || self.info.machine_imsic.is_some())
+        {
+            if let Some((_, mtimecmp)) = self.info.mtimer {
+                self.info.ipi = Some((mtimecmp, MachineClintType::Aclint));
+            }
+        }
         self.sbi_ipi_init();
+        self.sbi_aplic_init();
         self.sbi_hsm_init();
         self.sbi_reset_init();
         self.sbi_rfence_init();
@@ -232,20 +459,22 @@ impl Platform {
     }
 
     fn sbi_misc_init(&mut self, tree: &Tree) {
-        // Get memory info
-        // TODO: More than one memory node or range?
-        let memory_reg = tree
-            .memory
-            .iter()
-            .next()
-            .unwrap()
-            .deserialize::<Memory>()
-            .reg;
-        let memory_range = memory_reg.iter().next().unwrap().0;
-        self.info.memory_range = Some(memory_range);
+        // Get memory info from every memory node and range
+        let mut memory_regions = Vec::new();
+        for memory in tree.memory.iter() {
+            let memory_reg = memory.deserialize::<Memory>().reg;
+            memory_regions.extend(
+                memory_reg
+                    .iter()
+                    .map(|region| region.0)
+                    .filter(|region| !region.is_empty()),
+            );
+        }
+        self.info.memory_regions = merge_memory_regions(memory_regions);
 
         // Get cpu number info
         self.info.cpu_num = Some(tree.cpus.cpu.len());
+        self.info.timebase_frequency = tree.cpus.timebase_frequency.filter(|&hz| hz != 0);
 
         // Get model info
         if let Some(ref model) = tree.model {
@@ -279,38 +508,45 @@ impl Platform {
         self.info.cpu_enabled = Some(cpu_list);
     }
 
-    fn sbi_console_init(&mut self) {
-        if let Some((base, console_type)) = self.info.console {
-            self.sbi.console = match console_type {
-                MachineConsoleType::Uart16550U8 => Some(SbiConsole::new(Mutex::new(Box::new(
-                    Uart16550Wrap::<u8>::new(base),
-                )))),
-                MachineConsoleType::Uart16550U32 => Some(SbiConsole::new(Mutex::new(Box::new(
-                    Uart16550Wrap::<u32>::new(base),
-                )))),
-                MachineConsoleType::UartAxiLite => Some(SbiConsole::new(Mutex::new(Box::new(
-                    MmioUartAxiLite::new(base),
-                )))),
-                MachineConsoleType::UartBflb => Some(SbiConsole::new(Mutex::new(Box::new(
-                    UartBflbWrap::new(base),
-                )))),
-                MachineConsoleType::UartSifive => Some(SbiConsole::new(Mutex::new(Box::new(
-                    UartSifiveWrap::new(base),
-                )))),
-                MachineConsoleType::UartPl011 => Some(SbiConsole::new(Mutex::new(Box::new(
-                    UartPl011Wrap::new(base),
-                )))),
-            };
-        } else {
-            self.sbi.console = None;
+    fn sbi_console_init(&mut self, root: &serde_device_tree::buildin::Node) {
+        self.sbi.console = self.info.console.map(|(base, console_type)| {
+            SbiConsole::new(Mutex::new(console_device(base, console_type)))
+        });
+        // Secondary consoles mirror the output of the primary one, e.g. log UARTs.
+        let Some(chosen) = root.find("/chosen") else {
+            return;
+        };
+        let Some(paths) = chosen
+            .get_prop(SECONDARY_CONSOLES_PROP)
+            .map(|prop| prop.deserialize::<StrSeq>())
+        else {
+            return;
+        };
+        for path in paths.iter() {
+            // Paths may carry UART options like `stdout-path`, e.g. `/soc/serial@10000000:115200`.
+            let path = path.split(':').next().unwrap_or_default();
+            let info = root.find(path).and_then(|node| console_info(&node));
+            match info {
+                Some((base, console_type)) if self.info.console.map(|(b, _)| b) != Some(base) => {
+                    self.add_console_device(console_device(base, console_type))
+                }
+                // The logger is not up yet, so invalid entries are skipped silently.
+                _ => {}
+            }
         }
     }
 
     fn sbi_reset_init(&mut self) {
+        // The test device reports failure codes, so it is preferred over power controllers.
         if let Some(base) = self.info.reset {
             self.sbi.reset = Some(SbiReset::new(Mutex::new(Box::new(
                 SifiveTestDeviceWrap::new(base),
             ))));
+        } else if self.info.poweroff.is_some() || self.info.reboot.is_some() {
+            self.sbi.reset = Some(SbiReset::new(Mutex::new(Box::new(BoardResetDevice::new(
+                self.info.poweroff,
+                self.info.reboot,
+            )))));
         } else {
             self.sbi.reset = None;
         }
@@ -318,25 +554,42 @@ impl Platform {
 
     fn sbi_ipi_init(&mut self) {
         if let Some((base, clint_type)) = self.info.ipi {
-            self.sbi.ipi = match clint_type {
-                MachineClintType::SiFiveClint => Some(SbiIpi::new(
-                    Mutex::new(Box::new(SifiveClintWrap::new(base))),
-                    self.info.cpu_num.unwrap_or(NUM_HART_MAX),
-                )),
-                MachineClintType::TheadClint => Some(SbiIpi::new(
-                    Mutex::new(Box::new(THeadClintWrap::new(base))),
-                    self.info.cpu_num.unwrap_or(NUM_HART_MAX),
-                )),
+            let mut ipi_dev: Box<dyn IpiDevice> = match clint_type {
+                MachineClintType::SiFiveClint => Box::new(SifiveClintWrap::new(base)),
+                MachineClintType::TheadClint => Box::new(THeadClintWrap::new(base)),
+                MachineClintType::Aclint => {
+                    let (mtime, mtimecmp) = self.info.mtimer.unwrap();
+                    Box::new(AclintWrap::new(mtime, mtimecmp, self.info.mswi))
+                }
             };
+            // Machine-level IMSIC takes over software interrupts from the timer device.
+            if let Some(imsic) = self.info.machine_imsic {
+                ipi_dev = Box::new(ImsicIpiWrap::new(ipi_dev, imsic));
+            }
+            self.sbi.ipi = Some(SbiIpi::new(
+                Mutex::new(ipi_dev),
+                self.info.cpu_num.unwrap_or(NUM_HART_MAX),
+            ));
         } else {
             self.sbi.ipi = None;
         }
     }
 
+    fn sbi_aplic_init(&mut self) {
+        // Supervisor software owns the child domains once sources are delegated.
+        if let Some(aplic) = &self.info.aplic {
+            aia::aplic_init(
+                aplic,
+                self.info.machine_imsic.as_ref(),
+                self.info.supervisor_imsic.as_ref(),
+            );
+        }
+    }
+
     fn sbi_hsm_init(&mut self) {
         // TODO: Can HSM work properly when there is no ipi device?
         if self.info.ipi.is_some() {
-            self.sbi.hsm = Some(SbiHsm);
+            self.sbi.hsm = Some(SbiHsm::new());
         } else {
             self.sbi.hsm = None;
         }
@@ -359,6 +612,70 @@ impl Platform {
         }
     }
 
+    fn sbi_fwft_init(&mut self) {
+        // Misaligned exception delegation is always available; other features
+        // are checked against the calling hart's features on each call.
+        self.sbi.fwft = Some(SbiFwft);
+    }
+
+    fn sbi_dbtr_init(&mut self) {
+        // Only the boot hart has detected its triggers yet, other harts are expected
+        // to implement the same ones.
+        if hart_triggers(current_hartid()).count != 0 {
+            self.sbi.dbtr = Some(SbiDbtr);
+        }
+    }
+
+    fn sbi_mpxy_init(&mut self) {
+        #[cfg(feature = "mpxy-loopback")]
+        self.register_mpxy_channel(Box::new(mpxy::LoopbackChannel::new()));
+    }
+
+    /// Registers a message channel to the MPXY extension and returns its channel ID.
+    ///
+    /// The MPXY extension is only available after at least one channel is registered.
+    pub fn register_mpxy_channel(&mut self, channel: Box<dyn MpxyChannel>) -> u32 {
+        self.sbi
+            .mpxy
+            .get_or_insert_with(SbiMpxy::new)
+            .register(channel)
+    }
+
+    fn sbi_cppc_init(&mut self, tree: &Tree) {
+        let cpus = tree.cpus.cpu.iter().map(|cpu| cpu.deserialize::<Cpu>());
+        if let Some(cppc) = cppc::soft_cppc_from_cpus(cpus) {
+            self.register_cppc_device(cppc);
+        }
+    }
+
+    /// Registers the CPPC register provider, replacing the emulated one if present.
+    ///
+    /// Vendor drivers with real performance control hardware should call this
+    /// after platform initialization.
+    pub fn register_cppc_device(&mut self, cppc_dev: Box<dyn CppcDevice>) {
+        self.sbi.cppc = Some(SbiCppc::new(Mutex::new(cppc_dev)));
+    }
+
+    fn sbi_power_init(&mut self, root: &serde_device_tree::buildin::Node) {
+        if let Some(power_dev) = power::idle_states_from_root(root) {
+            self.register_power_device(power_dev);
+        }
+    }
+
+    fn sbi_sta_init(&mut self) {
+        // Stolen time is only meaningful when harts are shared with other contexts.
+        #[cfg(feature = "hypervisor")]
+        self.enable_sta();
+    }
+
+    #[cfg(feature = "hypervisor")]
+    fn sbi_nacl_init(&mut self) {
+        // Batched requests are applied to hypervisor CSRs, which need the H extension.
+        if hart_extension_probe(current_hartid(), Extension::Hypervisor) {
+            self.sbi.nacl = Some(SbiNacl);
+        }
+    }
+
     pub fn print_board_info(&self) {
         info!("RustSBI version {}", rustsbi::VERSION);
         rustsbi::LOGO.lines().for_each(|line| info!("{}", line));
@@ -368,6 +685,7 @@ impl Platform {
         self.print_cpu_info();
         self.print_device_info();
         self.print_memory_info();
+        self.print_domain_info();
         self.print_additional_info();
     }
 
@@ -401,12 +719,20 @@ impl Platform {
     #[inline]
     fn print_device_info(&self) {
         self.print_clint_info();
+        self.print_aia_info();
         self.print_console_info();
         self.print_reset_info();
         self.print_hsm_info();
         self.print_rfence_info();
         self.print_susp_info();
         self.print_pmu_info();
+        self.print_fwft_info();
+        self.print_dbtr_info();
+        self.print_mpxy_info();
+        self.print_cppc_info();
+        self.print_sta_info();
+        #[cfg(feature = "hypervisor")]
+        self.print_nacl_info();
     }
 
     #[inline]
@@ -422,6 +748,28 @@ impl Platform {
         }
     }
 
+    #[inline]
+    fn print_aia_info(&self) {
+        if let Some(imsic) = &self.info.machine_imsic {
+            info!(
+                "{:<30}: IMSIC (Base Address: 0x{:x})",
+                "Platform IPI Device", imsic.base
+            );
+        }
+        if let Some(aplic) = &self.info.aplic {
+            info!(
+                "{:<30}: Delegated (Base Address: 0x{:x})",
+                "Platform APLIC Device", aplic.base
+            );
+        }
+        if let Some(sswi) = self.info.sswi {
+            info!(
+                "{:<30}: Available (Base Address: 0x{:x})",
+                "Platform SSWI Device", sswi
+            );
+        }
+    }
+
     #[inline]
     fn print_console_info(&self) {
         match self.info.console {
@@ -430,6 +778,10 @@ impl Platform {
                     "{:<30}: {:?} (Base Address: 0x{:x})",
                     "Platform Console Extension", device, base
                 );
+                let consoles = self.sbi.console.as_ref().map_or(0, |c| c.device_count());
+                if consoles > 1 {
+                    info!("{:<30}: {}", "Platform Secondary Consoles", consoles - 1);
+                }
             }
             None => warn!("{:<30}: Not Available", "Platform Console Device"),
         }
@@ -442,6 +794,11 @@ impl Platform {
                 "{:<30}: Available (Base Address: 0x{:x})",
                 "Platform Reset Extension", base
             );
+        } else if self.info.poweroff.is_some() || self.info.reboot.is_some() {
+            info!(
+                "{:<30}: Available (Poweroff: {:x?}, Reboot: {:x?})",
+                "Platform Reset Extension", self.info.poweroff, self.info.reboot
+            );
         } else {
             warn!("{:<30}: Not Available", "Platform Reset Device");
         }
@@ -483,15 +840,86 @@ impl Platform {
         }
     }
 
+    #[inline]
+    fn print_fwft_info(&self) {
+        if self.have_fwft() {
+            info!("{:<30}: {}", "Platform FWFT Extension", "Available");
+        } else {
+            warn!("{:<30}: {}", "Platform FWFT Extension", "Not Available");
+        }
+    }
+
+    #[inline]
+    fn print_dbtr_info(&self) {
+        if self.have_dbtr() {
+            info!("{:<30}: {}", "Platform DBTR Extension", "Available");
+        } else {
+            warn!("{:<30}: {}", "Platform DBTR Extension", "Not Available");
+        }
+    }
+
+    #[inline]
+    fn print_mpxy_info(&self) {
+        if self.have_mpxy() {
+            info!("{:<30}: {}", "Platform MPXY Extension", "Available");
+        } else {
+            warn!("{:<30}: {}", "Platform MPXY Extension", "Not Available");
+        }
+    }
+
+    #[inline]
+    fn print_cppc_info(&self) {
+        if self.have_cppc() {
+            info!("{:<30}: {}", "Platform CPPC Extension", "Available");
+        } else {
+            warn!("{:<30}: {}", "Platform CPPC Extension", "Not Available");
+        }
+    }
+
+    #[inline]
+    fn print_sta_info(&self) {
+        if self.have_sta() {
+            info!("{:<30}: {}", "Platform STA Extension", "Available");
+        } else {
+            warn!("{:<30}: {}", "Platform STA Extension", "Not Available");
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "hypervisor")]
+    fn print_nacl_info(&self) {
+        if self.have_nacl() {
+            info!("{:<30}: {}", "Platform NACL Extension", "Available");
+        } else {
+            warn!("{:<30}: {}", "Platform NACL Extension", "Not Available");
+        }
+    }
+
     #[inline]
     fn print_memory_info(&self) {
-        if let Some(memory_range) = &self.info.memory_range {
+        if self.info.memory_regions.is_empty() {
+            warn!("{:<30}: Not Available", "Memory range");
+        }
+        for memory_range in &self.info.memory_regions {
             info!(
                 "{:<30}: 0x{:x} - 0x{:x}",
                 "Memory range", memory_range.start, memory_range.end
             );
-        } else {
-            warn!("{:<30}: Not Available", "Memory range");
+        }
+    }
+
+    fn print_domain_info(&self) {
+        for (index, domain) in self.info.domains.iter().enumerate() {
+            info!(
+                "{:<30}: {} (Harts: {:?}, Entry: 0x{:x} in {:?} mode)",
+                "Isolation Domain", index, domain.harts, domain.next_addr, domain.next_mode
+            );
+            for region in &domain.regions {
+                info!(
+                    "{:<30}: 0x{:x} - 0x{:x} ({:#05b})",
+                    "Isolation Domain Region", region.range.start, region.range.end, region.perm
+                );
+            }
         }
     }
 
@@ -541,9 +969,168 @@ impl Platform {
         self.sbi.pmu.is_some()
     }
 
+    pub fn have_fwft(&self) -> bool {
+        self.sbi.fwft.is_some()
+    }
+
+    pub fn have_dbtr(&self) -> bool {
+        self.sbi.dbtr.is_some()
+    }
+
+    pub fn have_mpxy(&self) -> bool {
+        self.sbi.mpxy.is_some()
+    }
+
+    pub fn have_cppc(&self) -> bool {
+        self.sbi.cppc.is_some()
+    }
+
+    pub fn have_sta(&self) -> bool {
+        self.sbi.sta.is_some()
+    }
+
+    #[cfg(feature = "hypervisor")]
+    pub fn have_nacl(&self) -> bool {
+        self.sbi.nacl.is_some()
+    }
+
+    /// Adds a console device which mirrors firmware and DBCN output, e.g. a log UART.
+    ///
+    /// Becomes the input device as well if there is no console from the device tree.
+    pub fn add_console_device(&mut self, console_dev: Box<dyn ConsoleDevice>) {
+        match self.sbi.console.as_mut() {
+            Some(console) => console.add_device(Mutex::new(console_dev)),
+            None => self.sbi.console = Some(SbiConsole::new(Mutex::new(console_dev))),
+        }
+    }
+
+    /// Enables the steal-time accounting extension.
+    ///
+    /// The firmware reports time spent on IPIs and remote fences of other harts as stolen.
+    /// Platforms that withhold harts from supervisor software should add the time they
+    /// take with [`crate::sbi::sta::add_steal_time`], which keeps steal time monotonic.
+    pub fn enable_sta(&mut self) {
+        self.sbi.sta = Some(SbiSta);
+    }
+
+    /// Registers the platform power controller used by HSM and SUSP suspend.
+    ///
+    /// Vendor drivers should call this after platform initialization, replacing the
+    /// one for device tree idle states. Without one, suspended harts wait for
+    /// interrupts at full power.
+    pub fn register_power_device(&mut self, power_dev: Box<dyn PowerDevice>) {
+        match self.sbi.hsm.as_mut() {
+            Some(hsm) => hsm.power_dev = Some(power_dev),
+            None => warn!("Power device ignored as HSM extension is not available"),
+        }
+    }
+
+    /// Registers a fixup of the device tree handed to the next stage.
+    ///
+    /// Must be called before the boot hart patches the device tree.
+    pub fn register_fdt_fixup(&mut self, fixup: Fixup) {
+        self.info.fdt_fixups.push(fixup);
+    }
+
     pub fn ready(&self) -> bool {
         self.ready.load(Ordering::Acquire)
     }
+
+    /// Gets the isolation domain of a hart, `None` for the root domain.
+    pub fn hart_domain(&self, hart_id: usize) -> Option<&Domain> {
+        self.info
+            .domains
+            .iter()
+            .find(|domain| domain.contains_hart(hart_id))
+    }
+
+    /// Checks if `[start, start + size)` lies in one main memory region and outside the
+    /// firmware, i.e. it can be used as memory handed over by supervisor software.
+    ///
+    /// The range must also be readable and writable from the isolation domain of the
+    /// current hart.
+    pub fn is_supervisor_memory(&self, start: usize, size: usize) -> bool {
+        let Some(end) = start.checked_add(size) else {
+            return false;
+        };
+        let firmware_range = firmware::firmware_range();
+        let in_domain = match self.hart_domain(current_hartid()) {
+            Some(domain) => domain.regions.iter().any(|region| {
+                region.range.start <= start
+                    && end <= region.range.end
+                    && region.perm & (perm::READ | perm::WRITE) == perm::READ | perm::WRITE
+            }),
+            None => self
+                .info
+                .domains
+                .iter()
+                .flat_map(|domain| &domain.regions)
+                .all(|region| end <= region.range.start || region.range.end <= start),
+        };
+        self.info
+            .memory_regions
+            .iter()
+            .any(|region| region.start <= start && end <= region.end)
+            && (end <= firmware_range.start || firmware_range.end <= start)
+            && in_domain
+    }
+}
+
+/// Sorts memory regions and merges overlapping or adjacent ones.
+fn merge_memory_regions(mut regions: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    regions.sort_unstable_by_key(|region| region.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(regions.len());
+    for region in regions {
+        match merged.last_mut() {
+            Some(last) if region.start <= last.end => last.end = last.end.max(region.end),
+            _ => merged.push(region),
+        }
+    }
+    merged
 }
 
 pub(crate) static mut PLATFORM: Platform = Platform::new();
+
+/// Property of `/chosen` listing paths of secondary console UARTs.
+const SECONDARY_CONSOLES_PROP: &str = "rustsbi,secondary-consoles";
+
+/// Gets the base address and type of a supported console UART node.
+fn console_info(
+    node: &serde_device_tree::buildin::Node,
+) -> Option<(BaseAddress, MachineConsoleType)> {
+    let (compatible, regs) = get_compatible_and_range(node)?;
+    let mut info = None;
+    for device_id in compatible.iter() {
+        if UART16650U8_COMPATIBLE.contains(&device_id) {
+            info = Some((regs.start, MachineConsoleType::Uart16550U8));
+        }
+        if UART16650U32_COMPATIBLE.contains(&device_id) {
+            info = Some((regs.start, MachineConsoleType::Uart16550U32));
+        }
+        if UARTAXILITE_COMPATIBLE.contains(&device_id) {
+            info = Some((regs.start, MachineConsoleType::UartAxiLite));
+        }
+        if UARTBFLB_COMPATIBLE.contains(&device_id) {
+            info = Some((regs.start, MachineConsoleType::UartBflb));
+        }
+        if UARTSIFIVE_COMPATIBLE.contains(&device_id) {
+            info = Some((regs.start, MachineConsoleType::UartSifive));
+        }
+        if UARTPL011_COMPATIBLE.contains(&device_id) {
+            info = Some((regs.start, MachineConsoleType::UartPl011));
+        }
+    }
+    info
+}
+
+/// Creates the driver of a console UART.
+fn console_device(base: BaseAddress, console_type: MachineConsoleType) -> Box<dyn ConsoleDevice> {
+    match console_type {
+        MachineConsoleType::Uart16550U8 => Box::new(Uart16550Wrap::<u8>::new(base)),
+        MachineConsoleType::Uart16550U32 => Box::new(Uart16550Wrap::<u32>::new(base)),
+        MachineConsoleType::UartAxiLite => Box::new(MmioUartAxiLite::new(base)),
+        MachineConsoleType::UartBflb => Box::new(UartBflbWrap::new(base)),
+        MachineConsoleType::UartSifive => Box::new(UartSifiveWrap::new(base)),
+        MachineConsoleType::UartPl011 => Box::new(UartPl011Wrap::new(base)),
+    }
+}
@@ -0,0 +1,93 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use rustsbi::SbiRet;
+use spin::Mutex;
+
+use crate::sbi::mpxy::{
+    ChannelAttributes, MPXY_SHMEM_SIZE, MpxyChannel, NotificationEvents, capability,
+};
+
+/// Vendor specific message protocol ID of the loopback channel.
+pub(crate) const LOOPBACK_PROTOCOL_ID: u32 = 0x8000_0000;
+/// Notification events data starts at offset `0x10` of the shared memory.
+const EVENTS_DATA_MAX_LEN: usize = MPXY_SHMEM_SIZE - 0x10;
+/// Maximum number of queued notifications before new ones are lost.
+const PENDING_MAX: usize = 16;
+
+/// Loopback message channel for testing the MPXY extension.
+///
+/// Messages sent with response are echoed back unchanged. Messages sent without
+/// response are queued and returned as notification events, each encoded as
+/// `message_id: u32`, `data_len: u32` and message data padded to 4 bytes.
+pub struct LoopbackChannel {
+    pending: Mutex<VecDeque<(u32, Vec<u8>)>>,
+    lost: Mutex<u32>,
+}
+
+impl LoopbackChannel {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(VecDeque::new()),
+            lost: Mutex::new(0),
+        }
+    }
+}
+
+impl MpxyChannel for LoopbackChannel {
+    fn attributes(&self) -> ChannelAttributes {
+        ChannelAttributes {
+            protocol_id: LOOPBACK_PROTOCOL_ID,
+            protocol_version: 1,
+            // Keep room for the event header so any message can come back as an event.
+            msg_data_max_len: (EVENTS_DATA_MAX_LEN - 8) as u32,
+            msg_send_timeout: 0,
+            msg_completion_timeout: 0,
+            capability: capability::EVENTS_STATE
+                | capability::SEND_WITH_RESPONSE
+                | capability::SEND_WITHOUT_RESPONSE
+                | capability::GET_NOTIFICATIONS,
+        }
+    }
+
+    fn send_with_response(
+        &self,
+        _message_id: u32,
+        _data: &mut [u8],
+        len: usize,
+    ) -> Result<usize, SbiRet> {
+        // The request already sits where the response is expected.
+        Ok(len)
+    }
+
+    fn send_without_response(&self, message_id: u32, data: &[u8]) -> Result<(), SbiRet> {
+        let mut pending = self.pending.lock();
+        if pending.len() >= PENDING_MAX {
+            *self.lost.lock() += 1;
+        } else {
+            pending.push_back((message_id, data.to_vec()));
+        }
+        Ok(())
+    }
+
+    fn get_notification_events(&self, events: &mut [u8]) -> Result<NotificationEvents, SbiRet> {
+        let mut pending = self.pending.lock();
+        let mut state = NotificationEvents::default();
+        while let Some((message_id, data)) = pending.front() {
+            let size = 8 + data.len().next_multiple_of(4);
+            if state.len + size > events.len() {
+                break;
+            }
+            let event = &mut events[state.len..state.len + size];
+            event[0..4].copy_from_slice(&message_id.to_le_bytes());
+            event[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            event[8..8 + data.len()].copy_from_slice(data);
+            event[8 + data.len()..].fill(0);
+            state.len += size;
+            state.returned += 1;
+            pending.pop_front();
+        }
+        state.remaining = pending.len() as u32;
+        state.lost = core::mem::take(&mut *self.lost.lock());
+        Ok(state)
+    }
+}
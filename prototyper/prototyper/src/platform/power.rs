@@ -0,0 +1,48 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use serde_device_tree::buildin::Node;
+
+use crate::devicetree::{get_compatible, get_prop_u32};
+use crate::sbi::power::{HART_NON_RETENTIVE_PLATFORM, HART_RETENTIVE_PLATFORM, PowerDevice};
+
+pub(crate) const IDLE_STATE_COMPATIBLE: &str = "riscv,idle-state";
+
+/// Platform-specific HSM suspend types of the `riscv,idle-state` nodes.
+///
+/// Harts wait for interrupts in every idle state, so platforms which gate clocks
+/// or power on `wfi` reach the deeper states without a dedicated controller.
+pub struct IdleStates {
+    suspend_types: Vec<u32>,
+}
+
+impl PowerDevice for IdleStates {
+    #[inline]
+    fn hart_suspend_supported(&self, suspend_type: u32) -> bool {
+        self.suspend_types.contains(&suspend_type)
+    }
+}
+
+/// Collects `riscv,sbi-suspend-param` of idle states, `None` if none is platform-specific.
+pub fn idle_states_from_root(root: &Node) -> Option<Box<dyn PowerDevice>> {
+    let mut suspend_types = Vec::new();
+    let mut find_idle_state = |node: &Node| {
+        let Some(compatible) = get_compatible(node) else {
+            return;
+        };
+        if !compatible.iter().any(|id| id == IDLE_STATE_COMPATIBLE) {
+            return;
+        }
+        match get_prop_u32(node, "riscv,sbi-suspend-param") {
+            Some(t) if HART_RETENTIVE_PLATFORM.contains(&t) => suspend_types.push(t),
+            Some(t) if HART_NON_RETENTIVE_PLATFORM.contains(&t) => suspend_types.push(t),
+            // Default suspend types are always supported.
+            Some(_) => {}
+            None => warn!("Idle state without riscv,sbi-suspend-param, ignored"),
+        }
+    };
+    root.search(&mut find_idle_state);
+    if suspend_types.is_empty() {
+        return None;
+    }
+    Some(Box::new(IdleStates { suspend_types }))
+}
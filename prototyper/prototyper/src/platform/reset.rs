@@ -1,7 +1,11 @@
+use alloc::collections::BTreeMap;
+use serde_device_tree::buildin::Node;
 use sifive_test_device::SifiveTestDevice;
 
+use crate::devicetree::{get_prop_cells, get_prop_u32};
 use crate::sbi::reset::ResetDevice;
 pub(crate) const SIFIVETEST_COMPATIBLE: [&str; 1] = ["sifive,test0"];
+pub(crate) const SIFIVETEST_NODE_NAMES: [&str; 1] = ["test"];
 
 pub struct SifiveTestDeviceWrap {
     inner: *const SifiveTestDevice,
@@ -32,3 +36,249 @@ impl ResetDevice for SifiveTestDeviceWrap {
         unsafe { (*self.inner).reset() }
     }
 }
+
+pub(crate) const SYSCON_POWEROFF_COMPATIBLE: [&str; 1] = ["syscon-poweroff"];
+pub(crate) const SYSCON_REBOOT_COMPATIBLE: [&str; 1] = ["syscon-reboot"];
+pub(crate) const GPIO_POWEROFF_COMPATIBLE: [&str; 1] = ["gpio-poweroff"];
+pub(crate) const GPIO_RESTART_COMPATIBLE: [&str; 1] = ["gpio-restart"];
+pub(crate) const SIFIVE_GPIO_COMPATIBLE: [&str; 1] = ["sifive,gpio0"];
+
+/// Register offsets of a SiFive GPIO controller.
+mod sifive_gpio {
+    pub const OUTPUT_EN: usize = 0x08;
+    pub const OUTPUT_VAL: usize = 0x0c;
+    pub const IOF_EN: usize = 0x38;
+}
+
+/// `GPIO_ACTIVE_LOW` flag of a GPIO specifier.
+const GPIO_ACTIVE_LOW: u32 = 1;
+
+/// Operation a reset node performs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetKind {
+    Poweroff,
+    Reboot,
+}
+
+/// Hardware action that powers off or reboots the system.
+#[derive(Clone, Copy, Debug)]
+pub enum ResetLine {
+    /// Updates the bits in `mask` of the 32-bit register at `address` to `value`.
+    Syscon {
+        address: usize,
+        value: u32,
+        mask: u32,
+    },
+    /// Drives a pin of a SiFive GPIO controller to its active level.
+    SifiveGpio {
+        base: usize,
+        pin: u32,
+        active_low: bool,
+    },
+}
+
+impl ResetLine {
+    /// Performs the action.
+    pub fn assert(&self) {
+        match *self {
+            ResetLine::Syscon {
+                address,
+                value,
+                mask,
+            } => unsafe {
+                let reg = address as *mut u32;
+                reg.write_volatile((reg.read_volatile() & !mask) | (value & mask));
+            },
+            ResetLine::SifiveGpio {
+                base,
+                pin,
+                active_low,
+            } => unsafe {
+                let bit = 1 << pin;
+                let update = |offset: usize, set: bool| {
+                    let reg = (base + offset) as *mut u32;
+                    let old = reg.read_volatile();
+                    reg.write_volatile(if set { old | bit } else { old & !bit });
+                };
+                update(sifive_gpio::IOF_EN, false);
+                update(sifive_gpio::OUTPUT_VAL, !active_low);
+                update(sifive_gpio::OUTPUT_EN, true);
+            },
+        }
+    }
+}
+
+/// Device tree node a reset node refers to by phandle.
+#[derive(Clone, Copy, Debug)]
+pub struct ResetController {
+    pub base: usize,
+    pub sifive_gpio: bool,
+}
+
+enum ResetSource {
+    Syscon {
+        regmap: u32,
+        offset: u32,
+        value: u32,
+        mask: u32,
+    },
+    Gpio {
+        controller: u32,
+        pin: u32,
+        flags: u32,
+    },
+}
+
+/// `syscon-*` or `gpio-*` reset node, with the controller it refers to not resolved yet.
+pub struct ResetNode {
+    kind: ResetKind,
+    source: ResetSource,
+}
+
+impl ResetNode {
+    /// Parses a reset node with compatible string `compatible`.
+    ///
+    /// Syscon nodes must refer to their register map through `regmap`. GPIO nodes
+    /// use the first specifier of `gpios`, in the two-cell `<pin flags>` format.
+    pub fn from_node(node: &Node, compatible: &str) -> Option<Self> {
+        let (kind, syscon) = if SYSCON_POWEROFF_COMPATIBLE.contains(&compatible) {
+            (ResetKind::Poweroff, true)
+        } else if SYSCON_REBOOT_COMPATIBLE.contains(&compatible) {
+            (ResetKind::Reboot, true)
+        } else if GPIO_POWEROFF_COMPATIBLE.contains(&compatible) {
+            (ResetKind::Poweroff, false)
+        } else if GPIO_RESTART_COMPATIBLE.contains(&compatible) {
+            (ResetKind::Reboot, false)
+        } else {
+            return None;
+        };
+        let source = if syscon {
+            let Some(regmap) = get_prop_u32(node, "regmap") else {
+                warn!(
+                    "{} node without regmap is not supported, ignored",
+                    compatible
+                );
+                return None;
+            };
+            let mask = get_prop_u32(node, "mask");
+            // Legacy bindings without `value` write `mask` to the whole register, as Linux does.
+            let (value, mask) = match (get_prop_u32(node, "value"), mask) {
+                (Some(value), mask) => (value, mask.unwrap_or(u32::MAX)),
+                (None, Some(mask)) => (mask, u32::MAX),
+                (None, None) => return None,
+            };
+            ResetSource::Syscon {
+                regmap,
+                offset: get_prop_u32(node, "offset").unwrap_or(0),
+                value,
+                mask,
+            }
+        } else {
+            let gpios = get_prop_cells(node, "gpios")?;
+            let [controller, pin, flags] = *gpios.first_chunk::<3>()?;
+            ResetSource::Gpio {
+                controller,
+                pin,
+                flags,
+            }
+        };
+        Some(Self { kind, source })
+    }
+
+    /// Resolves the referenced controller of this node by phandle.
+    fn resolve(&self, controllers: &BTreeMap<u32, ResetController>) -> Option<ResetLine> {
+        match self.source {
+            ResetSource::Syscon {
+                regmap,
+                offset,
+                value,
+                mask,
+            } => controllers
+                .get(&regmap)
+                .map(|controller| ResetLine::Syscon {
+                    address: controller.base + offset as usize,
+                    value,
+                    mask,
+                }),
+            ResetSource::Gpio {
+                controller,
+                pin,
+                flags,
+            } => match controllers.get(&controller) {
+                Some(gpio) if gpio.sifive_gpio && pin < 32 => Some(ResetLine::SifiveGpio {
+                    base: gpio.base,
+                    pin,
+                    active_low: flags & GPIO_ACTIVE_LOW != 0,
+                }),
+                _ => {
+                    warn!("Unsupported GPIO controller for reset line, ignored");
+                    None
+                }
+            },
+        }
+    }
+}
+
+/// Picks the poweroff and reboot lines from reset nodes.
+///
+/// Syscon nodes take priority over GPIO nodes; among nodes of the same type the
+/// first one in the device tree wins.
+pub fn select_reset_lines(
+    nodes: &[ResetNode],
+    controllers: &BTreeMap<u32, ResetController>,
+) -> (Option<ResetLine>, Option<ResetLine>) {
+    let select = |kind: ResetKind| {
+        let syscon = nodes
+            .iter()
+            .filter(|node| matches!(node.source, ResetSource::Syscon { .. }));
+        let gpio = nodes
+            .iter()
+            .filter(|node| matches!(node.source, ResetSource::Gpio { .. }));
+        syscon
+            .chain(gpio)
+            .filter(|node| node.kind == kind)
+            .find_map(|node| node.resolve(controllers))
+    };
+    (select(ResetKind::Poweroff), select(ResetKind::Reboot))
+}
+
+/// Reset Device: poweroff and reboot lines described by the device tree
+pub struct BoardResetDevice {
+    poweroff: Option<ResetLine>,
+    reboot: Option<ResetLine>,
+}
+
+impl BoardResetDevice {
+    pub fn new(poweroff: Option<ResetLine>, reboot: Option<ResetLine>) -> Self {
+        Self { poweroff, reboot }
+    }
+
+    fn perform(line: Option<ResetLine>, kind: ResetKind) -> ! {
+        match line {
+            Some(line) => line.assert(),
+            None => warn!("No {:?} line on this platform, halting", kind),
+        }
+        // Wait for the power controller to take effect.
+        loop {
+            riscv::asm::wfi();
+        }
+    }
+}
+
+impl ResetDevice for BoardResetDevice {
+    #[inline]
+    fn fail(&self, _code: u16) -> ! {
+        // Power controllers can not report failure codes.
+        Self::perform(self.poweroff, ResetKind::Poweroff)
+    }
+
+    #[inline]
+    fn pass(&self) -> ! {
+        Self::perform(self.poweroff, ResetKind::Poweroff)
+    }
+
+    #[inline]
+    fn reset(&self) -> ! {
+        Self::perform(self.reboot, ResetKind::Reboot)
+    }
+}
@@ -5,8 +5,19 @@ use seq_macro::seq;
 
 /// CSR addresses
 pub const CSR_STIMECMP: u16 = 0x14D;
+pub const CSR_STIMECMPH: u16 = 0x15D;
 pub const CSR_MCOUNTEREN: u16 = 0x306;
 pub const CSR_MENVCFG: u16 = 0x30a;
+pub const CSR_MSECCFG: u16 = 0x747;
+pub const CSR_PMPCFG0: u16 = 0x3a0;
+pub const CSR_PMPCFG2: u16 = 0x3a2;
+pub const CSR_PMPADDR0: u16 = 0x3b0;
+pub const CSR_TSELECT: u16 = 0x7a0;
+pub const CSR_TDATA1: u16 = 0x7a1;
+pub const CSR_TDATA2: u16 = 0x7a2;
+pub const CSR_TDATA3: u16 = 0x7a3;
+pub const CSR_TINFO: u16 = 0x7a4;
+pub const CSR_SCOUNTOVF: u16 = 0xda0;
 pub const CSR_MCYCLE: u16 = 0xb00;
 pub const CSR_MINSTRET: u16 = 0xb02;
 seq!(N in 3..32 {
@@ -34,6 +45,24 @@ seq!(N in 3..32 {
     pub const CSR_MHPMEVENT~N: u16 = 0x320 + N;
 });
 
+/// Machine hardware performance-monitoring event selector (mhpmevent) bit fields (Sscofpmf).
+pub mod mhpmevent {
+    /// Counter overflowed, masks further overflow interrupts while set.
+    pub const OF: u64 = 0x1 << 63;
+    /// Inhibit counting in M-mode.
+    pub const MINH: u64 = 0x1 << 62;
+    /// Inhibit counting in S/HS-mode.
+    pub const SINH: u64 = 0x1 << 61;
+    /// Inhibit counting in U-mode.
+    pub const UINH: u64 = 0x1 << 60;
+    /// Inhibit counting in VS-mode.
+    pub const VSINH: u64 = 0x1 << 59;
+    /// Inhibit counting in VU-mode.
+    pub const VUINH: u64 = 0x1 << 58;
+    /// All Sscofpmf bits.
+    pub const SSCOF_MASK: u64 = OF | MINH | SINH | UINH | VSINH | VUINH;
+}
+
 // For RV32
 pub const CSR_CYCLEH: u16 = 0xc80;
 pub const CSR_TIMEH: u16 = 0xc81;
@@ -48,6 +77,10 @@ pub mod menvcfg {
 
     /// Fence of I/O implies memory.
     pub const FIOM: usize = 0x1 << 0;
+    /// Landing pad enable (Zicfilp).
+    pub const LPE: usize = 0x1 << 2;
+    /// Shadow stack enable (Zicfiss).
+    pub const SSE: usize = 0x1 << 3;
     /// Cache block invalidate - flush.
     pub const CBIE_FLUSH: usize = 0x01 << 4;
     /// Cache block invalidate - invalidate.
@@ -56,6 +89,16 @@ pub mod menvcfg {
     pub const CBCFE: usize = 0x1 << 6;
     /// Cache block zero for enclave.
     pub const CBZE: usize = 0x1 << 7;
+    /// Pointer masking mode (Smnpm).
+    pub const PMM: usize = 0x3 << 32;
+    /// Pointer masking with PMLEN = XLEN - 57.
+    pub const PMM_PMLEN_7: usize = 0x2 << 32;
+    /// Pointer masking with PMLEN = XLEN - 48.
+    pub const PMM_PMLEN_16: usize = 0x3 << 32;
+    /// Double trap enable (Ssdbltrp).
+    pub const DTE: usize = 0x1 << 59;
+    /// Hardware A/D bit updating enable (Svadu).
+    pub const ADUE: usize = 0x1 << 61;
     /// Page-based memory types enable.
     pub const PBMTE: usize = 0x1 << 62;
     /// Supervisor timer counter enable.
@@ -67,6 +110,24 @@ pub mod menvcfg {
         set_bits(STCE);
     }
 
+    /// Reads the current value of menvcfg register.
+    #[inline]
+    pub fn read() -> usize {
+        let bits: usize;
+        unsafe {
+            asm!("csrr {}, menvcfg", out(reg) bits, options(nomem));
+        }
+        bits
+    }
+
+    /// Clears specified bits in menvcfg register.
+    pub fn clear_bits(option: usize) {
+        let bits = read() & !option;
+        unsafe {
+            asm!("csrw menvcfg, {}", in(reg) bits, options(nomem));
+        }
+    }
+
     /// Sets specified bits in menvcfg register.
     pub fn set_bits(option: usize) {
         let mut bits: usize;
@@ -83,6 +144,164 @@ pub mod menvcfg {
     }
 }
 
+/// Machine security configuration register (mseccfg) bit fields (Smepmp).
+pub mod mseccfg {
+    use core::arch::asm;
+
+    /// Machine mode lockdown, locked PMP entries become M-mode only.
+    pub const MML: usize = 0x1 << 0;
+    /// Machine mode whitelist policy, M-mode accesses matching no entry are denied.
+    pub const MMWP: usize = 0x1 << 1;
+    /// Rule locking bypass, locked PMP entries may still be modified.
+    pub const RLB: usize = 0x1 << 2;
+
+    /// Reads the current value of mseccfg register.
+    #[inline]
+    pub fn read() -> usize {
+        let bits: usize;
+        unsafe {
+            asm!("csrr {}, 0x747", out(reg) bits, options(nomem));
+        }
+        bits
+    }
+
+    /// Sets specified bits in mseccfg register.
+    #[inline]
+    pub fn set_bits(option: usize) {
+        unsafe {
+            asm!("csrs 0x747, {}", in(reg) option, options(nomem));
+        }
+    }
+
+    /// Clears specified bits in mseccfg register.
+    #[inline]
+    pub fn clear_bits(option: usize) {
+        unsafe {
+            asm!("csrc 0x747, {}", in(reg) option, options(nomem));
+        }
+    }
+}
+
+/// Debug trigger registers (Sdtrig) operations.
+pub mod sdtrig {
+    use core::arch::asm;
+
+    /// Trigger type field of `tdata1`.
+    pub const TDATA1_TYPE_SHIFT: usize = 60;
+    /// Trigger is only writable from debug mode.
+    pub const TDATA1_DMODE: usize = 0x1 << 59;
+    /// Trigger type of match control type 6 (`mcontrol6`).
+    pub const TYPE_MCONTROL6: usize = 6;
+
+    /// Bit fields of `tdata1` when the trigger type is `mcontrol6`.
+    pub mod mcontrol6 {
+        /// Fire in VS-mode.
+        pub const VS: usize = 0x1 << 24;
+        /// Fire in VU-mode.
+        pub const VU: usize = 0x1 << 23;
+        /// Action taken when the trigger fires.
+        pub const ACTION: usize = 0xf << 12;
+        /// Chain with the next trigger.
+        pub const CHAIN: usize = 0x1 << 11;
+        /// Fire in M-mode.
+        pub const M: usize = 0x1 << 6;
+        /// Fire in S-mode.
+        pub const S: usize = 0x1 << 4;
+        /// Fire in U-mode.
+        pub const U: usize = 0x1 << 3;
+        /// All privilege mode bits.
+        pub const MODE_MASK: usize = VS | VU | M | S | U;
+        /// Hit bits set by hardware when the trigger fires.
+        pub const HIT_MASK: usize = (0x1 << 25) | (0x1 << 22);
+    }
+
+    macro_rules! trigger_csr {
+        ($name: ident, $csr: literal) => {
+            pub mod $name {
+                use super::*;
+
+                #[inline]
+                pub fn read() -> usize {
+                    let bits: usize;
+                    unsafe { asm!(concat!("csrr {}, ", $csr), out(reg) bits, options(nomem)) };
+                    bits
+                }
+
+                #[inline]
+                pub fn write(bits: usize) {
+                    unsafe { asm!(concat!("csrw ", $csr, ", {}"), in(reg) bits, options(nomem)) };
+                }
+            }
+        };
+    }
+
+    trigger_csr!(tselect, "tselect");
+    trigger_csr!(tdata1, "tdata1");
+    trigger_csr!(tdata2, "tdata2");
+    trigger_csr!(tdata3, "tdata3");
+}
+
+/// Machine-level IMSIC interrupt file operations (Smaia).
+///
+/// Interrupt file registers are accessed indirectly through `miselect` (0x350) and
+/// `mireg` (0x351); pending interrupts are claimed through `mtopei` (0x35c).
+pub mod imsic {
+    use core::arch::asm;
+
+    /// External interrupt delivery enable register.
+    pub const EIDELIVERY: usize = 0x70;
+    /// External interrupt enable threshold register.
+    pub const EITHRESHOLD: usize = 0x72;
+    /// First external interrupt-pending register.
+    pub const EIP0: usize = 0x80;
+    /// First external interrupt-enable register.
+    pub const EIE0: usize = 0xc0;
+
+    /// Writes `value` to the interrupt file register selected by `select`.
+    #[inline]
+    pub fn write(select: usize, value: usize) {
+        unsafe {
+            asm!("csrw 0x350, {}", "csrw 0x351, {}", in(reg) select, in(reg) value, options(nomem));
+        }
+    }
+
+    /// Reads the interrupt file register selected by `select`.
+    #[inline]
+    pub fn read(select: usize) -> usize {
+        let bits: usize;
+        unsafe {
+            asm!("csrw 0x350, {}", "csrr {}, 0x351", in(reg) select, out(reg) bits, options(nomem));
+        }
+        bits
+    }
+
+    /// Sets `bits` in the interrupt file register selected by `select`.
+    #[inline]
+    pub fn set_bits(select: usize, bits: usize) {
+        unsafe {
+            asm!("csrw 0x350, {}", "csrs 0x351, {}", in(reg) select, in(reg) bits, options(nomem));
+        }
+    }
+
+    /// Clears `bits` in the interrupt file register selected by `select`.
+    #[inline]
+    pub fn clear_bits(select: usize, bits: usize) {
+        unsafe {
+            asm!("csrw 0x350, {}", "csrc 0x351, {}", in(reg) select, in(reg) bits, options(nomem));
+        }
+    }
+
+    /// Claims the top pending external interrupt and returns its identity, or 0 if none.
+    #[inline]
+    pub fn claim() -> usize {
+        let bits: usize;
+        unsafe {
+            asm!("csrrw {}, 0x35c, zero", out(reg) bits, options(nomem));
+        }
+        bits >> 16
+    }
+}
+
 /// Supervisor timer compare register operations.
 pub mod stimecmp {
     use core::arch::asm;
@@ -1,9 +1,15 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec, vec::Vec};
 use core::fmt::{self, Write};
 use rustsbi::{Console, Physical, SbiRet};
 use spin::Mutex;
 
+use crate::firmware::map_supervisor_memory;
 use crate::platform::PLATFORM;
+use crate::sbi::fifo::Fifo;
+use crate::sbi::supervisor_shmem;
+
+/// Size of the buffer for bytes received but not yet read by supervisor software.
+const RX_BUFFER_SIZE: usize = 256;
 
 /// A trait that must be implemented by console devices to provide basic I/O functionality.
 pub trait ConsoleDevice {
@@ -20,12 +26,14 @@ pub trait ConsoleDevice {
     fn write(&self, buf: &[u8]) -> usize;
 }
 
-/// An implementation of the SBI console interface that wraps a console device.
+/// An implementation of the SBI console interface that wraps console devices.
 ///
 /// This provides a safe interface for interacting with console hardware through the
-/// SBI specification.
+/// SBI specification. Output is mirrored to every device, while input is taken from
+/// the first one and buffered until supervisor software reads it.
 pub struct SbiConsole {
-    inner: Mutex<Box<dyn ConsoleDevice>>,
+    devices: Vec<Mutex<Box<dyn ConsoleDevice>>>,
+    rx: Mutex<Fifo<u8, RX_BUFFER_SIZE>>,
 }
 
 impl SbiConsole {
@@ -35,7 +43,22 @@ impl SbiConsole {
     /// * `inner` - A mutex containing the console device implementation
     #[inline]
     pub fn new(inner: Mutex<Box<dyn ConsoleDevice>>) -> Self {
-        Self { inner }
+        Self {
+            devices: vec![inner],
+            rx: Mutex::new(Fifo::new()),
+        }
+    }
+
+    /// Adds an output-only console device, e.g. a secondary log UART.
+    #[inline]
+    pub fn add_device(&mut self, device: Mutex<Box<dyn ConsoleDevice>>) {
+        self.devices.push(device);
+    }
+
+    /// Returns the number of console devices.
+    #[inline]
+    pub fn device_count(&self) -> usize {
+        self.devices.len()
     }
 
     /// Writes a single character to the console.
@@ -59,13 +82,44 @@ impl SbiConsole {
     /// The read character as a usize
     #[inline]
     pub fn getchar(&self) -> usize {
-        let mut c = 0u8;
-        let console = self.inner.lock();
-        // Block until we successfully read 1 byte
-        while console.read(core::slice::from_mut(&mut c)) != 1 {
+        loop {
+            self.receive();
+            if let Ok(c) = self.rx.lock().pop() {
+                return c as usize;
+            }
             core::hint::spin_loop();
         }
-        c as usize
+    }
+
+    /// Moves bytes received by the input device into the receive buffer.
+    ///
+    /// Called around console output, so input arriving while supervisor software
+    /// is writing is kept until the buffer fills.
+    pub fn receive(&self) {
+        let mut rx = self.rx.lock();
+        let console = self.devices[0].lock();
+        let mut c = 0u8;
+        while !rx.is_full() && console.read(core::slice::from_mut(&mut c)) == 1 {
+            rx.push(c).unwrap();
+        }
+    }
+
+    /// Writes `buf` to the primary device and mirrors written bytes to the others.
+    ///
+    /// # Returns
+    /// The number of bytes written to the primary device.
+    fn write_bytes(&self, buf: &[u8]) -> usize {
+        let count = self.devices[0].lock().write(buf);
+        for device in &self.devices[1..] {
+            let device = device.lock();
+            let mut bytes = &buf[..count];
+            while !bytes.is_empty() {
+                let written = device.write(bytes);
+                bytes = &bytes[written..];
+            }
+        }
+        self.receive();
+        count
     }
 }
 
@@ -73,40 +127,75 @@ impl Console for SbiConsole {
     /// Write a physical memory buffer to the console.
     #[inline]
     fn write(&self, bytes: Physical<&[u8]>) -> SbiRet {
-        // TODO: verify valid memory range for a `Physical` slice.
+        if !is_supervisor_buffer(
+            bytes.phys_addr_lo(),
+            bytes.phys_addr_hi(),
+            bytes.num_bytes(),
+        ) {
+            return SbiRet::invalid_param();
+        }
         let start = bytes.phys_addr_lo();
+        let _mapping = map_supervisor_memory(start, bytes.num_bytes());
         let buf = unsafe { core::slice::from_raw_parts(start as *const u8, bytes.num_bytes()) };
-        let bytes_written = self.inner.lock().write(buf);
-        SbiRet::success(bytes_written)
+        SbiRet::success(self.write_bytes(buf))
     }
 
     /// Read from console into a physical memory buffer.
     #[inline]
     fn read(&self, bytes: Physical<&mut [u8]>) -> SbiRet {
-        // TODO: verify valid memory range for a `Physical` slice.
+        if !is_supervisor_buffer(
+            bytes.phys_addr_lo(),
+            bytes.phys_addr_hi(),
+            bytes.num_bytes(),
+        ) {
+            return SbiRet::invalid_param();
+        }
         let start = bytes.phys_addr_lo();
+        let _mapping = map_supervisor_memory(start, bytes.num_bytes());
         let buf = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, bytes.num_bytes()) };
-        let bytes_read = self.inner.lock().read(buf);
+        // Reads never block, only bytes already received are returned.
+        self.receive();
+        let mut rx = self.rx.lock();
+        let mut bytes_read = 0;
+        while bytes_read < buf.len() {
+            match rx.pop() {
+                Ok(c) => buf[bytes_read] = c,
+                Err(_) => break,
+            }
+            bytes_read += 1;
+        }
         SbiRet::success(bytes_read)
     }
 
     /// Write a single byte to the console.
     #[inline]
     fn write_byte(&self, byte: u8) -> SbiRet {
-        self.inner.lock().write(&[byte]);
+        while self.write_bytes(&[byte]) == 0 {
+            core::hint::spin_loop();
+        }
         SbiRet::success(0)
     }
 }
 
+/// Checks if a console buffer lies in memory accessible to supervisor software.
+///
+/// Unlike shared memory, all-ones addresses do not stand for a missing buffer.
+#[inline]
+fn is_supervisor_buffer(phys_addr_lo: usize, phys_addr_hi: usize, num_bytes: usize) -> bool {
+    matches!(
+        supervisor_shmem(phys_addr_lo, phys_addr_hi, num_bytes, 1),
+        Ok(Some(_))
+    )
+}
+
 impl fmt::Write for SbiConsole {
     /// Implement Write trait for string formatting.
     #[inline]
     fn write_str(&mut self, s: &str) -> fmt::Result {
         let mut bytes = s.as_bytes();
-        let console = self.inner.lock();
         // Write all bytes in chunks
         while !bytes.is_empty() {
-            let count = console.write(bytes);
+            let count = self.write_bytes(bytes);
             bytes = &bytes[count..];
         }
         Ok(())
@@ -0,0 +1,128 @@
+use alloc::boxed::Box;
+use rustsbi::SbiRet;
+use spin::Mutex;
+
+use crate::riscv::current_hartid;
+
+/// CPPC register identifiers.
+#[allow(unused)]
+pub mod reg {
+    pub const HIGHEST_PERF: u32 = 0x00;
+    pub const NOMINAL_PERF: u32 = 0x01;
+    pub const LOWEST_NONLINEAR_PERF: u32 = 0x02;
+    pub const LOWEST_PERF: u32 = 0x03;
+    pub const GUARANTEED_PERF: u32 = 0x04;
+    pub const DESIRED_PERF: u32 = 0x05;
+    pub const MIN_PERF: u32 = 0x06;
+    pub const MAX_PERF: u32 = 0x07;
+    pub const PERF_REDUCTION_TOLERANCE: u32 = 0x08;
+    pub const TIME_WINDOW: u32 = 0x09;
+    pub const COUNTER_WRAPAROUND_TIME: u32 = 0x0a;
+    pub const REFERENCE_COUNTER: u32 = 0x0b;
+    pub const DELIVERED_COUNTER: u32 = 0x0c;
+    pub const PERF_LIMITED: u32 = 0x0d;
+    pub const ENABLE: u32 = 0x0e;
+    pub const AUTO_SEL_ENABLE: u32 = 0x0f;
+    pub const AUTO_ACTIVITY_WINDOW: u32 = 0x10;
+    pub const ENERGY_PERF_PREFERENCE: u32 = 0x11;
+    pub const REFERENCE_PERF: u32 = 0x12;
+    pub const LOWEST_FREQ: u32 = 0x13;
+    pub const NOMINAL_FREQ: u32 = 0x14;
+    pub const ACPI_LAST: u32 = NOMINAL_FREQ;
+    pub const TRANSITION_LATENCY: u32 = 0x8000_0000;
+
+    /// Checks if `reg_id` is reserved by the SBI specification.
+    #[inline]
+    pub const fn is_reserved(reg_id: u32) -> bool {
+        reg_id > ACPI_LAST && reg_id != TRANSITION_LATENCY
+    }
+
+    /// Checks if `reg_id` is a read-only register.
+    #[inline]
+    pub const fn is_read_only(reg_id: u32) -> bool {
+        matches!(
+            reg_id,
+            HIGHEST_PERF..=GUARANTEED_PERF
+                | COUNTER_WRAPAROUND_TIME..=DELIVERED_COUNTER
+                | REFERENCE_PERF..=NOMINAL_FREQ
+                | TRANSITION_LATENCY
+        )
+    }
+}
+
+/// Platform provider of per-hart CPPC registers.
+///
+/// Register IDs passed to the provider are never reserved, and `read` and `write`
+/// are only called on registers whose `width` is not zero. Writes to read-only
+/// registers are rejected before reaching the provider.
+pub trait CppcDevice {
+    /// Returns the bit width of register `reg_id` on `hart_id`, or 0 if it is not implemented.
+    fn width(&self, hart_id: usize, reg_id: u32) -> u32;
+    /// Reads register `reg_id` of `hart_id`.
+    fn read(&mut self, hart_id: usize, reg_id: u32) -> Result<u64, SbiRet>;
+    /// Writes `value` to register `reg_id` of `hart_id`.
+    fn write(&mut self, hart_id: usize, reg_id: u32, value: u64) -> Result<(), SbiRet>;
+}
+
+pub struct SbiCppc {
+    pub cppc_dev: Mutex<Box<dyn CppcDevice>>,
+}
+
+impl SbiCppc {
+    pub fn new(cppc_dev: Mutex<Box<dyn CppcDevice>>) -> Self {
+        Self { cppc_dev }
+    }
+
+    /// Checks that `reg_id` is implemented on the current hart and returns the hart id.
+    fn check_implemented(&self, reg_id: u32) -> Result<usize, SbiRet> {
+        if reg::is_reserved(reg_id) {
+            return Err(SbiRet::invalid_param());
+        }
+        let hart_id = current_hartid();
+        if self.cppc_dev.lock().width(hart_id, reg_id) == 0 {
+            return Err(SbiRet::not_supported());
+        }
+        Ok(hart_id)
+    }
+}
+
+impl rustsbi::Cppc for SbiCppc {
+    fn probe(&self, reg_id: u32) -> SbiRet {
+        if reg::is_reserved(reg_id) {
+            return SbiRet::invalid_param();
+        }
+        let width = self.cppc_dev.lock().width(current_hartid(), reg_id);
+        SbiRet::success(width as usize)
+    }
+
+    fn read(&self, reg_id: u32) -> SbiRet {
+        let result = self
+            .check_implemented(reg_id)
+            .and_then(|hart_id| self.cppc_dev.lock().read(hart_id, reg_id));
+        match result {
+            Ok(value) => SbiRet::success(value as usize),
+            Err(error) => error,
+        }
+    }
+
+    fn read_hi(&self, reg_id: u32) -> SbiRet {
+        // Supervisor XLEN is 64, the whole register is returned by `read`.
+        match self.check_implemented(reg_id) {
+            Ok(_) => SbiRet::success(0),
+            Err(error) => error,
+        }
+    }
+
+    fn write(&self, reg_id: u32, val: u64) -> SbiRet {
+        let result = self.check_implemented(reg_id).and_then(|hart_id| {
+            if reg::is_read_only(reg_id) {
+                return Err(SbiRet::denied());
+            }
+            self.cppc_dev.lock().write(hart_id, reg_id, val)
+        });
+        match result {
+            Ok(()) => SbiRet::success(0),
+            Err(error) => error,
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use core::arch::asm;
+use core::fmt::{self, Write};
+use core::ops::Range;
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+use riscv::interrupt::machine::{Exception, Interrupt};
+use riscv::register::{mcause, mcause::Trap, mepc, mstatus, mtval};
+
+use crate::firmware::{firmware_range, map_supervisor_memory};
+use crate::platform::PLATFORM;
+use crate::riscv::current_hartid;
+use crate::sbi::trap_stack::stack_range;
+
+/// Compatible string of the reserved memory node holding the crash log.
+///
+/// Without such a node the crash log is kept in firmware memory, which survives warm
+/// reset but not reloading the firmware image.
+///
+/// ```dts
+/// reserved-memory {
+///     crash-log@8fff0000 {
+///         compatible = "rustsbi,crash-log";
+///         reg = <0x0 0x8fff0000 0x0 0x10000>;
+///         no-map;
+///     };
+/// };
+/// ```
+pub const CRASH_LOG_COMPATIBLE: &str = "rustsbi,crash-log";
+
+const CRASH_LOG_MAGIC: u64 = u64::from_le_bytes(*b"SBICRASH");
+/// Size of the crash log kept in firmware memory without a reserved memory node.
+const FIRMWARE_LOG_SIZE: usize = 4096;
+const MAX_FRAMES: usize = 32;
+
+const REGISTER_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Crash log placed outside the BSS cleared at boot, so it survives warm reset.
+#[unsafe(link_section = ".bss.pstore")]
+static mut FIRMWARE_LOG: [u64; FIRMWARE_LOG_SIZE / 8] = [0; FIRMWARE_LOG_SIZE / 8];
+
+static CRASHING: AtomicBool = AtomicBool::new(false);
+
+/// Header of the crash log, followed by the report text.
+#[repr(C)]
+struct LogHeader {
+    magic: u64,
+    len: usize,
+    checksum: u64,
+}
+
+/// Registers of a trapped context.
+pub struct TrapRegisters {
+    /// General purpose registers, `x[0]` being unused.
+    pub x: [usize; 32],
+    pub pc: usize,
+}
+
+/// Reports a firmware panic, with a backtrace of the firmware stack.
+pub fn report_panic(info: &PanicInfo) {
+    if CRASHING.swap(true, Ordering::AcqRel) {
+        // Panicked while reporting another crash, which is in the log already.
+        error!("Hart {} {info}", current_hartid());
+        return;
+    }
+    let mut report = Report::new();
+    report.line(format_args!("Hart {} {info}", current_hartid()));
+    report.csrs();
+    let fp: usize;
+    unsafe { asm!("mv {}, s0", out(reg) fp) };
+    report.backtrace(fp);
+    report.finish();
+}
+
+/// Reports a trap the firmware can not handle, with the trapped context.
+///
+/// The firmware stack is unwound if the trap came from M-mode.
+pub fn report_trap(trap: Option<Trap<Interrupt, Exception>>, regs: &TrapRegisters) {
+    if CRASHING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let mut report = Report::new();
+    report.line(format_args!(
+        "Hart {} unsupported trap {:?}",
+        current_hartid(),
+        trap
+    ));
+    report.csrs();
+    report.line(format_args!(
+        "pc:      {:#018x} {}",
+        regs.pc,
+        Symbol(regs.pc)
+    ));
+    for (names, values) in REGISTER_NAMES.chunks(4).zip(regs.x.chunks(4)) {
+        report.line(format_args!(
+            "{:>4}: {:#018x} {:>4}: {:#018x} {:>4}: {:#018x} {:>4}: {:#018x}",
+            names[0], values[0], names[1], values[1], names[2], values[2], names[3], values[3]
+        ));
+    }
+    if mstatus::read().mpp() == mstatus::MPP::Machine {
+        report.backtrace(regs.x[8]);
+    }
+    report.finish();
+}
+
+/// Prints and clears the crash log of the previous boot, if any.
+pub fn report_previous() {
+    let region = log_region();
+    let _mapping = map_log(&region);
+    let header = unsafe { &mut *(region.start as *mut LogHeader) };
+    if header.magic != CRASH_LOG_MAGIC {
+        return;
+    }
+    let capacity = region.len() - size_of::<LogHeader>();
+    let text = unsafe {
+        core::slice::from_raw_parts(
+            (region.start + size_of::<LogHeader>()) as *const u8,
+            header.len.min(capacity),
+        )
+    };
+    if header.len > capacity || checksum(text) != header.checksum {
+        warn!("Crash log of the previous boot is corrupted");
+    } else {
+        warn!("Previous boot crashed:");
+        for line in core::str::from_utf8(text)
+            .unwrap_or("<invalid text>")
+            .lines()
+        {
+            warn!("| {}", line);
+        }
+    }
+    header.magic = 0;
+}
+
+/// Gets the crash log region, from the device tree or in firmware memory.
+fn log_region() -> Range<usize> {
+    match unsafe { PLATFORM.info.crash_log.clone() } {
+        Some(region) => region,
+        None => {
+            let start = &raw const FIRMWARE_LOG as usize;
+            start..start + FIRMWARE_LOG_SIZE
+        }
+    }
+}
+
+/// Maps a crash log region from the device tree, which lies in supervisor memory.
+fn map_log(region: &Range<usize>) -> Option<crate::firmware::SupervisorMemory> {
+    let in_firmware = firmware_range().contains(&region.start);
+    (!in_firmware).then(|| map_supervisor_memory(region.start, region.len()))
+}
+
+/// FNV-1a hash of the report text.
+fn checksum(text: &[u8]) -> u64 {
+    text.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+/// Crash report, written to the console and the crash log.
+struct Report {
+    region: Range<usize>,
+    len: usize,
+}
+
+impl Report {
+    fn new() -> Self {
+        Report {
+            region: log_region(),
+            len: 0,
+        }
+    }
+
+    fn line(&mut self, args: fmt::Arguments) {
+        error!("{}", args);
+        let _ = writeln!(self, "{}", args);
+    }
+
+    fn csrs(&mut self) {
+        self.line(format_args!("mcause:  {:#018x}", mcause::read().bits()));
+        self.line(format_args!(
+            "mepc:    {:#018x} {}",
+            mepc::read(),
+            Symbol(mepc::read())
+        ));
+        self.line(format_args!("mtval:   {:#018x}", mtval::read()));
+        self.line(format_args!("mstatus: {:#018x}", mstatus::read().bits()));
+    }
+
+    /// Unwinds the firmware stack through frame pointers, starting at frame `fp`.
+    fn backtrace(&mut self, mut fp: usize) {
+        self.line(format_args!("Backtrace:"));
+        let Some(stack) = stack_range(current_hartid()) else {
+            return;
+        };
+        for depth in 0..MAX_FRAMES {
+            // The return address and the previous frame pointer lie below `fp`.
+            if !fp.is_multiple_of(8) || fp < stack.start + 16 || fp > stack.end {
+                break;
+            }
+            let ra = unsafe { ((fp - 8) as *const usize).read() };
+            let prev_fp = unsafe { ((fp - 16) as *const usize).read() };
+            self.line(format_args!("#{:<2} {:#018x} {}", depth, ra, Symbol(ra)));
+            if prev_fp <= fp {
+                break;
+            }
+            fp = prev_fp;
+        }
+    }
+
+    /// Seals the crash log so that the next boot reports it.
+    fn finish(self) {
+        let _mapping = map_log(&self.region);
+        let text = unsafe {
+            core::slice::from_raw_parts(
+                (self.region.start + size_of::<LogHeader>()) as *const u8,
+                self.len,
+            )
+        };
+        let header = LogHeader {
+            magic: CRASH_LOG_MAGIC,
+            len: self.len,
+            checksum: checksum(text),
+        };
+        unsafe { (self.region.start as *mut LogHeader).write_volatile(header) };
+    }
+}
+
+impl Write for Report {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let capacity = self.region.len() - size_of::<LogHeader>();
+        let len = s.len().min(capacity - self.len);
+        let _mapping = map_log(&self.region);
+        unsafe {
+            core::ptr::copy_nonoverlapping(
+                s.as_ptr(),
+                (self.region.start + size_of::<LogHeader>() + self.len) as *mut u8,
+                len,
+            )
+        };
+        self.len += len;
+        Ok(())
+    }
+}
+
+/// Placeholder magic of the symbol table, replaced when `xtask prototyper` fills it.
+const SYMBOL_TABLE_PLACEHOLDER: [u8; 16] = *b"RUSTSBI-NOSYMTAB";
+const SYMBOL_TABLE_MAGIC: [u8; 16] = *b"RUSTSBI-SYMTAB\0\0";
+const SYMBOL_TABLE_SIZE: usize = 32 * 1024;
+
+/// Symbol table of the firmware text, filled in after linking.
+///
+/// `data` holds the symbol count as `u32` and 4 reserved bytes, then `(offset, name)`
+/// pairs of `u32` sorted by offset from `sbi_start`, then NUL-terminated names the
+/// pairs point into, relative to the start of the pairs.
+#[repr(C)]
+struct SymbolTable {
+    magic: [u8; 16],
+    data: [u8; SYMBOL_TABLE_SIZE],
+}
+
+#[used]
+#[unsafe(no_mangle)]
+static RUSTSBI_SYMBOL_TABLE: SymbolTable = SymbolTable {
+    magic: SYMBOL_TABLE_PLACEHOLDER,
+    data: [0; SYMBOL_TABLE_SIZE],
+};
+
+/// Firmware address printed with the symbol it belongs to.
+struct Symbol(usize);
+
+impl Symbol {
+    fn lookup(&self) -> Option<(&'static str, usize)> {
+        let firmware = firmware_range();
+        if !firmware.contains(&self.0) {
+            return None;
+        }
+        // The table is patched after linking, its contents must not be assumed.
+        let table: &SymbolTable =
+            unsafe { &*core::hint::black_box(&raw const RUSTSBI_SYMBOL_TABLE) };
+        if table.magic != SYMBOL_TABLE_MAGIC {
+            return None;
+        }
+        let word = |offset: usize| {
+            let bytes = table.data.get(offset..offset + 4)?;
+            Some(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+        };
+        let count = word(0)?;
+        let entries = 8;
+        let offset = self.0 - firmware.start;
+        // Last symbol starting at or before `offset`.
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let mid = (low + high) / 2;
+            match word(entries + mid * 8)? <= offset {
+                true => low = mid + 1,
+                false => high = mid,
+            }
+        }
+        let index = low.checked_sub(1)?;
+        let start = word(entries + index * 8)?;
+        let name_start = entries + word(entries + index * 8 + 4)?;
+        let name = table.data.get(name_start..)?;
+        let len = name.iter().position(|&byte| byte == 0)?;
+        let name = core::str::from_utf8(&name[..len]).ok()?;
+        Some((name, offset - start))
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.lookup() {
+            Some((name, offset)) => write!(f, "<{}+{:#x}>", name, offset),
+            None => Ok(()),
+        }
+    }
+}
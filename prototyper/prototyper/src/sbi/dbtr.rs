@@ -0,0 +1,388 @@
+use rustsbi::{Dbtr, SbiRet, SharedPtr, TriggerMask};
+
+use crate::firmware::map_supervisor_memory;
+use crate::riscv::csr::sdtrig::{self, mcontrol6};
+use crate::riscv::current_hartid;
+use crate::sbi::features::{TRIGGER_MAX, TriggerFeatures, hart_triggers};
+use crate::sbi::supervisor_shmem;
+use crate::sbi::trap_stack::{hart_context, hart_context_mut};
+
+/// Size of one trigger entry in shared memory: `trig_state` and `trig_tdata1..3`.
+const ENTRY_WORDS: usize = 4;
+const ENTRY_SIZE: usize = ENTRY_WORDS * size_of::<usize>();
+
+/// Bit fields of `trig_state` word reported to supervisor software.
+mod trig_state {
+    pub const MAPPED: usize = 0x1 << 0;
+    pub const U: usize = 0x1 << 1;
+    pub const S: usize = 0x1 << 2;
+    pub const VU: usize = 0x1 << 3;
+    pub const VS: usize = 0x1 << 4;
+    pub const HAVE_HW_TRIG: usize = 0x1 << 5;
+    pub const HW_TRIG_IDX_SHIFT: usize = 8;
+}
+
+/// Software copy of an installed debug trigger.
+#[derive(Copy, Clone)]
+struct Trigger {
+    mapped: bool,
+    enabled: bool,
+    tdata1: usize,
+    tdata2: usize,
+    tdata3: usize,
+}
+
+impl Trigger {
+    const EMPTY: Self = Self {
+        mapped: false,
+        enabled: false,
+        tdata1: 0,
+        tdata2: 0,
+        tdata3: 0,
+    };
+}
+
+/// Per-hart debug trigger state.
+///
+/// Logical trigger indices map one to one onto the hart's hardware triggers.
+pub struct DbtrState {
+    /// Physical address of shared memory, `None` when disabled.
+    shmem: Option<usize>,
+    triggers: [Trigger; TRIGGER_MAX],
+}
+
+impl DbtrState {
+    /// Creates a new state with shared memory disabled and no trigger installed.
+    pub const fn new() -> Self {
+        Self {
+            shmem: None,
+            triggers: [Trigger::EMPTY; TRIGGER_MAX],
+        }
+    }
+
+    /// Uninstalls all hardware triggers of the current hart and disables shared memory.
+    pub fn reset(&mut self) {
+        for (index, trigger) in self.triggers.iter_mut().enumerate() {
+            if trigger.mapped {
+                clear_hw_trigger(index);
+            }
+            *trigger = Trigger::EMPTY;
+        }
+        self.shmem = None;
+    }
+
+    #[inline]
+    fn entry_ptr(&self, shmem: usize, index: usize) -> *mut usize {
+        (shmem + index * ENTRY_SIZE) as *mut usize
+    }
+
+    #[inline]
+    fn read_entry(&self, shmem: usize, index: usize) -> [usize; ENTRY_WORDS] {
+        let ptr = self.entry_ptr(shmem, index);
+        let _mapping = map_supervisor_memory(ptr as usize, ENTRY_SIZE);
+        core::array::from_fn(|word| unsafe { ptr.add(word).read_volatile() })
+    }
+
+    #[inline]
+    fn write_entry(&self, shmem: usize, index: usize, entry: [usize; ENTRY_WORDS]) {
+        let ptr = self.entry_ptr(shmem, index);
+        let _mapping = map_supervisor_memory(ptr as usize, ENTRY_SIZE);
+        for (word, value) in entry.into_iter().enumerate() {
+            unsafe { ptr.add(word).write_volatile(value) };
+        }
+    }
+}
+
+/// Implementation of SBI Debug Triggers extension on top of Sdtrig `mcontrol6` triggers.
+pub struct SbiDbtr;
+
+impl Dbtr for SbiDbtr {
+    fn num_triggers(&self, trig_tdata1: usize) -> SbiRet {
+        let triggers = hart_triggers(current_hartid());
+        if trig_tdata1 == 0 {
+            return SbiRet::success(triggers.count);
+        }
+        let trig_type = trig_tdata1 >> sdtrig::TDATA1_TYPE_SHIFT;
+        let count = triggers.types[..triggers.count]
+            .iter()
+            .filter(|&&types| types & (1 << trig_type) != 0)
+            .count();
+        SbiRet::success(count)
+    }
+
+    fn set_shmem(&self, shmem: SharedPtr<u8>, flags: usize) -> SbiRet {
+        if flags != 0 {
+            return SbiRet::invalid_param();
+        }
+        let hart_id = current_hartid();
+        let size = hart_triggers(hart_id).count * ENTRY_SIZE;
+        match supervisor_shmem(
+            shmem.phys_addr_lo(),
+            shmem.phys_addr_hi(),
+            size,
+            size_of::<usize>(),
+        ) {
+            Ok(shmem) => {
+                hart_context_mut(hart_id).dbtr_state.shmem = shmem;
+                SbiRet::success(0)
+            }
+            Err(error) => error,
+        }
+    }
+
+    fn read_triggers(&self, trig_idx_base: usize, trig_count: usize) -> SbiRet {
+        let hart_id = current_hartid();
+        let state = &hart_context(hart_id).dbtr_state;
+        let Some(shmem) = state.shmem else {
+            return SbiRet::no_shmem();
+        };
+        let trig_max = hart_triggers(hart_id).count;
+        match trig_idx_base.checked_add(trig_count) {
+            Some(end) if trig_idx_base < trig_max && end < trig_max => {}
+            _ => return SbiRet::bad_range(),
+        }
+
+        for i in 0..trig_count {
+            let index = trig_idx_base + i;
+            let trigger = &state.triggers[index];
+            let entry = if trigger.mapped {
+                // Read back from hardware so that hit bits are reported.
+                sdtrig::tselect::write(index);
+                let tdata1 = sdtrig::tdata1::read();
+                [
+                    trigger_state(index, trigger),
+                    (tdata1 & !mcontrol6::MODE_MASK) | (trigger.tdata1 & mcontrol6::MODE_MASK),
+                    trigger.tdata2,
+                    trigger.tdata3,
+                ]
+            } else {
+                [0; ENTRY_WORDS]
+            };
+            state.write_entry(shmem, i, entry);
+        }
+        SbiRet::success(0)
+    }
+
+    fn install_triggers(&self, trig_count: usize) -> SbiRet {
+        let hart_id = current_hartid();
+        let triggers = *hart_triggers(hart_id);
+        let state = &mut hart_context_mut(hart_id).dbtr_state;
+        let Some(shmem) = state.shmem else {
+            return SbiRet::no_shmem();
+        };
+        if trig_count >= triggers.count {
+            return SbiRet::bad_range();
+        }
+
+        // Validate all configurations before touching any hardware trigger.
+        for i in 0..trig_count {
+            let [_, tdata1, _, _] = state.read_entry(shmem, i);
+            if let Err(error) = validate_tdata1(tdata1) {
+                return SbiRet { value: i, ..error };
+            }
+        }
+
+        // Hardware triggers installed by this call, uninstalled again on failure.
+        let mut installed = [0; TRIGGER_MAX];
+        for i in 0..trig_count {
+            let [_, tdata1, tdata2, tdata3] = state.read_entry(shmem, i);
+            let free = (0..triggers.count).find(|&index| {
+                !state.triggers[index].mapped
+                    && triggers.types[index] & (1 << sdtrig::TYPE_MCONTROL6) != 0
+            });
+            let trigger = Trigger {
+                mapped: true,
+                enabled: true,
+                tdata1,
+                tdata2,
+                tdata3,
+            };
+            let result = match free {
+                Some(index) => program_hw_trigger(index, &trigger, &triggers).map(|()| index),
+                None => Err(SbiRet::failed()),
+            };
+            let index = match result {
+                Ok(index) => index,
+                Err(error) => {
+                    for &index in &installed[..i] {
+                        clear_hw_trigger(index);
+                        state.triggers[index] = Trigger::EMPTY;
+                    }
+                    return SbiRet { value: i, ..error };
+                }
+            };
+            state.triggers[index] = trigger;
+            installed[i] = index;
+            let mut entry = state.read_entry(shmem, i);
+            entry[0] = index;
+            state.write_entry(shmem, i, entry);
+        }
+        SbiRet::success(0)
+    }
+
+    fn update_triggers(&self, trig_count: usize) -> SbiRet {
+        let hart_id = current_hartid();
+        let triggers = *hart_triggers(hart_id);
+        let state = &mut hart_context_mut(hart_id).dbtr_state;
+        let Some(shmem) = state.shmem else {
+            return SbiRet::no_shmem();
+        };
+        if trig_count >= triggers.count {
+            return SbiRet::bad_range();
+        }
+
+        for i in 0..trig_count {
+            let [index, tdata1, _, _] = state.read_entry(shmem, i);
+            let error = if index >= triggers.count {
+                SbiRet::invalid_param()
+            } else if !state.triggers[index].mapped {
+                SbiRet::failed()
+            } else if let Err(error) = validate_tdata1(tdata1) {
+                error
+            } else {
+                continue;
+            };
+            return SbiRet { value: i, ..error };
+        }
+
+        for i in 0..trig_count {
+            let [index, tdata1, tdata2, tdata3] = state.read_entry(shmem, i);
+            let trigger = Trigger {
+                mapped: true,
+                enabled: state.triggers[index].enabled,
+                tdata1,
+                tdata2,
+                tdata3,
+            };
+            if let Err(error) = program_hw_trigger(index, &trigger, &triggers) {
+                // Keep the previous configuration in effect.
+                let _ = program_hw_trigger(index, &state.triggers[index], &triggers);
+                return SbiRet { value: i, ..error };
+            }
+            state.triggers[index] = trigger;
+        }
+        SbiRet::success(0)
+    }
+
+    fn uninstall_triggers(&self, trig_mask: TriggerMask) -> SbiRet {
+        for_each_masked_trigger(trig_mask, |index, trigger| {
+            clear_hw_trigger(index);
+            *trigger = Trigger::EMPTY;
+        })
+    }
+
+    fn enable_triggers(&self, trig_mask: TriggerMask) -> SbiRet {
+        let triggers = *hart_triggers(current_hartid());
+        for_each_masked_trigger(trig_mask, |index, trigger| {
+            trigger.enabled = true;
+            // The configuration was accepted by hardware on install.
+            let _ = program_hw_trigger(index, trigger, &triggers);
+        })
+    }
+
+    fn disable_triggers(&self, trig_mask: TriggerMask) -> SbiRet {
+        let triggers = *hart_triggers(current_hartid());
+        for_each_masked_trigger(trig_mask, |index, trigger| {
+            trigger.enabled = false;
+            let _ = program_hw_trigger(index, trigger, &triggers);
+        })
+    }
+}
+
+/// Checks a supervisor provided `tdata1` value.
+///
+/// Only unchained `mcontrol6` triggers raising breakpoint exceptions in S/U/VS/VU-mode are accepted.
+fn validate_tdata1(tdata1: usize) -> Result<(), SbiRet> {
+    if tdata1 >> sdtrig::TDATA1_TYPE_SHIFT != sdtrig::TYPE_MCONTROL6 {
+        return Err(SbiRet::not_supported());
+    }
+    // Chains could link to triggers the supervisor does not own.
+    if tdata1 & (sdtrig::TDATA1_DMODE | mcontrol6::M | mcontrol6::ACTION | mcontrol6::CHAIN) != 0 {
+        return Err(SbiRet::invalid_param());
+    }
+    Ok(())
+}
+
+/// Builds the `trig_state` word of an installed trigger.
+fn trigger_state(index: usize, trigger: &Trigger) -> usize {
+    let mut state =
+        trig_state::MAPPED | trig_state::HAVE_HW_TRIG | (index << trig_state::HW_TRIG_IDX_SHIFT);
+    for (mode, bit) in [
+        (mcontrol6::U, trig_state::U),
+        (mcontrol6::S, trig_state::S),
+        (mcontrol6::VU, trig_state::VU),
+        (mcontrol6::VS, trig_state::VS),
+    ] {
+        if trigger.tdata1 & mode != 0 {
+            state |= bit;
+        }
+    }
+    state
+}
+
+/// Writes the trigger configuration into hardware trigger `index`.
+///
+/// A disabled trigger is programmed with all privilege mode bits cleared so it never fires.
+fn program_hw_trigger(
+    index: usize,
+    trigger: &Trigger,
+    triggers: &TriggerFeatures,
+) -> Result<(), SbiRet> {
+    let tdata1 = if trigger.enabled {
+        trigger.tdata1
+    } else {
+        trigger.tdata1 & !mcontrol6::MODE_MASK
+    };
+    sdtrig::tselect::write(index);
+    // Disable the trigger while `tdata2` and `tdata3` are inconsistent.
+    sdtrig::tdata1::write(0);
+    sdtrig::tdata2::write(trigger.tdata2);
+    if triggers.has_tdata3 {
+        sdtrig::tdata3::write(trigger.tdata3);
+    } else if trigger.tdata3 != 0 {
+        return Err(SbiRet::not_supported());
+    }
+    sdtrig::tdata1::write(tdata1);
+    // `tdata1` is WARL; unimplemented options read back differently.
+    if sdtrig::tdata1::read() & !mcontrol6::HIT_MASK != tdata1 & !mcontrol6::HIT_MASK {
+        sdtrig::tdata1::write(0);
+        return Err(SbiRet::not_supported());
+    }
+    Ok(())
+}
+
+fn clear_hw_trigger(index: usize) {
+    sdtrig::tselect::write(index);
+    sdtrig::tdata1::write(0);
+    sdtrig::tdata2::write(0);
+    if hart_triggers(current_hartid()).has_tdata3 {
+        sdtrig::tdata3::write(0);
+    }
+}
+
+/// Applies `f` to every trigger selected by `trig_mask` on the current hart.
+///
+/// Fails without modifying any trigger if one of them is out of range or not installed.
+fn for_each_masked_trigger(
+    trig_mask: TriggerMask,
+    mut f: impl FnMut(usize, &mut Trigger),
+) -> SbiRet {
+    let hart_id = current_hartid();
+    let trig_max = hart_triggers(hart_id).count;
+    let state = &mut hart_context_mut(hart_id).dbtr_state;
+    let (mask, base) = trig_mask.into_inner();
+    let selected = (0..usize::BITS as usize)
+        .filter(move |bit| mask & (1 << *bit) != 0)
+        .map(move |bit| base.checked_add(bit));
+
+    for index in selected.clone() {
+        match index {
+            Some(index) if index < trig_max && state.triggers[index].mapped => {}
+            _ => return SbiRet::invalid_param(),
+        }
+    }
+    for index in selected.flatten() {
+        f(index, &mut state.triggers[index]);
+    }
+    SbiRet::success(0)
+}
@@ -17,6 +17,23 @@ pub struct HartFeatures {
     privileged_version: PrivilegedVersion,
     mhpm_mask: u32,
     mhpm_bits: u32,
+    triggers: TriggerFeatures,
+    pmp_count: usize,
+    has_mseccfg: bool,
+}
+
+/// Maximum number of debug triggers managed on each hart.
+pub const TRIGGER_MAX: usize = 16;
+
+/// Debug triggers implemented by a hart (Sdtrig).
+#[derive(Copy, Clone)]
+pub struct TriggerFeatures {
+    /// Number of implemented triggers.
+    pub count: usize,
+    /// Supported trigger types of each trigger, in `tinfo.info` format.
+    pub types: [u16; TRIGGER_MAX],
+    /// Whether `tdata3` is implemented.
+    pub has_tdata3: bool,
 }
 
 impl HartFeatures {
@@ -37,15 +54,29 @@ pub enum PrivilegedVersion {
 pub enum Extension {
     Sstc = 0,
     Hypervisor = 1,
+    Zicfilp = 2,
+    Zicfiss = 3,
+    Ssdbltrp = 4,
+    Svadu = 5,
+    Smnpm = 6,
+    Sscofpmf = 7,
+    Smepmp = 8,
 }
 
 impl Extension {
-    pub const COUNT: usize = 2;
+    pub const COUNT: usize = 9;
 
     pub const fn as_str(&self) -> &'static str {
         match self {
             Self::Sstc => "sstc",
             Self::Hypervisor => "h",
+            Self::Zicfilp => "zicfilp",
+            Self::Zicfiss => "zicfiss",
+            Self::Ssdbltrp => "ssdbltrp",
+            Self::Svadu => "svadu",
+            Self::Smnpm => "smnpm",
+            Self::Sscofpmf => "sscofpmf",
+            Self::Smepmp => "smepmp",
         }
     }
 
@@ -55,7 +86,18 @@ impl Extension {
     }
 
     pub fn iter() -> impl Iterator<Item = Self> {
-        [Self::Sstc, Self::Hypervisor].into_iter()
+        [
+            Self::Sstc,
+            Self::Hypervisor,
+            Self::Zicfilp,
+            Self::Zicfiss,
+            Self::Ssdbltrp,
+            Self::Svadu,
+            Self::Smnpm,
+            Self::Sscofpmf,
+            Self::Smepmp,
+        ]
+        .into_iter()
     }
 }
 
@@ -77,6 +119,28 @@ pub fn hart_mhpm_mask(hart_id: usize) -> u32 {
     hart_context(hart_id).features.mhpm_mask
 }
 
+/// Gets the number of implemented PMP entries of the given hart.
+///
+/// Only the 16 entries configured by `pmpcfg0` and `pmpcfg2` are counted.
+#[inline]
+pub fn hart_pmp_count(hart_id: usize) -> usize {
+    hart_context(hart_id).features.pmp_count
+}
+
+/// Checks if the given hart implements `mseccfg`.
+///
+/// `mseccfg` also exists with Zkr alone, PMP setup checks its Smepmp bits stick.
+#[inline]
+pub fn hart_has_mseccfg(hart_id: usize) -> bool {
+    hart_context(hart_id).features.has_mseccfg
+}
+
+/// Gets the debug triggers of the given hart.
+#[inline]
+pub fn hart_triggers(hart_id: usize) -> &'static TriggerFeatures {
+    &hart_context(hart_id).features.triggers
+}
+
 /// Detects RISC-V extensions from the device tree for all harts.
 #[cfg(not(feature = "nemu"))]
 pub fn extension_detection(cpus: &NodeSeq) {
@@ -96,6 +160,10 @@ pub fn extension_detection(cpus: &NodeSeq) {
                 Extension::Hypervisor if hart_id == current_hartid() => {
                     misa::read().unwrap().has_extension('H')
                 }
+                // `scountovf` only exists with Sscofpmf.
+                Extension::Sscofpmf if hart_id == current_hartid() => {
+                    dt_supported || has_csr!(CSR_SCOUNTOVF)
+                }
                 _ => dt_supported,
             };
         }
@@ -174,9 +242,93 @@ fn mhpm_detection() {
     hart_context_mut(current_hartid()).features.mhpm_bits = 64;
 }
 
+fn trigger_detection() {
+    let mut triggers = TriggerFeatures {
+        count: 0,
+        types: [0; TRIGGER_MAX],
+        has_tdata3: false,
+    };
+    let mut trap_info: TrapInfo = TrapInfo::default();
+
+    // Harts without Sdtrig raise illegal instruction on `tselect` access.
+    let old_tselect = unsafe { csr_read_allow::<CSR_TSELECT>(&mut trap_info) };
+    if trap_info.mcause == usize::MAX {
+        triggers.has_tdata3 = has_csr!(CSR_TDATA3);
+        for index in 0..TRIGGER_MAX {
+            // `tselect` is WARL, an unimplemented index reads back differently.
+            sdtrig::tselect::write(index);
+            if sdtrig::tselect::read() != index {
+                break;
+            }
+            let tinfo = unsafe { csr_read_allow::<CSR_TINFO>(&mut trap_info) };
+            let types = if trap_info.mcause == usize::MAX {
+                tinfo & 0xffff
+            } else {
+                // Without `tinfo`, only the current type in `tdata1` is known.
+                1 << (sdtrig::tdata1::read() >> sdtrig::TDATA1_TYPE_SHIFT)
+            };
+            // Type 0 only means there is no trigger at this index.
+            if types <= 1 {
+                break;
+            }
+            triggers.types[index] = types as u16;
+            triggers.count += 1;
+        }
+        sdtrig::tselect::write(old_tselect);
+    }
+
+    hart_context_mut(current_hartid()).features.triggers = triggers;
+}
+
+/// Lock bit of a PMP entry configuration.
+const PMP_LOCK: usize = 1 << 7;
+
+fn pmp_detection() {
+    let mut trap_info: TrapInfo = TrapInfo::default();
+
+    // Unimplemented `pmpaddr` registers are read-only zero, implemented ones are
+    // writable unless their entry is locked.
+    fn check_pmpaddr_csr<const CSR_NUM: u16>(trap_info: *mut TrapInfo) -> bool {
+        unsafe {
+            let old_value = csr_read_allow::<CSR_NUM>(trap_info);
+            if (*trap_info).mcause != usize::MAX {
+                return false;
+            }
+            csr_write_allow::<CSR_NUM>(trap_info, usize::MAX);
+            (*trap_info).mcause == usize::MAX && csr_swap::<CSR_NUM>(old_value) != 0
+        }
+    }
+
+    let mut pmpcfg = [0usize; 2];
+    unsafe {
+        pmpcfg[0] = csr_read_allow::<CSR_PMPCFG0>(&mut trap_info);
+        pmpcfg[1] = csr_read_allow::<CSR_PMPCFG2>(&mut trap_info);
+    }
+    if trap_info.mcause != usize::MAX {
+        pmpcfg = [0; 2];
+    }
+    let locked = |index: usize| (pmpcfg[index / 8] >> (index % 8 * 8)) & PMP_LOCK != 0;
+
+    // Entries are implemented from the lowest-numbered one.
+    let mut count = 0;
+    seq!(index in 0..16 {
+        if count == index
+            && (locked(index) || check_pmpaddr_csr::<{ CSR_PMPADDR0 + index }>(&mut trap_info))
+        {
+            count += 1;
+        }
+    });
+
+    hart_context_mut(current_hartid()).features.pmp_count = count;
+}
+
 pub fn hart_features_detection() {
     privileged_version_detection();
     mhpm_detection();
+    trigger_detection();
+    pmp_detection();
+    // Probed on every hart, as lockdown is set up by each hart on its own PMP.
+    hart_context_mut(current_hartid()).features.has_mseccfg = has_csr!(CSR_MSECCFG);
 }
 
 #[cfg(feature = "nemu")]
@@ -1,6 +1,6 @@
 use core::mem::MaybeUninit;
 
-/// Size of the FIFO buffer.
+/// Default size of the FIFO buffer.
 const FIFO_SIZE: usize = 16;
 
 #[derive(Debug)]
@@ -10,18 +10,18 @@ pub enum FifoError {
 }
 
 /// A fixed-size FIFO (First In First Out) queue implementation.
-pub struct Fifo<T: Copy + Clone> {
-    data: [MaybeUninit<T>; FIFO_SIZE],
+pub struct Fifo<T: Copy + Clone, const N: usize = FIFO_SIZE> {
+    data: [MaybeUninit<T>; N],
     head: usize,
     tail: usize,
     count: usize,
 }
 
-impl<T: Copy + Clone> Fifo<T> {
+impl<T: Copy + Clone, const N: usize> Fifo<T, N> {
     #[inline]
     pub const fn new() -> Self {
         // Initialize array with uninitialized values
-        let data = [MaybeUninit::uninit(); FIFO_SIZE];
+        let data = [MaybeUninit::uninit(); N];
         Self {
             data,
             head: 0,
@@ -32,7 +32,7 @@ impl<T: Copy + Clone> Fifo<T> {
 
     #[inline]
     pub fn is_full(&self) -> bool {
-        self.count == FIFO_SIZE
+        self.count == N
     }
 
     #[inline]
@@ -47,7 +47,7 @@ impl<T: Copy + Clone> Fifo<T> {
 
         // Write element and update tail position
         self.data[self.tail].write(element);
-        self.tail = (self.tail + 1) % FIFO_SIZE;
+        self.tail = (self.tail + 1) % N;
         self.count += 1;
 
         Ok(())
@@ -62,7 +62,7 @@ impl<T: Copy + Clone> Fifo<T> {
         let element = unsafe { self.data[self.head].assume_init_read() };
 
         // Update head position
-        self.head = (self.head + 1) % FIFO_SIZE;
+        self.head = (self.head + 1) % N;
         self.count -= 1;
 
         Ok(element)
@@ -0,0 +1,176 @@
+use riscv::register::medeleg;
+use rustsbi::{Fwft, SbiRet};
+use sbi_spec::fwft::{feature_type::*, flags::SetFlags};
+
+use crate::riscv::csr::menvcfg;
+use crate::riscv::current_hartid;
+use crate::sbi::features::{
+    Extension, PrivilegedVersion, hart_extension_probe, hart_privileged_version,
+};
+use crate::sbi::trap_stack::{hart_context, hart_context_mut};
+
+/// Number of firmware features defined by the SBI specification.
+const FWFT_FEATURE_MAX: usize = 6;
+
+/// Per-hart firmware feature state.
+///
+/// Feature values live in the hart's own `medeleg` and `menvcfg` CSRs; this
+/// structure only records which features were locked by supervisor software.
+pub struct FwftState {
+    /// Bitmap of locked features (1 bit per feature type).
+    locked: usize,
+}
+
+impl FwftState {
+    /// Creates a new state with no feature locked.
+    pub const fn new() -> Self {
+        Self { locked: 0 }
+    }
+
+    #[inline]
+    fn is_locked(&self, feature: usize) -> bool {
+        self.locked & (1 << feature) != 0
+    }
+
+    #[inline]
+    fn lock(&mut self, feature: usize) {
+        self.locked |= 1 << feature;
+    }
+}
+
+/// Implementation of SBI Firmware Features extension.
+pub struct SbiFwft;
+
+impl Fwft for SbiFwft {
+    fn set(&self, feature: u32, value: usize, flags: usize) -> SbiRet {
+        let Some(flags) = SetFlags::from_bits(flags) else {
+            return SbiRet::invalid_param();
+        };
+        let feature = feature as usize;
+        let hart_id = current_hartid();
+        if let Err(error) = feature_supported(hart_id, feature) {
+            return error;
+        }
+        if hart_context(hart_id).fwft_state.is_locked(feature) {
+            return SbiRet::denied_locked();
+        }
+
+        let ret = match feature {
+            MISALIGNED_EXC_DELEG => set_misaligned_delegation(value),
+            LANDING_PAD => set_menvcfg_flag(menvcfg::LPE, value),
+            SHADOW_STACK => set_menvcfg_flag(menvcfg::SSE, value),
+            DOUBLE_TRAP => set_menvcfg_flag(menvcfg::DTE, value),
+            PTE_AD_HW_UPDATING => set_menvcfg_flag(menvcfg::ADUE, value),
+            POINTER_MASKING_PMLEN => set_pointer_masking(value),
+            _ => SbiRet::denied(),
+        };
+        if ret.is_ok() && flags.contains(SetFlags::LOCK) {
+            hart_context_mut(hart_id).fwft_state.lock(feature);
+        }
+        ret
+    }
+
+    fn get(&self, feature: u32) -> SbiRet {
+        let feature = feature as usize;
+        if let Err(error) = feature_supported(current_hartid(), feature) {
+            return error;
+        }
+
+        match feature {
+            MISALIGNED_EXC_DELEG => {
+                let medeleg = medeleg::read();
+                let delegated = medeleg.load_misaligned() && medeleg.store_misaligned();
+                SbiRet::success(delegated as usize)
+            }
+            LANDING_PAD => get_menvcfg_flag(menvcfg::LPE),
+            SHADOW_STACK => get_menvcfg_flag(menvcfg::SSE),
+            DOUBLE_TRAP => get_menvcfg_flag(menvcfg::DTE),
+            PTE_AD_HW_UPDATING => get_menvcfg_flag(menvcfg::ADUE),
+            POINTER_MASKING_PMLEN => match menvcfg::read() & menvcfg::PMM {
+                menvcfg::PMM_PMLEN_7 => SbiRet::success(7),
+                menvcfg::PMM_PMLEN_16 => SbiRet::success(16),
+                _ => SbiRet::success(0),
+            },
+            _ => SbiRet::denied(),
+        }
+    }
+}
+
+/// Checks if the feature is implemented by the prototyper and supported by the hart.
+fn feature_supported(hart_id: usize, feature: usize) -> Result<(), SbiRet> {
+    if feature >= FWFT_FEATURE_MAX {
+        return Err(SbiRet::denied());
+    }
+    // All features but misaligned delegation are controlled through `menvcfg`.
+    if feature != MISALIGNED_EXC_DELEG
+        && hart_privileged_version(hart_id) < PrivilegedVersion::Version1_12
+    {
+        return Err(SbiRet::not_supported());
+    }
+    let extension = match feature {
+        LANDING_PAD => Extension::Zicfilp,
+        SHADOW_STACK => Extension::Zicfiss,
+        DOUBLE_TRAP => Extension::Ssdbltrp,
+        PTE_AD_HW_UPDATING => Extension::Svadu,
+        POINTER_MASKING_PMLEN => Extension::Smnpm,
+        _ => return Ok(()),
+    };
+    if hart_extension_probe(hart_id, extension) {
+        Ok(())
+    } else {
+        Err(SbiRet::not_supported())
+    }
+}
+
+/// Delegates misaligned loads and stores, or takes them back for emulation.
+///
+/// Instruction-misaligned exceptions are always delegated, as the firmware has no
+/// handler for them.
+fn set_misaligned_delegation(value: usize) -> SbiRet {
+    match value {
+        0 => unsafe {
+            medeleg::clear_load_misaligned();
+            medeleg::clear_store_misaligned();
+        },
+        1 => unsafe {
+            medeleg::set_load_misaligned();
+            medeleg::set_store_misaligned();
+        },
+        _ => return SbiRet::invalid_param(),
+    }
+    SbiRet::success(0)
+}
+
+fn set_menvcfg_flag(bit: usize, value: usize) -> SbiRet {
+    match value {
+        0 => menvcfg::clear_bits(bit),
+        1 => menvcfg::set_bits(bit),
+        _ => return SbiRet::invalid_param(),
+    }
+    SbiRet::success(0)
+}
+
+#[inline]
+fn get_menvcfg_flag(bit: usize) -> SbiRet {
+    SbiRet::success((menvcfg::read() & bit != 0) as usize)
+}
+
+/// Selects the smallest supported PMLEN which is not less than `value`.
+fn set_pointer_masking(value: usize) -> SbiRet {
+    let pmm = match value {
+        0 => 0,
+        1..=7 => menvcfg::PMM_PMLEN_7,
+        8..=16 => menvcfg::PMM_PMLEN_16,
+        _ => return SbiRet::invalid_param(),
+    };
+    let prev = menvcfg::read() & menvcfg::PMM;
+    menvcfg::clear_bits(menvcfg::PMM);
+    menvcfg::set_bits(pmm);
+    // PMM is WARL; an unsupported mode reads back differently.
+    if menvcfg::read() & menvcfg::PMM != pmm {
+        menvcfg::clear_bits(menvcfg::PMM);
+        menvcfg::set_bits(prev);
+        return SbiRet::not_supported();
+    }
+    SbiRet::success(0)
+}
@@ -8,7 +8,13 @@ use core::sync::atomic::Ordering;
 use fast_trap::FlowContext;
 use riscv::register::mstatus;
 
+use super::dbtr::DbtrState;
+use super::fwft::FwftState;
+use super::mpxy::MpxyState;
+#[cfg(feature = "hypervisor")]
+use super::nacl::NaclState;
 use super::pmu::PmuState;
+use super::sta::StaState;
 
 /// Context for managing hart (hardware thread) state and operations.
 pub(crate) struct HartContext {
@@ -24,6 +30,17 @@ pub(crate) struct HartContext {
     pub features: HartFeatures,
     /// PMU State
     pub pmu_state: PmuState,
+    /// Firmware features lock state.
+    pub fwft_state: FwftState,
+    /// Debug triggers state.
+    pub dbtr_state: DbtrState,
+    /// Message proxy shared memory state.
+    pub mpxy_state: MpxyState,
+    /// Steal-time accounting shared memory state.
+    pub sta_state: StaState,
+    /// Nested acceleration shared memory state.
+    #[cfg(feature = "hypervisor")]
+    pub nacl_state: NaclState,
 }
 
 // Make sure HartContext is aligned.
@@ -40,6 +57,12 @@ impl HartContext {
         self.hsm = HsmCell::new();
         self.rfence = RFenceCell::new();
         self.pmu_state = PmuState::new();
+        self.fwft_state = FwftState::new();
+        self.dbtr_state = DbtrState::new();
+        self.mpxy_state = MpxyState::new();
+        self.sta_state = StaState::new();
+        #[cfg(feature = "hypervisor")]
+        self.nacl_state = NaclState::new();
     }
 
     /// Get a non-null pointer to the trap context.
@@ -55,6 +78,20 @@ impl HartContext {
         self.pmu_state_reset();
     }
 
+    /// Drops SBI state registered by supervisor software, when the hart stops.
+    ///
+    /// Kept across non-retentive suspend, as supervisor software does not register
+    /// it again on resume.
+    #[inline]
+    pub fn sbi_state_reset(&mut self) {
+        self.dbtr_state.reset();
+        self.fwft_state = FwftState::new();
+        self.mpxy_state = MpxyState::new();
+        self.sta_state = StaState::new();
+        #[cfg(feature = "hypervisor")]
+        self.nacl_state = NaclState::new();
+    }
+
     #[inline]
     fn rfence_reset(&mut self) {
         self.rfence = RFenceCell::new();
@@ -1,3 +1,4 @@
+use alloc::boxed::Box;
 use core::{
     cell::UnsafeCell,
     hint::spin_loop,
@@ -9,6 +10,10 @@ use rustsbi::{SbiRet, spec::hsm::hart_state};
 use crate::platform::PLATFORM;
 use crate::riscv::current_hartid;
 use crate::sbi::hart_context::NextStage;
+use crate::sbi::power::{
+    HART_NON_RETENTIVE_PLATFORM, HART_RETENTIVE_PLATFORM, MachineCsrs, PowerDevice,
+    with_resume_context,
+};
 use crate::sbi::trap_stack::ROOT_STACK;
 use crate::trap_stack::hart_context_mut;
 
@@ -199,8 +204,28 @@ pub(crate) fn remote_hsm(hart_id: usize) -> Option<RemoteHsmCell<'static, NextSt
     }
 }
 
+/// Checks if all enabled harts other than `hart_id` are stopped or suspended.
+fn is_last_hart(hart_id: usize) -> bool {
+    let hart_enable = unsafe { PLATFORM.info.cpu_enabled.unwrap() };
+    hart_enable
+        .iter()
+        .enumerate()
+        .filter(|&(id, &enabled)| enabled && id != hart_id)
+        .all(|(id, _)| {
+            remote_hsm(id).is_none_or(|remote| {
+                matches!(
+                    remote.get_status(),
+                    hart_state::STOPPED | hart_state::SUSPENDED
+                )
+            })
+        })
+}
+
 /// Implementation of SBI HSM (Hart State Management) extension.
-pub(crate) struct SbiHsm;
+pub(crate) struct SbiHsm {
+    /// Platform controller for low-power states, waits for interrupts if absent.
+    pub power_dev: Option<Box<dyn PowerDevice>>,
+}
 
 impl rustsbi::Hsm for SbiHsm {
     /// Starts execution on a stopped hart.
@@ -210,6 +235,16 @@ impl rustsbi::Hsm for SbiHsm {
         if !enabled {
             return SbiRet::invalid_param();
         }
+        // Harts can only be started from their own isolation domain.
+        let same_domain = unsafe {
+            PLATFORM
+                .hart_domain(hartid)
+                .map(|domain| domain.contains_hart(current_hartid()))
+                .unwrap_or_else(|| PLATFORM.hart_domain(current_hartid()).is_none())
+        };
+        if !same_domain {
+            return SbiRet::invalid_param();
+        }
 
         match remote_hsm(hartid) {
             Some(remote) => {
@@ -233,6 +268,7 @@ impl rustsbi::Hsm for SbiHsm {
     /// Stops execution on the current hart.
     #[inline]
     fn hart_stop(&self) -> SbiRet {
+        hart_context_mut(current_hartid()).sbi_state_reset();
         local_hsm().stop();
         unsafe {
             riscv::register::mie::clear_msoft();
@@ -260,40 +296,111 @@ impl rustsbi::Hsm for SbiHsm {
     fn hart_suspend(&self, suspend_type: u32, resume_addr: usize, opaque: usize) -> SbiRet {
         use rustsbi::spec::hsm::suspend_type::{NON_RETENTIVE, RETENTIVE};
 
-        if !matches!(suspend_type, NON_RETENTIVE | RETENTIVE) {
-            return SbiRet::invalid_param();
+        let retentive = match suspend_type {
+            RETENTIVE => true,
+            NON_RETENTIVE => false,
+            t if HART_RETENTIVE_PLATFORM.contains(&t) => true,
+            t if HART_NON_RETENTIVE_PLATFORM.contains(&t) => false,
+            _ => return SbiRet::invalid_param(),
+        };
+        if !matches!(suspend_type, NON_RETENTIVE | RETENTIVE)
+            && !self
+                .power_dev
+                .as_ref()
+                .is_some_and(|dev| dev.hart_suspend_supported(suspend_type))
+        {
+            return SbiRet::not_supported();
         }
+        if !retentive && !unsafe { PLATFORM.is_supervisor_memory(resume_addr, 1) } {
+            return SbiRet::invalid_address();
+        }
+
+        self.suspend(SuspendState::Hart(suspend_type));
+        if retentive {
+            local_hsm().resume();
+            SbiRet::success(0)
+        } else {
+            self.hart_resume(current_hartid(), resume_addr, opaque)
+        }
+    }
+}
 
+/// Low-power state requested by supervisor software.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum SuspendState {
+    /// HSM hart suspend of the given suspend type.
+    Hart(u32),
+    /// SUSP system suspend of the given sleep type.
+    System(u32),
+}
+
+impl SuspendState {
+    /// Checks if M-mode state is retained in this low-power state.
+    #[inline]
+    fn is_retentive(self) -> bool {
+        match self {
+            SuspendState::Hart(suspend_type) => {
+                suspend_type & rustsbi::spec::hsm::suspend_type::NON_RETENTIVE == 0
+            }
+            SuspendState::System(_) => false,
+        }
+    }
+}
+
+impl SbiHsm {
+    /// Creates the HSM extension without a platform power controller.
+    pub fn new() -> Self {
+        Self { power_dev: None }
+    }
+
+    /// Checks if a platform-specific SUSP sleep type is implemented.
+    pub(crate) fn system_suspend_supported(&self, sleep_type: u32) -> bool {
+        self.power_dev
+            .as_ref()
+            .is_some_and(|dev| dev.system_suspend_supported(sleep_type))
+    }
+
+    /// Enters `state` on the current hart and returns once it is woken up by an IPI.
+    ///
+    /// M-mode CSRs and the resume context are saved around non-retentive states, in
+    /// which the power device may power the hart down.
+    pub(crate) fn suspend(&self, state: SuspendState) {
+        let hart_id = current_hartid();
         crate::sbi::trap::handler::msoft_ipi_handler();
         unsafe {
-            PLATFORM
-                .sbi
-                .ipi
-                .as_ref()
-                .unwrap()
-                .clear_msip(current_hartid());
+            PLATFORM.sbi.ipi.as_ref().unwrap().clear_msip(hart_id);
         }
         unsafe {
             riscv::register::mie::set_msoft();
         }
         local_hsm().suspend();
-        riscv::asm::wfi();
-        crate::sbi::trap::handler::msoft_ipi_handler();
 
-        match suspend_type {
-            RETENTIVE => {
-                local_hsm().resume();
-                return SbiRet::success(0);
+        match &self.power_dev {
+            Some(power_dev) => {
+                let enter = || match state {
+                    SuspendState::Hart(suspend_type) => {
+                        power_dev.hart_suspend(hart_id, suspend_type, is_last_hart(hart_id))
+                    }
+                    SuspendState::System(sleep_type) => {
+                        power_dev.system_suspend(hart_id, sleep_type)
+                    }
+                };
+                if state.is_retentive() {
+                    enter();
+                } else {
+                    // The hart may be powered down and restart from `_start`.
+                    let saved = MachineCsrs::save(hart_id);
+                    with_resume_context(hart_id, enter);
+                    saved.restore();
+                }
             }
-            NON_RETENTIVE => return self.hart_resume(current_hartid(), resume_addr, opaque),
-            _ => return SbiRet::invalid_param(),
+            None => riscv::asm::wfi(),
         }
+        crate::sbi::trap::handler::msoft_ipi_handler();
     }
-}
 
-impl SbiHsm {
     // non retentive resume
-    fn hart_resume(&self, hartid: usize, resume_addr: usize, opaque: usize) -> SbiRet {
+    pub(crate) fn hart_resume(&self, hartid: usize, resume_addr: usize, opaque: usize) -> SbiRet {
         match remote_hsm(hartid) {
             Some(remote) => {
                 if remote.resume(NextStage {
@@ -34,6 +34,14 @@ pub trait IpiDevice {
     fn set_msip(&self, hart_idx: usize);
     /// Clear machine software interrupt pending bit for given hart.
     fn clear_msip(&self, hart_idx: usize);
+    /// Prepare machine software interrupt delivery on the current hart.
+    fn hart_init(&self) {}
+    /// Claim a pending machine external interrupt on the current hart.
+    ///
+    /// Returns `true` if it was an inter-processor interrupt.
+    fn claim_external_ipi(&self) -> bool {
+        false
+    }
 }
 
 /// SBI IPI implementation.
@@ -215,6 +223,12 @@ impl SbiIpi {
         self.ipi_dev.lock().clear_msip(hart_idx);
     }
 
+    /// Read machine timer compare value for hart.
+    #[inline]
+    pub fn read_mtimecmp(&self, hart_idx: usize) -> u64 {
+        self.ipi_dev.lock().read_mtimecmp(hart_idx)
+    }
+
     /// Write machine timer compare value for hart.
     #[inline]
     pub fn write_mtimecmp(&self, hart_idx: usize, val: u64) {
@@ -251,6 +265,25 @@ pub fn clear_msip() {
     }
 }
 
+/// Prepare inter-processor interrupt delivery for current hart.
+#[inline]
+pub fn hart_init() {
+    if let Some(ipi) = unsafe { PLATFORM.sbi.ipi.as_ref() } {
+        ipi.ipi_dev.lock().hart_init();
+    }
+}
+
+/// Claim a pending machine external interrupt for current hart.
+///
+/// Returns `true` if it was an inter-processor interrupt.
+#[inline]
+pub fn claim_external_ipi() -> bool {
+    match unsafe { PLATFORM.sbi.ipi.as_ref() } {
+        Some(ipi) => ipi.ipi_dev.lock().claim_external_ipi(),
+        None => false,
+    }
+}
+
 /// Clear machine timer interrupt for current hart.
 #[inline]
 pub fn clear_mtime() {
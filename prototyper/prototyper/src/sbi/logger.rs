@@ -7,8 +7,13 @@ pub struct Logger;
 impl Logger {
     /// Initialize the logger with log level from RUST_LOG env var or default to Info.
     pub fn init() -> Result<(), log::SetLoggerError> {
-        // Set max log level from LOG_LEVEL from config file, otherwise use Info
-        let max_level = LevelFilter::from_str(crate::cfg::LOG_LEVEL).unwrap_or(LevelFilter::Info);
+        // Set max log level from firmware options, then from LOG_LEVEL from config file,
+        // otherwise use Info
+        let max_level = crate::firmware::boot_options()
+            .log_level
+            .unwrap_or_else(|| {
+                LevelFilter::from_str(crate::cfg::LOG_LEVEL).unwrap_or(LevelFilter::Info)
+            });
 
         log::set_max_level(max_level);
         log::set_logger(&Logger)
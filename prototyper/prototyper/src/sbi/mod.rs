@@ -1,13 +1,24 @@
-use rustsbi::RustSBI;
+use rustsbi::{RustSBI, SbiRet};
+
+use crate::platform::PLATFORM;
 
 pub mod console;
+pub mod cppc;
+pub mod dbtr;
+pub mod fwft;
 pub mod hsm;
 pub mod ipi;
+pub mod mpxy;
+#[cfg(feature = "hypervisor")]
+pub mod nacl;
 pub mod pmu;
+pub mod power;
 pub mod reset;
 pub mod rfence;
+pub mod sta;
 pub mod suspend;
 
+pub mod crash;
 pub mod early_trap;
 pub mod features;
 pub mod fifo;
@@ -18,11 +29,18 @@ pub mod trap;
 pub mod trap_stack;
 
 use console::SbiConsole;
+use cppc::SbiCppc;
+use dbtr::SbiDbtr;
+use fwft::SbiFwft;
 use hsm::SbiHsm;
 use ipi::SbiIpi;
+use mpxy::SbiMpxy;
+#[cfg(feature = "hypervisor")]
+use nacl::SbiNacl;
 use pmu::SbiPmu;
 use reset::SbiReset;
 use rfence::SbiRFence;
+use sta::SbiSta;
 use suspend::SbiSuspend;
 
 #[derive(RustSBI, Default)]
@@ -43,6 +61,19 @@ pub struct SBI {
     pub pmu: Option<SbiPmu>,
     #[rustsbi(susp)]
     pub susp: Option<SbiSuspend>,
+    #[rustsbi(fwft)]
+    pub fwft: Option<SbiFwft>,
+    #[rustsbi(dbtr)]
+    pub dbtr: Option<SbiDbtr>,
+    #[rustsbi(mpxy)]
+    pub mpxy: Option<SbiMpxy>,
+    #[rustsbi(cppc)]
+    pub cppc: Option<SbiCppc>,
+    #[rustsbi(sta)]
+    pub sta: Option<SbiSta>,
+    #[cfg(feature = "hypervisor")]
+    #[rustsbi(nacl)]
+    pub nacl: Option<SbiNacl>,
 }
 
 impl SBI {
@@ -55,6 +86,37 @@ impl SBI {
             rfence: None,
             pmu: None,
             susp: None,
+            fwft: None,
+            dbtr: None,
+            mpxy: None,
+            cppc: None,
+            sta: None,
+            #[cfg(feature = "hypervisor")]
+            nacl: None,
         }
     }
 }
+
+/// Checks shared memory of `size` bytes given to a `set_shmem` call of an extension.
+///
+/// Returns `None` if both address halves are all ones, which disables the shared memory.
+/// A physical address not aligned to `align` is an invalid parameter, and one outside
+/// supervisor memory an invalid address.
+pub fn supervisor_shmem(
+    phys_addr_lo: usize,
+    phys_addr_hi: usize,
+    size: usize,
+    align: usize,
+) -> Result<Option<usize>, SbiRet> {
+    if phys_addr_lo == usize::MAX && phys_addr_hi == usize::MAX {
+        return Ok(None);
+    }
+    if !phys_addr_lo.is_multiple_of(align) {
+        return Err(SbiRet::invalid_param());
+    }
+    // RustSBI Prototyper only supports RV64, which has no high address bits.
+    if phys_addr_hi != 0 || !unsafe { PLATFORM.is_supervisor_memory(phys_addr_lo, size) } {
+        return Err(SbiRet::invalid_address());
+    }
+    Ok(Some(phys_addr_lo))
+}
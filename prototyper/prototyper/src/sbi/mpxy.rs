@@ -0,0 +1,529 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use rustsbi::{Mpxy, SbiRet, SharedPtr};
+use spin::Mutex;
+
+use crate::firmware::map_supervisor_memory;
+use crate::riscv::current_hartid;
+use crate::sbi::supervisor_shmem;
+use crate::sbi::trap_stack::{hart_context, hart_context_mut};
+
+/// Size of the per-hart shared memory for sending and receiving messages.
+pub const MPXY_SHMEM_SIZE: usize = 4096;
+/// Required alignment of the per-hart shared memory.
+const MPXY_SHMEM_ALIGN: usize = 4096;
+
+/// Standard message channel attribute IDs.
+pub mod attribute {
+    pub const MSG_PROT_ID: u32 = 0x0;
+    pub const MSG_PROT_VERSION: u32 = 0x1;
+    pub const MSG_DATA_MAX_LEN: u32 = 0x2;
+    pub const MSG_SEND_TIMEOUT: u32 = 0x3;
+    pub const MSG_COMPLETION_TIMEOUT: u32 = 0x4;
+    pub const CHANNEL_CAPABILITY: u32 = 0x5;
+    pub const SSE_EVENT_ID: u32 = 0x6;
+    pub const MSI_CONTROL: u32 = 0x7;
+    pub const MSI_ADDR_LO: u32 = 0x8;
+    pub const MSI_ADDR_HI: u32 = 0x9;
+    pub const MSI_DATA: u32 = 0xa;
+    pub const EVENTS_STATE_CONTROL: u32 = 0xb;
+    /// Number of standard attributes.
+    pub const STD_MAX: u32 = 0xc;
+    /// First message protocol specific attribute ID.
+    pub const MSG_PROT_START: u32 = 0x8000_0000;
+}
+
+/// Bits of the `CHANNEL_CAPABILITY` attribute.
+#[allow(unused)]
+pub mod capability {
+    pub const MSI: u32 = 0x1 << 0;
+    pub const SSE: u32 = 0x1 << 1;
+    pub const EVENTS_STATE: u32 = 0x1 << 2;
+    pub const SEND_WITH_RESPONSE: u32 = 0x1 << 3;
+    pub const SEND_WITHOUT_RESPONSE: u32 = 0x1 << 4;
+    pub const GET_NOTIFICATIONS: u32 = 0x1 << 5;
+}
+
+/// Read-only attributes describing a message channel.
+#[derive(Clone, Copy, Debug)]
+pub struct ChannelAttributes {
+    /// Message protocol ID, e.g. `0x0` for RPMI.
+    pub protocol_id: u32,
+    /// Message protocol version.
+    pub protocol_version: u32,
+    /// Maximum message data length in bytes, clamped to [`MPXY_SHMEM_SIZE`] on registration.
+    pub msg_data_max_len: u32,
+    /// Message send timeout in microseconds.
+    pub msg_send_timeout: u32,
+    /// Message completion timeout in microseconds.
+    pub msg_completion_timeout: u32,
+    /// Channel capability bits, see [`capability`].
+    pub capability: u32,
+}
+
+/// Notification events state returned by a channel.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NotificationEvents {
+    /// Number of events still pending in the channel.
+    pub remaining: u32,
+    /// Number of events written into the events buffer.
+    pub returned: u32,
+    /// Number of events lost by the channel.
+    pub lost: u32,
+    /// Number of bytes written into the events buffer.
+    pub len: usize,
+}
+
+/// A trait that must be implemented by message channels registered to the MPXY extension.
+///
+/// Buffers passed to the channel are the calling hart's shared memory, they are valid
+/// only for the duration of each call.
+pub trait MpxyChannel {
+    /// Returns the read-only attributes of this channel.
+    fn attributes(&self) -> ChannelAttributes;
+
+    /// Reads a message protocol specific attribute.
+    fn read_protocol_attribute(&self, attribute_id: u32) -> Result<u32, SbiRet> {
+        let _ = attribute_id;
+        Err(SbiRet::bad_range())
+    }
+
+    /// Checks whether a message protocol specific attribute accepts `value`.
+    ///
+    /// Called for every attribute of a write request before any of them is written.
+    fn check_protocol_attribute(&self, attribute_id: u32, value: u32) -> Result<(), SbiRet> {
+        let _ = (attribute_id, value);
+        Err(SbiRet::bad_range())
+    }
+
+    /// Writes a message protocol specific attribute.
+    fn write_protocol_attribute(&self, attribute_id: u32, value: u32) -> Result<(), SbiRet> {
+        let _ = (attribute_id, value);
+        Err(SbiRet::bad_range())
+    }
+
+    /// Sends the first `len` bytes of `data` as a message and waits for its response.
+    ///
+    /// The response is written back into `data` from offset `0`.
+    ///
+    /// # Returns
+    /// The length of response data in bytes.
+    fn send_with_response(
+        &self,
+        message_id: u32,
+        data: &mut [u8],
+        len: usize,
+    ) -> Result<usize, SbiRet> {
+        let _ = (message_id, data, len);
+        Err(SbiRet::not_supported())
+    }
+
+    /// Sends `data` as a message without waiting for a response.
+    fn send_without_response(&self, message_id: u32, data: &[u8]) -> Result<(), SbiRet> {
+        let _ = (message_id, data);
+        Err(SbiRet::not_supported())
+    }
+
+    /// Writes pending notification events into `events`.
+    fn get_notification_events(&self, events: &mut [u8]) -> Result<NotificationEvents, SbiRet> {
+        let _ = events;
+        Err(SbiRet::not_supported())
+    }
+}
+
+/// Writable standard attributes of a message channel.
+#[derive(Default)]
+struct ChannelConfig {
+    msi_control: u32,
+    msi_addr_lo: u32,
+    msi_addr_hi: u32,
+    msi_data: u32,
+    events_state_control: u32,
+}
+
+struct ChannelEntry {
+    id: u32,
+    attributes: ChannelAttributes,
+    config: Mutex<ChannelConfig>,
+    channel: Mutex<Box<dyn MpxyChannel>>,
+}
+
+/// Per-hart message proxy state.
+pub struct MpxyState {
+    /// Physical address of shared memory, `None` when disabled.
+    shmem: Option<usize>,
+}
+
+impl MpxyState {
+    /// Creates a new state with shared memory disabled.
+    pub const fn new() -> Self {
+        Self { shmem: None }
+    }
+}
+
+/// Implementation of SBI Message Proxy extension on top of registered message channels.
+pub struct SbiMpxy {
+    channels: Vec<ChannelEntry>,
+}
+
+impl SbiMpxy {
+    /// Creates a message proxy without any channel.
+    pub const fn new() -> Self {
+        Self {
+            channels: Vec::new(),
+        }
+    }
+
+    /// Registers a message channel and returns its channel ID.
+    ///
+    /// Message data length of the channel is limited to [`MPXY_SHMEM_SIZE`].
+    pub fn register(&mut self, channel: Box<dyn MpxyChannel>) -> u32 {
+        let id = self.channels.len() as u32;
+        let mut attributes = channel.attributes();
+        if attributes.msg_data_max_len as usize > MPXY_SHMEM_SIZE {
+            warn!(
+                "MPXY channel {} message data length {} clamped to {}",
+                id, attributes.msg_data_max_len, MPXY_SHMEM_SIZE
+            );
+            attributes.msg_data_max_len = MPXY_SHMEM_SIZE as u32;
+        }
+        self.channels.push(ChannelEntry {
+            id,
+            attributes,
+            config: Mutex::new(ChannelConfig::default()),
+            channel: Mutex::new(channel),
+        });
+        id
+    }
+
+    fn find_channel(&self, channel_id: u32) -> Result<&ChannelEntry, SbiRet> {
+        self.channels
+            .iter()
+            .find(|entry| entry.id == channel_id)
+            .ok_or(SbiRet::not_supported())
+    }
+}
+
+impl Mpxy for SbiMpxy {
+    #[inline]
+    fn get_shmem_size(&self) -> SbiRet {
+        SbiRet::success(MPXY_SHMEM_SIZE)
+    }
+
+    fn set_shmem(&self, shmem: SharedPtr<u8>, flags: usize) -> SbiRet {
+        const OVERWRITE: usize = 0b00;
+        const OVERWRITE_RETURN: usize = 0b01;
+
+        let mode = flags & 0b11;
+        if flags & !0b11 != 0 || !matches!(mode, OVERWRITE | OVERWRITE_RETURN) {
+            return SbiRet::invalid_param();
+        }
+        let hart_id = current_hartid();
+        let old_shmem = hart_context(hart_id).mpxy_state.shmem;
+        let new_shmem = match supervisor_shmem(
+            shmem.phys_addr_lo(),
+            shmem.phys_addr_hi(),
+            MPXY_SHMEM_SIZE,
+            MPXY_SHMEM_ALIGN,
+        ) {
+            Ok(new_shmem) => new_shmem,
+            Err(error) => return error,
+        };
+
+        hart_context_mut(hart_id).mpxy_state.shmem = new_shmem;
+        if let (Some(new_shmem), OVERWRITE_RETURN) = (new_shmem, mode) {
+            let (lo, hi) = match old_shmem {
+                Some(old_shmem) => (old_shmem, 0),
+                None => (usize::MAX, usize::MAX),
+            };
+            let _mapping = map_supervisor_memory(new_shmem, MPXY_SHMEM_SIZE);
+            let ptr = new_shmem as *mut usize;
+            unsafe {
+                ptr.write_volatile(lo);
+                ptr.add(1).write_volatile(hi);
+            }
+        }
+        SbiRet::success(0)
+    }
+
+    fn get_channel_ids(&self, start_index: u32) -> SbiRet {
+        const HEADER_SIZE: usize = 8;
+
+        let shmem = match current_shmem() {
+            Ok(shmem) => shmem,
+            Err(error) => return error,
+        };
+        let _mapping = map_supervisor_memory(shmem, MPXY_SHMEM_SIZE);
+        let start_index = start_index as usize;
+        if start_index > self.channels.len() {
+            return SbiRet::invalid_param();
+        }
+        let max_returned = (MPXY_SHMEM_SIZE - HEADER_SIZE) / size_of::<u32>();
+        let ids = &self.channels[start_index..];
+        let returned = ids.len().min(max_returned);
+        let remaining = ids.len() - returned;
+
+        write_u32(shmem, 0, remaining as u32);
+        write_u32(shmem, 4, returned as u32);
+        for (i, entry) in ids[..returned].iter().enumerate() {
+            write_u32(shmem, HEADER_SIZE + i * 4, entry.id);
+        }
+        SbiRet::success(0)
+    }
+
+    fn read_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet {
+        let (shmem, entry) = match self.prepare(channel_id) {
+            Ok(prepared) => prepared,
+            Err(error) => return error,
+        };
+        let _mapping = map_supervisor_memory(shmem, MPXY_SHMEM_SIZE);
+        let ids = match attribute_range(base_attribute_id, attribute_count) {
+            Ok(ids) => ids,
+            Err(error) => return error,
+        };
+
+        for (i, attribute_id) in ids.enumerate() {
+            let value = match entry.read_attribute(attribute_id) {
+                Ok(value) => value,
+                Err(error) => return error,
+            };
+            write_u32(shmem, i * 4, value);
+        }
+        SbiRet::success(0)
+    }
+
+    fn write_attributes(
+        &self,
+        channel_id: u32,
+        base_attribute_id: u32,
+        attribute_count: u32,
+    ) -> SbiRet {
+        let (shmem, entry) = match self.prepare(channel_id) {
+            Ok(prepared) => prepared,
+            Err(error) => return error,
+        };
+        let _mapping = map_supervisor_memory(shmem, MPXY_SHMEM_SIZE);
+        let ids = match attribute_range(base_attribute_id, attribute_count) {
+            Ok(ids) => ids,
+            Err(error) => return error,
+        };
+
+        // Snapshot and check every attribute before changing any of them, so that a
+        // rejected request leaves the channel untouched.
+        let values: Vec<u32> = (0..ids.len()).map(|i| read_u32(shmem, i * 4)).collect();
+        for (attribute_id, &value) in ids.clone().zip(&values) {
+            if let Err(error) = entry.check_write(attribute_id, value) {
+                return error;
+            }
+        }
+        for (attribute_id, &value) in ids.zip(&values) {
+            if let Err(error) = entry.write_attribute(attribute_id, value) {
+                return error;
+            }
+        }
+        SbiRet::success(0)
+    }
+
+    fn send_message_with_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet {
+        let (shmem, entry) = match self.prepare(channel_id) {
+            Ok(prepared) => prepared,
+            Err(error) => return error,
+        };
+        let _mapping = map_supervisor_memory(shmem, MPXY_SHMEM_SIZE);
+        if let Err(error) = entry.check_send(capability::SEND_WITH_RESPONSE, message_data_len) {
+            return error;
+        }
+        let data = unsafe { shmem_slice(shmem) };
+        match entry
+            .channel
+            .lock()
+            .send_with_response(message_id, data, message_data_len)
+        {
+            Ok(len) => SbiRet::success(len),
+            Err(error) => error,
+        }
+    }
+
+    fn send_message_without_response(
+        &self,
+        channel_id: u32,
+        message_id: u32,
+        message_data_len: usize,
+    ) -> SbiRet {
+        let (shmem, entry) = match self.prepare(channel_id) {
+            Ok(prepared) => prepared,
+            Err(error) => return error,
+        };
+        let _mapping = map_supervisor_memory(shmem, MPXY_SHMEM_SIZE);
+        if let Err(error) = entry.check_send(capability::SEND_WITHOUT_RESPONSE, message_data_len) {
+            return error;
+        }
+        let data = unsafe { &shmem_slice(shmem)[..message_data_len] };
+        match entry.channel.lock().send_without_response(message_id, data) {
+            Ok(()) => SbiRet::success(0),
+            Err(error) => error,
+        }
+    }
+
+    fn get_notification_events(&self, channel_id: u32) -> SbiRet {
+        const EVENTS_OFFSET: usize = 0x10;
+
+        let (shmem, entry) = match self.prepare(channel_id) {
+            Ok(prepared) => prepared,
+            Err(error) => return error,
+        };
+        let _mapping = map_supervisor_memory(shmem, MPXY_SHMEM_SIZE);
+        if entry.attributes.capability & capability::GET_NOTIFICATIONS == 0 {
+            return SbiRet::not_supported();
+        }
+        let events = unsafe { &mut shmem_slice(shmem)[EVENTS_OFFSET..] };
+        let state = match entry.channel.lock().get_notification_events(events) {
+            Ok(state) => state,
+            Err(error) => return error,
+        };
+        if entry.config.lock().events_state_control != 0 {
+            write_u32(shmem, 0x0, state.remaining);
+            write_u32(shmem, 0x4, state.returned);
+            write_u32(shmem, 0x8, state.lost);
+            write_u32(shmem, 0xc, 0);
+        }
+        SbiRet::success(state.len)
+    }
+}
+
+impl SbiMpxy {
+    /// Looks up the shared memory of the calling hart and the requested channel.
+    fn prepare(&self, channel_id: u32) -> Result<(usize, &ChannelEntry), SbiRet> {
+        let shmem = current_shmem()?;
+        let entry = self.find_channel(channel_id)?;
+        Ok((shmem, entry))
+    }
+}
+
+impl ChannelEntry {
+    fn read_attribute(&self, attribute_id: u32) -> Result<u32, SbiRet> {
+        use attribute::*;
+        let config = self.config.lock();
+        Ok(match attribute_id {
+            MSG_PROT_ID => self.attributes.protocol_id,
+            MSG_PROT_VERSION => self.attributes.protocol_version,
+            MSG_DATA_MAX_LEN => self.attributes.msg_data_max_len,
+            MSG_SEND_TIMEOUT => self.attributes.msg_send_timeout,
+            MSG_COMPLETION_TIMEOUT => self.attributes.msg_completion_timeout,
+            CHANNEL_CAPABILITY => self.attributes.capability,
+            // No SSE event is used for notifications.
+            SSE_EVENT_ID => 0,
+            MSI_CONTROL => config.msi_control,
+            MSI_ADDR_LO => config.msi_addr_lo,
+            MSI_ADDR_HI => config.msi_addr_hi,
+            MSI_DATA => config.msi_data,
+            EVENTS_STATE_CONTROL => config.events_state_control,
+            _ => return self.channel.lock().read_protocol_attribute(attribute_id),
+        })
+    }
+
+    fn check_write(&self, attribute_id: u32, value: u32) -> Result<(), SbiRet> {
+        use attribute::*;
+        let (required, control) = match attribute_id {
+            MSI_CONTROL => (capability::MSI, true),
+            EVENTS_STATE_CONTROL => (capability::EVENTS_STATE, true),
+            MSI_ADDR_LO | MSI_ADDR_HI | MSI_DATA => (capability::MSI, false),
+            id if id >= MSG_PROT_START => {
+                return self.channel.lock().check_protocol_attribute(id, value);
+            }
+            _ => return Err(SbiRet::bad_range()),
+        };
+        if control && value > 1 {
+            return Err(SbiRet::invalid_param());
+        }
+        if value != 0 && self.attributes.capability & required == 0 {
+            return Err(SbiRet::denied());
+        }
+        Ok(())
+    }
+
+    fn write_attribute(&self, attribute_id: u32, value: u32) -> Result<(), SbiRet> {
+        use attribute::*;
+        let mut config = self.config.lock();
+        match attribute_id {
+            MSI_CONTROL => config.msi_control = value,
+            MSI_ADDR_LO => config.msi_addr_lo = value,
+            MSI_ADDR_HI => config.msi_addr_hi = value,
+            MSI_DATA => config.msi_data = value,
+            EVENTS_STATE_CONTROL => config.events_state_control = value,
+            _ => {
+                drop(config);
+                return self
+                    .channel
+                    .lock()
+                    .write_protocol_attribute(attribute_id, value);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_send(&self, required: u32, message_data_len: usize) -> Result<(), SbiRet> {
+        if self.attributes.capability & required == 0 {
+            return Err(SbiRet::not_supported());
+        }
+        if message_data_len > self.attributes.msg_data_max_len as usize {
+            return Err(SbiRet::invalid_param());
+        }
+        Ok(())
+    }
+}
+
+/// Checks an attribute range and returns the attribute IDs in it.
+///
+/// A range must lie entirely in either the standard or the message protocol specific attributes.
+fn attribute_range(base: u32, count: u32) -> Result<core::ops::Range<u32>, SbiRet> {
+    if count == 0 || count as usize > MPXY_SHMEM_SIZE / size_of::<u32>() {
+        return Err(SbiRet::invalid_param());
+    }
+    let end = base.checked_add(count).ok_or(SbiRet::invalid_param())?;
+    if base < attribute::MSG_PROT_START {
+        if base >= attribute::STD_MAX {
+            return Err(SbiRet::invalid_param());
+        }
+        if end > attribute::STD_MAX {
+            return Err(SbiRet::bad_range());
+        }
+    }
+    Ok(base..end)
+}
+
+#[inline]
+fn current_shmem() -> Result<usize, SbiRet> {
+    hart_context(current_hartid())
+        .mpxy_state
+        .shmem
+        .ok_or(SbiRet::no_shmem())
+}
+
+/// # Safety
+///
+/// `shmem` must be the shared memory address set by the calling hart.
+#[inline]
+unsafe fn shmem_slice(shmem: usize) -> &'static mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut(shmem as *mut u8, MPXY_SHMEM_SIZE) }
+}
+
+#[inline]
+fn read_u32(shmem: usize, offset: usize) -> u32 {
+    u32::from_le(unsafe { ((shmem + offset) as *const u32).read_volatile() })
+}
+
+#[inline]
+fn write_u32(shmem: usize, offset: usize, value: u32) {
+    unsafe { ((shmem + offset) as *mut u32).write_volatile(value.to_le()) }
+}
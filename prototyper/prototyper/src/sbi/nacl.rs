@@ -0,0 +1,356 @@
+use core::arch::asm;
+use riscv::register::{mepc, mstatus, sepc, sstatus};
+use rustsbi::{SbiRet, SharedPtr};
+use sbi_spec::nacl::feature_id;
+use sbi_spec::nacl::shmem_size::NATIVE;
+
+use crate::firmware::map_supervisor_memory;
+use crate::riscv::current_hartid;
+use crate::sbi::supervisor_shmem;
+use crate::sbi::trap_stack::{hart_context, hart_context_mut};
+
+/// Alignment of the nested acceleration shared memory.
+const NACL_SHMEM_ALIGN: usize = 4096;
+
+/// Offsets of the nested acceleration shared memory.
+mod offset {
+    /// Nested SRET context, holding `x1` to `x31` at their register index.
+    pub const SRET: usize = 0x0000;
+    /// Nested HFENCE entries.
+    pub const HFENCE: usize = 0x0800;
+    /// Nested CSR dirty bitmap, one bit per CSR space entry.
+    pub const DIRTY_BITMAP: usize = 0x0f80;
+    /// CSR space, one XLEN-wide entry per CSR.
+    pub const CSR: usize = 0x1000;
+}
+
+/// Nested HFENCE entry layout and configuration word fields.
+mod hfence {
+    /// Size of one entry: configuration, page number, reserved and page count.
+    pub const ENTRY_SIZE: usize = 4 * size_of::<usize>();
+    /// Number of entries in the scratch space.
+    pub const ENTRY_MAX: usize = 3840 / usize::BITS as usize;
+    /// Offset of the page number in an entry.
+    pub const PNUM: usize = size_of::<usize>();
+    /// Offset of the page count in an entry.
+    pub const PCOUNT: usize = 3 * size_of::<usize>();
+
+    pub const CONFIG_PEND: usize = 1 << (usize::BITS - 1);
+    pub const CONFIG_TYPE_SHIFT: u32 = usize::BITS - 8;
+    pub const CONFIG_ORDER_SHIFT: u32 = CONFIG_TYPE_SHIFT - 8;
+    pub const CONFIG_VMID_SHIFT: u32 = 16;
+    /// Page orders are encoded relative to 4 KiB pages.
+    pub const ORDER_BASE: usize = 12;
+
+    pub const TYPE_GVMA: usize = 0x0;
+    pub const TYPE_GVMA_ALL: usize = 0x1;
+    pub const TYPE_GVMA_VMID: usize = 0x2;
+    pub const TYPE_GVMA_VMID_ALL: usize = 0x3;
+    pub const TYPE_VVMA: usize = 0x4;
+    pub const TYPE_VVMA_ALL: usize = 0x5;
+    pub const TYPE_VVMA_ASID: usize = 0x6;
+    pub const TYPE_VVMA_ASID_ALL: usize = 0x7;
+}
+
+/// Number of pages above which an HFENCE entry flushes its whole address space.
+const HFENCE_PAGE_LIMIT: usize = 64;
+
+const CSR_HSTATUS: u16 = 0x600;
+const CSR_HGATP: u16 = 0x680;
+const HSTATUS_SPV: usize = 1 << 7;
+const HGATP_VMID_SHIFT: u32 = 44;
+const HGATP_VMID_MASK: usize = 0x3fff << HGATP_VMID_SHIFT;
+const MSTATUS_MPV: usize = 1 << 39;
+
+macro_rules! define_h_csrs {
+    (read_write: [$($rw:literal),* $(,)?], read_only: [$($ro:literal),* $(,)?] $(,)?) => {
+        /// Hypervisor and virtual supervisor CSRs synchronized through the CSR space.
+        ///
+        /// `henvcfg` is left out as it only exists on privileged version 1.12 harts.
+        const H_CSRS: &[u16] = &[$($rw,)* $($ro,)*];
+
+        /// Reads hypervisor CSR `csr_num`, or returns `None` if it is not synchronized.
+        #[inline]
+        fn read_h_csr(csr_num: u16) -> Option<usize> {
+            let value: usize;
+            match csr_num {
+                $($rw => unsafe { asm!("csrr {}, {}", out(reg) value, const $rw) },)*
+                $($ro => unsafe { asm!("csrr {}, {}", out(reg) value, const $ro) },)*
+                _ => return None,
+            }
+            Some(value)
+        }
+
+        /// Writes `value` to hypervisor CSR `csr_num`, ignoring read-only CSRs.
+        #[inline]
+        fn write_h_csr(csr_num: u16, value: usize) {
+            match csr_num {
+                $($rw => unsafe { asm!("csrw {}, {}", const $rw, in(reg) value) },)*
+                _ => {}
+            }
+        }
+    };
+}
+
+define_h_csrs! {
+    read_write: [
+        // hstatus, hedeleg, hideleg, hie, htimedelta, hcounteren, hgeie
+        0x600, 0x602, 0x603, 0x604, 0x605, 0x606, 0x607,
+        // htval, hip, hvip, htinst, hgatp
+        0x643, 0x644, 0x645, 0x64a, 0x680,
+        // vsstatus, vsie, vstvec, vsscratch, vsepc, vscause, vstval, vsip, vsatp
+        0x200, 0x204, 0x205, 0x240, 0x241, 0x242, 0x243, 0x244, 0x280,
+    ],
+    // hgeip
+    read_only: [0xe12],
+}
+
+/// Index of CSR `csr_num` in the CSR space and the dirty bitmap.
+#[inline]
+const fn csr_index(csr_num: u16) -> usize {
+    (((csr_num & 0xc00) >> 2) | (csr_num & 0xff)) as usize
+}
+
+/// Nested acceleration state of a hart.
+pub struct NaclState {
+    /// Physical address of the registered shared memory.
+    pub shmem: Option<usize>,
+}
+
+impl NaclState {
+    pub const fn new() -> Self {
+        Self { shmem: None }
+    }
+}
+
+/// Nested acceleration on harts with the hypervisor extension.
+///
+/// CSR and HFENCE requests batched in the shared memory are applied to the
+/// hardware hypervisor CSRs and TLBs of the calling hart.
+pub struct SbiNacl;
+
+impl SbiNacl {
+    #[inline]
+    fn shmem() -> Result<usize, SbiRet> {
+        hart_context(current_hartid())
+            .nacl_state
+            .shmem
+            .ok_or(SbiRet::no_shmem())
+    }
+}
+
+impl rustsbi::Nacl for SbiNacl {
+    fn probe_feature(&self, feature_id: u32) -> SbiRet {
+        let available = matches!(
+            feature_id as usize,
+            feature_id::SYNC_CSR | feature_id::SYNC_HFENCE | feature_id::SYNC_SRET
+        );
+        SbiRet::success(available as usize)
+    }
+
+    fn set_shmem(&self, shmem: SharedPtr<[u8; NATIVE]>, flags: usize) -> SbiRet {
+        if flags != 0 {
+            return SbiRet::invalid_param();
+        }
+        let hart_id = current_hartid();
+        let shmem = match supervisor_shmem(
+            shmem.phys_addr_lo(),
+            shmem.phys_addr_hi(),
+            NATIVE,
+            NACL_SHMEM_ALIGN,
+        ) {
+            Ok(Some(shmem)) => shmem,
+            Ok(None) => {
+                hart_context_mut(hart_id).nacl_state.shmem = None;
+                return SbiRet::success(0);
+            }
+            Err(error) => return error,
+        };
+        let _mapping = map_supervisor_memory(shmem, NATIVE);
+        let ptr = shmem as *mut usize;
+        for i in 0..NATIVE / size_of::<usize>() {
+            unsafe { ptr.add(i).write_volatile(0) };
+        }
+        hart_context_mut(hart_id).nacl_state.shmem = Some(shmem);
+        SbiRet::success(0)
+    }
+
+    fn sync_csr(&self, csr_num: usize) -> SbiRet {
+        let shmem = match Self::shmem() {
+            Ok(shmem) => shmem,
+            Err(error) => return error,
+        };
+        if csr_num == usize::MAX {
+            H_CSRS.iter().for_each(|&csr_num| sync_csr(shmem, csr_num));
+            return SbiRet::success(0);
+        }
+        match u16::try_from(csr_num) {
+            Ok(csr_num) if csr_num & 0x300 == 0x200 && H_CSRS.contains(&csr_num) => {
+                sync_csr(shmem, csr_num);
+                SbiRet::success(0)
+            }
+            _ => SbiRet::invalid_param(),
+        }
+    }
+
+    fn sync_hfence(&self, entry_index: usize) -> SbiRet {
+        let shmem = match Self::shmem() {
+            Ok(shmem) => shmem,
+            Err(error) => return error,
+        };
+        if entry_index == usize::MAX {
+            (0..hfence::ENTRY_MAX).for_each(|index| sync_hfence(shmem, index));
+        } else if entry_index < hfence::ENTRY_MAX {
+            sync_hfence(shmem, entry_index);
+        } else {
+            return SbiRet::invalid_param();
+        }
+        SbiRet::success(0)
+    }
+
+    fn sync_sret(&self) -> SbiRet {
+        let shmem = match Self::shmem() {
+            Ok(shmem) => shmem,
+            Err(error) => return error,
+        };
+        H_CSRS.iter().for_each(|&csr_num| sync_csr(shmem, csr_num));
+        (0..hfence::ENTRY_MAX).for_each(|index| sync_hfence(shmem, index));
+        // The trap handler restores the SRET context and emulates SRET on success.
+        SbiRet::success(0)
+    }
+}
+
+/// Writes CSR `csr_num` back to hardware if it is dirty, then refreshes its CSR space entry.
+fn sync_csr(shmem: usize, csr_num: u16) {
+    let index = csr_index(csr_num);
+    let _mapping = map_supervisor_memory(shmem, NATIVE);
+    let entry = (shmem + offset::CSR) as *mut usize;
+    let bitmap = (shmem + offset::DIRTY_BITMAP) as *mut u8;
+    unsafe {
+        let dirty = bitmap.add(index / 8).read_volatile();
+        if dirty & (1 << (index % 8)) != 0 {
+            bitmap
+                .add(index / 8)
+                .write_volatile(dirty & !(1 << (index % 8)));
+            write_h_csr(csr_num, entry.add(index).read_volatile());
+        }
+        // WARL fields and hardware updated bits are reflected back to supervisor software.
+        if let Some(value) = read_h_csr(csr_num) {
+            entry.add(index).write_volatile(value);
+        }
+    }
+}
+
+/// Executes HFENCE entry `index` if it is pending, then clears its pending bit.
+fn sync_hfence(shmem: usize, index: usize) {
+    let _mapping = map_supervisor_memory(shmem, NATIVE);
+    let entry = shmem + offset::HFENCE + index * hfence::ENTRY_SIZE;
+    let config_ptr = entry as *mut usize;
+    let config = unsafe { config_ptr.read_volatile() };
+    if config & hfence::CONFIG_PEND == 0 {
+        return;
+    }
+    let pnum = unsafe { ((entry + hfence::PNUM) as *const usize).read_volatile() };
+    let pcount = unsafe { ((entry + hfence::PCOUNT) as *const usize).read_volatile() };
+    let fence_type = (config >> hfence::CONFIG_TYPE_SHIFT) & 0xf;
+    let order = ((config >> hfence::CONFIG_ORDER_SHIFT) & 0x7f) + hfence::ORDER_BASE;
+    let vmid = (config >> hfence::CONFIG_VMID_SHIFT) & 0x3fff;
+    let asid = config & 0xffff;
+
+    let full_flush = pcount > HFENCE_PAGE_LIMIT || order >= usize::BITS as usize;
+    let addresses = (0..pcount).map(|page| (pnum + page) << order);
+    match fence_type {
+        hfence::TYPE_GVMA
+        | hfence::TYPE_GVMA_VMID
+        | hfence::TYPE_GVMA_ALL
+        | hfence::TYPE_GVMA_VMID_ALL => {
+            let with_vmid = matches!(
+                fence_type,
+                hfence::TYPE_GVMA_VMID | hfence::TYPE_GVMA_VMID_ALL
+            );
+            let all = matches!(
+                fence_type,
+                hfence::TYPE_GVMA_ALL | hfence::TYPE_GVMA_VMID_ALL
+            );
+            match (all || full_flush, with_vmid) {
+                (true, true) => unsafe { asm!("hfence.gvma x0, {}", in(reg) vmid) },
+                (true, false) => unsafe { asm!("hfence.gvma x0, x0") },
+                // Guest physical addresses are passed shifted right by 2 bits.
+                (false, true) => addresses.for_each(|gpa| unsafe {
+                    asm!("hfence.gvma {}, {}", in(reg) gpa >> 2, in(reg) vmid)
+                }),
+                (false, false) => addresses
+                    .for_each(|gpa| unsafe { asm!("hfence.gvma {}, x0", in(reg) gpa >> 2) }),
+            }
+        }
+        hfence::TYPE_VVMA
+        | hfence::TYPE_VVMA_ALL
+        | hfence::TYPE_VVMA_ASID
+        | hfence::TYPE_VVMA_ASID_ALL => {
+            // HFENCE.VVMA applies to the VMID in `hgatp`, switch to the requested one.
+            let old_hgatp = read_h_csr(CSR_HGATP).unwrap_or(0);
+            write_h_csr(
+                CSR_HGATP,
+                (old_hgatp & !HGATP_VMID_MASK) | (vmid << HGATP_VMID_SHIFT),
+            );
+            let with_asid = matches!(
+                fence_type,
+                hfence::TYPE_VVMA_ASID | hfence::TYPE_VVMA_ASID_ALL
+            );
+            let all = matches!(
+                fence_type,
+                hfence::TYPE_VVMA_ALL | hfence::TYPE_VVMA_ASID_ALL
+            );
+            match (all || full_flush, with_asid) {
+                (true, true) => unsafe { asm!("hfence.vvma x0, {}", in(reg) asid) },
+                (true, false) => unsafe { asm!("hfence.vvma x0, x0") },
+                (false, true) => addresses
+                    .for_each(|va| unsafe { asm!("hfence.vvma {}, {}", in(reg) va, in(reg) asid) }),
+                (false, false) => {
+                    addresses.for_each(|va| unsafe { asm!("hfence.vvma {}, x0", in(reg) va) })
+                }
+            }
+            write_h_csr(CSR_HGATP, old_hgatp);
+        }
+        // Reserved types are dropped.
+        _ => {}
+    }
+    unsafe { config_ptr.write_volatile(config & !hfence::CONFIG_PEND) };
+}
+
+/// Reads register `x{reg_id}` of the nested SRET context of the current hart.
+#[inline]
+pub fn sret_context_reg(reg_id: usize) -> usize {
+    let shmem = hart_context(current_hartid()).nacl_state.shmem.unwrap();
+    let _mapping = map_supervisor_memory(shmem, NATIVE);
+    let ptr = (shmem + offset::SRET) as *const usize;
+    unsafe { ptr.add(reg_id).read_volatile() }
+}
+
+/// Emulates SRET of the supervisor by returning through `mret`.
+///
+/// The return address, privilege mode and virtualization mode are taken from
+/// `sepc`, `sstatus.SPP` and `hstatus.SPV`; `sstatus` is updated as SRET would.
+pub fn emulate_sret() {
+    let status = sstatus::read();
+    let hstatus = read_h_csr(CSR_HSTATUS).unwrap_or(0);
+    unsafe {
+        mepc::write(sepc::read());
+        match status.spp() {
+            sstatus::SPP::Supervisor => mstatus::set_mpp(mstatus::MPP::Supervisor),
+            sstatus::SPP::User => mstatus::set_mpp(mstatus::MPP::User),
+        }
+        if hstatus & HSTATUS_SPV != 0 {
+            asm!("csrs mstatus, {}", in(reg) MSTATUS_MPV);
+        } else {
+            asm!("csrc mstatus, {}", in(reg) MSTATUS_MPV);
+        }
+        if status.spie() {
+            sstatus::set_sie();
+        } else {
+            sstatus::clear_sie();
+        }
+        sstatus::set_spie();
+        sstatus::set_spp(sstatus::SPP::User);
+    }
+}
@@ -6,10 +6,15 @@ use sbi_spec::binary::SharedPtr;
 use sbi_spec::pmu::shmem_size::SIZE;
 use sbi_spec::pmu::*;
 
+use crate::firmware::map_supervisor_memory;
+use crate::riscv::csr::mhpmevent;
 use crate::riscv::csr::*;
 use crate::{riscv::current_hartid, sbi::features::hart_mhpm_mask};
 
-use super::features::{PrivilegedVersion, hart_privileged_version};
+use super::features::{
+    Extension, PrivilegedVersion, hart_extension_probe, hart_privileged_version,
+};
+use super::supervisor_shmem;
 use super::trap_stack::{hart_context, hart_context_mut};
 
 /// Maximum number of hardware performance counters supported.
@@ -19,6 +24,16 @@ const PMU_FIRMWARE_COUNTER_MAX: usize = 16;
 /// Marker value for inactive/invalid event indices.
 const PMU_EVENT_IDX_INVALID: usize = usize::MAX;
 
+/// Field offsets of the snapshot shared memory.
+mod snapshot {
+    /// Bitmap of overflown counters relative to `counter_idx_base`.
+    pub const OVERFLOW_BITMAP: usize = 0x0000;
+    /// Values of logical counters, indexed by counter index.
+    pub const COUNTER_VALUES: usize = 0x0008;
+    /// Number of entries in the counter value array.
+    pub const COUNTER_VALUES_MAX: usize = 64;
+}
+
 /// PMU state tracking hardware and firmware performance counters
 #[repr(C)]
 pub struct PmuState {
@@ -29,6 +44,8 @@ pub struct PmuState {
     fw_counter: [u64; PMU_FIRMWARE_COUNTER_MAX],
     hw_counters_num: usize,
     total_counters_num: usize,
+    /// Physical address of the registered snapshot shared memory.
+    snapshot_shmem: Option<usize>,
 }
 
 impl PmuState {
@@ -49,6 +66,7 @@ impl PmuState {
             fw_counter: [0; PMU_FIRMWARE_COUNTER_MAX],
             hw_counters_num,
             total_counters_num,
+            snapshot_shmem: None,
         }
     }
 
@@ -135,6 +153,41 @@ impl PmuState {
         Ok(())
     }
 
+    /// Reads the value of counter `counter_idx` saved in the snapshot shared memory.
+    #[inline]
+    fn snapshot_counter_value(&self, counter_idx: usize) -> Option<u64> {
+        let shmem = self.snapshot_shmem?;
+        if counter_idx >= snapshot::COUNTER_VALUES_MAX {
+            return None;
+        }
+        let _mapping = map_supervisor_memory(shmem, SIZE);
+        let ptr = (shmem + snapshot::COUNTER_VALUES) as *const u64;
+        Some(unsafe { ptr.add(counter_idx).read_volatile() })
+    }
+
+    /// Saves `value` of counter `counter_idx` to the snapshot shared memory.
+    #[inline]
+    fn save_snapshot_counter_value(&self, counter_idx: usize, value: u64) {
+        let Some(shmem) = self.snapshot_shmem else {
+            return;
+        };
+        if counter_idx < snapshot::COUNTER_VALUES_MAX {
+            let _mapping = map_supervisor_memory(shmem, SIZE);
+            let ptr = (shmem + snapshot::COUNTER_VALUES) as *mut u64;
+            unsafe { ptr.add(counter_idx).write_volatile(value) };
+        }
+    }
+
+    /// Writes the overflow bitmap of the snapshot shared memory.
+    #[inline]
+    fn save_snapshot_overflow_bitmap(&self, bitmap: u64) {
+        if let Some(shmem) = self.snapshot_shmem {
+            let _mapping = map_supervisor_memory(shmem, SIZE);
+            let ptr = (shmem + snapshot::OVERFLOW_BITMAP) as *mut u64;
+            unsafe { ptr.write_volatile(bitmap) };
+        }
+    }
+
     #[inline]
     pub fn is_firmware_event_start(&self, counter_idx: usize) -> bool {
         if counter_idx < self.hw_counters_num || counter_idx >= self.total_counters_num {
@@ -289,7 +342,9 @@ impl Pmu for SbiPmu {
         };
 
         let pmu_state = &mut hart_context_mut(current_hartid()).pmu_state;
-        let is_update_value = flags.contains(flags::CounterStartFlags::INIT_VALUE);
+        let is_init_snapshot = flags.contains(flags::CounterStartFlags::INIT_SNAPSHOT);
+        let is_update_value =
+            flags.contains(flags::CounterStartFlags::INIT_VALUE) || is_init_snapshot;
 
         if counter_idx_base >= pmu_state.total_counters_num
             || (counter_idx_mask & ((1 << pmu_state.total_counters_num) - 1)) == 0
@@ -297,7 +352,7 @@ impl Pmu for SbiPmu {
             return SbiRet::invalid_param();
         }
 
-        if flags.contains(flags::CounterStartFlags::INIT_SNAPSHOT) {
+        if is_init_snapshot && pmu_state.snapshot_shmem.is_none() {
             return SbiRet::no_shmem();
         }
 
@@ -306,6 +361,13 @@ impl Pmu for SbiPmu {
                 return SbiRet::invalid_param();
             }
 
+            let initial_value = if is_init_snapshot {
+                pmu_state
+                    .snapshot_counter_value(counter_idx)
+                    .unwrap_or(initial_value)
+            } else {
+                initial_value
+            };
             let start_result = if counter_idx >= pmu_state.get_hw_counter_num() {
                 pmu_state.start_fw_counter(counter_idx, initial_value, is_update_value)
             } else {
@@ -340,6 +402,7 @@ impl Pmu for SbiPmu {
 
         let pmu_state = &mut hart_context_mut(current_hartid()).pmu_state;
         let is_reset = flags.contains(flags::CounterStopFlags::RESET);
+        let is_take_snapshot = flags.contains(flags::CounterStopFlags::TAKE_SNAPSHOT);
 
         if counter_idx_base >= pmu_state.total_counters_num
             || (counter_idx_mask & ((1 << pmu_state.total_counters_num) - 1)) == 0
@@ -347,15 +410,24 @@ impl Pmu for SbiPmu {
             return SbiRet::invalid_param();
         }
 
-        if flags.contains(flags::CounterStopFlags::TAKE_SNAPSHOT) {
+        if is_take_snapshot && pmu_state.snapshot_shmem.is_none() {
             return SbiRet::no_shmem();
         }
 
+        let mut overflow_bitmap = 0;
         for counter_idx in CounterMask::new(counter_idx_base, counter_idx_mask) {
             if counter_idx >= pmu_state.total_counters_num {
                 return SbiRet::invalid_param();
             }
 
+            // Read the overflow bit before a reset clears the event selector.
+            if is_take_snapshot
+                && counter_idx < pmu_state.get_hw_counter_num()
+                && is_hardware_counter_overflowed(get_mhpm_csr_offset(counter_idx).unwrap())
+            {
+                overflow_bitmap |= 1 << (counter_idx - counter_idx_base);
+            }
+
             let stop_result = if counter_idx >= pmu_state.get_hw_counter_num() {
                 pmu_state.stop_fw_counter(counter_idx, is_reset)
             } else {
@@ -371,6 +443,18 @@ impl Pmu for SbiPmu {
                 Err(StopCounterErr::OffsetInvalid) => return SbiRet::invalid_param(),
                 Err(StopCounterErr::AlreadyStop) => return SbiRet::already_stopped(),
             }
+
+            if is_take_snapshot {
+                let value = if counter_idx >= pmu_state.get_hw_counter_num() {
+                    pmu_state.get_fw_counter(counter_idx).unwrap_or(0)
+                } else {
+                    read_mhpmcounter(get_mhpm_csr_offset(counter_idx).unwrap())
+                };
+                pmu_state.save_snapshot_counter_value(counter_idx, value);
+            }
+        }
+        if is_take_snapshot {
+            pmu_state.save_snapshot_overflow_bitmap(overflow_bitmap);
         }
         SbiRet::success(0)
     }
@@ -406,9 +490,25 @@ impl Pmu for SbiPmu {
     /// Function: Set PMU snapshot shared memory (FID #7).
     #[inline]
     fn snapshot_set_shmem(&self, shmem: SharedPtr<[u8; SIZE]>, flags: usize) -> SbiRet {
-        // Optional function, `not_supported` is returned if not implemented.
-        let _ = (shmem, flags);
-        SbiRet::not_supported()
+        if flags != 0 {
+            return SbiRet::invalid_param();
+        }
+        let pmu_state = &mut hart_context_mut(current_hartid()).pmu_state;
+        let shmem = match supervisor_shmem(shmem.phys_addr_lo(), shmem.phys_addr_hi(), SIZE, SIZE) {
+            Ok(Some(shmem)) => shmem,
+            Ok(None) => {
+                pmu_state.snapshot_shmem = None;
+                return SbiRet::success(0);
+            }
+            Err(error) => return error,
+        };
+        let _mapping = map_supervisor_memory(shmem, SIZE);
+        let ptr = shmem as *mut u64;
+        for i in 0..SIZE / size_of::<u64>() {
+            unsafe { ptr.add(i).write_volatile(0) };
+        }
+        pmu_state.snapshot_shmem = Some(shmem);
+        SbiRet::success(0)
     }
 }
 
@@ -640,6 +740,9 @@ fn configure_counter(
         }
     } else {
         let mhpm_offset = get_mhpm_csr_offset(counter_idx).unwrap();
+        if has_sscofpmf(mhpm_offset) {
+            update_mhpmevent_filter(mhpm_offset, flags);
+        }
         if clear_value {
             write_mhpmcounter(mhpm_offset, 0);
         }
@@ -694,6 +797,11 @@ fn start_hardware_counter(
         return Err(StartCounterErr::AlreadyStart);
     }
 
+    // Clear the overflow bit to unmask overflow interrupts of this counter.
+    if has_sscofpmf(mhpm_offset) {
+        write_mhpmevent(mhpm_offset, read_mhpmevent(mhpm_offset) & !mhpmevent::OF);
+    }
+
     if is_update_value {
         write_mhpmcounter(mhpm_offset, new_value);
     }
@@ -742,6 +850,52 @@ fn stop_hardware_counter(mhpm_offset: u16, is_reset: bool) -> Result<(), StopCou
     Ok(())
 }
 
+/// Checks if the counter at `mhpm_offset` has Sscofpmf overflow and filtering bits.
+///
+/// Only mhpmcounter3-31 have them; mcycle and minstret need Smcntrpmf.
+#[inline]
+fn has_sscofpmf(mhpm_offset: u16) -> bool {
+    mhpm_offset >= 3 && hart_extension_probe(current_hartid(), Extension::Sscofpmf)
+}
+
+/// Checks if the overflow bit of the counter at `mhpm_offset` is set.
+#[inline]
+fn is_hardware_counter_overflowed(mhpm_offset: u16) -> bool {
+    has_sscofpmf(mhpm_offset) && read_mhpmevent(mhpm_offset) & mhpmevent::OF != 0
+}
+
+/// Sets privilege mode filtering bits of mhpmevent from counter configuration flags.
+///
+/// The overflow bit is set to mask overflow interrupts until the counter is started.
+fn update_mhpmevent_filter(mhpm_offset: u16, flags: flags::CounterCfgFlags) {
+    let mut mhpmevent_val = (read_mhpmevent(mhpm_offset) & !mhpmevent::SSCOF_MASK) | mhpmevent::OF;
+    for (flag, bit) in [
+        (flags::CounterCfgFlags::SET_MINH, mhpmevent::MINH),
+        (flags::CounterCfgFlags::SET_VUINH, mhpmevent::VUINH),
+        (flags::CounterCfgFlags::SET_VSINH, mhpmevent::VSINH),
+        (flags::CounterCfgFlags::SET_UINH, mhpmevent::UINH),
+        (flags::CounterCfgFlags::SET_SINH, mhpmevent::SINH),
+    ] {
+        if flags.contains(flag) {
+            mhpmevent_val |= bit;
+        }
+    }
+    write_mhpmevent(mhpm_offset, mhpmevent_val);
+}
+
+/// Read MHPMEVENT
+fn read_mhpmevent(mhpm_offset: u16) -> u64 {
+    // Use seq_macro to generate all valid indices from 3 to 31
+    seq_macro::seq!(N in 3..=31 {
+        match mhpm_offset {
+            #(
+                N => pastey::paste!{ [<mhpmevent ~N>]::read() as u64 },
+            )*
+            _ => 0,
+        }
+    })
+}
+
 /// Write MHPMEVENT or MHPMCOUNTER
 fn write_mhpmevent(mhpm_offset: u16, mhpmevent_val: u64) {
     let csr = CSR_MHPMEVENT3 + mhpm_offset - 3;
@@ -790,6 +944,23 @@ fn write_mhpmcounter(mhpm_offset: u16, mhpmcounter_val: u64) {
     }
 }
 
+/// Reads MHPMCOUNTER, MCYCLE or MINSTRET
+fn read_mhpmcounter(mhpm_offset: u16) -> u64 {
+    match mhpm_offset {
+        0 => ::riscv::register::mcycle::read() as u64,
+        2 => ::riscv::register::minstret::read() as u64,
+        // Use seq_macro to generate all valid indices from 3 to 31
+        _ => seq_macro::seq!(N in 3..=31 {
+            match mhpm_offset {
+                #(
+                    N => pastey::paste!{ [<mhpmcounter ~N>]::read() as u64 },
+                )*
+                _ => 0,
+            }
+        }),
+    }
+}
+
 /// Wrap for counter info
 struct CounterInfo {
     /// Packed representation of counter information:
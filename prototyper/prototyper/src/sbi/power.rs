@@ -0,0 +1,258 @@
+use core::arch::{asm, naked_asm};
+use core::ops::RangeInclusive;
+
+use crate::cfg::NUM_HART_MAX;
+use crate::sbi::features::{PrivilegedVersion, hart_privileged_version};
+
+/// HSM suspend types reserved for platform-specific retentive states.
+pub const HART_RETENTIVE_PLATFORM: RangeInclusive<u32> = 0x1000_0000..=0x7fff_ffff;
+/// HSM suspend types reserved for platform-specific non-retentive states.
+pub const HART_NON_RETENTIVE_PLATFORM: RangeInclusive<u32> = 0x9000_0000..=0xffff_ffff;
+/// SUSP sleep types reserved for platform-specific states.
+pub const SYSTEM_PLATFORM: RangeInclusive<u32> = 0x8000_0000..=0xffff_ffff;
+
+/// Platform controller for hart, cluster and system low-power states.
+///
+/// Every method returns once the hart is woken up by an interrupt. Memory must
+/// survive; M-mode CSRs may be lost in non-retentive states as the caller saves
+/// and restores them.
+///
+/// In non-retentive states the hart may also be powered down. It then restarts
+/// from its reset vector `_start`, which resumes it as if the method has returned,
+/// so the device must not hold locks or other state across the power-down.
+///
+/// Harts call into the device concurrently and without locking, since they
+/// stay inside it while suspended.
+pub trait PowerDevice {
+    /// Checks if a platform-specific HSM suspend type is implemented.
+    fn hart_suspend_supported(&self, _suspend_type: u32) -> bool {
+        false
+    }
+
+    /// Checks if a platform-specific SUSP sleep type is implemented.
+    fn system_suspend_supported(&self, _sleep_type: u32) -> bool {
+        false
+    }
+
+    /// Enters the low-power state `suspend_type` on the current hart.
+    ///
+    /// `last_hart` is set when all other harts are stopped or suspended, so the
+    /// cluster of the current hart may be powered down as well.
+    fn hart_suspend(&self, _hart_id: usize, _suspend_type: u32, _last_hart: bool) {
+        riscv::asm::wfi();
+    }
+
+    /// Enters the system sleep state `sleep_type`, e.g. suspend-to-RAM.
+    ///
+    /// All other harts are stopped when this is called.
+    fn system_suspend(&self, _hart_id: usize, _sleep_type: u32) {
+        riscv::asm::wfi();
+    }
+}
+
+/// M-mode CSRs which do not survive non-retentive suspend.
+pub(crate) struct MachineCsrs {
+    mie: usize,
+    mideleg: usize,
+    medeleg: usize,
+    mtvec: usize,
+    mscratch: usize,
+    mcounteren: usize,
+    mcountinhibit: Option<usize>,
+    menvcfg: Option<usize>,
+}
+
+impl MachineCsrs {
+    /// Saves M-mode CSRs of the current hart.
+    pub fn save(hart_id: usize) -> Self {
+        let priv_version = hart_privileged_version(hart_id);
+        let mut csrs = Self {
+            mie: 0,
+            mideleg: 0,
+            medeleg: 0,
+            mtvec: 0,
+            mscratch: 0,
+            mcounteren: 0,
+            mcountinhibit: None,
+            menvcfg: None,
+        };
+        unsafe {
+            asm!("csrr {}, mie", out(reg) csrs.mie);
+            asm!("csrr {}, mideleg", out(reg) csrs.mideleg);
+            asm!("csrr {}, medeleg", out(reg) csrs.medeleg);
+            asm!("csrr {}, mtvec", out(reg) csrs.mtvec);
+            asm!("csrr {}, mscratch", out(reg) csrs.mscratch);
+            asm!("csrr {}, mcounteren", out(reg) csrs.mcounteren);
+            if priv_version >= PrivilegedVersion::Version1_11 {
+                let value: usize;
+                asm!("csrr {}, mcountinhibit", out(reg) value);
+                csrs.mcountinhibit = Some(value);
+            }
+            if priv_version >= PrivilegedVersion::Version1_12 {
+                let value: usize;
+                asm!("csrr {}, menvcfg", out(reg) value);
+                csrs.menvcfg = Some(value);
+            }
+        }
+        csrs
+    }
+
+    /// Restores saved CSRs and reprograms PMP of the current hart, locking it down again.
+    pub fn restore(&self) {
+        unsafe {
+            asm!("csrw mtvec, {}", in(reg) self.mtvec);
+            asm!("csrw mscratch, {}", in(reg) self.mscratch);
+            asm!("csrw medeleg, {}", in(reg) self.medeleg);
+            asm!("csrw mideleg, {}", in(reg) self.mideleg);
+            asm!("csrw mcounteren, {}", in(reg) self.mcounteren);
+            if let Some(value) = self.mcountinhibit {
+                asm!("csrw mcountinhibit, {}", in(reg) value);
+            }
+            if let Some(value) = self.menvcfg {
+                asm!("csrw menvcfg, {}", in(reg) value);
+            }
+            crate::firmware::set_hart_pmp();
+            crate::firmware::lock_hart_pmp();
+            asm!("csrw mie, {}", in(reg) self.mie);
+        }
+    }
+}
+
+/// Callee-saved registers of a hart which may be powered down in a non-retentive state.
+#[repr(C)]
+struct ResumeContext {
+    /// `ra`, `sp`, `gp`, `tp` and `s0` to `s11`.
+    regs: [usize; 16],
+    /// [`RESUME_MAGIC`] while the hart may be powered down.
+    magic: usize,
+}
+
+const RESUME_MAGIC: usize = 0x5253_4249_5253_4d45;
+
+/// Resume contexts of all harts, cleared with the bss on cold boot.
+static mut RESUME_CONTEXT: [ResumeContext; NUM_HART_MAX] = [const {
+    ResumeContext {
+        regs: [0; 16],
+        magic: 0,
+    }
+}; NUM_HART_MAX];
+
+/// Runs `f` on the current hart so that it can be powered down inside.
+///
+/// A hart restarting from `_start` while in `f` continues after this function,
+/// with its callee-saved registers and stack restored by [`warm_resume`].
+/// Floating-point registers are not saved, as the firmware does not use them.
+pub(crate) fn with_resume_context<F: FnOnce()>(hart_id: usize, f: F) {
+    extern "C" fn trampoline<F: FnOnce()>(arg: *mut ()) {
+        let f = unsafe { &mut *arg.cast::<Option<F>>() };
+        if let Some(f) = f.take() {
+            f();
+        }
+    }
+    assert!(hart_id < NUM_HART_MAX);
+    let mut f = Some(f);
+    unsafe {
+        let ctx = (&raw mut RESUME_CONTEXT)
+            .cast::<ResumeContext>()
+            .add(hart_id);
+        call_with_resume_context(ctx, (&raw mut f).cast(), trampoline::<F>);
+    }
+}
+
+/// Saves callee-saved registers to `ctx`, then calls `f(arg)`.
+#[unsafe(naked)]
+unsafe extern "C" fn call_with_resume_context(
+    ctx: *mut ResumeContext,
+    arg: *mut (),
+    f: extern "C" fn(*mut ()),
+) {
+    naked_asm!(
+        "   sd   ra,   0(a0)
+            sd   sp,   8(a0)
+            sd   gp,  16(a0)
+            sd   tp,  24(a0)
+            sd   s0,  32(a0)
+            sd   s1,  40(a0)
+            sd   s2,  48(a0)
+            sd   s3,  56(a0)
+            sd   s4,  64(a0)
+            sd   s5,  72(a0)
+            sd   s6,  80(a0)
+            sd   s7,  88(a0)
+            sd   s8,  96(a0)
+            sd   s9, 104(a0)
+            sd   s10, 112(a0)
+            sd   s11, 120(a0)
+            li   t0, {magic}
+            sd   t0, {magic_offset}(a0)
+            fence                       // Publish the context before powering down
+            mv   s0, a0
+            mv   a0, a1
+            jalr a2                     // Call `f(arg)`, which keeps `s0`
+            mv   a0, s0
+            j    {restore}
+        ",
+        magic        = const RESUME_MAGIC,
+        magic_offset = const core::mem::offset_of!(ResumeContext, magic),
+        restore      =   sym restore_context,
+    )
+}
+
+/// Invalidates the resume context in `a0` and returns to its caller of
+/// [`call_with_resume_context`].
+#[unsafe(naked)]
+unsafe extern "C" fn restore_context() -> ! {
+    naked_asm!(
+        "   sd   zero, {magic_offset}(a0)
+            ld   ra,   0(a0)
+            ld   sp,   8(a0)
+            ld   gp,  16(a0)
+            ld   tp,  24(a0)
+            ld   s0,  32(a0)
+            ld   s1,  40(a0)
+            ld   s2,  48(a0)
+            ld   s3,  56(a0)
+            ld   s4,  64(a0)
+            ld   s5,  72(a0)
+            ld   s6,  80(a0)
+            ld   s7,  88(a0)
+            ld   s8,  96(a0)
+            ld   s9, 104(a0)
+            ld   s10, 112(a0)
+            ld   s11, 120(a0)
+            ret
+        ",
+        magic_offset = const core::mem::offset_of!(ResumeContext, magic),
+    )
+}
+
+/// Resumes the current hart if it restarted while powered down in a non-retentive
+/// state, otherwise returns.
+///
+/// Called from `_start` without a stack once the bss is cleared; only `t0` to `t2`
+/// and `ra` are used, so that boot arguments are kept.
+#[unsafe(naked)]
+pub(crate) unsafe extern "C" fn warm_resume() {
+    naked_asm!(
+        "   csrr t2, mhartid
+            li   t0, {hart_max}
+            bgeu t2, t0, 1f             // Harts without context never suspend
+            li   t0, {context_size}
+            mul  t2, t2, t0
+            lla  t0, {context}
+            add  t2, t2, t0
+            ld   t0, {magic_offset}(t2)
+            li   t1, {magic}
+            bne  t0, t1, 1f             // Not powered down in a non-retentive state
+            mv   a0, t2
+            j    {restore}
+         1: ret
+        ",
+        hart_max     = const NUM_HART_MAX,
+        context_size = const size_of::<ResumeContext>(),
+        context      =   sym RESUME_CONTEXT,
+        magic_offset = const core::mem::offset_of!(ResumeContext, magic),
+        magic        = const RESUME_MAGIC,
+        restore      =   sym restore_context,
+    )
+}
@@ -2,7 +2,7 @@ use rustsbi::{HartMask, SbiRet};
 use sbi_spec::pmu::firmware_event;
 use spin::Mutex;
 
-use crate::cfg::{PAGE_SIZE, TLB_FLUSH_LIMIT};
+use crate::cfg::PAGE_SIZE;
 use crate::platform::PLATFORM;
 use crate::riscv::current_hartid;
 use crate::sbi::fifo::{Fifo, FifoError};
@@ -390,7 +390,8 @@ pub fn rfence_single_handler() {
     if let Some((ctx, source_hart_id)) = local_rf.get() {
         let full_flush = (ctx.start_addr == 0 && ctx.size == 0)
             || (ctx.size == usize::MAX)
-            || (ctx.size > TLB_FLUSH_LIMIT && ctx.size != usize::MAX);
+            || (ctx.size > unsafe { PLATFORM.info.config.tlb_flush_limit() }
+                && ctx.size != usize::MAX);
 
         match ctx.op {
             RFenceType::FenceI => {
@@ -0,0 +1,123 @@
+use core::sync::atomic::{Ordering, fence};
+use rustsbi::{SbiRet, SharedPtr};
+
+use crate::firmware::map_supervisor_memory;
+use crate::platform::PLATFORM;
+use crate::riscv::current_hartid;
+use crate::sbi::supervisor_shmem;
+use crate::sbi::trap_stack::{hart_context, hart_context_mut};
+
+/// Size and alignment of the steal-time shared memory.
+pub const STA_SHMEM_SIZE: usize = 64;
+
+/// Field offsets of the steal-time shared memory.
+mod offset {
+    pub const SEQUENCE: usize = 0;
+    pub const STEAL: usize = 8;
+    pub const PREEMPTED: usize = 16;
+}
+
+/// Steal-time accounting state of a hart.
+pub struct StaState {
+    /// Physical address of the registered shared memory.
+    pub shmem: Option<usize>,
+    /// Nanoseconds stolen from supervisor software since registration.
+    pub steal_ns: u64,
+}
+
+impl StaState {
+    pub const fn new() -> Self {
+        Self {
+            shmem: None,
+            steal_ns: 0,
+        }
+    }
+}
+
+pub struct SbiSta;
+
+impl rustsbi::Sta for SbiSta {
+    fn set_shmem(&self, shmem: SharedPtr<[u8; 64]>, flags: usize) -> SbiRet {
+        if flags != 0 {
+            return SbiRet::invalid_param();
+        }
+        let hart_id = current_hartid();
+        let shmem = match supervisor_shmem(
+            shmem.phys_addr_lo(),
+            shmem.phys_addr_hi(),
+            STA_SHMEM_SIZE,
+            STA_SHMEM_SIZE,
+        ) {
+            Ok(Some(shmem)) => shmem,
+            Ok(None) => {
+                hart_context_mut(hart_id).sta_state.shmem = None;
+                return SbiRet::success(0);
+            }
+            Err(error) => return error,
+        };
+        let _mapping = map_supervisor_memory(shmem, STA_SHMEM_SIZE);
+        let ptr = shmem as *mut u64;
+        for i in 0..STA_SHMEM_SIZE / size_of::<u64>() {
+            unsafe { ptr.add(i).write_volatile(0) };
+        }
+        hart_context_mut(hart_id).sta_state = StaState {
+            shmem: Some(shmem),
+            steal_ns: 0,
+        };
+        SbiRet::success(0)
+    }
+}
+
+/// Adds `stolen_ns` nanoseconds to the steal time of `hart_id` and publishes it.
+///
+/// Sets the preempted flag of the hart as well, wrapping the update with the sequence
+/// counter as the STA extension requires. Does nothing if the hart has not registered
+/// steal-time shared memory.
+///
+/// This is the hook for a hypervisor or a platform scheduler, sharing one accumulator
+/// with the time the firmware itself takes. Updates of one hart must not race with
+/// each other, and should stop while the hart is not runnable.
+pub fn add_steal_time(hart_id: usize, stolen_ns: u64, preempted: bool) {
+    let state = &mut hart_context_mut(hart_id).sta_state;
+    let Some(shmem) = state.shmem else {
+        return;
+    };
+    state.steal_ns = state.steal_ns.wrapping_add(stolen_ns);
+    let steal_ns = state.steal_ns;
+    let _mapping = map_supervisor_memory(shmem, STA_SHMEM_SIZE);
+    unsafe {
+        let sequence = (shmem + offset::SEQUENCE) as *mut u32;
+        let steal = (shmem + offset::STEAL) as *mut u64;
+        let preempted_ptr = (shmem + offset::PREEMPTED) as *mut u8;
+        // An odd sequence number tells the supervisor an update is in progress.
+        let start = sequence.read_volatile() | 1;
+        sequence.write_volatile(start);
+        fence(Ordering::Release);
+        steal.write_volatile(steal_ns);
+        preempted_ptr.write_volatile(preempted as u8);
+        fence(Ordering::Release);
+        sequence.write_volatile(start.wrapping_add(1));
+    }
+}
+
+/// Runs `f` and charges the time it takes to the steal time of the current hart.
+///
+/// Used around work the firmware does for other harts, such as remote fences, which
+/// keeps the hart away from supervisor software. Runs `f` without accounting if the
+/// hart has no steal-time shared memory or the timebase frequency is unknown.
+pub fn charge_steal_time<R>(f: impl FnOnce() -> R) -> R {
+    let hart_id = current_hartid();
+    let frequency = unsafe { PLATFORM.info.timebase_frequency };
+    let ipi = unsafe { PLATFORM.sbi.ipi.as_ref() };
+    let (Some(_), Some(frequency), Some(ipi)) =
+        (hart_context(hart_id).sta_state.shmem, frequency, ipi)
+    else {
+        return f();
+    };
+    let start = ipi.get_time() as u64;
+    let ret = f();
+    let elapsed = (ipi.get_time() as u64).wrapping_sub(start);
+    let stolen_ns = elapsed as u128 * 1_000_000_000 / frequency as u128;
+    add_steal_time(hart_id, stolen_ns as u64, false);
+    ret
+}
@@ -1,10 +1,11 @@
 use riscv::register::mstatus;
-use rustsbi::{Hsm, SbiRet};
-use sbi_spec::hsm::{hart_state::STOPPED, suspend_type::NON_RETENTIVE};
+use rustsbi::SbiRet;
+use sbi_spec::hsm::hart_state::STOPPED;
 
 use crate::{platform::PLATFORM, riscv::current_hartid};
 
-use super::hsm::remote_hsm;
+use super::hsm::{SuspendState, remote_hsm};
+use super::power::SYSTEM_PLATFORM;
 
 const SUSPEND_TO_RAM: u32 = 0x0;
 
@@ -13,12 +14,20 @@ pub(crate) struct SbiSuspend;
 
 impl rustsbi::Susp for SbiSuspend {
     fn system_suspend(&self, sleep_type: u32, resume_addr: usize, opaque: usize) -> SbiRet {
+        let Some(hsm) = (unsafe { &PLATFORM.sbi.hsm }) else {
+            return SbiRet::not_supported();
+        };
         if sleep_type != SUSPEND_TO_RAM {
-            return SbiRet::invalid_param();
+            if !SYSTEM_PLATFORM.contains(&sleep_type) {
+                return SbiRet::invalid_param();
+            }
+            if !hsm.system_suspend_supported(sleep_type) {
+                return SbiRet::not_supported();
+            }
         }
 
         let prev_mode = mstatus::read().mpp();
-        if prev_mode != mstatus::MPP::Supervisor || prev_mode != mstatus::MPP::User {
+        if !matches!(prev_mode, mstatus::MPP::Supervisor | mstatus::MPP::User) {
             return SbiRet::failed();
         }
 
@@ -41,15 +50,11 @@ impl rustsbi::Susp for SbiSuspend {
             }
         }
 
-        // TODO: The validity of `resume_addr` should be checked.
-        // If it is invalid, `SBI_ERR_INVALID_ADDRESS` should be returned.
-
-        if let Some(hsm) = unsafe { &PLATFORM.sbi.hsm } {
-            hsm.hart_suspend(NON_RETENTIVE, resume_addr, opaque);
-        } else {
-            return SbiRet::not_supported();
+        if !unsafe { PLATFORM.is_supervisor_memory(resume_addr, 1) } {
+            return SbiRet::invalid_address();
         }
 
-        unreachable!();
+        hsm.suspend(SuspendState::System(sleep_type));
+        hsm.hart_resume(current_hartid(), resume_addr, opaque)
     }
 }
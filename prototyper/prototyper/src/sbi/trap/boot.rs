@@ -1,3 +1,4 @@
+use crate::firmware;
 use crate::riscv::current_hartid;
 use crate::sbi::hsm::local_hsm;
 use crate::sbi::ipi;
@@ -54,6 +55,7 @@ pub extern "C" fn boot_handler(ctx: &mut BootContext) {
         // Handle HSM Start
         Ok(next_stage) => {
             ipi::clear_msip();
+            firmware::lock_hart_pmp();
             unsafe {
                 mstatus::set_mpie();
                 mstatus::set_mpp(next_stage.next_mode);
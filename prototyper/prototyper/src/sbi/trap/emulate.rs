@@ -0,0 +1,351 @@
+use core::arch::asm;
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::sync::atomic::{Ordering, fence};
+
+use fast_trap::EntireContextSeparated;
+use riscv::register::mstatus::{self, MPP};
+
+use crate::cfg::{
+    EMULATE_ATOMIC, EMULATE_CYCLE, EMULATE_INSTRET, EMULATE_STIMECMP, EMULATE_TIME, NUM_HART_MAX,
+};
+use crate::platform::PLATFORM;
+use crate::riscv::csr::{
+    CSR_CYCLE, CSR_CYCLEH, CSR_INSTRET, CSR_INSTRETH, CSR_STIMECMP, CSR_STIMECMPH, CSR_TIME,
+    CSR_TIMEH,
+};
+use crate::riscv::current_hartid;
+use crate::sbi::features::{Extension, hart_extension_probe};
+
+use super::helper::*;
+
+/// Condition under which a lower privilege mode may read an emulated CSR.
+enum CsrGate {
+    /// Counter enabled for U-mode by bit `n` of `scounteren`.
+    ///
+    /// `mcounteren` is not checked: the firmware enables all counters for S-mode, but
+    /// bits of counters the hart lacks may read back as zero.
+    Counter(usize),
+    /// S-mode only, on harts without Sstc.
+    Stimecmp,
+}
+
+/// CSR read emulated for supervisor and user software.
+struct EmulatedCsr {
+    csr: u16,
+    enabled: bool,
+    gate: CsrGate,
+    read: fn() -> usize,
+}
+
+static EMULATED_CSRS: &[EmulatedCsr] = &[
+    EmulatedCsr {
+        csr: CSR_CYCLE,
+        enabled: EMULATE_CYCLE,
+        gate: CsrGate::Counter(0),
+        read: || ::riscv::register::mcycle::read(),
+    },
+    EmulatedCsr {
+        csr: CSR_CYCLEH,
+        enabled: EMULATE_CYCLE,
+        gate: CsrGate::Counter(0),
+        read: || ::riscv::register::mcycle::read() >> 32,
+    },
+    EmulatedCsr {
+        csr: CSR_TIME,
+        enabled: EMULATE_TIME,
+        gate: CsrGate::Counter(1),
+        read: || unsafe { PLATFORM.sbi.ipi.as_ref() }.unwrap().get_time(),
+    },
+    EmulatedCsr {
+        csr: CSR_TIMEH,
+        enabled: EMULATE_TIME,
+        gate: CsrGate::Counter(1),
+        read: || unsafe { PLATFORM.sbi.ipi.as_ref() }.unwrap().get_timeh(),
+    },
+    EmulatedCsr {
+        csr: CSR_INSTRET,
+        enabled: EMULATE_INSTRET,
+        gate: CsrGate::Counter(2),
+        read: || ::riscv::register::minstret::read(),
+    },
+    EmulatedCsr {
+        csr: CSR_INSTRETH,
+        enabled: EMULATE_INSTRET,
+        gate: CsrGate::Counter(2),
+        read: || ::riscv::register::minstret::read() >> 32,
+    },
+    EmulatedCsr {
+        csr: CSR_STIMECMP,
+        enabled: EMULATE_STIMECMP,
+        gate: CsrGate::Stimecmp,
+        read: || read_mtimecmp() as usize,
+    },
+    EmulatedCsr {
+        csr: CSR_STIMECMPH,
+        enabled: EMULATE_STIMECMP,
+        gate: CsrGate::Stimecmp,
+        read: || (read_mtimecmp() >> 32) as usize,
+    },
+];
+
+fn read_mtimecmp() -> u64 {
+    unsafe { PLATFORM.sbi.ipi.as_ref() }
+        .unwrap()
+        .read_mtimecmp(current_hartid())
+}
+
+impl CsrGate {
+    /// Checks if the trapped privilege mode may read the CSR.
+    fn allows(&self, mode: MPP) -> bool {
+        match *self {
+            CsrGate::Counter(bit) => {
+                let scounteren: usize;
+                unsafe { asm!("csrr {}, scounteren", out(reg) scounteren) };
+                mode == MPP::Supervisor || scounteren & (1 << bit) != 0
+            }
+            CsrGate::Stimecmp => {
+                mode == MPP::Supervisor && !hart_extension_probe(current_hartid(), Extension::Sstc)
+            }
+        }
+    }
+}
+
+/// Emulates a CSR read into `rd`, returns false if the CSR is not emulated.
+pub fn emulate_csr_read(ctx: &mut EntireContextSeparated, csr: u16, rd: usize) -> bool {
+    let mode = mstatus::read().mpp();
+    match EMULATED_CSRS.iter().find(|entry| entry.csr == csr) {
+        Some(entry) if entry.enabled && entry.gate.allows(mode) => {
+            save_reg_x(ctx, rd, (entry.read)());
+            true
+        }
+        _ => false,
+    }
+}
+
+const OPCODE_AMO: u32 = 0b010_1111;
+
+mod amo {
+    pub const ADD: u32 = 0b00000;
+    pub const SWAP: u32 = 0b00001;
+    pub const LR: u32 = 0b00010;
+    pub const SC: u32 = 0b00011;
+    pub const XOR: u32 = 0b00100;
+    pub const OR: u32 = 0b01000;
+    pub const AND: u32 = 0b01100;
+    pub const MIN: u32 = 0b10000;
+    pub const MAX: u32 = 0b10100;
+    pub const MINU: u32 = 0b11000;
+    pub const MAXU: u32 = 0b11100;
+}
+
+/// Serializes emulated atomics and holds the LR reservation of each hart.
+///
+/// Emulated atomics are atomic with respect to each other, plain stores from
+/// other harts do not break reservations.
+static RESERVATIONS: BakeryLock<[Option<usize>; NUM_HART_MAX]> =
+    BakeryLock::new([None; NUM_HART_MAX]);
+
+/// Lock built on plain loads, stores and fences with Lamport's bakery algorithm.
+///
+/// Harts emulating atomics lack the A extension, so a spin lock can not be taken on
+/// them. Each hart draws a ticket and waits for all harts with smaller ones.
+struct BakeryLock<T> {
+    choosing: UnsafeCell<[bool; NUM_HART_MAX]>,
+    tickets: UnsafeCell<[usize; NUM_HART_MAX]>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for BakeryLock<T> {}
+
+impl<T> BakeryLock<T> {
+    const fn new(data: T) -> Self {
+        Self {
+            choosing: UnsafeCell::new([false; NUM_HART_MAX]),
+            tickets: UnsafeCell::new([0; NUM_HART_MAX]),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Runs `f` on the data while holding the lock on behalf of `hart_id`.
+    fn with<R>(&self, hart_id: usize, f: impl FnOnce(&mut T) -> R) -> R {
+        let choosing = self.choosing.get() as *mut bool;
+        let tickets = self.tickets.get() as *mut usize;
+        unsafe {
+            choosing.add(hart_id).write_volatile(true);
+            fence(Ordering::SeqCst);
+            let ticket = (0..NUM_HART_MAX)
+                .map(|other| tickets.add(other).read_volatile())
+                .max()
+                .unwrap_or(0)
+                + 1;
+            tickets.add(hart_id).write_volatile(ticket);
+            fence(Ordering::SeqCst);
+            choosing.add(hart_id).write_volatile(false);
+            fence(Ordering::SeqCst);
+            for other in (0..NUM_HART_MAX).filter(|&other| other != hart_id) {
+                while choosing.add(other).read_volatile() {
+                    spin_loop();
+                }
+                fence(Ordering::SeqCst);
+                loop {
+                    let other_ticket = tickets.add(other).read_volatile();
+                    if other_ticket == 0 || (ticket, hart_id) < (other_ticket, other) {
+                        break;
+                    }
+                    spin_loop();
+                }
+            }
+            fence(Ordering::SeqCst);
+            let ret = f(&mut *self.data.get());
+            fence(Ordering::SeqCst);
+            tickets.add(hart_id).write_volatile(0);
+            ret
+        }
+    }
+}
+
+/// Exception causes raised by emulated atomics.
+mod cause {
+    pub const LOAD_MISALIGNED: usize = 4;
+    pub const LOAD_FAULT: usize = 5;
+    pub const STORE_MISALIGNED: usize = 6;
+    pub const STORE_FAULT: usize = 7;
+    pub const LOAD_PAGE_FAULT: usize = 13;
+    pub const STORE_PAGE_FAULT: usize = 15;
+    pub const LOAD_GUEST_PAGE_FAULT: usize = 21;
+    pub const STORE_GUEST_PAGE_FAULT: usize = 23;
+}
+
+/// Exception of an emulated instruction, to be redirected to the trapped mode.
+pub struct EmulatedException {
+    pub cause: usize,
+    pub tval: usize,
+}
+
+/// Checks if `inst` is an AMO, LR or SC instruction to be emulated.
+#[inline]
+pub fn is_atomic(inst: u32) -> bool {
+    EMULATE_ATOMIC && inst & 0x7f == OPCODE_AMO
+}
+
+/// Emulates an AMO, LR or SC instruction, returns false if it is not a valid one.
+///
+/// Misaligned addresses and faulting accesses raise the exception the instruction
+/// would raise, AMO and SC ones as store/AMO exceptions.
+pub fn emulate_atomic(
+    ctx: &mut EntireContextSeparated,
+    inst: u32,
+) -> Result<bool, EmulatedException> {
+    let len = match (inst >> 12) & 0b111 {
+        0b010 => 4,
+        0b011 => 8,
+        _ => return Ok(false),
+    };
+    let funct5 = inst >> 27;
+    let rd = ((inst >> 7) & 0b1_1111) as usize;
+    let addr = get_reg_x(ctx, ((inst >> 15) & 0b1_1111) as usize);
+    let src = get_reg_x(ctx, ((inst >> 20) & 0b1_1111) as usize);
+    // Values are handled sign-extended, as word results are written to `rd`.
+    let extend = |value: usize| match len {
+        4 => value as i32 as usize,
+        _ => value,
+    };
+    let exception = |code: usize| {
+        let cause = match (funct5, code) {
+            (amo::LR, _) => code,
+            (_, cause::LOAD_MISALIGNED) => cause::STORE_MISALIGNED,
+            (_, cause::LOAD_FAULT) => cause::STORE_FAULT,
+            (_, cause::LOAD_PAGE_FAULT) => cause::STORE_PAGE_FAULT,
+            (_, cause::LOAD_GUEST_PAGE_FAULT) => cause::STORE_GUEST_PAGE_FAULT,
+            _ => code,
+        };
+        EmulatedException { cause, tval: addr }
+    };
+    if !addr.is_multiple_of(len) {
+        return Err(exception(cause::LOAD_MISALIGNED));
+    }
+
+    let hart_id = current_hartid();
+    let result = RESERVATIONS.with(hart_id, |reservations| -> Result<_, EmulatedException> {
+        let result = match funct5 {
+            amo::LR => {
+                let value = load_data(addr, len).map_err(exception)?;
+                reservations[hart_id] = Some(addr);
+                extend(value)
+            }
+            amo::SC => {
+                let reserved = reservations[hart_id].take() == Some(addr);
+                if reserved {
+                    store_data(addr, len, src).map_err(exception)?;
+                    clear_reservations(reservations, addr);
+                }
+                !reserved as usize
+            }
+            _ => {
+                let old = extend(load_data(addr, len).map_err(exception)?);
+                let new = match funct5 {
+                    amo::SWAP => src,
+                    amo::ADD => old.wrapping_add(src),
+                    amo::XOR => old ^ src,
+                    amo::AND => old & src,
+                    amo::OR => old | src,
+                    amo::MIN => (extend(old) as isize).min(extend(src) as isize) as usize,
+                    amo::MAX => (extend(old) as isize).max(extend(src) as isize) as usize,
+                    amo::MINU => truncate(old, len).min(truncate(src, len)),
+                    amo::MAXU => truncate(old, len).max(truncate(src, len)),
+                    _ => return Ok(None),
+                };
+                store_data(addr, len, new).map_err(exception)?;
+                clear_reservations(reservations, addr);
+                old
+            }
+        };
+        Ok(Some(result))
+    })?;
+    let Some(result) = result else {
+        return Ok(false);
+    };
+    save_reg_x(ctx, rd, result);
+    Ok(true)
+}
+
+#[inline]
+fn truncate(value: usize, len: usize) -> usize {
+    match len {
+        4 => value as u32 as usize,
+        _ => value,
+    }
+}
+
+/// Loads `len` bytes, returns the exception cause on a faulting access.
+#[inline]
+fn load_data(addr: usize, len: usize) -> Result<usize, usize> {
+    let mut data = 0;
+    for i in (0..len).rev() {
+        data = (data << 8) | try_load_byte(addr + i)? as usize;
+    }
+    Ok(data)
+}
+
+/// Stores `len` bytes, returns the exception cause on a faulting access.
+///
+/// The address is naturally aligned, so the access does not cross pages and only
+/// its first byte may fault.
+#[inline]
+fn store_data(addr: usize, len: usize, value: usize) -> Result<(), usize> {
+    for (i, byte) in value.to_le_bytes()[..len].iter().enumerate() {
+        try_store_byte(addr + i, *byte)?;
+    }
+    Ok(())
+}
+
+/// Breaks reservations of all harts on `addr`.
+#[inline]
+fn clear_reservations(reservations: &mut [Option<usize>; NUM_HART_MAX], addr: usize) {
+    for reservation in reservations.iter_mut() {
+        if *reservation == Some(addr) {
+            *reservation = None;
+        }
+    }
+}
@@ -1,19 +1,25 @@
 use fast_trap::{EntireContext, EntireContextSeparated, EntireResult, FastContext, FastResult};
-use riscv::register::{mepc, mie, mstatus, mtval, satp, sstatus};
+use riscv::register::{mcause, mepc, mie, mstatus, mtval, satp, sstatus};
 use riscv_decode::{Instruction, decode};
 use rustsbi::RustSBI;
 use sbi_spec::pmu::firmware_event;
 
+use crate::fail::unsupported_trap;
 use crate::platform::PLATFORM;
-use crate::riscv::csr::{CSR_TIME, CSR_TIMEH};
 use crate::riscv::current_hartid;
 use crate::sbi::console;
+use crate::sbi::crash::TrapRegisters;
 use crate::sbi::hsm::local_hsm;
 use crate::sbi::ipi;
 use crate::sbi::pmu::pmu_firmware_counter_increment;
 use crate::sbi::rfence;
+use crate::sbi::sta;
 
+use super::emulate::{emulate_atomic, emulate_csr_read, is_atomic};
 use super::helper::*;
+use super::misaligned::{
+    decode_compressed_load, decode_compressed_store, decode_vector, emulate_vector,
+};
 
 #[inline]
 pub fn switch(mut ctx: FastContext, start_addr: usize, opaque: usize) -> FastResult {
@@ -47,6 +53,16 @@ pub fn msoft_ipi_handler() {
     }
 }
 
+/// Handle machine external interrupts, forwarding IPIs to the software interrupt handler.
+#[inline]
+pub fn mext_handler(ctx: FastContext) -> FastResult {
+    if ipi::claim_external_ipi() {
+        msoft_handler(ctx)
+    } else {
+        ctx.restore()
+    }
+}
+
 #[inline]
 pub fn msoft_handler(ctx: FastContext) -> FastResult {
     match local_hsm().start() {
@@ -70,9 +86,9 @@ pub fn msoft_handler(ctx: FastContext) -> FastResult {
             riscv::asm::wfi();
             ctx.restore()
         }
-        // Handle IPI and RFence
+        // Handle IPI and RFence, which other harts request
         _ => {
-            msoft_ipi_handler();
+            sta::charge_steal_time(msoft_ipi_handler);
             ctx.restore()
         }
     }
@@ -104,6 +120,11 @@ pub fn sbi_call_handler(
             {
                 return switch(ctx, a1, a2);
             }
+            // Handle nested acceleration synchronized SRET
+            #[cfg(feature = "hypervisor")]
+            (sbi_spec::nacl::EID_NACL, sbi_spec::nacl::SYNC_SRET) => {
+                return ctx.continue_with(nacl_sync_sret_handler, ());
+            }
             // Handle legacy console probe
             (base::EID_BASE, base::PROBE_EXTENSION)
                 if matches!(
@@ -137,11 +158,16 @@ pub fn sbi_call_handler(
 /// Delegate trap handling to supervisor mode.
 #[inline]
 pub fn delegate(ctx: &mut EntireContextSeparated) {
-    use riscv::register::{mcause, scause, sepc, sstatus, stval, stvec};
+    redirect(ctx, mcause::read().bits(), mtval::read());
+}
+
+/// Raise exception `cause` with trap value `tval` to supervisor mode.
+pub fn redirect(ctx: &mut EntireContextSeparated, cause: usize, tval: usize) {
+    use riscv::register::{scause, sepc, sstatus, stval, stvec};
     unsafe {
         sepc::write(ctx.regs().pc);
-        scause::write(mcause::read().bits());
-        stval::write(mtval::read());
+        scause::write(cause);
+        stval::write(tval);
         sstatus::clear_sie();
         if mstatus::read().mpp() == mstatus::MPP::Supervisor {
             sstatus::set_spp(sstatus::SPP::Supervisor);
@@ -153,37 +179,57 @@ pub fn delegate(ctx: &mut EntireContextSeparated) {
     }
 }
 
-/// Handle illegal instructions, particularly CSR access.
+/// Restore registers from the nested SRET context and emulate SRET.
+#[cfg(feature = "hypervisor")]
+#[inline]
+pub extern "C" fn nacl_sync_sret_handler(raw_ctx: EntireContext) -> EntireResult {
+    use crate::sbi::nacl;
+    let mut ctx = raw_ctx.split().0;
+    for reg_id in 1..32 {
+        save_reg_x(&mut ctx, reg_id, nacl::sret_context_reg(reg_id));
+    }
+    nacl::emulate_sret();
+    ctx.restore()
+}
+
+/// Report traps the firmware can not handle, with the whole trapped context.
+pub extern "C" fn unsupported_trap_handler(raw_ctx: EntireContext) -> EntireResult {
+    let mut ctx = raw_ctx.split().0;
+    let mut regs = TrapRegisters {
+        x: [0; 32],
+        pc: ctx.regs().pc,
+    };
+    for reg_id in 1..32 {
+        regs.x[reg_id] = get_reg_x(&mut ctx, reg_id);
+    }
+    unsupported_trap(mcause::read().cause().try_into().ok(), &regs)
+}
+
+/// Handle illegal instructions, emulating CSRs and instructions the hart lacks.
 #[inline]
 pub extern "C" fn illegal_instruction_handler(raw_ctx: EntireContext) -> EntireResult {
     let mut ctx = raw_ctx.split().0;
 
-    let inst = decode(mtval::read() as u32);
-    match inst {
-        Ok(Instruction::Csrrs(csr)) => match csr.csr() as u16 {
-            CSR_TIME => {
-                save_reg_x(
-                    &mut ctx,
-                    csr.rd() as usize,
-                    unsafe { PLATFORM.sbi.ipi.as_ref() }.unwrap().get_time(),
-                );
-            }
-            CSR_TIMEH => {
-                save_reg_x(
-                    &mut ctx,
-                    csr.rd() as usize,
-                    unsafe { PLATFORM.sbi.ipi.as_ref() }.unwrap().get_timeh(),
-                );
-            }
-            _ => {
-                delegate(&mut ctx);
+    let raw_inst = mtval::read() as u32;
+    let emulated = match decode(raw_inst) {
+        Ok(Instruction::Csrrs(csr)) | Ok(Instruction::Csrrc(csr)) if csr.rs1() == 0 => {
+            emulate_csr_read(&mut ctx, csr.csr() as u16, csr.rd() as usize)
+        }
+        Ok(Instruction::Csrrsi(csr)) | Ok(Instruction::Csrrci(csr)) if csr.zimm() == 0 => {
+            emulate_csr_read(&mut ctx, csr.csr() as u16, csr.rd() as usize)
+        }
+        _ if is_atomic(raw_inst) => match emulate_atomic(&mut ctx, raw_inst) {
+            Ok(emulated) => emulated,
+            Err(exception) => {
+                redirect(&mut ctx, exception.cause, exception.tval);
                 return ctx.restore();
             }
         },
-        _ => {
-            delegate(&mut ctx);
-            return ctx.restore();
-        }
+        _ => false,
+    };
+    if !emulated {
+        delegate(&mut ctx);
+        return ctx.restore();
     }
     let epc = mepc::read();
     mepc::write(epc + get_inst(epc).1);
@@ -193,6 +239,7 @@ pub extern "C" fn illegal_instruction_handler(raw_ctx: EntireContext) -> EntireR
 #[inline]
 pub extern "C" fn load_misaligned_handler(ctx: EntireContext) -> EntireResult {
     let mut ctx = ctx.split().0;
+    let current_cause = mcause::read().bits();
     let current_pc = mepc::read();
     let current_addr = mtval::read();
 
@@ -201,22 +248,37 @@ pub extern "C" fn load_misaligned_handler(ctx: EntireContext) -> EntireResult {
         "Misaligned load: inst/{:x?}, load {:x?} in {:x?}",
         current_inst, current_addr, current_pc
     );
-    let decode_result = decode(current_inst as u32);
+    if let Some(access) = decode_vector(current_inst as u32).filter(|access| !access.is_store) {
+        if !emulate_vector(&mut ctx, &access) {
+            // The access can not be emulated, raise the exception to supervisor mode.
+            redirect(&mut ctx, current_cause, current_addr);
+            return ctx.restore();
+        }
+        mepc::write(current_pc + inst_len);
+        return ctx.restore();
+    }
 
-    // TODO: INST FLD c.*sp
     // TODO: maybe can we reduce the time to update csr for read virtual-address.
-    let inst_type = match decode_result {
-        Ok(Instruction::Lb(data)) => (data.rd(), VarType::Signed, 1),
-        Ok(Instruction::Lbu(data)) => (data.rd(), VarType::UnSigned, 1),
-        Ok(Instruction::Lh(data)) => (data.rd(), VarType::Signed, 2),
-        Ok(Instruction::Lhu(data)) => (data.rd(), VarType::UnSigned, 2),
-        Ok(Instruction::Lw(data)) => (data.rd(), VarType::Signed, 4),
-        Ok(Instruction::Lwu(data)) => (data.rd(), VarType::UnSigned, 4),
-        Ok(Instruction::Ld(data)) => (data.rd(), VarType::Signed, 8),
-        Ok(Instruction::Flw(data)) => (data.rd(), VarType::Float, 4),
-        _ => panic!("Unsupported inst"),
+    let inst_type = if inst_len == 2 {
+        decode_compressed_load(current_inst as u16)
+    } else {
+        match decode(current_inst as u32) {
+            Ok(Instruction::Lb(data)) => Some((data.rd() as usize, VarType::Signed, 1)),
+            Ok(Instruction::Lbu(data)) => Some((data.rd() as usize, VarType::UnSigned, 1)),
+            Ok(Instruction::Lh(data)) => Some((data.rd() as usize, VarType::Signed, 2)),
+            Ok(Instruction::Lhu(data)) => Some((data.rd() as usize, VarType::UnSigned, 2)),
+            Ok(Instruction::Lw(data)) => Some((data.rd() as usize, VarType::Signed, 4)),
+            Ok(Instruction::Lwu(data)) => Some((data.rd() as usize, VarType::UnSigned, 4)),
+            Ok(Instruction::Ld(data)) => Some((data.rd() as usize, VarType::Signed, 8)),
+            Ok(Instruction::Flw(data)) => Some((data.rd() as usize, VarType::Float, 4)),
+            Ok(Instruction::Fld(data)) => Some((data.rd() as usize, VarType::Float, 8)),
+            _ => None,
+        }
+    };
+    let Some((target_reg, var_type, len)) = inst_type else {
+        redirect(&mut ctx, current_cause, current_addr);
+        return ctx.restore();
     };
-    let (target_reg, var_type, len) = inst_type;
     let raw_data = get_data(current_addr, len);
     let read_data = match var_type {
         VarType::Signed => match len {
@@ -244,8 +306,8 @@ pub extern "C" fn load_misaligned_handler(ctx: EntireContext) -> EntireResult {
         read_data, current_addr, target_reg, len
     );
     match var_type {
-        VarType::Signed | VarType::UnSigned => save_reg_x(&mut ctx, target_reg as usize, read_data),
-        VarType::Float => set_reg_f(target_reg as usize, len, read_data),
+        VarType::Signed | VarType::UnSigned => save_reg_x(&mut ctx, target_reg, read_data),
+        VarType::Float => set_reg_f(target_reg, len, read_data),
     };
     mepc::write(current_pc + inst_len);
     ctx.restore()
@@ -254,6 +316,7 @@ pub extern "C" fn load_misaligned_handler(ctx: EntireContext) -> EntireResult {
 #[inline]
 pub extern "C" fn store_misaligned_handler(ctx: EntireContext) -> EntireResult {
     let mut ctx = ctx.split().0;
+    let current_cause = mcause::read().bits();
     let current_pc = mepc::read();
     let current_addr = mtval::read();
 
@@ -263,22 +326,37 @@ pub extern "C" fn store_misaligned_handler(ctx: EntireContext) -> EntireResult {
         current_inst, current_addr, current_pc
     );
 
-    let decode_result = decode(current_inst as u32);
+    if let Some(access) = decode_vector(current_inst as u32).filter(|access| access.is_store) {
+        if !emulate_vector(&mut ctx, &access) {
+            // The access can not be emulated, raise the exception to supervisor mode.
+            redirect(&mut ctx, current_cause, current_addr);
+            return ctx.restore();
+        }
+        mepc::write(current_pc + inst_len);
+        return ctx.restore();
+    }
 
-    // TODO: INST FSD c.*sp
     // TODO: maybe can we reduce the time to update csr for read virtual-address.
-    let inst_type = match decode_result {
-        Ok(Instruction::Sb(data)) => (data.rs2(), VarType::UnSigned, 1),
-        Ok(Instruction::Sh(data)) => (data.rs2(), VarType::UnSigned, 2),
-        Ok(Instruction::Sw(data)) => (data.rs2(), VarType::UnSigned, 4),
-        Ok(Instruction::Sd(data)) => (data.rs2(), VarType::UnSigned, 8),
-        Ok(Instruction::Fsw(data)) => (data.rs2(), VarType::Float, 4),
-        _ => panic!("Unsupported inst"),
+    let inst_type = if inst_len == 2 {
+        decode_compressed_store(current_inst as u16)
+    } else {
+        match decode(current_inst as u32) {
+            Ok(Instruction::Sb(data)) => Some((data.rs2() as usize, VarType::UnSigned, 1)),
+            Ok(Instruction::Sh(data)) => Some((data.rs2() as usize, VarType::UnSigned, 2)),
+            Ok(Instruction::Sw(data)) => Some((data.rs2() as usize, VarType::UnSigned, 4)),
+            Ok(Instruction::Sd(data)) => Some((data.rs2() as usize, VarType::UnSigned, 8)),
+            Ok(Instruction::Fsw(data)) => Some((data.rs2() as usize, VarType::Float, 4)),
+            Ok(Instruction::Fsd(data)) => Some((data.rs2() as usize, VarType::Float, 8)),
+            _ => None,
+        }
+    };
+    let Some((target_reg, var_type, len)) = inst_type else {
+        redirect(&mut ctx, current_cause, current_addr);
+        return ctx.restore();
     };
-    let (target_reg, var_type, len) = inst_type;
     let raw_data = match var_type {
-        VarType::Signed | VarType::UnSigned => get_reg_x(&mut ctx, target_reg as usize),
-        VarType::Float => get_reg_f(target_reg as usize, len),
+        VarType::Signed | VarType::UnSigned => get_reg_x(&mut ctx, target_reg),
+        VarType::Float => get_reg_f(target_reg, len),
     };
 
     let read_data = match var_type {
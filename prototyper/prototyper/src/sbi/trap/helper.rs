@@ -1,5 +1,5 @@
 use core::arch::asm;
-use riscv::register::{mtvec, sscratch};
+use riscv::register::{mcause, mepc, mtvec, sscratch};
 
 use fast_trap::EntireContextSeparated;
 
@@ -93,6 +93,77 @@ pub fn save_byte(addr: usize, data: usize) {
     }
 }
 
+/// Loads a byte with the privilege of the trapped mode.
+///
+/// Returns the exception cause if the access faults.
+#[inline(never)]
+pub fn try_load_byte(addr: usize) -> Result<u8, usize> {
+    let data: usize;
+    let faulted: usize;
+    unsafe {
+        let prev_mtvec = mtvec::read().bits();
+        let prev_mepc = mepc::read();
+        mtvec::write(
+            crate::sbi::early_trap::light_expected_trap as _,
+            mtvec::TrapMode::Direct,
+        );
+        asm!(
+            ".option push",
+            ".option norvc",
+            "csrrs t3, mstatus, t3",
+            "lbu t0, 0(t1)",
+            "csrw mstatus, t3",
+            ".option pop",
+            in("t1") addr,
+            inout("t3") MPRV_BIT => _,
+            out("t0") data,
+            inout("a0") 0usize => faulted,
+            out("a1") _,
+        );
+        asm!("csrw mtvec, {}", in(reg) prev_mtvec);
+        if faulted != 0 {
+            mepc::write(prev_mepc);
+            return Err(mcause::read().bits());
+        }
+    }
+    Ok(data as u8)
+}
+
+/// Stores a byte with the privilege of the trapped mode.
+///
+/// Returns the exception cause if the access faults.
+#[inline(never)]
+pub fn try_store_byte(addr: usize, data: u8) -> Result<(), usize> {
+    let faulted: usize;
+    unsafe {
+        let prev_mtvec = mtvec::read().bits();
+        let prev_mepc = mepc::read();
+        mtvec::write(
+            crate::sbi::early_trap::light_expected_trap as _,
+            mtvec::TrapMode::Direct,
+        );
+        asm!(
+            ".option push",
+            ".option norvc",
+            "csrrs t3, mstatus, t3",
+            "sb t0, 0(t1)",
+            "csrw mstatus, t3",
+            ".option pop",
+            in("t0") data as usize,
+            in("t1") addr,
+            inout("t3") MPRV_BIT => _,
+            inout("a0") 0usize => faulted,
+            out("a1") _,
+        );
+        asm!("csrw mtvec, {}", in(reg) prev_mtvec);
+        if faulted != 0 {
+            mepc::write(prev_mepc);
+            return Err(mcause::read().bits());
+        }
+    }
+    Ok(())
+}
+
 #[inline(always)]
 pub fn get_data(addr: usize, len: usize) -> usize {
     let mut data: usize = 0;
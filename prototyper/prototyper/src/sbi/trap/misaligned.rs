@@ -0,0 +1,262 @@
+use core::arch::asm;
+
+use fast_trap::EntireContextSeparated;
+
+use super::helper::*;
+
+/// Widest vector register the emulation can buffer, in bytes (VLEN = 4096).
+const MAX_VLENB: usize = 512;
+
+/// Decodes a compressed load into its target register, value type and length.
+pub fn decode_compressed_load(inst: u16) -> Option<(usize, VarType, usize)> {
+    let rd_prime = ((inst >> 2) & 0b111) as usize + 8;
+    let rd = ((inst >> 7) & 0b1_1111) as usize;
+    match (inst & 0b11, inst >> 13) {
+        // c.fld
+        (0b00, 0b001) => Some((rd_prime, VarType::Float, 8)),
+        // c.lw
+        (0b00, 0b010) => Some((rd_prime, VarType::Signed, 4)),
+        // c.ld
+        (0b00, 0b011) => Some((rd_prime, VarType::Signed, 8)),
+        // c.fldsp
+        (0b10, 0b001) => Some((rd, VarType::Float, 8)),
+        // c.lwsp
+        (0b10, 0b010) if rd != 0 => Some((rd, VarType::Signed, 4)),
+        // c.ldsp
+        (0b10, 0b011) if rd != 0 => Some((rd, VarType::Signed, 8)),
+        _ => None,
+    }
+}
+
+/// Decodes a compressed store into its source register, value type and length.
+pub fn decode_compressed_store(inst: u16) -> Option<(usize, VarType, usize)> {
+    let rs2_prime = ((inst >> 2) & 0b111) as usize + 8;
+    let rs2 = ((inst >> 2) & 0b1_1111) as usize;
+    match (inst & 0b11, inst >> 13) {
+        // c.fsd
+        (0b00, 0b101) => Some((rs2_prime, VarType::Float, 8)),
+        // c.sw
+        (0b00, 0b110) => Some((rs2_prime, VarType::UnSigned, 4)),
+        // c.sd
+        (0b00, 0b111) => Some((rs2_prime, VarType::UnSigned, 8)),
+        // c.fsdsp
+        (0b10, 0b101) => Some((rs2, VarType::Float, 8)),
+        // c.swsp
+        (0b10, 0b110) => Some((rs2, VarType::UnSigned, 4)),
+        // c.sdsp
+        (0b10, 0b111) => Some((rs2, VarType::UnSigned, 8)),
+        _ => None,
+    }
+}
+
+/// Addressing mode of a vector load or store.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum VectorMode {
+    UnitStride,
+    Strided,
+    WholeRegister,
+    Mask,
+}
+
+/// Vector load or store decoded from the LOAD-FP or STORE-FP opcode.
+pub struct VectorAccess {
+    pub is_store: bool,
+    pub mode: VectorMode,
+    /// Destination (load) or source (store) register group.
+    pub vd: usize,
+    /// Base address register.
+    pub rs1: usize,
+    /// Stride register of strided accesses.
+    pub rs2: usize,
+    pub masked: bool,
+    /// Number of fields per segment.
+    pub nf: usize,
+    /// Effective element width in bytes.
+    pub eew: usize,
+}
+
+/// Decodes unit-stride, strided, whole register and mask vector loads and stores.
+pub fn decode_vector(inst: u32) -> Option<VectorAccess> {
+    let is_store = match inst & 0x7f {
+        0b000_0111 => false,
+        0b010_0111 => true,
+        _ => return None,
+    };
+    let eew = match (inst >> 12) & 0b111 {
+        0b000 => 1,
+        0b101 => 2,
+        0b110 => 4,
+        0b111 => 8,
+        _ => return None,
+    };
+    // Extended element widths are reserved.
+    if (inst >> 28) & 1 != 0 {
+        return None;
+    }
+    let lumop = ((inst >> 20) & 0b1_1111) as usize;
+    let mode = match ((inst >> 26) & 0b11, lumop) {
+        // Fault-only-first loads behave as unit-stride once an element traps.
+        (0b00, 0b0_0000) => VectorMode::UnitStride,
+        (0b00, 0b1_0000) if !is_store => VectorMode::UnitStride,
+        (0b00, 0b0_1000) => VectorMode::WholeRegister,
+        (0b00, 0b0_1011) => VectorMode::Mask,
+        (0b10, _) => VectorMode::Strided,
+        _ => return None,
+    };
+    Some(VectorAccess {
+        is_store,
+        mode,
+        vd: ((inst >> 7) & 0b1_1111) as usize,
+        rs1: ((inst >> 15) & 0b1_1111) as usize,
+        rs2: lumop,
+        masked: (inst >> 25) & 1 == 0,
+        nf: ((inst >> 29) & 0b111) as usize + 1,
+        eew,
+    })
+}
+
+/// Single vector register cached in memory.
+struct VectorRegister {
+    buf: [u8; MAX_VLENB],
+    index: Option<usize>,
+    dirty: bool,
+}
+
+impl VectorRegister {
+    const fn new() -> Self {
+        Self {
+            buf: [0; MAX_VLENB],
+            index: None,
+            dirty: false,
+        }
+    }
+
+    /// Loads register `index` into the buffer, writing back the previous one if modified.
+    fn select(&mut self, index: usize) {
+        if self.index != Some(index) {
+            self.flush();
+            read_vreg(index, &mut self.buf);
+            self.index = Some(index);
+        }
+    }
+
+    /// Writes the buffer back to its register if modified.
+    fn flush(&mut self) {
+        if let (Some(index), true) = (self.index, self.dirty) {
+            write_vreg(index, &self.buf);
+        }
+        self.dirty = false;
+    }
+}
+
+/// Emulates a misaligned vector access element by element, from `vstart` to the end.
+///
+/// Returns false if the vector registers are too wide to be emulated, or the register
+/// group runs past `v31`.
+pub fn emulate_vector(ctx: &mut EntireContextSeparated, access: &VectorAccess) -> bool {
+    let (vstart, vl, vtype, vlenb): (usize, usize, usize, usize);
+    unsafe {
+        asm!("csrr {}, 0x008", out(reg) vstart);
+        asm!("csrr {}, 0xc20", out(reg) vl);
+        asm!("csrr {}, 0xc21", out(reg) vtype);
+        asm!("csrr {}, 0xc22", out(reg) vlenb);
+    }
+    if vlenb > MAX_VLENB {
+        return false;
+    }
+
+    let eew = access.eew;
+    // Register groups of each field, from EMUL = EEW / SEW * LMUL.
+    let lmul_eighths = match vtype & 0b111 {
+        0b000 => 8,
+        0b001 => 16,
+        0b010 => 32,
+        0b011 => 64,
+        0b101 => 1,
+        0b110 => 2,
+        _ => 4,
+    };
+    let sew = 1 << ((vtype >> 3) & 0b111);
+    let emul = (lmul_eighths * eew / sew / 8).max(1);
+
+    let base = get_reg_x(ctx, access.rs1);
+    let (evl, fields, stride) = match access.mode {
+        VectorMode::UnitStride => (vl, access.nf, access.nf * eew),
+        VectorMode::Strided => (vl, access.nf, get_reg_x(ctx, access.rs2)),
+        VectorMode::WholeRegister => (access.nf * vlenb / eew, 1, eew),
+        VectorMode::Mask => (vl.div_ceil(8), 1, eew),
+    };
+    let registers = match access.mode {
+        VectorMode::WholeRegister => access.nf,
+        VectorMode::Mask => 1,
+        _ => fields * emul,
+    };
+    if access.vd + registers > 32 {
+        return false;
+    }
+    let masked =
+        access.masked && matches!(access.mode, VectorMode::UnitStride | VectorMode::Strided);
+
+    // Whole register moves start at `vstart`, the saved value is used from here on.
+    unsafe { asm!("csrw 0x008, zero") };
+    let mut mask = VectorRegister::new();
+    if masked {
+        mask.select(0);
+    }
+    let mut data = VectorRegister::new();
+    for i in vstart..evl {
+        if masked && mask.buf[i / 8] & (1 << (i % 8)) == 0 {
+            continue;
+        }
+        for field in 0..fields {
+            let addr = base
+                .wrapping_add(i.wrapping_mul(stride))
+                .wrapping_add(field * eew);
+            let offset = i * eew;
+            data.select(access.vd + field * emul + offset / vlenb);
+            let element = &mut data.buf[offset % vlenb..offset % vlenb + eew];
+            if access.is_store {
+                for (j, byte) in element.iter().enumerate() {
+                    save_byte(addr + j, *byte as usize);
+                }
+            } else {
+                for (j, byte) in element.iter_mut().enumerate() {
+                    *byte = get_unsigned_byte(addr + j);
+                }
+                data.dirty = true;
+            }
+        }
+    }
+    data.flush();
+    true
+}
+
+fn read_vreg(index: usize, buf: &mut [u8; MAX_VLENB]) {
+    let ptr = buf.as_mut_ptr();
+    seq_macro::seq!(N in 0..32 {
+        match index {
+            #(
+                N => unsafe {
+                    asm!(".option push", ".option arch, +v", "vs1r.v v{x}, ({ptr})", ".option pop",
+                        x = const N, ptr = in(reg) ptr, options(nostack))
+                },
+            )*
+            _ => unreachable!()
+        }
+    });
+}
+
+fn write_vreg(index: usize, buf: &[u8; MAX_VLENB]) {
+    let ptr = buf.as_ptr();
+    seq_macro::seq!(N in 0..32 {
+        match index {
+            #(
+                N => unsafe {
+                    asm!(".option push", ".option arch, +v", "vl1r.v v{x}, ({ptr})", ".option pop",
+                        x = const N, ptr = in(reg) ptr, options(nostack))
+                },
+            )*
+            _ => unreachable!()
+        }
+    });
+}
@@ -1,9 +1,10 @@
 pub mod boot;
 pub mod handler;
 
+mod emulate;
 mod helper;
+mod misaligned;
 use super::pmu::pmu_firmware_counter_increment;
-use crate::fail::unsupported_trap;
 
 use fast_trap::{FastContext, FastResult};
 use riscv::interrupt::machine::{Exception, Interrupt};
@@ -39,6 +40,11 @@ pub extern "C" fn fast_handler(
                     save_regs(&mut ctx);
                     handler::msoft_handler(ctx)
                 }
+                // Handle MExternal, which carries IPIs on platforms with IMSIC
+                Trap::Interrupt(Interrupt::MachineExternal) => {
+                    save_regs(&mut ctx);
+                    handler::mext_handler(ctx)
+                }
                 // Handle MTimer
                 Trap::Interrupt(Interrupt::MachineTimer) => {
                     use crate::sbi::ipi;
@@ -73,13 +79,17 @@ pub extern "C" fn fast_handler(
                     save_regs(&mut ctx);
                     ctx.continue_with(handler::store_misaligned_handler, ())
                 }
-                // Handle other traps
-                trap => unsupported_trap(Some(trap)),
+                // Report other traps with the whole context
+                _ => {
+                    save_regs(&mut ctx);
+                    ctx.continue_with(handler::unsupported_trap_handler, ())
+                }
             }
         }
         Err(err) => {
             error!("Failed to parse mcause: {:?}", err);
-            unsupported_trap(None);
+            save_regs(&mut ctx);
+            ctx.continue_with(handler::unsupported_trap_handler, ())
         }
     }
 }
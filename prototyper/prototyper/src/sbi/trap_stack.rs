@@ -3,6 +3,7 @@ use crate::riscv::current_hartid;
 use crate::sbi::hart_context::HartContext;
 use crate::sbi::trap::fast_handler;
 use core::mem::forget;
+use core::ops::Range;
 use fast_trap::FreeTrapStack;
 
 /// Root stack array for all harts, placed in BSS Stack section.
@@ -43,6 +44,12 @@ pub(crate) fn prepare_for_trap() {
     };
 }
 
+/// Gets the memory range of the stack of `hart_id`.
+pub(crate) fn stack_range(hart_id: usize) -> Option<Range<usize>> {
+    let start = unsafe { ROOT_STACK.get(hart_id)? }.0.as_ptr() as usize;
+    Some(start..start + STACK_SIZE_PER_HART)
+}
+
 pub fn hart_context_mut(hart_id: usize) -> &'static mut HartContext {
     unsafe { ROOT_STACK.get_mut(hart_id).unwrap().hart_context_mut() }
 }
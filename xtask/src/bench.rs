@@ -1,12 +1,15 @@
 use std::{
+    collections::HashMap,
     env, fs,
     path::{Path, PathBuf},
     process::{Command, ExitStatus},
+    time::Duration,
 };
 
 use clap::Args;
+use serde::{Deserialize, Serialize};
 
-use crate::utils::cargo;
+use crate::utils::{cargo, failure, qemu::Qemu};
 
 #[derive(Debug, Args, Clone)]
 pub struct BenchArg {
@@ -16,33 +19,109 @@ pub struct BenchArg {
         help = "Create a combined image with Prototyper and bench kernel"
     )]
     pub pack: bool,
+
+    /// Boot the bench kernel in QEMU and collect its report
+    #[clap(long)]
+    pub run: bool,
+
+    /// Number of harts of the QEMU machine
+    #[clap(long, default_value_t = 4)]
+    pub smp: usize,
+
+    /// Prototyper firmware to boot the bench kernel with [default: dynamic firmware in target]
+    #[clap(long)]
+    pub firmware: Option<PathBuf>,
+
+    /// Report file to write [default: rustsbi-bench-kernel.json in target]
+    #[clap(long, short = 'o')]
+    pub output: Option<PathBuf>,
+
+    /// Report file to compare against, implies --run
+    #[clap(long)]
+    pub baseline: Option<PathBuf>,
+
+    /// Slowdown of a median in percent reported as a regression
+    #[clap(long, default_value_t = 10.0)]
+    pub threshold: f64,
+
+    /// Seconds to wait for the bench kernel to shut down
+    #[clap(long, default_value_t = 300)]
+    pub timeout: u64,
 }
 
 const ARCH: &str = "riscv64imac-unknown-none-elf";
 const BENCH_KERNEL_NAME: &str = "rustsbi-bench-kernel";
 const PROTOTYPER_BIN: &str = "rustsbi-prototyper.bin";
+const PROTOTYPER_ARCH: &str = "riscv64gc-unknown-none-elf";
+const PROTOTYPER_DYNAMIC_ELF: &str = "rustsbi-prototyper-dynamic.elf";
+/// Lines around the JSON report printed by the bench kernel.
+const REPORT_BEGIN: &str = "==== rustsbi-bench-kernel report begin ====";
+const REPORT_END: &str = "==== rustsbi-bench-kernel report end ====";
+const REPORT_SCHEMA: u32 = 1;
+
+/// Benchmark report printed by the bench kernel.
+#[derive(Debug, Serialize, Deserialize)]
+struct BenchReport {
+    schema: u32,
+    timebase_frequency: u64,
+    harts: usize,
+    samples: usize,
+    results: Vec<BenchResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BenchResult {
+    name: String,
+    /// Number of target harts, for cases scaling with it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    harts: Option<usize>,
+    /// Reason the case did not run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    skipped: Option<String>,
+    /// `ns` per call, or `B/s`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    median: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mean: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<u64>,
+}
+
+impl BenchResult {
+    fn key(&self) -> String {
+        match self.harts {
+            Some(harts) => format!("{}[{}]", self.name, harts),
+            None => self.name.clone(),
+        }
+    }
+}
 
 #[must_use]
 pub fn run(arg: &BenchArg) -> Option<ExitStatus> {
     let current_dir = env::current_dir().ok()?;
     let target_dir = get_target_dir(&current_dir);
 
-    // Build the bench kernel
-    info!("Building bench kernel");
-    let build_status = build_bench_kernel()?;
-    if !build_status.success() {
-        error!("Failed to build bench kernel");
-        return Some(build_status);
-    }
-
-    // Convert to binary format
-    info!("Converting to binary format");
-    let exit_status = convert_to_binary(&target_dir)?;
+    let exit_status = build(&target_dir)?;
     if !exit_status.success() {
-        error!("Failed to convert bench kernel to binary format");
         return Some(exit_status);
     }
 
+    // Run in QEMU and compare against the baseline if requested
+    if arg.run || arg.baseline.is_some() {
+        match run_bench(arg, &current_dir, &target_dir) {
+            Ok(true) => {}
+            Ok(false) => return failure(),
+            Err(err_msg) => {
+                error!("{}", err_msg);
+                return failure();
+            }
+        }
+    }
+
     // Pack into image if requested
     if arg.pack {
         info!("Packing into image");
@@ -58,11 +137,7 @@ pub fn run(arg: &BenchArg) -> Option<ExitStatus> {
             }
             Err(err_msg) => {
                 error!("{}", err_msg);
-                // TODO cross-platform ExitStatus return value
-                #[cfg(unix)]
-                return Some(<ExitStatus as std::os::unix::process::ExitStatusExt>::from_raw(1));
-                #[cfg(not(unix))]
-                return None;
+                return failure();
             }
         }
     } else {
@@ -77,6 +152,196 @@ pub fn run(arg: &BenchArg) -> Option<ExitStatus> {
     Some(exit_status)
 }
 
+/// Boots the bench kernel, saves its report and compares it against the baseline.
+///
+/// Returns `false` if a benchmark regressed.
+fn run_bench(arg: &BenchArg, current_dir: &Path, target_dir: &Path) -> Result<bool, String> {
+    let firmware = arg.firmware.clone().unwrap_or_else(|| {
+        current_dir
+            .join("target")
+            .join(PROTOTYPER_ARCH)
+            .join("release")
+            .join(PROTOTYPER_DYNAMIC_ELF)
+    });
+    if !firmware.exists() {
+        return Err(format!(
+            "Error: Prototyper firmware not found at '{}'\n\
+             Please run 'cargo prototyper' first to build the Prototyper firmware.",
+            firmware.display()
+        ));
+    }
+
+    info!("Running bench kernel in QEMU with {} harts", arg.smp);
+    let output = Qemu::new()
+        .bios(&firmware)
+        .kernel(target_dir.join(format!("{}.bin", BENCH_KERNEL_NAME)))
+        .smp(arg.smp)
+        .capture(Duration::from_secs(arg.timeout))
+        .map_err(|e| format!("Failed to run qemu-system-riscv64: {}", e))?;
+    if !output.status.success() {
+        warn!("QEMU exited with {}", output.status);
+    }
+    if output.timed_out {
+        return Err(format!(
+            "Bench kernel did not shut down within {} seconds",
+            arg.timeout
+        ));
+    }
+
+    let report = parse_report(&output.serial)?;
+    let output_path = arg
+        .output
+        .clone()
+        .unwrap_or_else(|| target_dir.join(format!("{}.json", BENCH_KERNEL_NAME)));
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("Failed to serialize report: {}", e))?;
+    fs::write(&output_path, json + "\n").map_err(|e| format!("Failed to write report: {}", e))?;
+    info!("Benchmark report written to: {}", output_path.display());
+
+    match &arg.baseline {
+        Some(baseline_path) => {
+            let baseline = fs::read_to_string(baseline_path)
+                .map_err(|e| format!("Failed to read baseline file: {}", e))?;
+            let baseline: BenchReport = serde_json::from_str(&baseline)
+                .map_err(|e| format!("Failed to parse baseline file: {}", e))?;
+            Ok(compare(&baseline, &report, arg.threshold))
+        }
+        None => Ok(true),
+    }
+}
+
+/// Extracts the JSON report from the serial output.
+fn parse_report(serial: &str) -> Result<BenchReport, String> {
+    let json: String = serial
+        .lines()
+        .skip_while(|line| !line.contains(REPORT_BEGIN))
+        .skip(1)
+        .take_while(|line| !line.contains(REPORT_END))
+        .collect();
+    if json.is_empty() {
+        return Err("Bench kernel printed no report".to_string());
+    }
+    let report: BenchReport =
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse report: {}", e))?;
+    if report.schema != REPORT_SCHEMA {
+        return Err(format!(
+            "Unsupported report schema {}, expected {}",
+            report.schema, REPORT_SCHEMA
+        ));
+    }
+    Ok(report)
+}
+
+/// Compares medians against the baseline, returning `false` on any regression.
+///
+/// Benchmarks measured in the baseline but skipped or missing in the report count as
+/// regressions.
+fn compare(baseline: &BenchReport, report: &BenchReport, threshold: f64) -> bool {
+    if baseline.harts != report.harts {
+        warn!(
+            "Baseline ran with {} harts, this run with {}",
+            baseline.harts, report.harts
+        );
+    }
+    let reported: HashMap<String, &BenchResult> = report
+        .results
+        .iter()
+        .map(|result| (result.key(), result))
+        .collect();
+    let baseline_results: HashMap<String, &BenchResult> = baseline
+        .results
+        .iter()
+        .map(|result| (result.key(), result))
+        .collect();
+
+    let mut regressions = 0;
+    for result in &report.results {
+        let key = result.key();
+        let Some(old_result) = baseline_results
+            .get(&key)
+            .filter(|result| result.median.is_some())
+        else {
+            info!("{:<40} {:>16} (no baseline)", key, format_median(result));
+            continue;
+        };
+        let old = old_result.median.unwrap();
+        let Some(new) = result.median else {
+            regressions += 1;
+            warn!(
+                "{:<40} {:>16} -> {:>16} regressed",
+                key,
+                format_median(old_result),
+                format_median(result)
+            );
+            continue;
+        };
+        let change = (new as f64 - old as f64) / old.max(1) as f64 * 100.0;
+        // Latencies regress when growing, throughputs when shrinking.
+        let slowdown = match result.unit.as_deref() {
+            Some("B/s") => -change,
+            _ => change,
+        };
+        let line = format!(
+            "{:<40} {:>16} -> {:>16} ({:+.1}%)",
+            key,
+            format_median(old_result),
+            format_median(result),
+            change
+        );
+        if slowdown > threshold {
+            regressions += 1;
+            warn!("{} regressed", line);
+        } else {
+            info!("{}", line);
+        }
+    }
+    for old_result in &baseline.results {
+        let key = old_result.key();
+        if old_result.median.is_some() && !reported.contains_key(&key) {
+            regressions += 1;
+            warn!(
+                "{:<40} {:>16} -> {:>16} regressed",
+                key,
+                format_median(old_result),
+                "missing"
+            );
+        }
+    }
+
+    if regressions > 0 {
+        error!(
+            "{} benchmarks regressed by more than {}% or stopped being measured",
+            regressions, threshold
+        );
+    }
+    regressions == 0
+}
+
+fn format_median(result: &BenchResult) -> String {
+    match (&result.skipped, result.median) {
+        (Some(reason), _) => format!("skipped: {}", reason),
+        (None, Some(median)) => format!("{} {}", median, result.unit.as_deref().unwrap_or("")),
+        (None, None) => "-".to_string(),
+    }
+}
+
+/// Builds the bench kernel and converts it to binary format in `target_dir`.
+pub fn build(target_dir: &Path) -> Option<ExitStatus> {
+    info!("Building bench kernel");
+    let build_status = build_bench_kernel()?;
+    if !build_status.success() {
+        error!("Failed to build bench kernel");
+        return Some(build_status);
+    }
+
+    info!("Converting to binary format");
+    let exit_status = convert_to_binary(target_dir)?;
+    if !exit_status.success() {
+        error!("Failed to convert bench kernel to binary format");
+    }
+    Some(exit_status)
+}
+
 fn get_target_dir(current_dir: &Path) -> PathBuf {
     current_dir.join("target").join(ARCH).join("release")
 }
@@ -155,3 +420,108 @@ fn pack_image(current_dir: &Path, target_dir: &Path) -> Result<ExitStatus, Strin
 
     Ok(status)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str, median: Option<u64>) -> BenchResult {
+        BenchResult {
+            name: name.to_string(),
+            harts: None,
+            skipped: median.is_none().then(|| "not supported".to_string()),
+            unit: median.map(|_| "ns".to_string()),
+            min: median,
+            median,
+            mean: median,
+            max: median,
+        }
+    }
+
+    fn report(results: Vec<BenchResult>) -> BenchReport {
+        BenchReport {
+            schema: REPORT_SCHEMA,
+            timebase_frequency: 10_000_000,
+            harts: 4,
+            samples: 100,
+            results,
+        }
+    }
+
+    #[test]
+    fn parse_report_between_markers() {
+        let serial = format!(
+            "[rustsbi] booting\n{}\n{{\"schema\": 1, \"timebase_frequency\": 10000000,\n\
+             \"harts\": 4, \"samples\": 100, \"results\": [\n\
+             {{\"name\": \"ipi\", \"harts\": 2, \"unit\": \"ns\", \"median\": 800}},\n\
+             {{\"name\": \"pmu\", \"skipped\": \"not supported\"}}]}}\n{}\nshutdown\n",
+            REPORT_BEGIN, REPORT_END
+        );
+        let report = parse_report(&serial).unwrap();
+        assert_eq!(report.harts, 4);
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(report.results[0].key(), "ipi[2]");
+        assert_eq!(report.results[0].median, Some(800));
+        assert_eq!(report.results[1].skipped.as_deref(), Some("not supported"));
+    }
+
+    #[test]
+    fn parse_report_rejects_missing_or_unknown() {
+        assert!(parse_report("no report here\n").is_err());
+        let serial = format!(
+            "{}\n{{\"schema\": 2, \"timebase_frequency\": 1, \"harts\": 1, \
+             \"samples\": 1, \"results\": []}}\n{}\n",
+            REPORT_BEGIN, REPORT_END
+        );
+        assert!(parse_report(&serial).is_err());
+    }
+
+    #[test]
+    fn compare_threshold() {
+        let baseline = report(vec![result("base", Some(1000))]);
+        assert!(compare(
+            &baseline,
+            &report(vec![result("base", Some(1050))]),
+            10.0
+        ));
+        assert!(!compare(
+            &baseline,
+            &report(vec![result("base", Some(1200))]),
+            10.0
+        ));
+    }
+
+    #[test]
+    fn compare_throughput_regresses_when_shrinking() {
+        let mut old = result("console", Some(1000));
+        old.unit = Some("B/s".to_string());
+        let mut faster = result("console", Some(1200));
+        faster.unit = Some("B/s".to_string());
+        let mut slower = result("console", Some(800));
+        slower.unit = Some("B/s".to_string());
+        let baseline = report(vec![old]);
+        assert!(compare(&baseline, &report(vec![faster]), 10.0));
+        assert!(!compare(&baseline, &report(vec![slower]), 10.0));
+    }
+
+    #[test]
+    fn compare_missing_or_skipped_regresses() {
+        let baseline = report(vec![result("base", Some(1000)), result("time", Some(500))]);
+        let missing = report(vec![result("base", Some(1000))]);
+        assert!(!compare(&baseline, &missing, 10.0));
+        let skipped = report(vec![result("base", Some(1000)), result("time", None)]);
+        assert!(!compare(&baseline, &skipped, 10.0));
+    }
+
+    #[test]
+    fn compare_new_or_previously_skipped_passes() {
+        let baseline = report(vec![result("base", Some(1000)), result("pmu", None)]);
+        let current = report(vec![
+            result("base", Some(1000)),
+            result("pmu", Some(300)),
+            result("sta", Some(200)),
+        ]);
+        assert!(compare(&baseline, &current, 10.0));
+        assert!(compare(&current, &current, 0.0));
+    }
+}
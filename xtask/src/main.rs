@@ -7,6 +7,7 @@ mod utils;
 mod bench;
 mod logger;
 mod prototyper;
+mod qemu;
 mod test;
 
 #[macro_use]
@@ -14,6 +15,7 @@ extern crate log;
 
 use crate::bench::BenchArg;
 use crate::prototyper::PrototyperArg;
+use crate::qemu::QemuArg;
 use crate::test::TestArg;
 
 #[derive(Parser)]
@@ -37,6 +39,8 @@ enum Cmd {
     Test(TestArg),
     /// Build bench-kernel for the RustSBI Prototyper.
     Bench(BenchArg),
+    /// Run test kernels in QEMU and check their serial output against golden patterns.
+    Qemu(QemuArg),
 }
 
 fn main() -> ExitCode {
@@ -51,6 +55,7 @@ fn main() -> ExitCode {
         Cmd::Prototyper(arg) => prototyper::run(arg),
         Cmd::Test(arg) => test::run(arg),
         Cmd::Bench(arg) => bench::run(arg),
+        Cmd::Qemu(arg) => qemu::run(arg),
     };
 
     match result {
@@ -63,6 +68,7 @@ fn main() -> ExitCode {
                 Cmd::Prototyper(_) => "prototyper",
                 Cmd::Test(_) => "test",
                 Cmd::Bench(_) => "bench",
+                Cmd::Qemu(_) => "qemu",
             };
             error!("Task '{}' failed with exit code: {}", cmd_name, exit_status);
             ExitCode::FAILURE
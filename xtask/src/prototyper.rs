@@ -49,6 +49,32 @@ pub fn run(arg: &PrototyperArg) -> Option<ExitStatus> {
     Some(exit_status)
 }
 
+/// Builds the dynamic firmware with extra `features`, copying its ELF to `elf_name` in
+/// the target directory so that `rustsbi-prototyper-dynamic.elf` is kept.
+#[must_use]
+pub fn build_variant(features: &[String], elf_name: &str) -> Option<ExitStatus> {
+    let arg = PrototyperArg {
+        features: features.to_vec(),
+        fdt: None,
+        payload: None,
+        jump: false,
+        config_file: None,
+        target: None,
+    };
+    let dirs = prepare_directories(&arg)?;
+    setup_config_file(&dirs.target_config_toml, &arg)?;
+
+    let exit_status = build_prototyper(&arg)?;
+    if !exit_status.success() {
+        return Some(exit_status);
+    }
+
+    let elf_dest = dirs.target_dir.join(elf_name);
+    info!("Copying ELF file to {}", elf_dest.display());
+    fs::copy(dirs.target_dir.join(PACKAGE_NAME), &elf_dest).ok()?;
+    Some(exit_status)
+}
+
 struct Directories {
     target_dir: PathBuf,
     target_config_toml: PathBuf,
@@ -116,10 +142,11 @@ fn build_prototyper(arg: &PrototyperArg) -> Option<ExitStatus> {
     info!("Building Prototyper");
 
     let enable_h = arg.features.iter().any(|f| f == "hypervisor");
+    // Frame pointers let crash reports unwind the firmware stack.
     let rustflags = if enable_h {
-        "-C relocation-model=pie -C link-arg=-pie -C target-feature=+h"
+        "-C relocation-model=pie -C link-arg=-pie -C force-frame-pointers=yes -C target-feature=+h"
     } else {
-        "-C relocation-model=pie -C link-arg=-pie"
+        "-C relocation-model=pie -C link-arg=-pie -C force-frame-pointers=yes"
     };
 
     let arch = arg.target.as_deref().unwrap_or(ARCH);
@@ -156,6 +183,10 @@ fn build_prototyper(arg: &PrototyperArg) -> Option<ExitStatus> {
     let elf_path = target_dir.join(PACKAGE_NAME);
     let bin_path = target_dir.join(format!("{}.bin", PACKAGE_NAME));
 
+    if embed_symbol_table(&elf_path).is_none() {
+        warn!("Failed to embed symbol table, crash reports will not be symbolized");
+    }
+
     // Create binary from ELF
     info!("Converting ELF to binary with rust-objcopy");
     let result = Command::new("rust-objcopy")
@@ -183,6 +214,103 @@ fn build_prototyper(arg: &PrototyperArg) -> Option<ExitStatus> {
     result
 }
 
+/// Placeholder of the firmware symbol table, see `sbi/crash.rs` of the prototyper.
+const SYMBOL_TABLE_PLACEHOLDER: &[u8; 16] = b"RUSTSBI-NOSYMTAB";
+const SYMBOL_TABLE_MAGIC: &[u8; 16] = b"RUSTSBI-SYMTAB\0\0";
+const SYMBOL_TABLE_SIZE: usize = 32 * 1024;
+/// Longest symbol name kept, longer ones are truncated.
+const SYMBOL_NAME_MAX: usize = 96;
+
+/// Fills the symbol table of the firmware ELF with its text symbols.
+fn embed_symbol_table(elf_path: &Path) -> Option<()> {
+    info!("Embedding symbol table with rust-nm");
+    let output = Command::new("rust-nm")
+        .args(["--defined-only", "--numeric-sort", "--demangle"])
+        .arg(elf_path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let symbols = String::from_utf8(output.stdout).ok()?;
+    let mut sbi_start = None;
+    let mut text = Vec::new();
+    for line in symbols.lines() {
+        let mut fields = line.splitn(3, ' ');
+        let (Some(address), Some(kind), Some(name)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let Ok(address) = u64::from_str_radix(address, 16) else {
+            continue;
+        };
+        match kind {
+            _ if name == "sbi_start" => sbi_start = Some(address),
+            "t" | "T" => text.push((address, strip_hash(name))),
+            _ => {}
+        }
+    }
+    let sbi_start = sbi_start?;
+
+    let mut entries: Vec<(u32, u32)> = Vec::new();
+    let mut names: Vec<u8> = Vec::new();
+    for (address, name) in text {
+        let Some(offset) = address
+            .checked_sub(sbi_start)
+            .and_then(|offset| u32::try_from(offset).ok())
+        else {
+            continue;
+        };
+        if entries.last().is_some_and(|(last, _)| *last == offset) {
+            continue;
+        }
+        let name = &name.as_bytes()[..name.len().min(SYMBOL_NAME_MAX)];
+        if 8 + (entries.len() + 1) * 8 + names.len() + name.len() + 1 > SYMBOL_TABLE_SIZE {
+            warn!("Symbol table is full, later symbols are left out");
+            break;
+        }
+        entries.push((offset, names.len() as u32));
+        names.extend_from_slice(name);
+        names.push(0);
+    }
+
+    // Name offsets are relative to the start of the pairs.
+    let pairs_len = (entries.len() * 8) as u32;
+    let mut table = Vec::with_capacity(16 + SYMBOL_TABLE_SIZE);
+    table.extend_from_slice(SYMBOL_TABLE_MAGIC);
+    table.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    table.extend_from_slice(&[0; 4]);
+    for (offset, name) in &entries {
+        table.extend_from_slice(&offset.to_le_bytes());
+        table.extend_from_slice(&(name + pairs_len).to_le_bytes());
+    }
+    table.extend_from_slice(&names);
+
+    let mut elf = fs::read(elf_path).ok()?;
+    let mut found = elf
+        .windows(SYMBOL_TABLE_PLACEHOLDER.len())
+        .enumerate()
+        .filter(|(_, window)| window == SYMBOL_TABLE_PLACEHOLDER)
+        .map(|(position, _)| position);
+    let (Some(position), None) = (found.next(), found.next()) else {
+        return None;
+    };
+    elf[position..position + table.len()].copy_from_slice(&table);
+    fs::write(elf_path, elf).ok()?;
+    info!("Embedded {} symbols", entries.len());
+    Some(())
+}
+
+/// Removes the hash suffix of legacy mangled Rust symbols, e.g. `::h0123456789abcdef`.
+fn strip_hash(name: &str) -> &str {
+    match name.rsplit_once("::h") {
+        Some((path, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            path
+        }
+        _ => name,
+    }
+}
+
 fn copy_output_files(target_dir: &PathBuf, arg: &PrototyperArg) -> Option<()> {
     let mode_suffix = if arg.payload.is_some() {
         info!("Copy for payload mode");
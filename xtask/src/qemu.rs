@@ -0,0 +1,427 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    time::Duration,
+};
+
+use clap::{Args, ValueEnum, builder::BoolishValueParser};
+use serde::Deserialize;
+
+use crate::utils::{
+    failure,
+    qemu::{Qemu, QemuOutput},
+};
+use crate::{bench, prototyper, test};
+
+#[derive(Debug, Args, Clone)]
+pub struct QemuArg {
+    /// Test cases to run [default: all test cases]
+    pub cases: Vec<String>,
+
+    /// List test cases and exit
+    #[clap(long)]
+    pub list: bool,
+
+    /// Number of harts, overriding the test cases
+    #[clap(long)]
+    pub smp: Option<usize>,
+
+    /// Memory size such as 512M, overriding the test cases
+    #[clap(long, short = 'm')]
+    pub memory: Option<String>,
+
+    /// Interrupt controllers of the virt machine, overriding the test cases
+    #[clap(long, value_enum)]
+    pub aia: Option<Aia>,
+
+    /// Enable or disable the Sstc extension, overriding the test cases
+    #[clap(long, value_parser = BoolishValueParser::new())]
+    pub sstc: Option<bool>,
+
+    /// Seconds to wait for each test case, overriding the test cases
+    #[clap(long)]
+    pub timeout: Option<u64>,
+
+    /// Prototyper firmware to boot kernels of test cases without extra firmware features
+    /// [default: dynamic firmware in target]
+    #[clap(long)]
+    pub firmware: Option<PathBuf>,
+
+    /// Use the kernels and firmware built before instead of building them
+    #[clap(long)]
+    pub no_build: bool,
+}
+
+/// Advanced interrupt architecture of the virt machine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Aia {
+    /// PLIC and ACLINT
+    #[default]
+    None,
+    /// APLIC in place of PLIC
+    Aplic,
+    /// APLIC and IMSIC
+    AplicImsic,
+}
+
+impl Aia {
+    fn as_str(self) -> &'static str {
+        match self {
+            Aia::None => "none",
+            Aia::Aplic => "aplic",
+            Aia::AplicImsic => "aplic-imsic",
+        }
+    }
+}
+
+/// Kernels booted by test cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Kernel {
+    TestKernel,
+    BenchKernel,
+}
+
+impl Kernel {
+    fn name(self) -> &'static str {
+        match self {
+            Kernel::TestKernel => "rustsbi-test-kernel",
+            Kernel::BenchKernel => "rustsbi-bench-kernel",
+        }
+    }
+
+    fn build(self, target_dir: &Path) -> Option<ExitStatus> {
+        match self {
+            Kernel::TestKernel => test::build(target_dir),
+            Kernel::BenchKernel => bench::build(target_dir),
+        }
+    }
+}
+
+/// A test case read from a file in [`CASES_DIR`], named after the file.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Case {
+    description: String,
+    kernel: Kernel,
+    /// Extra Prototyper features, the dynamic firmware is built with them for this test case.
+    #[serde(default)]
+    features: Vec<String>,
+    #[serde(default = "default_smp")]
+    smp: usize,
+    #[serde(default)]
+    memory: Option<String>,
+    #[serde(default)]
+    aia: Aia,
+    #[serde(default = "default_true")]
+    sstc: bool,
+    #[serde(default = "default_timeout")]
+    timeout: u64,
+    /// QEMU is expected to exit successfully, that is the kernel shuts down without a failure.
+    #[serde(default = "default_true")]
+    success: bool,
+    /// Golden patterns the serial output should contain in order.
+    expect: Vec<String>,
+    /// Patterns the serial output should never contain.
+    #[serde(default)]
+    reject: Vec<String>,
+}
+
+fn default_smp() -> usize {
+    1
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_timeout() -> u64 {
+    60
+}
+
+const ARCH: &str = "riscv64imac-unknown-none-elf";
+const PROTOTYPER_ARCH: &str = "riscv64gc-unknown-none-elf";
+const PROTOTYPER_DYNAMIC_ELF: &str = "rustsbi-prototyper-dynamic.elf";
+const CASES_DIR: &str = "xtask/qemu-cases";
+/// Directory in target to save serial output of every test case.
+const LOGS_DIR: &str = "qemu";
+
+#[must_use]
+pub fn run(arg: &QemuArg) -> Option<ExitStatus> {
+    let current_dir = env::current_dir().ok()?;
+    let target_dir = current_dir.join("target").join(ARCH).join("release");
+
+    let cases = match load_cases(&current_dir.join(CASES_DIR), &arg.cases) {
+        Ok(cases) => cases,
+        Err(err_msg) => {
+            error!("{}", err_msg);
+            return failure();
+        }
+    };
+    if arg.list {
+        for (name, case) in &cases {
+            println!("{:<24} {}", name, case.description);
+        }
+        return Some(ExitStatus::default());
+    }
+
+    let prototyper_dir = current_dir
+        .join("target")
+        .join(PROTOTYPER_ARCH)
+        .join("release");
+    let firmware = arg
+        .firmware
+        .clone()
+        .unwrap_or_else(|| prototyper_dir.join(PROTOTYPER_DYNAMIC_ELF));
+    let needs_firmware = cases.values().any(|case| case.features.is_empty());
+    if needs_firmware && !firmware.exists() {
+        error!(
+            "Error: Prototyper firmware not found at '{}'\n\
+             Please run 'cargo prototyper' first to build the Prototyper firmware.",
+            firmware.display()
+        );
+        return failure();
+    }
+
+    if !arg.no_build {
+        let mut variants: Vec<Vec<String>> = cases
+            .values()
+            .filter(|case| !case.features.is_empty())
+            .map(|case| sorted(&case.features))
+            .collect();
+        variants.sort();
+        variants.dedup();
+        for features in variants {
+            let exit_status = prototyper::build_variant(&features, &variant_elf(&features))?;
+            if !exit_status.success() {
+                return Some(exit_status);
+            }
+        }
+
+        let mut kernels: Vec<Kernel> = cases.values().map(|case| case.kernel).collect();
+        kernels.sort();
+        kernels.dedup();
+        for kernel in kernels {
+            let exit_status = kernel.build(&target_dir)?;
+            if !exit_status.success() {
+                return Some(exit_status);
+            }
+        }
+    }
+
+    let logs_dir = target_dir.join(LOGS_DIR);
+    if let Err(e) = fs::create_dir_all(&logs_dir) {
+        error!("Failed to create directory for serial logs: {}", e);
+        return failure();
+    }
+
+    let mut results = Vec::new();
+    for (name, case) in &cases {
+        let firmware = match case.features.is_empty() {
+            true => firmware.clone(),
+            false => prototyper_dir.join(variant_elf(&sorted(&case.features))),
+        };
+        let result = run_case(arg, name, case, &firmware, &target_dir, &logs_dir);
+        if let Err(reason) = &result {
+            error!("Test case '{}' failed: {}", name, reason);
+        }
+        results.push((name, result));
+    }
+
+    info!("QEMU test results:");
+    for (name, result) in &results {
+        match result {
+            Ok(()) => info!("{:<24} PASSED", name),
+            Err(reason) => error!("{:<24} FAILED ({})", name, reason),
+        }
+    }
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed > 0 {
+        error!(
+            "{} of {} test cases failed, serial output saved in '{}'",
+            failed,
+            results.len(),
+            logs_dir.display()
+        );
+        return failure();
+    }
+    Some(ExitStatus::default())
+}
+
+fn sorted(features: &[String]) -> Vec<String> {
+    let mut features = features.to_vec();
+    features.sort();
+    features
+}
+
+/// Name of the dynamic firmware built with extra `features`.
+fn variant_elf(features: &[String]) -> String {
+    format!("rustsbi-prototyper-dynamic+{}.elf", features.join("+"))
+}
+
+/// Reads test cases, all of them if `names` is empty.
+fn load_cases(cases_dir: &Path, names: &[String]) -> Result<BTreeMap<String, Case>, String> {
+    let entries = fs::read_dir(cases_dir).map_err(|e| {
+        format!(
+            "Failed to read test cases in '{}': {}",
+            cases_dir.display(),
+            e
+        )
+    })?;
+    let mut cases = BTreeMap::new();
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.extension().is_none_or(|ext| ext != "toml") {
+            continue;
+        }
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        if !names.is_empty() && !names.contains(&name) {
+            continue;
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        let case: Case = toml::from_str(&content)
+            .map_err(|e| format!("Failed to parse '{}': {}", path.display(), e))?;
+        cases.insert(name, case);
+    }
+    if let Some(name) = names.iter().find(|name| !cases.contains_key(*name)) {
+        return Err(format!("Test case '{}' not found", name));
+    }
+    Ok(cases)
+}
+
+/// Boots the kernel of a test case and matches its serial output against the golden patterns.
+fn run_case(
+    arg: &QemuArg,
+    name: &str,
+    case: &Case,
+    firmware: &Path,
+    target_dir: &Path,
+    logs_dir: &Path,
+) -> Result<(), String> {
+    let smp = arg.smp.unwrap_or(case.smp);
+    let aia = arg.aia.unwrap_or(case.aia);
+    let sstc = arg.sstc.unwrap_or(case.sstc);
+    let timeout = arg.timeout.unwrap_or(case.timeout);
+    info!(
+        "Running test case '{}' with {} harts, AIA {}, Sstc {}",
+        name,
+        smp,
+        aia.as_str(),
+        if sstc { "on" } else { "off" }
+    );
+
+    let mut qemu = Qemu::new();
+    qemu.bios(firmware)
+        .kernel(target_dir.join(format!("{}.bin", case.kernel.name())))
+        .smp(smp)
+        .aia(aia.as_str())
+        .sstc(sstc);
+    if let Some(memory) = arg.memory.as_ref().or(case.memory.as_ref()) {
+        qemu.memory(memory);
+    }
+    let output = qemu
+        .capture(Duration::from_secs(timeout))
+        .map_err(|e| format!("failed to run qemu-system-riscv64: {}", e))?;
+
+    let log_path = logs_dir.join(format!("{}.log", name));
+    fs::write(&log_path, &output.serial)
+        .map_err(|e| format!("failed to save serial output: {}", e))?;
+    check_output(case, &output, timeout)
+}
+
+/// Checks the serial output and exit status of QEMU against a test case.
+fn check_output(case: &Case, output: &QemuOutput, timeout: u64) -> Result<(), String> {
+    if output.timed_out {
+        return Err(format!("timed out after {} seconds", timeout));
+    }
+    let mut lines = output.serial.lines();
+    for pattern in &case.expect {
+        if !lines.any(|line| line.contains(pattern.as_str())) {
+            return Err(format!("expected '{}' in order, not found", pattern));
+        }
+    }
+    if let Some(pattern) = case
+        .reject
+        .iter()
+        .find(|pattern| output.serial.contains(pattern.as_str()))
+    {
+        return Err(format!("rejected '{}' found", pattern));
+    }
+    if output.status.success() != case.success {
+        return Err(format!("QEMU exited with {}", output.status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+
+    use super::*;
+
+    const CASE: &str = r#"
+        description = "test"
+        kernel = "test-kernel"
+        expect = ["RustSBI version", "SBI test PASSED"]
+        reject = ["RustSBI panic"]
+    "#;
+
+    fn output(serial: &str, code: i32) -> QemuOutput {
+        QemuOutput {
+            // Wait status with the exit code in the second byte.
+            status: ExitStatus::from_raw(code << 8),
+            serial: serial.to_string(),
+            timed_out: false,
+        }
+    }
+
+    #[test]
+    fn expects_in_order() {
+        let case: Case = toml::from_str(CASE).unwrap();
+        let passed = "RustSBI version 0.4\n[test-kernel] SBI test PASSED\n";
+        assert!(check_output(&case, &output(passed, 0), 60).is_ok());
+        let reversed = "[test-kernel] SBI test PASSED\nRustSBI version 0.4\n";
+        assert!(check_output(&case, &output(reversed, 0), 60).is_err());
+        let missing = "RustSBI version 0.4\n";
+        assert!(check_output(&case, &output(missing, 0), 60).is_err());
+    }
+
+    #[test]
+    fn rejects_patterns() {
+        let case: Case = toml::from_str(CASE).unwrap();
+        let serial = "RustSBI version 0.4\n[test-kernel] SBI test PASSED\nRustSBI panic\n";
+        assert!(check_output(&case, &output(serial, 0), 60).is_err());
+    }
+
+    #[test]
+    fn checks_exit_status() {
+        let serial = "RustSBI version 0.4\n[test-kernel] SBI test PASSED\n";
+        let case: Case = toml::from_str(CASE).unwrap();
+        assert!(check_output(&case, &output(serial, 1), 60).is_err());
+        let case: Case = toml::from_str(&format!("{}success = false\n", CASE)).unwrap();
+        assert!(check_output(&case, &output(serial, 1), 60).is_ok());
+        assert!(check_output(&case, &output(serial, 0), 60).is_err());
+    }
+
+    #[test]
+    fn fails_on_timeout() {
+        let case: Case = toml::from_str(CASE).unwrap();
+        let mut output = output("RustSBI version 0.4\n[test-kernel] SBI test PASSED\n", 0);
+        output.timed_out = true;
+        assert!(check_output(&case, &output, 60).is_err());
+    }
+
+    #[test]
+    fn cases_parse() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("qemu-cases");
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let case = fs::read_to_string(&path).unwrap();
+            assert!(toml::from_str::<Case>(&case).is_ok(), "{}", path.display());
+        }
+    }
+}
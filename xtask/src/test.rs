@@ -2,44 +2,68 @@ use std::{
     env, fs,
     path::{Path, PathBuf},
     process::{Command, ExitStatus},
+    time::Duration,
 };
 
 use clap::Args;
 
-use crate::utils::cargo;
+use crate::utils::{cargo, failure, qemu::Qemu};
 
 #[derive(Debug, Args, Clone)]
 pub struct TestArg {
     /// Package Prototyper and Test-Kernel into a single image
     #[clap(long, help = "Create a combined image with Prototyper and test kernel")]
     pub pack: bool,
+
+    /// Boot the test kernel in QEMU and fail if any SBI test fails
+    #[clap(long)]
+    pub run: bool,
+
+    /// Number of harts of the QEMU machine
+    #[clap(long, default_value_t = 1)]
+    pub smp: usize,
+
+    /// Prototyper firmware to boot the test kernel with [default: dynamic firmware in target]
+    #[clap(long)]
+    pub firmware: Option<PathBuf>,
+
+    /// Seconds to wait for the test kernel to shut down
+    #[clap(long, default_value_t = 60)]
+    pub timeout: u64,
 }
 
 const ARCH: &str = "riscv64imac-unknown-none-elf";
 const TEST_KERNEL_NAME: &str = "rustsbi-test-kernel";
 const PROTOTYPER_BIN: &str = "rustsbi-prototyper.bin";
+const PROTOTYPER_ARCH: &str = "riscv64gc-unknown-none-elf";
+const PROTOTYPER_DYNAMIC_ELF: &str = "rustsbi-prototyper-dynamic.elf";
+/// Lines printed by the test kernel on its result.
+const TEST_PASSED: &str = "[test-kernel] SBI test PASSED";
+const TEST_FAILED: &str = "[test-kernel] SBI test FAILED";
+const TEST_PANICKED: &str = "[test-kernel-panic]";
 
 #[must_use]
 pub fn run(arg: &TestArg) -> Option<ExitStatus> {
     let current_dir = env::current_dir().ok()?;
     let target_dir = get_target_dir(&current_dir);
 
-    // Build the test kernel
-    info!("Building test kernel");
-    let build_status = build_test_kernel()?;
-    if !build_status.success() {
-        error!("Failed to build test kernel");
-        return Some(build_status);
-    }
-
-    // Convert to binary format
-    info!("Converting to binary format");
-    let exit_status = convert_to_binary(&target_dir)?;
+    let exit_status = build(&target_dir)?;
     if !exit_status.success() {
-        error!("Failed to convert test kernel to binary format");
         return Some(exit_status);
     }
 
+    // Run in QEMU if requested
+    if arg.run {
+        match run_test(arg, &current_dir, &target_dir) {
+            Ok(true) => {}
+            Ok(false) => return failure(),
+            Err(err_msg) => {
+                error!("{}", err_msg);
+                return failure();
+            }
+        }
+    }
+
     // Pack into image if requested
     if arg.pack {
         info!("Packing into image");
@@ -55,11 +79,7 @@ pub fn run(arg: &TestArg) -> Option<ExitStatus> {
             }
             Err(err_msg) => {
                 error!("{}", err_msg);
-                // TODO cross-platform ExitStatus return value
-                #[cfg(unix)]
-                return Some(<ExitStatus as std::os::unix::process::ExitStatusExt>::from_raw(1));
-                #[cfg(not(unix))]
-                return None;
+                return failure();
             }
         }
     } else {
@@ -74,6 +94,72 @@ pub fn run(arg: &TestArg) -> Option<ExitStatus> {
     Some(exit_status)
 }
 
+/// Boots the test kernel in QEMU.
+///
+/// Returns `false` if any SBI test failed or the test kernel panicked.
+fn run_test(arg: &TestArg, current_dir: &Path, target_dir: &Path) -> Result<bool, String> {
+    let firmware = arg.firmware.clone().unwrap_or_else(|| {
+        current_dir
+            .join("target")
+            .join(PROTOTYPER_ARCH)
+            .join("release")
+            .join(PROTOTYPER_DYNAMIC_ELF)
+    });
+    if !firmware.exists() {
+        return Err(format!(
+            "Error: Prototyper firmware not found at '{}'\n\
+             Please run 'cargo prototyper' first to build the Prototyper firmware.",
+            firmware.display()
+        ));
+    }
+
+    info!("Running test kernel in QEMU with {} harts", arg.smp);
+    let output = Qemu::new()
+        .bios(&firmware)
+        .kernel(target_dir.join(format!("{}.bin", TEST_KERNEL_NAME)))
+        .smp(arg.smp)
+        .capture(Duration::from_secs(arg.timeout))
+        .map_err(|e| format!("Failed to run qemu-system-riscv64: {}", e))?;
+    if output.timed_out {
+        return Err(format!(
+            "Test kernel did not shut down within {} seconds",
+            arg.timeout
+        ));
+    }
+
+    let printed = |pattern: &str| output.serial.lines().any(|line| line.contains(pattern));
+    if printed(TEST_PANICKED) {
+        error!("Test kernel panicked");
+        Ok(false)
+    } else if printed(TEST_FAILED) || !printed(TEST_PASSED) {
+        error!("SBI test failed");
+        Ok(false)
+    } else if !output.status.success() {
+        error!("QEMU exited with {}", output.status);
+        Ok(false)
+    } else {
+        info!("SBI test passed");
+        Ok(true)
+    }
+}
+
+/// Builds the test kernel and converts it to binary format in `target_dir`.
+pub fn build(target_dir: &Path) -> Option<ExitStatus> {
+    info!("Building test kernel");
+    let build_status = build_test_kernel()?;
+    if !build_status.success() {
+        error!("Failed to build test kernel");
+        return Some(build_status);
+    }
+
+    info!("Converting to binary format");
+    let exit_status = convert_to_binary(target_dir)?;
+    if !exit_status.success() {
+        error!("Failed to convert test kernel to binary format");
+    }
+    Some(exit_status)
+}
+
 fn get_target_dir(current_dir: &Path) -> PathBuf {
     current_dir.join("target").join(ARCH).join("release")
 }
@@ -1,8 +1,11 @@
 pub mod cargo;
+pub mod qemu;
 
 #[macro_use]
 pub mod envs;
 
+use std::process::ExitStatus;
+
 pub trait CmdOptional {
     fn optional(&mut self, pred: bool, f: impl FnOnce(&mut Self) -> &mut Self) -> &mut Self {
         if pred {
@@ -11,3 +14,13 @@ pub trait CmdOptional {
         self
     }
 }
+
+/// Exit status reported for a task that failed without a failing command.
+pub fn failure() -> Option<ExitStatus> {
+    // TODO cross-platform ExitStatus return value
+    // Raw wait status with exit code 1.
+    #[cfg(unix)]
+    return Some(<ExitStatus as std::os::unix::process::ExitStatusExt>::from_raw(1 << 8));
+    #[cfg(not(unix))]
+    return None;
+}
@@ -0,0 +1,131 @@
+use std::{
+    ffi::OsStr,
+    io::{self, BufRead, BufReader},
+    ops::{Deref, DerefMut},
+    process::{Command, ExitStatus, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::CmdOptional;
+
+pub struct Qemu {
+    cmd: Command,
+}
+
+/// Serial output of a QEMU run.
+pub struct QemuOutput {
+    pub status: ExitStatus,
+    pub serial: String,
+    /// QEMU was killed when the timeout elapsed.
+    pub timed_out: bool,
+}
+
+#[allow(unused)]
+impl Qemu {
+    pub fn new() -> Self {
+        let mut cmd = Command::new("qemu-system-riscv64");
+        cmd.args(["-machine", "virt", "-display", "none", "-serial", "stdio"]);
+        Self { cmd }
+    }
+
+    pub fn bios<S: AsRef<OsStr>>(&mut self, bios: S) -> &mut Self {
+        self.arg("-bios").arg(bios);
+        self
+    }
+
+    pub fn kernel<S: AsRef<OsStr>>(&mut self, kernel: S) -> &mut Self {
+        self.arg("-kernel").arg(kernel);
+        self
+    }
+
+    pub fn smp(&mut self, smp: usize) -> &mut Self {
+        self.args(["-smp", &smp.to_string()]);
+        self
+    }
+
+    pub fn memory<S: AsRef<OsStr>>(&mut self, memory: S) -> &mut Self {
+        self.arg("-m").arg(memory);
+        self
+    }
+
+    /// Selects the interrupt controllers of the virt machine, `none`, `aplic` or `aplic-imsic`.
+    pub fn aia(&mut self, aia: &str) -> &mut Self {
+        self.arg("-machine").arg(format!("aia={}", aia));
+        self
+    }
+
+    pub fn sstc(&mut self, sstc: bool) -> &mut Self {
+        let cpu = if sstc {
+            "rv64,sstc=on"
+        } else {
+            "rv64,sstc=off"
+        };
+        self.args(["-cpu", cpu]);
+        self
+    }
+
+    /// Runs QEMU, echoing and capturing its serial output until it exits or `timeout` elapses.
+    pub fn capture(&mut self, timeout: Duration) -> io::Result<QemuOutput> {
+        let mut child = self
+            .cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            let mut line = Vec::new();
+            while reader.read_until(b'\n', &mut line).is_ok_and(|len| len > 0) {
+                let text = String::from_utf8_lossy(&line).trim_end().to_string();
+                if sender.send(text).is_err() {
+                    break;
+                }
+                line.clear();
+            }
+        });
+
+        let deadline = Instant::now() + timeout;
+        let mut serial = String::new();
+        let mut timed_out = false;
+        loop {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) => {
+                    println!("{}", line);
+                    serial.push_str(&line);
+                    serial.push('\n');
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {
+                    timed_out = true;
+                    child.kill()?;
+                    break;
+                }
+            }
+        }
+        let status = child.wait()?;
+        Ok(QemuOutput {
+            status,
+            serial,
+            timed_out,
+        })
+    }
+}
+
+impl CmdOptional for Qemu {}
+
+impl Deref for Qemu {
+    type Target = Command;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cmd
+    }
+}
+
+impl DerefMut for Qemu {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cmd
+    }
+}